@@ -0,0 +1,342 @@
+use anyhow::{anyhow, Result};
+use log::*;
+
+use vulkanalia::prelude::v1_0::*;
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
+// memory allocated from the device at a time, anything bigger gets its own block
+pub const BLOCK_SIZE: vk::DeviceSize = 64 * 1024 * 1024;
+
+// a range of a block, null memory means nothing was allocated
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Allocation {
+    pub memory: vk::DeviceMemory,
+    pub offset: vk::DeviceSize,
+    pub size: vk::DeviceSize,
+    block: usize,
+    // blocks and ranges are checked against these on free, so a stale copy can't release whatever reused its slot
+    block_id: u64,
+    id: u64,
+}
+
+// the padding before an aligned offset belongs to the allocation so it is returned on free
+#[derive(Clone, Debug)]
+struct Used {
+    id: u64,
+    start: vk::DeviceSize,
+    size: vk::DeviceSize,
+    name: String,
+}
+
+#[derive(Clone, Debug)]
+struct Block {
+    id: u64,
+    memory: vk::DeviceMemory,
+    memory_type: u32,
+    // buffers and optimal images never share a block so buffer image granularity can be ignored
+    linear: bool,
+    // device address memory is only handed to buffers that asked for it
+    flags: vk::MemoryAllocateFlags,
+    dedicated: bool,
+    // sorted by offset and never adjacent, neighbours are merged on free
+    free: Vec<(vk::DeviceSize, vk::DeviceSize)>,
+    used: HashMap<vk::DeviceSize, Used>,
+}
+
+impl Block {
+    fn take(&mut self, size: vk::DeviceSize, alignment: vk::DeviceSize, id: u64, name: &str) -> Option<vk::DeviceSize> {
+        let alignment = alignment.max(1);
+
+        // first fit
+        let (i, start, offset) = self.free.iter().enumerate().find_map(|(i, &(start, free_size))| {
+            let offset = start.div_ceil(alignment) * alignment;
+            (offset + size <= start + free_size).then_some((i, start, offset))
+        })?;
+
+        let (_, free_size) = self.free.remove(i);
+        let end = offset + size;
+        if end < start + free_size {
+            self.free.insert(i, (end, start + free_size - end));
+        }
+
+        self.used.insert(offset, Used { id, start, size: end - start, name: name.to_string() });
+        Some(offset)
+    }
+
+    fn release(&mut self, offset: vk::DeviceSize, id: u64) {
+        let used = match self.used.entry(offset) {
+            Entry::Occupied(used) if used.get().id == id => used.remove(),
+            _ => {
+                warn!("Freed an allocation at offset {} that was not in use.", offset);
+                return;
+            }
+        };
+
+        let i = self.free.partition_point(|&(start, _)| start < used.start);
+        self.free.insert(i, (used.start, used.size));
+
+        // merge with the next range, then the previous one
+        if i + 1 < self.free.len() && self.free[i].0 + self.free[i].1 == self.free[i + 1].0 {
+            self.free[i].1 += self.free.remove(i + 1).1;
+        }
+        if i > 0 && self.free[i - 1].0 + self.free[i - 1].1 == self.free[i].0 {
+            self.free[i - 1].1 += self.free.remove(i).1;
+        }
+    }
+}
+
+// sub-allocates buffers and images from a few large blocks instead of one allocation each
+// gpu-allocator and vk-mem are built on ash's handles and vulkanalia-vma builds vma's c++, this is small enough to own
+#[derive(Clone, Debug, Default)]
+pub struct Allocator {
+    blocks: Vec<Option<Block>>,
+    // shared by blocks and allocations, never reused
+    next_id: u64,
+}
+
+impl Allocator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// # Safety
+    ///
+    /// `device` has to be the one every other allocation from this allocator was made with.
+    pub unsafe fn allocate(
+        &mut self,
+        device: &Device,
+        requirements: vk::MemoryRequirements,
+        memory_type: u32,
+        linear: bool,
+        flags: vk::MemoryAllocateFlags,
+        name: &str,
+    ) -> Result<Allocation> {
+        let size = requirements.size;
+        self.next_id += 1;
+        let id = self.next_id;
+
+        if size <= BLOCK_SIZE {
+            let shared = self.blocks.iter_mut().enumerate().find_map(|(i, block)| {
+                let block = block.as_mut().filter(|b| {
+                    !b.dedicated && b.memory_type == memory_type && b.linear == linear && b.flags == flags
+                })?;
+                let offset = block.take(size, requirements.alignment, id, name)?;
+                Some(Allocation { memory: block.memory, offset, size, block: i, block_id: block.id, id })
+            });
+
+            if let Some(allocation) = shared {
+                return Ok(allocation);
+            }
+        }
+
+        // start a new block, or give a big allocation one to itself
+        let dedicated = size > BLOCK_SIZE;
+        let block_size = if dedicated { size } else { BLOCK_SIZE };
+        let mut flags_info = vk::MemoryAllocateFlagsInfo::builder()
+            .flags(flags);
+        let mut info = vk::MemoryAllocateInfo::builder()
+            .allocation_size(block_size)
+            .memory_type_index(memory_type);
+        if !flags.is_empty() {
+            info = info.push_next(&mut flags_info);
+        }
+
+        let memory = device.allocate_memory(&info, None)?;
+        debug!("Allocated a {} byte block of memory type {} for ({}).", block_size, memory_type, name);
+
+        self.next_id += 1;
+        let block_id = self.next_id;
+        let mut block = Block {
+            id: block_id,
+            memory,
+            memory_type,
+            linear,
+            flags,
+            dedicated,
+            free: vec![(0, block_size)],
+            used: HashMap::new(),
+        };
+        let offset = block
+            .take(size, requirements.alignment, id, name)
+            .ok_or_else(|| anyhow!("Failed to allocate ({}) from a new block.", name))?;
+
+        // reuse the slot of a block that was freed
+        let i = match self.blocks.iter().position(Option::is_none) {
+            Some(i) => {
+                self.blocks[i] = Some(block);
+                i
+            }
+            None => {
+                self.blocks.push(Some(block));
+                self.blocks.len() - 1
+            }
+        };
+
+        Ok(Allocation { memory, offset, size, block: i, block_id, id })
+    }
+
+    /// dedicated blocks go straight back to the device, shared ones are kept for reuse
+    ///
+    /// # Safety
+    ///
+    /// `allocation` has to come from this allocator, and nothing bound to it can still be in use.
+    pub unsafe fn free(&mut self, device: &Device, allocation: Allocation) {
+        if allocation.memory.is_null() {
+            return;
+        }
+
+        let block = self.blocks.get_mut(allocation.block).and_then(Option::as_mut);
+        let Some(block) = block.filter(|block| block.id == allocation.block_id) else {
+            warn!("Freed an allocation from a block that no longer exists.");
+            return;
+        };
+
+        // a range freed twice, or already handed out again, is left alone
+        let in_use = block.used.len();
+        block.release(allocation.offset, allocation.id);
+        if block.dedicated && block.used.len() < in_use {
+            device.free_memory(block.memory, None);
+            self.blocks[allocation.block] = None;
+        }
+    }
+
+    /// must run before the device is destroyed, anything still allocated is reported by name
+    ///
+    /// # Safety
+    ///
+    /// Every allocation has to be unused, none of them can be used after.
+    pub unsafe fn destroy(&mut self, device: &Device) {
+        for block in self.blocks.drain(..).flatten() {
+            for used in block.used.values() {
+                warn!("Allocation ({}) was never freed.", used.name);
+            }
+
+            device.free_memory(block.memory, None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(size: vk::DeviceSize) -> Block {
+        Block {
+            id: 0,
+            memory: vk::DeviceMemory::null(),
+            memory_type: 0,
+            linear: true,
+            flags: vk::MemoryAllocateFlags::empty(),
+            dedicated: false,
+            free: vec![(0, size)],
+            used: HashMap::new(),
+        }
+    }
+
+    // each name's allocation gets its own id
+    fn id(name: &str) -> u64 {
+        name.as_bytes()[0] as u64
+    }
+
+    #[test]
+    fn pads_to_the_alignment() {
+        let mut block = block(1024);
+        assert_eq!(block.take(10, 1, id("a"), "a"), Some(0));
+        assert_eq!(block.take(16, 256, id("b"), "b"), Some(256));
+        assert_eq!(block.free, vec![(272, 752)]);
+
+        // the padding goes back with it
+        let used = &block.used[&256];
+        assert_eq!((used.start, used.size), (10, 262));
+        block.release(256, id("b"));
+        assert_eq!(block.free, vec![(10, 1014)]);
+    }
+
+    #[test]
+    fn treats_a_zero_alignment_as_one() {
+        let mut block = block(1024);
+        assert_eq!(block.take(3, 0, id("a"), "a"), Some(0));
+        assert_eq!(block.take(3, 0, id("b"), "b"), Some(3));
+    }
+
+    #[test]
+    fn splits_a_free_range() {
+        let mut block = block(1024);
+        assert_eq!(block.take(100, 1, id("a"), "a"), Some(0));
+        assert_eq!(block.free, vec![(100, 924)]);
+
+        // an exact fit leaves nothing behind
+        assert_eq!(block.take(924, 1, id("b"), "b"), Some(100));
+        assert!(block.free.is_empty());
+        assert_eq!(block.take(1, 1, id("c"), "c"), None);
+    }
+
+    #[test]
+    fn skips_ranges_too_small_once_aligned() {
+        let mut block = block(1024);
+        let offsets = ["a", "b", "c"].map(|name| block.take(100, 1, id(name), name).unwrap());
+        block.release(offsets[1], id("b"));
+        assert_eq!(block.free, vec![(100, 100), (300, 724)]);
+
+        // fits in the hole at 100 unaligned, but not from 128
+        assert_eq!(block.take(80, 64, id("d"), "d"), Some(320));
+        assert_eq!(block.take(80, 1, id("e"), "e"), Some(100));
+    }
+
+    #[test]
+    fn merges_with_the_next_range() {
+        let mut block = block(1024);
+        let offsets = ["a", "b"].map(|name| block.take(100, 1, id(name), name).unwrap());
+        block.release(offsets[1], id("b"));
+        assert_eq!(block.free, vec![(100, 924)]);
+        block.release(offsets[0], id("a"));
+        assert_eq!(block.free, vec![(0, 1024)]);
+    }
+
+    #[test]
+    fn merges_with_the_previous_range() {
+        let mut block = block(300);
+        let offsets = ["a", "b", "c"].map(|name| block.take(100, 1, id(name), name).unwrap());
+        block.release(offsets[0], id("a"));
+        block.release(offsets[1], id("b"));
+        assert_eq!(block.free, vec![(0, 200)]);
+    }
+
+    #[test]
+    fn merges_with_both_neighbours() {
+        let mut block = block(1024);
+        let offsets = ["a", "b", "c"].map(|name| block.take(100, 1, id(name), name).unwrap());
+        block.release(offsets[0], id("a"));
+        block.release(offsets[2], id("c"));
+        assert_eq!(block.free, vec![(0, 100), (200, 824)]);
+
+        block.release(offsets[1], id("b"));
+        assert_eq!(block.free, vec![(0, 1024)]);
+        assert!(block.used.is_empty());
+    }
+
+    #[test]
+    fn ignores_offsets_not_in_use() {
+        let mut block = block(1024);
+        block.take(100, 1, id("a"), "a");
+        block.release(50, id("a"));
+        block.release(0, id("a"));
+        block.release(0, id("a"));
+        assert_eq!(block.free, vec![(0, 1024)]);
+    }
+
+    #[test]
+    fn ignores_a_stale_allocation_at_a_reused_offset() {
+        let mut block = block(1024);
+        assert_eq!(block.take(100, 1, id("a"), "a"), Some(0));
+        block.release(0, id("a"));
+        assert_eq!(block.take(100, 1, id("b"), "b"), Some(0));
+
+        // freeing a again can't release b
+        block.release(0, id("a"));
+        assert_eq!(block.free, vec![(100, 924)]);
+        assert_eq!(block.used[&0].name, "b");
+    }
+}
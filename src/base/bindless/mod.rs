@@ -0,0 +1,246 @@
+use super::allocator::{Allocation, Allocator};
+use super::app::data::Uploader;
+use super::app::{create_image_view, create_texture_image, create_texture_image_from_pixels, set_debug_name};
+use super::constants::MAX_BINDLESS_TEXTURES;
+
+use anyhow::{anyhow, Result};
+
+use vulkanalia::{prelude::v1_0::*, Instance};
+
+const FORMAT: vk::Format = vk::Format::R8G8B8A8_SRGB;
+
+// every texture in one partially bound array in its own set, objects pick theirs with the texture index push constant
+#[derive(Debug)]
+pub struct BindlessTextures {
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    descriptor_pool: vk::DescriptorPool,
+    descriptor_set: vk::DescriptorSet,
+    textures: Vec<(vk::Image, Allocation, vk::ImageView)>,
+}
+
+impl BindlessTextures {
+    /// # Safety
+    ///
+    /// `device` has to have descriptor indexing enabled and outlive the set.
+    pub unsafe fn new(device: &Device) -> Result<Self> {
+        let descriptor_set_layout = create_descriptor_set_layout(device)?;
+        let (descriptor_pool, descriptor_set) = match create_descriptor_set(device, descriptor_set_layout) {
+            Ok(pool_and_set) => pool_and_set,
+            Err(e) => {
+                device.destroy_descriptor_set_layout(descriptor_set_layout, None);
+                return Err(e);
+            }
+        };
+
+        Ok(Self { descriptor_set_layout, descriptor_pool, descriptor_set, textures: Vec::new() })
+    }
+
+    // set 1 of the scene pipeline layout
+    pub fn descriptor_set_layout(&self) -> vk::DescriptorSetLayout {
+        self.descriptor_set_layout
+    }
+
+    pub fn descriptor_set(&self) -> vk::DescriptorSet {
+        self.descriptor_set
+    }
+
+    pub fn len(&self) -> u32 {
+        self.textures.len() as u32
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.textures.is_empty()
+    }
+
+    /// loads a png into the next free slot and returns its index, the set is update after bind so nothing is rebound
+    ///
+    /// # Safety
+    ///
+    /// The handles passed in have to come from the device the set was made with.
+    #[allow(clippy::too_many_arguments)]
+    pub unsafe fn add(
+        &mut self,
+        instance: &Instance,
+        device: &Device,
+        phys_device: vk::PhysicalDevice,
+        allocator: &mut Allocator,
+        uploader: &Uploader,
+        sampler: vk::Sampler,
+        path: &str,
+    ) -> Result<u32> {
+        self.check_free()?;
+        let (image, image_memory, mip_levels) =
+            create_texture_image(instance, device, phys_device, allocator, uploader, &[path])?;
+
+        self.push(device, allocator, sampler, image, image_memory, mip_levels)
+    }
+
+    /// the same from rgba pixels, for textures embedded in or decoded by something else
+    ///
+    /// # Safety
+    ///
+    /// The handles passed in have to come from the device the set was made with.
+    #[allow(clippy::too_many_arguments)]
+    pub unsafe fn add_pixels(
+        &mut self,
+        instance: &Instance,
+        device: &Device,
+        phys_device: vk::PhysicalDevice,
+        allocator: &mut Allocator,
+        uploader: &Uploader,
+        sampler: vk::Sampler,
+        pixels: &[u8],
+        width: u32,
+        height: u32,
+    ) -> Result<u32> {
+        self.check_free()?;
+        let (image, image_memory, mip_levels) = create_texture_image_from_pixels(
+            instance,
+            device,
+            phys_device,
+            allocator,
+            uploader,
+            pixels,
+            width,
+            height,
+            1,
+            FORMAT,
+        )?;
+
+        self.push(device, allocator, sampler, image, image_memory, mip_levels)
+    }
+
+    fn check_free(&self) -> Result<()> {
+        if self.len() >= MAX_BINDLESS_TEXTURES {
+            return Err(anyhow!("All {} bindless texture slots are in use.", MAX_BINDLESS_TEXTURES));
+        }
+
+        Ok(())
+    }
+
+    unsafe fn push(
+        &mut self,
+        device: &Device,
+        allocator: &mut Allocator,
+        sampler: vk::Sampler,
+        image: vk::Image,
+        image_memory: Allocation,
+        mip_levels: u32,
+    ) -> Result<u32> {
+        let index = self.len();
+        let image_view = match create_image_view(device, image, FORMAT, vk::ImageAspectFlags::COLOR, mip_levels) {
+            Ok(view) => view,
+            Err(e) => {
+                device.destroy_image(image, None);
+                allocator.free(device, image_memory);
+                return Err(e);
+            }
+        };
+
+        self.textures.push((image, image_memory, image_view));
+        self.write(device, sampler, index);
+
+        Ok(index)
+    }
+
+    /// every slot samples through the app's sampler, so they're all rewritten when it changes
+    ///
+    /// # Safety
+    ///
+    /// `sampler` has to stay valid until it's replaced, pending frames can't use the old one.
+    pub unsafe fn update_sampler(&self, device: &Device, sampler: vk::Sampler) {
+        (0..self.len()).for_each(|i| self.write(device, sampler, i));
+    }
+
+    unsafe fn write(&self, device: &Device, sampler: vk::Sampler, index: u32) {
+        let info = vk::DescriptorImageInfo::builder()
+            .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+            .image_view(self.textures[index as usize].2)
+            .sampler(sampler);
+
+        let image_info = &[info];
+        let write = vk::WriteDescriptorSet::builder()
+            .dst_set(self.descriptor_set)
+            .dst_binding(0)
+            .dst_array_element(index)
+            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .image_info(image_info);
+
+        device.update_descriptor_sets(&[write], &[] as &[vk::CopyDescriptorSet]);
+    }
+
+    /// # Safety
+    ///
+    /// Nothing made here can still be in use on the gpu, and none of it can be used after.
+    pub unsafe fn destroy(&mut self, device: &Device, allocator: &mut Allocator) {
+        for (image, image_memory, image_view) in self.textures.drain(..) {
+            device.destroy_image_view(image_view, None);
+            device.destroy_image(image, None);
+            allocator.free(device, image_memory);
+        }
+        device.destroy_descriptor_pool(self.descriptor_pool, None);
+        device.destroy_descriptor_set_layout(self.descriptor_set_layout, None);
+    }
+
+    /// # Safety
+    ///
+    /// `device` has to be the one these were made with, created from `instance`.
+    pub unsafe fn set_debug_names(&self, instance: &Instance, device: &Device) -> Result<()> {
+        set_debug_name(instance, device, self.descriptor_set, "bindless-descriptor-set")?;
+        for (i, (image, _, _)) in self.textures.iter().enumerate() {
+            set_debug_name(instance, device, *image, &format!("bindless-texture-{}", i))?;
+        }
+
+        Ok(())
+    }
+}
+
+unsafe fn create_descriptor_set_layout(device: &Device) -> Result<vk::DescriptorSetLayout> {
+    // slots past the loaded textures are never written, and new ones are written while the set is bound
+    let binding = vk::DescriptorSetLayoutBinding::builder()
+        .binding(0)
+        .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+        .descriptor_count(MAX_BINDLESS_TEXTURES)
+        .stage_flags(vk::ShaderStageFlags::FRAGMENT);
+
+    let binding_flags = &[vk::DescriptorBindingFlags::PARTIALLY_BOUND | vk::DescriptorBindingFlags::UPDATE_AFTER_BIND];
+    let mut flags_info = vk::DescriptorSetLayoutBindingFlagsCreateInfo::builder()
+        .binding_flags(binding_flags);
+
+    let bindings = &[binding];
+    let info = vk::DescriptorSetLayoutCreateInfo::builder()
+        .flags(vk::DescriptorSetLayoutCreateFlags::UPDATE_AFTER_BIND_POOL)
+        .bindings(bindings)
+        .push_next(&mut flags_info);
+
+    Ok(device.create_descriptor_set_layout(&info, None)?)
+}
+
+unsafe fn create_descriptor_set(
+    device: &Device,
+    descriptor_set_layout: vk::DescriptorSetLayout,
+) -> Result<(vk::DescriptorPool, vk::DescriptorSet)> {
+    let pool_size = vk::DescriptorPoolSize::builder()
+        .type_(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+        .descriptor_count(MAX_BINDLESS_TEXTURES);
+
+    let pool_sizes = &[pool_size];
+    let info = vk::DescriptorPoolCreateInfo::builder()
+        .flags(vk::DescriptorPoolCreateFlags::UPDATE_AFTER_BIND)
+        .pool_sizes(pool_sizes)
+        .max_sets(1);
+    let descriptor_pool = device.create_descriptor_pool(&info, None)?;
+
+    let layouts = &[descriptor_set_layout];
+    let info = vk::DescriptorSetAllocateInfo::builder()
+        .descriptor_pool(descriptor_pool)
+        .set_layouts(layouts);
+
+    match device.allocate_descriptor_sets(&info) {
+        Ok(sets) => Ok((descriptor_pool, sets[0])),
+        Err(e) => {
+            device.destroy_descriptor_pool(descriptor_pool, None);
+            Err(e.into())
+        }
+    }
+}
@@ -0,0 +1,593 @@
+use super::allocator::{Allocation, Allocator};
+use super::app::{create_image, create_shader_module, set_debug_name};
+use super::shaders;
+
+use anyhow::Result;
+
+use vulkanalia::{prelude::v1_0::*, Instance};
+
+use std::mem::size_of;
+
+// has to match local_size_x and local_size_y in the bloom shaders
+const WORKGROUP_SIZE: u32 = 8;
+
+// the first mip is already half the scene, each after it half again
+const MAX_MIPS: u32 = 5;
+
+pub const BLOOM_FORMAT: vk::Format = vk::Format::R16G16B16A16_SFLOAT;
+
+// matches bloom_down.comp
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+struct BloomConstants {
+    threshold: f32,
+    bright_pass: u32,
+}
+
+// one image sampled and another written, sized by what's written
+#[derive(Copy, Clone, Debug)]
+struct Dispatch {
+    descriptor_set: vk::DescriptorSet,
+    extent: vk::Extent2D,
+}
+
+// everything sized by the render extent, rebuilt with the swapchain
+#[derive(Debug, Default)]
+struct Targets {
+    // the bloom chain, then the scratch image the first blur writes
+    images: [vk::Image; 2],
+    images_memory: [Allocation; 2],
+    // one per mip of each image
+    image_views: [Vec<vk::ImageView>; 2],
+    descriptor_pool: vk::DescriptorPool,
+    // the bright pass first
+    down: Vec<Dispatch>,
+    // across then down for each mip
+    blur: Vec<[Dispatch; 2]>,
+    // the smallest mip first
+    up: Vec<Dispatch>,
+}
+
+// the bright parts of the scene blurred over a chain of mips with compute shaders, added back by the post pass
+#[derive(Debug)]
+pub struct Bloom {
+    threshold: f32,
+    sampler: vk::Sampler,
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    pipeline_layout: vk::PipelineLayout,
+    down_pipeline: vk::Pipeline,
+    blur_pipelines: [vk::Pipeline; 2],
+    up_pipeline: vk::Pipeline,
+    targets: Targets,
+}
+
+/// the chain is written as storage images and filtered when it's sampled
+///
+/// # Safety
+///
+/// `phys_device` has to come from `instance`.
+pub unsafe fn is_supported(instance: &Instance, phys_device: vk::PhysicalDevice) -> bool {
+    let features = vk::FormatFeatureFlags::STORAGE_IMAGE | vk::FormatFeatureFlags::SAMPLED_IMAGE_FILTER_LINEAR;
+    instance
+        .get_physical_device_format_properties(phys_device, BLOOM_FORMAT)
+        .optimal_tiling_features
+        .contains(features)
+}
+
+impl Bloom {
+    /// the scene image has to be readable by compute shaders before this is recorded
+    ///
+    /// # Safety
+    ///
+    /// The handles passed in have to be valid and from one device, which has to outlive what's made here.
+    #[allow(clippy::too_many_arguments)]
+    pub unsafe fn new(
+        instance: &Instance,
+        device: &Device,
+        phys_device: vk::PhysicalDevice,
+        allocator: &mut Allocator,
+        pipeline_cache: vk::PipelineCache,
+        scene_image_view: vk::ImageView,
+        extent: vk::Extent2D,
+        threshold: f32,
+    ) -> Result<Self> {
+        let sampler = create_sampler(device)?;
+        let descriptor_set_layout = create_descriptor_set_layout(device)?;
+        let (pipeline_layout, down_pipeline, blur_pipelines, up_pipeline) =
+            create_pipelines(device, pipeline_cache, descriptor_set_layout)?;
+        let targets = create_targets(
+            instance,
+            device,
+            phys_device,
+            allocator,
+            descriptor_set_layout,
+            sampler,
+            scene_image_view,
+            extent,
+        )?;
+
+        Ok(Self {
+            threshold,
+            sampler,
+            descriptor_set_layout,
+            pipeline_layout,
+            down_pipeline,
+            blur_pipelines,
+            up_pipeline,
+            targets,
+        })
+    }
+
+    // the largest mip, which every smaller one has been added into
+    pub fn image_view(&self) -> vk::ImageView {
+        self.targets.image_views[0][0]
+    }
+
+    // linear, so the half sized chain is smooth when it's scaled up
+    pub fn sampler(&self) -> vk::Sampler {
+        self.sampler
+    }
+
+    pub fn set_threshold(&mut self, threshold: f32) {
+        self.threshold = threshold;
+    }
+
+    /// for a new render extent or scene image, the pipelines are kept
+    ///
+    /// # Safety
+    ///
+    /// The old images can't be in use by a pending command buffer.
+    pub unsafe fn resize(
+        &mut self,
+        instance: &Instance,
+        device: &Device,
+        phys_device: vk::PhysicalDevice,
+        allocator: &mut Allocator,
+        scene_image_view: vk::ImageView,
+        extent: vk::Extent2D,
+    ) -> Result<()> {
+        destroy_targets(device, allocator, &self.targets);
+        self.targets = create_targets(
+            instance,
+            device,
+            phys_device,
+            allocator,
+            self.descriptor_set_layout,
+            self.sampler,
+            scene_image_view,
+            extent,
+        )?;
+
+        Ok(())
+    }
+
+    /// records outside any rendering, between the scene and the post pass that reads the result
+    ///
+    /// # Safety
+    ///
+    /// `command_buffer` has to be recording outside any rendering, with the scene image readable.
+    pub unsafe fn record(&self, device: &Device, command_buffer: vk::CommandBuffer) {
+        // nothing is kept between frames, the last one's reads just have to finish first
+        let barriers = self.targets.images.map(|image| {
+            let subresource = vk::ImageSubresourceRange::builder()
+                .aspect_mask(vk::ImageAspectFlags::COLOR)
+                .base_mip_level(0)
+                .level_count(vk::REMAINING_MIP_LEVELS)
+                .base_array_layer(0)
+                .layer_count(1);
+
+            vk::ImageMemoryBarrier::builder()
+                .old_layout(vk::ImageLayout::UNDEFINED)
+                .new_layout(vk::ImageLayout::GENERAL)
+                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .image(image)
+                .subresource_range(subresource)
+                .src_access_mask(vk::AccessFlags::empty())
+                .dst_access_mask(vk::AccessFlags::SHADER_READ | vk::AccessFlags::SHADER_WRITE)
+                .build()
+        });
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::FRAGMENT_SHADER | vk::PipelineStageFlags::COMPUTE_SHADER,
+            vk::PipelineStageFlags::COMPUTE_SHADER,
+            vk::DependencyFlags::empty(),
+            &[] as &[vk::MemoryBarrier],
+            &[] as &[vk::BufferMemoryBarrier],
+            &barriers,
+        );
+
+        // each mip is read by the dispatch after the one that writes it
+        device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::COMPUTE, self.down_pipeline);
+        for (i, dispatch) in self.targets.down.iter().enumerate() {
+            if i > 0 {
+                cmd_shader_barrier(device, command_buffer, vk::PipelineStageFlags::COMPUTE_SHADER);
+            }
+            let constants = BloomConstants { threshold: self.threshold, bright_pass: (i == 0) as u32 };
+            device.cmd_push_constants(
+                command_buffer,
+                self.pipeline_layout,
+                vk::ShaderStageFlags::COMPUTE,
+                0,
+                std::slice::from_raw_parts((&constants as *const BloomConstants).cast(), size_of::<BloomConstants>()),
+            );
+            self.cmd_dispatch(device, command_buffer, dispatch);
+        }
+
+        // the mips don't depend on each other, so every one is blurred across before any is blurred down
+        for (direction, pipeline) in self.blur_pipelines.iter().enumerate() {
+            cmd_shader_barrier(device, command_buffer, vk::PipelineStageFlags::COMPUTE_SHADER);
+            device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::COMPUTE, *pipeline);
+            for dispatches in &self.targets.blur {
+                self.cmd_dispatch(device, command_buffer, &dispatches[direction]);
+            }
+        }
+
+        device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::COMPUTE, self.up_pipeline);
+        for dispatch in &self.targets.up {
+            cmd_shader_barrier(device, command_buffer, vk::PipelineStageFlags::COMPUTE_SHADER);
+            self.cmd_dispatch(device, command_buffer, dispatch);
+        }
+
+        cmd_shader_barrier(device, command_buffer, vk::PipelineStageFlags::FRAGMENT_SHADER);
+    }
+
+    unsafe fn cmd_dispatch(&self, device: &Device, command_buffer: vk::CommandBuffer, dispatch: &Dispatch) {
+        device.cmd_bind_descriptor_sets(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            self.pipeline_layout,
+            0,
+            &[dispatch.descriptor_set],
+            &[],
+        );
+        device.cmd_dispatch(
+            command_buffer,
+            dispatch.extent.width.div_ceil(WORKGROUP_SIZE),
+            dispatch.extent.height.div_ceil(WORKGROUP_SIZE),
+            1,
+        );
+    }
+
+    /// for shader reloads, the old pipelines are kept if the new ones fail
+    ///
+    /// # Safety
+    ///
+    /// The old pipelines can't be in use by a pending command buffer.
+    pub unsafe fn recreate_pipelines(&mut self, device: &Device, pipeline_cache: vk::PipelineCache) -> Result<()> {
+        let pipelines = create_pipelines(device, pipeline_cache, self.descriptor_set_layout)?;
+        self.destroy_pipelines(device);
+        (self.pipeline_layout, self.down_pipeline, self.blur_pipelines, self.up_pipeline) = pipelines;
+
+        Ok(())
+    }
+
+    unsafe fn destroy_pipelines(&self, device: &Device) {
+        device.destroy_pipeline(self.down_pipeline, None);
+        self.blur_pipelines
+            .iter()
+            .for_each(|p| device.destroy_pipeline(*p, None));
+        device.destroy_pipeline(self.up_pipeline, None);
+        device.destroy_pipeline_layout(self.pipeline_layout, None);
+    }
+
+    /// # Safety
+    ///
+    /// Nothing made here can still be in use on the gpu, and none of it can be used after.
+    pub unsafe fn destroy(&mut self, device: &Device, allocator: &mut Allocator) {
+        destroy_targets(device, allocator, &self.targets);
+        self.destroy_pipelines(device);
+        device.destroy_descriptor_set_layout(self.descriptor_set_layout, None);
+        device.destroy_sampler(self.sampler, None);
+    }
+
+    /// # Safety
+    ///
+    /// `device` has to be the one these were made with, created from `instance`.
+    pub unsafe fn set_debug_names(&self, instance: &Instance, device: &Device) -> Result<()> {
+        set_debug_name(instance, device, self.targets.images[0], "bloom-image")?;
+        set_debug_name(instance, device, self.targets.images[1], "bloom-blur-image")?;
+        set_debug_name(instance, device, self.down_pipeline, "bloom-down-pipeline")?;
+        set_debug_name(instance, device, self.blur_pipelines[0], "bloom-blur-across-pipeline")?;
+        set_debug_name(instance, device, self.blur_pipelines[1], "bloom-blur-down-pipeline")?;
+        set_debug_name(instance, device, self.up_pipeline, "bloom-up-pipeline")
+    }
+}
+
+// makes the last dispatch's writes visible to the next stage's reads
+unsafe fn cmd_shader_barrier(
+    device: &Device,
+    command_buffer: vk::CommandBuffer,
+    dst_stage_mask: vk::PipelineStageFlags,
+) {
+    let barrier = vk::MemoryBarrier::builder()
+        .src_access_mask(vk::AccessFlags::SHADER_WRITE)
+        .dst_access_mask(vk::AccessFlags::SHADER_READ | vk::AccessFlags::SHADER_WRITE);
+
+    device.cmd_pipeline_barrier(
+        command_buffer,
+        vk::PipelineStageFlags::COMPUTE_SHADER,
+        dst_stage_mask,
+        vk::DependencyFlags::empty(),
+        &[barrier],
+        &[] as &[vk::BufferMemoryBarrier],
+        &[] as &[vk::ImageMemoryBarrier],
+    );
+}
+
+#[allow(clippy::too_many_arguments)]
+unsafe fn create_targets(
+    instance: &Instance,
+    device: &Device,
+    phys_device: vk::PhysicalDevice,
+    allocator: &mut Allocator,
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    sampler: vk::Sampler,
+    scene_image_view: vk::ImageView,
+    extent: vk::Extent2D,
+) -> Result<Targets> {
+    let width = (extent.width / 2).max(1);
+    let height = (extent.height / 2).max(1);
+    let mip_levels = MAX_MIPS.min(width.min(height).ilog2() + 1);
+    let extents = (0..mip_levels)
+        .map(|m| vk::Extent2D { width: (width >> m).max(1), height: (height >> m).max(1) })
+        .collect::<Vec<_>>();
+
+    let mut targets = Targets::default();
+    for (i, name) in ["bloom-image", "bloom-blur-image"].into_iter().enumerate() {
+        let (image, image_memory) = create_image(
+            instance,
+            device,
+            phys_device,
+            allocator,
+            width,
+            height,
+            mip_levels,
+            1,
+            vk::SampleCountFlags::_1,
+            BLOOM_FORMAT,
+            vk::ImageTiling::OPTIMAL,
+            vk::ImageCreateFlags::empty(),
+            vk::ImageUsageFlags::STORAGE | vk::ImageUsageFlags::SAMPLED,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            name,
+        )?;
+        targets.images[i] = image;
+        targets.images_memory[i] = image_memory;
+        targets.image_views[i] = (0..mip_levels)
+            .map(|m| create_mip_image_view(device, image, m))
+            .collect::<Result<Vec<_>>>()?;
+    }
+
+    // the bright pass and each downsample, a blur each way for every mip, then each upsample
+    let set_count = mip_levels * 4 - 1;
+    let sampler_size = vk::DescriptorPoolSize::builder()
+        .type_(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+        .descriptor_count(set_count);
+    let storage_size = vk::DescriptorPoolSize::builder()
+        .type_(vk::DescriptorType::STORAGE_IMAGE)
+        .descriptor_count(set_count);
+
+    let pool_sizes = &[sampler_size, storage_size];
+    let info = vk::DescriptorPoolCreateInfo::builder()
+        .pool_sizes(pool_sizes)
+        .max_sets(set_count);
+    targets.descriptor_pool = device.create_descriptor_pool(&info, None)?;
+
+    let layouts = vec![descriptor_set_layout; set_count as usize];
+    let info = vk::DescriptorSetAllocateInfo::builder()
+        .descriptor_pool(targets.descriptor_pool)
+        .set_layouts(&layouts);
+    let mut descriptor_sets = device.allocate_descriptor_sets(&info)?.into_iter();
+
+    // the chain and the scratch image stay in the general layout, the scene is only ever read here
+    let mut dispatch = |source: vk::ImageView, source_layout: vk::ImageLayout, target: vk::ImageView, mip: u32| {
+        let descriptor_set = descriptor_sets.next().unwrap();
+        write_descriptor_set(device, descriptor_set, sampler, source, source_layout, target);
+        Dispatch { descriptor_set, extent: extents[mip as usize] }
+    };
+
+    let [chain, scratch] = &targets.image_views;
+    let general = vk::ImageLayout::GENERAL;
+    let down = (0..mip_levels)
+        .map(|m| match m {
+            0 => dispatch(scene_image_view, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL, chain[0], 0),
+            _ => dispatch(chain[m as usize - 1], general, chain[m as usize], m),
+        })
+        .collect();
+    let blur = (0..mip_levels as usize)
+        .map(|m| {
+            [
+                dispatch(chain[m], general, scratch[m], m as u32),
+                dispatch(scratch[m], general, chain[m], m as u32),
+            ]
+        })
+        .collect();
+    let up = (1..mip_levels)
+        .rev()
+        .map(|m| dispatch(chain[m as usize], general, chain[m as usize - 1], m - 1))
+        .collect();
+    (targets.down, targets.blur, targets.up) = (down, blur, up);
+
+    Ok(targets)
+}
+
+unsafe fn destroy_targets(device: &Device, allocator: &mut Allocator, targets: &Targets) {
+    // destroying the pool frees its sets
+    device.destroy_descriptor_pool(targets.descriptor_pool, None);
+    targets
+        .image_views
+        .iter()
+        .flatten()
+        .for_each(|v| device.destroy_image_view(*v, None));
+    for (image, image_memory) in targets.images.iter().zip(&targets.images_memory) {
+        device.destroy_image(*image, None);
+        allocator.free(device, *image_memory);
+    }
+}
+
+unsafe fn create_mip_image_view(device: &Device, image: vk::Image, mip_level: u32) -> Result<vk::ImageView> {
+    let subresource_range = vk::ImageSubresourceRange::builder()
+        .aspect_mask(vk::ImageAspectFlags::COLOR)
+        .base_mip_level(mip_level)
+        .level_count(1)
+        .base_array_layer(0)
+        .layer_count(1);
+
+    let info = vk::ImageViewCreateInfo::builder()
+        .image(image)
+        .view_type(vk::ImageViewType::_2D)
+        .format(BLOOM_FORMAT)
+        .subresource_range(subresource_range);
+
+    Ok(device.create_image_view(&info, None)?)
+}
+
+unsafe fn create_sampler(device: &Device) -> Result<vk::Sampler> {
+    let info = vk::SamplerCreateInfo::builder()
+        .mag_filter(vk::Filter::LINEAR)
+        .min_filter(vk::Filter::LINEAR)
+        .address_mode_u(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+        .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+        .address_mode_w(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+        .mipmap_mode(vk::SamplerMipmapMode::NEAREST);
+
+    Ok(device.create_sampler(&info, None)?)
+}
+
+unsafe fn create_descriptor_set_layout(device: &Device) -> Result<vk::DescriptorSetLayout> {
+    let source_binding = vk::DescriptorSetLayoutBinding::builder()
+        .binding(0)
+        .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+        .descriptor_count(1)
+        .stage_flags(vk::ShaderStageFlags::COMPUTE);
+
+    let target_binding = vk::DescriptorSetLayoutBinding::builder()
+        .binding(1)
+        .descriptor_type(vk::DescriptorType::STORAGE_IMAGE)
+        .descriptor_count(1)
+        .stage_flags(vk::ShaderStageFlags::COMPUTE);
+
+    let bindings = &[source_binding, target_binding];
+    let info = vk::DescriptorSetLayoutCreateInfo::builder()
+        .bindings(bindings);
+
+    Ok(device.create_descriptor_set_layout(&info, None)?)
+}
+
+unsafe fn write_descriptor_set(
+    device: &Device,
+    descriptor_set: vk::DescriptorSet,
+    sampler: vk::Sampler,
+    source: vk::ImageView,
+    source_layout: vk::ImageLayout,
+    target: vk::ImageView,
+) {
+    let source_info = vk::DescriptorImageInfo::builder()
+        .image_layout(source_layout)
+        .image_view(source)
+        .sampler(sampler);
+
+    let source_info = &[source_info];
+    let source_write = vk::WriteDescriptorSet::builder()
+        .dst_set(descriptor_set)
+        .dst_binding(0)
+        .dst_array_element(0)
+        .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+        .image_info(source_info);
+
+    let target_info = vk::DescriptorImageInfo::builder()
+        .image_layout(vk::ImageLayout::GENERAL)
+        .image_view(target);
+
+    let target_info = &[target_info];
+    let target_write = vk::WriteDescriptorSet::builder()
+        .dst_set(descriptor_set)
+        .dst_binding(1)
+        .dst_array_element(0)
+        .descriptor_type(vk::DescriptorType::STORAGE_IMAGE)
+        .image_info(target_info);
+
+    device.update_descriptor_sets(&[source_write, target_write], &[] as &[vk::CopyDescriptorSet]);
+}
+
+// the three share a layout, only the downsample reads the push constants
+unsafe fn create_pipelines(
+    device: &Device,
+    pipeline_cache: vk::PipelineCache,
+    descriptor_set_layout: vk::DescriptorSetLayout,
+) -> Result<(vk::PipelineLayout, vk::Pipeline, [vk::Pipeline; 2], vk::Pipeline)> {
+    let push_constant_range = vk::PushConstantRange::builder()
+        .stage_flags(vk::ShaderStageFlags::COMPUTE)
+        .offset(0)
+        .size(size_of::<BloomConstants>() as u32);
+
+    let set_layouts = &[descriptor_set_layout];
+    let push_constant_ranges = &[push_constant_range];
+    let layout_info = vk::PipelineLayoutCreateInfo::builder()
+        .set_layouts(set_layouts)
+        .push_constant_ranges(push_constant_ranges);
+    let pipeline_layout = device.create_pipeline_layout(&layout_info, None)?;
+
+    let modules = [shaders::BLOOM_DOWN_COMP, shaders::BLOOM_BLUR_COMP, shaders::BLOOM_UP_COMP]
+        .into_iter()
+        .map(|shader| shaders::load(shader).and_then(|bytecode| create_shader_module(device, &bytecode)))
+        .collect::<Vec<_>>();
+    let [Ok(down_module), Ok(blur_module), Ok(up_module)] = modules[..] else {
+        modules
+            .iter()
+            .flatten()
+            .for_each(|m| device.destroy_shader_module(*m, None));
+        device.destroy_pipeline_layout(pipeline_layout, None);
+        return Err(modules.into_iter().find_map(Result::err).unwrap());
+    };
+
+    // whether the blur goes down instead of across
+    let map_entries = &[vk::SpecializationMapEntry::builder()
+        .constant_id(0)
+        .offset(0)
+        .size(4)
+        .build()];
+    let across_data = vk::FALSE.to_ne_bytes();
+    let down_data = vk::TRUE.to_ne_bytes();
+    let across_info = vk::SpecializationInfo::builder()
+        .map_entries(map_entries)
+        .data(&across_data);
+    let down_info = vk::SpecializationInfo::builder()
+        .map_entries(map_entries)
+        .data(&down_data);
+
+    let stage = |module: vk::ShaderModule| {
+        vk::PipelineShaderStageCreateInfo::builder()
+            .stage(vk::ShaderStageFlags::COMPUTE)
+            .module(module)
+            .name(b"main\0")
+    };
+    let stages = [
+        stage(down_module),
+        stage(blur_module).specialization_info(&across_info),
+        stage(blur_module).specialization_info(&down_info),
+        stage(up_module),
+    ];
+    let infos = stages
+        .iter()
+        .map(|stage| {
+            vk::ComputePipelineCreateInfo::builder()
+                .stage(*stage)
+                .layout(pipeline_layout)
+        })
+        .collect::<Vec<_>>();
+
+    let result = device.create_compute_pipelines(pipeline_cache, &infos, None);
+    [down_module, blur_module, up_module]
+        .into_iter()
+        .for_each(|m| device.destroy_shader_module(m, None));
+
+    match result {
+        Ok((pipelines, _)) => Ok((pipeline_layout, pipelines[0], [pipelines[1], pipelines[2]], pipelines[3])),
+        Err(e) => {
+            device.destroy_pipeline_layout(pipeline_layout, None);
+            Err(e.into())
+        }
+    }
+}
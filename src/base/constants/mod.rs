@@ -1,8 +1,60 @@
 use vulkanalia::{
     Version,
-    vk::ExtensionName,
+    vk::{self, ExtensionName},
 };
 
 pub const PORTABILITY_MACOS_VERSION: Version = Version::new(1, 3, 216);
 pub const VALIDATION_ENABLED: bool = cfg!(debug_assertions);
-pub const VALIDATION_LAYER: ExtensionName = ExtensionName::from_bytes(b"VK_LAYER_KHRONOS_validation");
+// needed to present, headless rendering skips them
+pub const DEVICE_EXTENSIONS: &[ExtensionName] = &[vk::KHR_SWAPCHAIN_EXTENSION.name];
+pub const VALIDATION_LAYERS: &[ExtensionName] = &[ExtensionName::from_bytes(b"VK_LAYER_KHRONOS_validation")];
+pub const VALIDATION_MIN_SEVERITY: ValidationSeverity = ValidationSeverity::Verbose;
+pub const VALIDATION_TYPE_FLAGS: vk::DebugUtilsMessageTypeFlagsEXT = vk::DebugUtilsMessageTypeFlagsEXT::all();
+pub const SPIRV_MAGIC: u32 = 0x07230203;
+pub const SPIRV_MAX_ID_BOUND: u32 = 0x3f_ffff; // the spec's universal limit
+pub const MAX_FRAMES_IN_FLIGHT: usize = 2;
+pub const PUSH_CONSTANTS_SIZE: u32 = 4; // one texture index
+pub const MAX_OBJECTS: usize = 256; // including the loaded model
+pub const SCENE_PASSES: usize = 7; // depth prepass, main, outline, particles, skybox, debug lines and blended
+pub const MAX_BINDLESS_TEXTURES: u32 = 1024; // descriptors in the bindless array, most stay unbound
+pub const PARTICLE_POINT_SIZE: f32 = 3.0; // in pixels, points are 1 pixel without large points
+pub const COMPUTE_BUFFER_ELEMENTS: usize = 1024;
+pub const OFFSCREEN_FORMAT: vk::Format = vk::Format::R8G8B8A8_SRGB;
+// the scene's, in order of preference, the swapchain's if none can be rendered to
+pub const SCENE_FORMATS: &[vk::Format] = &[vk::Format::R16G16B16A16_SFLOAT, vk::Format::B10G11R11_UFLOAT_PACK32];
+// one texture array layer each, all the same size
+pub const TEXTURE_PATHS: &[&str] = &["resources/texture.png", "resources/checker.png", "resources/stripes.png"];
+pub const MODEL_PATH: &str = "resources/model.obj";
+// +x, -x, +y, -y, +z, -z
+pub const SKYBOX_PATHS: [&str; 6] = [
+    "resources/skybox/right.png",
+    "resources/skybox/left.png",
+    "resources/skybox/top.png",
+    "resources/skybox/bottom.png",
+    "resources/skybox/front.png",
+    "resources/skybox/back.png",
+];
+pub const PIPELINE_CACHE_PATH: &str = "pipeline_cache.bin";
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ValidationSeverity {
+    Verbose,
+    Info,
+    Warning,
+    Error,
+}
+
+impl ValidationSeverity {
+    // every severity at or above this one
+    pub fn flags(self) -> vk::DebugUtilsMessageSeverityFlagsEXT {
+        [
+            (Self::Verbose, vk::DebugUtilsMessageSeverityFlagsEXT::VERBOSE),
+            (Self::Info, vk::DebugUtilsMessageSeverityFlagsEXT::INFO),
+            (Self::Warning, vk::DebugUtilsMessageSeverityFlagsEXT::WARNING),
+            (Self::Error, vk::DebugUtilsMessageSeverityFlagsEXT::ERROR),
+        ]
+        .iter()
+        .filter(|(severity, _)| *severity >= self)
+        .fold(vk::DebugUtilsMessageSeverityFlagsEXT::empty(), |acc, (_, flag)| acc | *flag)
+    }
+}
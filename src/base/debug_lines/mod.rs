@@ -0,0 +1,382 @@
+use super::allocator::{Allocation, Allocator};
+use super::app::data::{Mat4, PipelineTarget, Vec3};
+use super::app::{create_buffer, create_shader_module, set_debug_name};
+use super::shaders;
+
+use anyhow::Result;
+
+use vulkanalia::{prelude::v1_0::*, Instance};
+
+use std::{
+    mem::{self, size_of},
+    ptr::copy_nonoverlapping as memcpy,
+};
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct LineVertex {
+    pub position: [f32; 3],
+    pub color: [f32; 3],
+}
+
+// host visible vertices for one swapchain image, grown when a frame doesn't fit
+#[derive(Copy, Clone, Debug, Default)]
+struct LineBuffer {
+    buffer: vk::Buffer,
+    buffer_memory: Allocation,
+    capacity: usize,
+    // vertices uploaded for the frame last recorded with this image
+    count: u32,
+}
+
+// lines collected over a frame then drawn over the opaque scene, depth tested but not written
+#[derive(Debug)]
+pub struct DebugLines {
+    pipeline_layout: vk::PipelineLayout,
+    pipeline: vk::Pipeline,
+    line_width: f32,
+    // the far plane is at 0 instead of 1
+    reverse_z: bool,
+    buffers: Vec<LineBuffer>,
+    // two per line, cleared on every upload
+    vertices: Vec<LineVertex>,
+}
+
+impl DebugLines {
+    /// # Safety
+    ///
+    /// The handles passed in have to be valid and from one device, which has to outlive what's made here.
+    pub unsafe fn new(
+        device: &Device,
+        pipeline_cache: vk::PipelineCache,
+        target: PipelineTarget,
+        samples: vk::SampleCountFlags,
+        reverse_z: bool,
+        line_width: f32,
+        image_count: usize,
+    ) -> Result<Self> {
+        let (pipeline_layout, pipeline) =
+            create_pipeline(device, pipeline_cache, target, samples, reverse_z, line_width)?;
+
+        Ok(Self {
+            pipeline_layout,
+            pipeline,
+            line_width,
+            reverse_z,
+            buffers: vec![LineBuffer::default(); image_count],
+            vertices: Vec::new(),
+        })
+    }
+
+    pub fn line(&mut self, a: Vec3, b: Vec3, color: Vec3) {
+        let color = color.into();
+        self.vertices.push(LineVertex { position: a.into(), color });
+        self.vertices.push(LineVertex { position: b.into(), color });
+    }
+
+    // the twelve edges of an axis aligned box
+    pub fn aabb(&mut self, min: Vec3, max: Vec3, color: Vec3) {
+        let corner = |i: usize| {
+            Vec3::new(
+                if i & 1 == 0 { min.x } else { max.x },
+                if i & 2 == 0 { min.y } else { max.y },
+                if i & 4 == 0 { min.z } else { max.z },
+            )
+        };
+
+        // each corner joins the corners one axis over
+        for i in 0..8 {
+            for axis in [1, 2, 4] {
+                if i & axis == 0 {
+                    self.line(corner(i), corner(i | axis), color);
+                }
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.vertices.len() / 2
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vertices.is_empty()
+    }
+
+    /// copies this frame's lines into the image's buffer and starts collecting the next frame's
+    ///
+    /// # Safety
+    ///
+    /// The buffer for `image_index` can't still be read by a pending frame.
+    pub unsafe fn upload(
+        &mut self,
+        instance: &Instance,
+        device: &Device,
+        phys_device: vk::PhysicalDevice,
+        allocator: &mut Allocator,
+        image_index: usize,
+    ) -> Result<()> {
+        let buffer = &mut self.buffers[image_index];
+        buffer.count = self.vertices.len() as u32;
+        if self.vertices.is_empty() {
+            return Ok(());
+        }
+
+        if buffer.capacity < self.vertices.len() {
+            // cleared first so a failed create leaves nothing to destroy twice
+            device.destroy_buffer(mem::take(&mut buffer.buffer), None);
+            allocator.free(device, mem::take(&mut buffer.buffer_memory));
+            buffer.capacity = 0;
+
+            // leave some room so a growing frame doesn't reallocate every time
+            let capacity = self.vertices.len().next_power_of_two();
+            (buffer.buffer, buffer.buffer_memory) = create_buffer(
+                instance,
+                device,
+                phys_device,
+                allocator,
+                (capacity * size_of::<LineVertex>()) as u64,
+                vk::BufferUsageFlags::VERTEX_BUFFER,
+                vk::MemoryPropertyFlags::HOST_COHERENT | vk::MemoryPropertyFlags::HOST_VISIBLE,
+                &format!("debug-lines-{}", image_index),
+            )?;
+            buffer.capacity = capacity;
+        }
+
+        let memory = device.map_memory(
+            buffer.buffer_memory.memory,
+            buffer.buffer_memory.offset,
+            (self.vertices.len() * size_of::<LineVertex>()) as u64,
+            vk::MemoryMapFlags::empty(),
+        )?;
+        memcpy(self.vertices.as_ptr(), memory.cast(), self.vertices.len());
+        device.unmap_memory(buffer.buffer_memory.memory);
+
+        self.vertices.clear();
+
+        Ok(())
+    }
+
+    /// records inside the scene subpass, draws what was last uploaded for the image
+    ///
+    /// # Safety
+    ///
+    /// `command_buffer` has to be recording inside the scene rendering.
+    pub unsafe fn record(
+        &self,
+        device: &Device,
+        command_buffer: vk::CommandBuffer,
+        image_index: usize,
+        view_proj: Mat4,
+    ) {
+        let buffer = &self.buffers[image_index];
+        if buffer.count == 0 {
+            return;
+        }
+
+        device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::GRAPHICS, self.pipeline);
+        device.cmd_bind_vertex_buffers(command_buffer, 0, &[buffer.buffer], &[0]);
+        device.cmd_push_constants(
+            command_buffer,
+            self.pipeline_layout,
+            vk::ShaderStageFlags::VERTEX,
+            0,
+            std::slice::from_raw_parts((&view_proj as *const Mat4).cast(), size_of::<Mat4>()),
+        );
+        device.cmd_draw(command_buffer, buffer.count, 1, 0, 0);
+    }
+
+    /// # Safety
+    ///
+    /// The old pipeline can't be in use by a pending command buffer.
+    pub unsafe fn recreate_pipeline(
+        &mut self,
+        device: &Device,
+        pipeline_cache: vk::PipelineCache,
+        target: PipelineTarget,
+        samples: vk::SampleCountFlags,
+    ) -> Result<()> {
+        device.destroy_pipeline(self.pipeline, None);
+        device.destroy_pipeline_layout(self.pipeline_layout, None);
+        (self.pipeline_layout, self.pipeline) =
+            create_pipeline(device, pipeline_cache, target, samples, self.reverse_z, self.line_width)?;
+
+        Ok(())
+    }
+
+    /// # Safety
+    ///
+    /// None of the old buffers can be in use by a pending command buffer.
+    pub unsafe fn recreate_buffers(&mut self, device: &Device, allocator: &mut Allocator, image_count: usize) {
+        self.destroy_buffers(device, allocator);
+        self.buffers = vec![LineBuffer::default(); image_count];
+    }
+
+    /// # Safety
+    ///
+    /// Nothing made here can still be in use on the gpu, and none of it can be used after.
+    pub unsafe fn destroy(&mut self, device: &Device, allocator: &mut Allocator) {
+        self.destroy_buffers(device, allocator);
+        device.destroy_pipeline(self.pipeline, None);
+        device.destroy_pipeline_layout(self.pipeline_layout, None);
+    }
+
+    // buffers that were never grown are null and have nothing to free
+    unsafe fn destroy_buffers(&mut self, device: &Device, allocator: &mut Allocator) {
+        for buffer in self.buffers.drain(..).filter(|b| !b.buffer.is_null()) {
+            device.destroy_buffer(buffer.buffer, None);
+            allocator.free(device, buffer.buffer_memory);
+        }
+    }
+
+    /// # Safety
+    ///
+    /// `device` has to be the one these were made with, created from `instance`.
+    pub unsafe fn set_debug_names(&self, instance: &Instance, device: &Device) -> Result<()> {
+        set_debug_name(instance, device, self.pipeline, "debug-lines-pipeline")
+    }
+}
+
+// a line list over the opaque scene, tested against its depth so lines hide behind what's in front of them
+unsafe fn create_pipeline(
+    device: &Device,
+    pipeline_cache: vk::PipelineCache,
+    target: PipelineTarget,
+    samples: vk::SampleCountFlags,
+    reverse_z: bool,
+    line_width: f32,
+) -> Result<(vk::PipelineLayout, vk::Pipeline)> {
+    let vert_shader_module = create_shader_module(device, &shaders::load(shaders::LINES_VERT)?)?;
+    let frag_shader_module = match shaders::load(shaders::LINES_FRAG)
+        .and_then(|bytecode| create_shader_module(device, &bytecode))
+    {
+        Ok(module) => module,
+        Err(e) => {
+            device.destroy_shader_module(vert_shader_module, None);
+            return Err(e);
+        }
+    };
+
+    let vert_stage = vk::PipelineShaderStageCreateInfo::builder()
+        .stage(vk::ShaderStageFlags::VERTEX)
+        .module(vert_shader_module)
+        .name(b"main\0");
+
+    let frag_stage = vk::PipelineShaderStageCreateInfo::builder()
+        .stage(vk::ShaderStageFlags::FRAGMENT)
+        .module(frag_shader_module)
+        .name(b"main\0");
+
+    let binding_description = vk::VertexInputBindingDescription::builder()
+        .binding(0)
+        .stride(size_of::<LineVertex>() as u32)
+        .input_rate(vk::VertexInputRate::VERTEX);
+    let position_description = vk::VertexInputAttributeDescription::builder()
+        .binding(0)
+        .location(0)
+        .format(vk::Format::R32G32B32_SFLOAT)
+        .offset(0);
+    let color_description = vk::VertexInputAttributeDescription::builder()
+        .binding(0)
+        .location(1)
+        .format(vk::Format::R32G32B32_SFLOAT)
+        .offset(size_of::<[f32; 3]>() as u32);
+
+    let binding_descriptions = &[binding_description];
+    let attribute_descriptions = &[position_description, color_description];
+    let vertex_input_state = vk::PipelineVertexInputStateCreateInfo::builder()
+        .vertex_binding_descriptions(binding_descriptions)
+        .vertex_attribute_descriptions(attribute_descriptions);
+
+    let input_assembly_state = vk::PipelineInputAssemblyStateCreateInfo::builder()
+        .topology(vk::PrimitiveTopology::LINE_LIST)
+        .primitive_restart_enable(false);
+
+    let viewport_state = vk::PipelineViewportStateCreateInfo::builder()
+        .viewport_count(1)
+        .scissor_count(1);
+
+    let dynamic_states = &[vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+    let dynamic_state = vk::PipelineDynamicStateCreateInfo::builder()
+        .dynamic_states(dynamic_states);
+
+    let rasterization_state = vk::PipelineRasterizationStateCreateInfo::builder()
+        .depth_clamp_enable(false)
+        .rasterizer_discard_enable(false)
+        .polygon_mode(vk::PolygonMode::FILL)
+        .line_width(line_width)
+        .cull_mode(vk::CullModeFlags::NONE)
+        .front_face(vk::FrontFace::COUNTER_CLOCKWISE)
+        .depth_bias_enable(false);
+
+    let multisample_state = vk::PipelineMultisampleStateCreateInfo::builder()
+        .sample_shading_enable(false)
+        .rasterization_samples(samples);
+
+    // equal depth too so lines along a surface's edges aren't lost to it
+    let depth_stencil_state = vk::PipelineDepthStencilStateCreateInfo::builder()
+        .depth_test_enable(true)
+        .depth_write_enable(false)
+        .depth_compare_op(if reverse_z { vk::CompareOp::GREATER_OR_EQUAL } else { vk::CompareOp::LESS_OR_EQUAL })
+        .depth_bounds_test_enable(false)
+        .stencil_test_enable(false);
+
+    let attachment = vk::PipelineColorBlendAttachmentState::builder()
+        .color_write_mask(vk::ColorComponentFlags::all())
+        .blend_enable(false);
+
+    let attachments = &[attachment];
+    let color_blend_state = vk::PipelineColorBlendStateCreateInfo::builder()
+        .logic_op_enable(false)
+        .logic_op(vk::LogicOp::COPY)
+        .attachments(attachments)
+        .blend_constants([0.0, 0.0, 0.0, 0.0]);
+
+    // the view projection, lines are given in world space
+    let push_constant_range = vk::PushConstantRange::builder()
+        .stage_flags(vk::ShaderStageFlags::VERTEX)
+        .offset(0)
+        .size(size_of::<Mat4>() as u32);
+
+    let push_constant_ranges = &[push_constant_range];
+    let layout_info = vk::PipelineLayoutCreateInfo::builder()
+        .push_constant_ranges(push_constant_ranges);
+    let pipeline_layout = match device.create_pipeline_layout(&layout_info, None) {
+        Ok(layout) => layout,
+        Err(e) => {
+            device.destroy_shader_module(vert_shader_module, None);
+            device.destroy_shader_module(frag_shader_module, None);
+            return Err(e.into());
+        }
+    };
+
+    let stages = &[vert_stage, frag_stage];
+    let mut rendering_info = target.rendering_info();
+    let mut info = vk::GraphicsPipelineCreateInfo::builder()
+        .stages(stages)
+        .vertex_input_state(&vertex_input_state)
+        .input_assembly_state(&input_assembly_state)
+        .viewport_state(&viewport_state)
+        .rasterization_state(&rasterization_state)
+        .multisample_state(&multisample_state)
+        .depth_stencil_state(&depth_stencil_state)
+        .color_blend_state(&color_blend_state)
+        .dynamic_state(&dynamic_state)
+        .layout(pipeline_layout)
+        .render_pass(target.render_pass())
+        .subpass(target.subpass());
+    if let Some(rendering_info) = &mut rendering_info {
+        info = info.push_next(rendering_info);
+    }
+
+    let result = device.create_graphics_pipelines(pipeline_cache, &[info], None);
+    device.destroy_shader_module(vert_shader_module, None);
+    device.destroy_shader_module(frag_shader_module, None);
+
+    match result {
+        Ok((pipelines, _)) => Ok((pipeline_layout, pipelines[0])),
+        Err(e) => {
+            device.destroy_pipeline_layout(pipeline_layout, None);
+            Err(e.into())
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use super::app::compute_tangents;
+use super::app::data::{BlendMode, Mat4, Vertex};
+
+use anyhow::{anyhow, Result};
+use log::*;
+
+use cgmath::{vec2, vec3, vec4, SquareMatrix};
+use gltf::{image::Format, material::AlphaMode, mesh::Mode};
+
+// what a primitive is drawn with, the texture is an index into the model's images
+#[derive(Copy, Clone, Debug)]
+pub struct Material {
+    pub base_color_texture: Option<usize>,
+    // linear, multiplies the texture in gltf but only stands in for a missing one here
+    pub base_color_factor: [f32; 4],
+    pub blend_mode: BlendMode,
+}
+
+// what primitives without a material get
+impl Default for Material {
+    fn default() -> Self {
+        Self { base_color_texture: None, base_color_factor: [1.0; 4], blend_mode: BlendMode::Opaque }
+    }
+}
+
+impl Material {
+    // the base color factor as one srgb texel, for materials without a texture
+    pub fn base_color_pixel(&self) -> [u8; 4] {
+        let encode = |c: f32| {
+            let c = c.clamp(0.0, 1.0);
+            let c = if c <= 0.0031308 { c * 12.92 } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 };
+            (c * 255.0).round() as u8
+        };
+        let [r, g, b, a] = self.base_color_factor;
+
+        [encode(r), encode(g), encode(b), (a.clamp(0.0, 1.0) * 255.0).round() as u8]
+    }
+}
+
+// one primitive of a mesh, with the transform of every node above it applied
+#[derive(Clone, Debug)]
+pub struct Primitive {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+    pub transform: Mat4,
+    // index into the model's materials, None for the default material
+    pub material: Option<usize>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Model {
+    pub primitives: Vec<Primitive>,
+    pub materials: Vec<Material>,
+    // decoded to rgba, the same layout load_png gives
+    pub images: Vec<(Vec<u8>, u32, u32)>,
+}
+
+// the default scene, or the first one if there's no default, with its buffers and images decoded
+pub fn load(path: &str) -> Result<Model> {
+    let (document, buffers, images) =
+        gltf::import(path).map_err(|e| anyhow!("Failed to load glTF model ({}): {}", path, e))?;
+    let scene = document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+        .ok_or_else(|| anyhow!("glTF model ({}) has no scenes.", path))?;
+
+    let mut model = Model {
+        primitives: Vec::new(),
+        materials: document.materials().map(|m| load_material(&m)).collect(),
+        images: images
+            .into_iter()
+            .enumerate()
+            .map(|(i, image)| to_rgba(image).map_err(|e| anyhow!("glTF model ({}) image {}: {}", path, i, e)))
+            .collect::<Result<Vec<_>>>()?,
+    };
+
+    let mut nodes = scene.nodes().map(|n| (n, Mat4::identity())).collect::<Vec<_>>();
+    while let Some((node, parent)) = nodes.pop() {
+        let transform = parent * Mat4::from(node.transform().matrix());
+        nodes.extend(node.children().map(|c| (c, transform)));
+
+        let Some(mesh) = node.mesh() else {
+            continue;
+        };
+
+        for primitive in mesh.primitives() {
+            if primitive.mode() != Mode::Triangles {
+                warn!("Skipping {:?} primitive in glTF model ({}), only triangles are drawn.", primitive.mode(), path);
+                continue;
+            }
+
+            let reader = primitive.reader(|b| buffers.get(b.index()).map(|d| d.0.as_slice()));
+            let Some(positions) = reader.read_positions() else {
+                warn!("Skipping a primitive without positions in glTF model ({}).", path);
+                continue;
+            };
+
+            let positions = positions.collect::<Vec<_>>();
+            let normals = reader.read_normals().map(|n| n.collect::<Vec<_>>()).unwrap_or_default();
+            let tex_coords = reader.read_tex_coords(0).map(|t| t.into_f32().collect::<Vec<_>>()).unwrap_or_default();
+            let colors = reader.read_colors(0).map(|c| c.into_rgb_f32().collect::<Vec<_>>()).unwrap_or_default();
+            let tangents = reader.read_tangents().map(|t| t.collect::<Vec<_>>());
+
+            // gltf has v going down already
+            let mut vertices = positions
+                .iter()
+                .enumerate()
+                .map(|(i, p)| {
+                    let normal = normals.get(i).copied().unwrap_or_default();
+                    let tex_coord = tex_coords.get(i).copied().unwrap_or_default();
+                    let color = colors.get(i).copied().unwrap_or([1.0; 3]);
+                    Vertex::new(vec3(p[0], p[1], p[2]), color.into(), vec2(tex_coord[0], tex_coord[1]), normal.into())
+                })
+                .collect::<Vec<_>>();
+
+            // non-indexed primitives draw every vertex in order
+            let indices = match reader.read_indices() {
+                Some(indices) => indices.into_u32().collect(),
+                None => (0..vertices.len() as u32).collect::<Vec<_>>(),
+            };
+
+            // generated like obj models' when the file doesn't have them
+            match tangents {
+                Some(tangents) => vertices
+                    .iter_mut()
+                    .zip(tangents)
+                    .for_each(|(v, t)| v.tangent = vec4(t[0], t[1], t[2], t[3])),
+                None => compute_tangents(&mut vertices, &indices),
+            }
+
+            model.primitives.push(Primitive {
+                vertices,
+                indices,
+                transform,
+                material: primitive.material().index(),
+            });
+        }
+    }
+
+    info!(
+        "Loaded glTF model ({}) with {} primitives, {} materials and {} images.",
+        path,
+        model.primitives.len(),
+        model.materials.len(),
+        model.images.len(),
+    );
+
+    Ok(model)
+}
+
+fn load_material(material: &gltf::Material) -> Material {
+    let pbr = material.pbr_metallic_roughness();
+    if pbr.base_color_texture().is_some_and(|t| t.tex_coord() != 0) {
+        warn!("Material {:?} samples a second uv set, the first is used.", material.name());
+    }
+
+    Material {
+        base_color_texture: pbr.base_color_texture().map(|t| t.texture().source().index()),
+        base_color_factor: pbr.base_color_factor(),
+        // masked materials are drawn opaque, there's no alpha test
+        blend_mode: match material.alpha_mode() {
+            AlphaMode::Blend => BlendMode::AlphaBlend,
+            AlphaMode::Opaque | AlphaMode::Mask => BlendMode::Opaque,
+        },
+    }
+}
+
+// 16 bit channels keep their high byte, float images aren't used for base colors
+fn to_rgba(image: gltf::image::Data) -> Result<(Vec<u8>, u32, u32)> {
+    let pixels = &image.pixels;
+    let pixels = match image.format {
+        Format::R8G8B8A8 => image.pixels,
+        Format::R8G8B8 => pixels.chunks_exact(3).flat_map(|p| [p[0], p[1], p[2], 255]).collect(),
+        Format::R8G8 => pixels.chunks_exact(2).flat_map(|p| [p[0], p[1], 0, 255]).collect(),
+        Format::R8 => pixels.iter().flat_map(|p| [*p, *p, *p, 255]).collect(),
+        Format::R16G16B16A16 => pixels.chunks_exact(8).flat_map(|p| [p[1], p[3], p[5], p[7]]).collect(),
+        Format::R16G16B16 => pixels.chunks_exact(6).flat_map(|p| [p[1], p[3], p[5], 255]).collect(),
+        Format::R16G16 => pixels.chunks_exact(4).flat_map(|p| [p[1], p[3], 0, 255]).collect(),
+        Format::R16 => pixels.chunks_exact(2).flat_map(|p| [p[1], p[1], p[1], 255]).collect(),
+        format => return Err(anyhow!("{:?} images aren't supported.", format)),
+    };
+
+    Ok((pixels, image.width, image.height))
+}
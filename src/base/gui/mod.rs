@@ -0,0 +1,675 @@
+use super::allocator::{Allocation, Allocator};
+use super::app::data::{PipelineTarget, Uploader};
+use super::app::{
+    copy_buffer_to_image, create_buffer, create_image, create_image_view, create_shader_module, set_debug_name,
+    transition_image_layout, FormatPreference,
+};
+use super::shaders;
+
+use anyhow::Result;
+
+use imgui::{Context, DrawCmd, DrawVert, FontSource, TextureId, Ui};
+use imgui_winit_support::{HiDpiMode, WinitPlatform};
+
+use winit::{event::Event, window::Window};
+
+use vulkanalia::{prelude::v1_0::*, Instance};
+
+use std::{
+    mem::{size_of, size_of_val},
+    ptr::copy_nonoverlapping as memcpy,
+    time::Duration,
+};
+
+// a draw command with its offsets into the whole frame's buffers
+#[derive(Copy, Clone, Debug)]
+struct GuiDraw {
+    clip_rect: [f32; 4],
+    index_count: u32,
+    first_index: u32,
+    vertex_offset: i32,
+}
+
+// host visible buffers for one swapchain image, grown when a frame doesn't fit
+#[derive(Copy, Clone, Debug, Default)]
+struct GuiBuffers {
+    vertex_buffer: vk::Buffer,
+    vertex_buffer_memory: Allocation,
+    vertex_capacity: usize,
+    index_buffer: vk::Buffer,
+    index_buffer_memory: Allocation,
+    index_capacity: usize,
+}
+
+// imgui drawn over the post-processed image in the last subpass
+#[derive(Debug)]
+pub struct Gui {
+    pub context: Context,
+    pub platform: WinitPlatform,
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    descriptor_pool: vk::DescriptorPool,
+    descriptor_set: vk::DescriptorSet,
+    pipeline_layout: vk::PipelineLayout,
+    pipeline: vk::Pipeline,
+    font_image: vk::Image,
+    font_image_memory: Allocation,
+    font_image_view: vk::ImageView,
+    font_sampler: vk::Sampler,
+    buffers: Vec<GuiBuffers>,
+    // copied out of imgui's draw data so it doesn't have to outlive the frame
+    vertices: Vec<DrawVert>,
+    indices: Vec<u16>,
+    draws: Vec<GuiDraw>,
+    display_pos: [f32; 2],
+    display_size: [f32; 2],
+}
+
+impl Gui {
+    /// # Safety
+    ///
+    /// The handles passed in have to be valid and from one device, which has to outlive what's made here.
+    #[allow(clippy::too_many_arguments)]
+    pub unsafe fn new(
+        window: &Window,
+        instance: &Instance,
+        device: &Device,
+        phys_device: vk::PhysicalDevice,
+        allocator: &mut Allocator,
+        uploader: &Uploader,
+        pipeline_cache: vk::PipelineCache,
+        target: PipelineTarget,
+        swapchain_format: vk::Format,
+        image_count: usize,
+    ) -> Result<Self> {
+        let mut context = Context::create();
+        context.set_ini_filename(None);
+
+        let mut platform = WinitPlatform::init(&mut context);
+        platform.attach_window(context.io_mut(), window, HiDpiMode::Default);
+
+        context.fonts().add_font(&[FontSource::DefaultFontData { config: None }]);
+        let (font_image, font_image_memory) =
+            create_font_image(instance, device, phys_device, allocator, uploader, &mut context)?;
+        // only one texture, every draw samples the font
+        context.fonts().tex_id = TextureId::new(0);
+
+        let font_image_view =
+            create_image_view(device, font_image, vk::Format::R8G8B8A8_UNORM, vk::ImageAspectFlags::COLOR, 1)?;
+        let font_sampler = create_font_sampler(device)?;
+
+        let descriptor_set_layout = create_descriptor_set_layout(device)?;
+        let (descriptor_pool, descriptor_set) =
+            create_descriptor_set(device, descriptor_set_layout, font_image_view, font_sampler)?;
+        let (pipeline_layout, pipeline) =
+            create_pipeline(device, pipeline_cache, target, descriptor_set_layout, swapchain_format)?;
+
+        Ok(Self {
+            context,
+            platform,
+            descriptor_set_layout,
+            descriptor_pool,
+            descriptor_set,
+            pipeline_layout,
+            pipeline,
+            font_image,
+            font_image_memory,
+            font_image_view,
+            font_sampler,
+            buffers: vec![GuiBuffers::default(); image_count],
+            vertices: Vec::new(),
+            indices: Vec::new(),
+            draws: Vec::new(),
+            display_pos: [0.0, 0.0],
+            display_size: [0.0, 0.0],
+        })
+    }
+
+    pub fn handle_event<T>(&mut self, window: &Window, event: &Event<T>) {
+        self.platform.handle_event(self.context.io_mut(), window, event);
+    }
+
+    // builds the ui for the next rendered frame
+    pub fn frame(&mut self, window: &Window, delta: Duration, build: impl FnOnce(&mut Ui)) -> Result<()> {
+        self.context.io_mut().update_delta_time(delta);
+        self.platform.prepare_frame(self.context.io_mut(), window)?;
+
+        let ui = self.context.new_frame();
+        build(ui);
+        self.platform.prepare_render(ui, window);
+
+        let draw_data = self.context.render();
+        self.vertices.clear();
+        self.indices.clear();
+        self.draws.clear();
+        self.display_pos = draw_data.display_pos;
+        self.display_size = draw_data.display_size;
+
+        for draw_list in draw_data.draw_lists() {
+            let (vertex_base, index_base) = (self.vertices.len(), self.indices.len());
+            self.vertices.extend_from_slice(draw_list.vtx_buffer());
+            self.indices.extend_from_slice(draw_list.idx_buffer());
+
+            // state is never changed by the ui so resets and callbacks have nothing to do
+            for command in draw_list.commands() {
+                if let DrawCmd::Elements { count, cmd_params } = command {
+                    self.draws.push(GuiDraw {
+                        clip_rect: cmd_params.clip_rect,
+                        index_count: count as u32,
+                        first_index: (index_base + cmd_params.idx_offset) as u32,
+                        vertex_offset: (vertex_base + cmd_params.vtx_offset) as i32,
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// copies the last built frame into the image's buffers, must run before recording
+    ///
+    /// # Safety
+    ///
+    /// The buffer for `image_index` can't still be read by a pending frame.
+    pub unsafe fn upload(
+        &mut self,
+        instance: &Instance,
+        device: &Device,
+        phys_device: vk::PhysicalDevice,
+        allocator: &mut Allocator,
+        image_index: usize,
+    ) -> Result<()> {
+        if self.draws.is_empty() {
+            return Ok(());
+        }
+
+        let buffers = &mut self.buffers[image_index];
+
+        if buffers.vertex_capacity < self.vertices.len() {
+            device.destroy_buffer(buffers.vertex_buffer, None);
+            allocator.free(device, buffers.vertex_buffer_memory);
+
+            // leave some room so a growing ui doesn't reallocate every frame
+            let capacity = self.vertices.len().next_power_of_two();
+            (buffers.vertex_buffer, buffers.vertex_buffer_memory) = create_buffer(
+                instance,
+                device,
+                phys_device,
+                allocator,
+                (capacity * size_of::<DrawVert>()) as u64,
+                vk::BufferUsageFlags::VERTEX_BUFFER,
+                vk::MemoryPropertyFlags::HOST_COHERENT | vk::MemoryPropertyFlags::HOST_VISIBLE,
+                &format!("gui-vertex-buffer-{}", image_index),
+            )?;
+            buffers.vertex_capacity = capacity;
+        }
+
+        if buffers.index_capacity < self.indices.len() {
+            device.destroy_buffer(buffers.index_buffer, None);
+            allocator.free(device, buffers.index_buffer_memory);
+
+            let capacity = self.indices.len().next_power_of_two();
+            (buffers.index_buffer, buffers.index_buffer_memory) = create_buffer(
+                instance,
+                device,
+                phys_device,
+                allocator,
+                (capacity * size_of::<u16>()) as u64,
+                vk::BufferUsageFlags::INDEX_BUFFER,
+                vk::MemoryPropertyFlags::HOST_COHERENT | vk::MemoryPropertyFlags::HOST_VISIBLE,
+                &format!("gui-index-buffer-{}", image_index),
+            )?;
+            buffers.index_capacity = capacity;
+        }
+
+        let memory = device.map_memory(
+            buffers.vertex_buffer_memory.memory,
+            buffers.vertex_buffer_memory.offset,
+            (self.vertices.len() * size_of::<DrawVert>()) as u64,
+            vk::MemoryMapFlags::empty(),
+        )?;
+        memcpy(self.vertices.as_ptr(), memory.cast(), self.vertices.len());
+        device.unmap_memory(buffers.vertex_buffer_memory.memory);
+
+        let memory = device.map_memory(
+            buffers.index_buffer_memory.memory,
+            buffers.index_buffer_memory.offset,
+            (self.indices.len() * size_of::<u16>()) as u64,
+            vk::MemoryMapFlags::empty(),
+        )?;
+        memcpy(self.indices.as_ptr(), memory.cast(), self.indices.len());
+        device.unmap_memory(buffers.index_buffer_memory.memory);
+
+        Ok(())
+    }
+
+    /// records inside the last subpass, leaves the scissor set to the last clip rect
+    ///
+    /// # Safety
+    ///
+    /// `command_buffer` has to be recording inside the post rendering.
+    pub unsafe fn record(
+        &self,
+        device: &Device,
+        command_buffer: vk::CommandBuffer,
+        image_index: usize,
+        extent: vk::Extent2D,
+    ) {
+        if self.draws.is_empty() {
+            return;
+        }
+
+        let buffers = &self.buffers[image_index];
+
+        device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::GRAPHICS, self.pipeline);
+        device.cmd_bind_descriptor_sets(
+            command_buffer,
+            vk::PipelineBindPoint::GRAPHICS,
+            self.pipeline_layout,
+            0,
+            &[self.descriptor_set],
+            &[],
+        );
+        device.cmd_bind_vertex_buffers(command_buffer, 0, &[buffers.vertex_buffer], &[0]);
+        device.cmd_bind_index_buffer(command_buffer, buffers.index_buffer, 0, vk::IndexType::UINT16);
+
+        // maps the display rect to clip space
+        let scale = [2.0 / self.display_size[0], 2.0 / self.display_size[1]];
+        let translate = [-1.0 - self.display_pos[0] * scale[0], -1.0 - self.display_pos[1] * scale[1]];
+        let constants = [scale[0], scale[1], translate[0], translate[1]];
+        device.cmd_push_constants(
+            command_buffer,
+            self.pipeline_layout,
+            vk::ShaderStageFlags::VERTEX,
+            0,
+            std::slice::from_raw_parts(constants.as_ptr().cast(), size_of_val(&constants)),
+        );
+
+        // the display covers the whole framebuffer, however many pixels that is
+        let clip_scale = [extent.width as f32 / self.display_size[0], extent.height as f32 / self.display_size[1]];
+        for draw in &self.draws {
+            // clip rects are in display coordinates, scissors in framebuffer pixels
+            let [left, top, right, bottom] = draw.clip_rect;
+            let left = ((left - self.display_pos[0]) * clip_scale[0]).max(0.0);
+            let top = ((top - self.display_pos[1]) * clip_scale[1]).max(0.0);
+            let right = ((right - self.display_pos[0]) * clip_scale[0]).min(extent.width as f32);
+            let bottom = ((bottom - self.display_pos[1]) * clip_scale[1]).min(extent.height as f32);
+            if right <= left || bottom <= top {
+                continue;
+            }
+
+            let scissor = vk::Rect2D::builder()
+                .offset(vk::Offset2D { x: left as i32, y: top as i32 })
+                .extent(vk::Extent2D { width: (right - left) as u32, height: (bottom - top) as u32 });
+            device.cmd_set_scissor(command_buffer, 0, &[scissor]);
+            device.cmd_draw_indexed(command_buffer, draw.index_count, 1, draw.first_index, draw.vertex_offset, 0);
+        }
+    }
+
+    /// the pipeline is built for the last subpass, rebuilt with it when the swapchain format changes
+    ///
+    /// # Safety
+    ///
+    /// The old pipeline can't be in use by a pending command buffer.
+    pub unsafe fn recreate_pipeline(
+        &mut self,
+        device: &Device,
+        pipeline_cache: vk::PipelineCache,
+        target: PipelineTarget,
+        swapchain_format: vk::Format,
+    ) -> Result<()> {
+        self.destroy_pipeline(device);
+        (self.pipeline_layout, self.pipeline) =
+            create_pipeline(device, pipeline_cache, target, self.descriptor_set_layout, swapchain_format)?;
+
+        Ok(())
+    }
+
+    /// one set of buffers per swapchain image, created on first use
+    ///
+    /// # Safety
+    ///
+    /// None of the old buffers can be in use by a pending command buffer.
+    pub unsafe fn recreate_buffers(&mut self, device: &Device, allocator: &mut Allocator, image_count: usize) {
+        self.destroy_buffers(device, allocator);
+        self.buffers = vec![GuiBuffers::default(); image_count];
+    }
+
+    /// # Safety
+    ///
+    /// Nothing made here can still be in use on the gpu, and none of it can be used after.
+    pub unsafe fn destroy(&mut self, device: &Device, allocator: &mut Allocator) {
+        self.destroy_buffers(device, allocator);
+        self.destroy_pipeline(device);
+        device.destroy_descriptor_pool(self.descriptor_pool, None);
+        device.destroy_descriptor_set_layout(self.descriptor_set_layout, None);
+        device.destroy_sampler(self.font_sampler, None);
+        device.destroy_image_view(self.font_image_view, None);
+        device.destroy_image(self.font_image, None);
+        allocator.free(device, self.font_image_memory);
+    }
+
+    unsafe fn destroy_pipeline(&mut self, device: &Device) {
+        device.destroy_pipeline(self.pipeline, None);
+        device.destroy_pipeline_layout(self.pipeline_layout, None);
+    }
+
+    // null buffers were never created, destroying them is a no-op
+    unsafe fn destroy_buffers(&mut self, device: &Device, allocator: &mut Allocator) {
+        for buffers in self.buffers.drain(..) {
+            device.destroy_buffer(buffers.vertex_buffer, None);
+            allocator.free(device, buffers.vertex_buffer_memory);
+            device.destroy_buffer(buffers.index_buffer, None);
+            allocator.free(device, buffers.index_buffer_memory);
+        }
+    }
+
+    /// # Safety
+    ///
+    /// `device` has to be the one these were made with, created from `instance`.
+    pub unsafe fn set_debug_names(&self, instance: &Instance, device: &Device) -> Result<()> {
+        set_debug_name(instance, device, self.pipeline, "gui-pipeline")?;
+        set_debug_name(instance, device, self.font_image, "gui-font-image")
+    }
+}
+
+unsafe fn create_font_image(
+    instance: &Instance,
+    device: &Device,
+    phys_device: vk::PhysicalDevice,
+    allocator: &mut Allocator,
+    uploader: &Uploader,
+    context: &mut Context,
+) -> Result<(vk::Image, Allocation)> {
+    let fonts = context.fonts();
+    let texture = fonts.build_rgba32_texture();
+    let size = texture.data.len() as u64;
+
+    let (staging_buffer, staging_buffer_memory) = create_buffer(
+        instance,
+        device,
+        phys_device,
+        allocator,
+        size,
+        vk::BufferUsageFlags::TRANSFER_SRC,
+        vk::MemoryPropertyFlags::HOST_COHERENT | vk::MemoryPropertyFlags::HOST_VISIBLE,
+        "gui-font-staging",
+    )?;
+
+    let memory = device.map_memory(
+        staging_buffer_memory.memory,
+        staging_buffer_memory.offset,
+        size,
+        vk::MemoryMapFlags::empty(),
+    )?;
+    memcpy(texture.data.as_ptr(), memory.cast(), texture.data.len());
+    device.unmap_memory(staging_buffer_memory.memory);
+
+    let (font_image, font_image_memory) = create_image(
+        instance,
+        device,
+        phys_device,
+        allocator,
+        texture.width,
+        texture.height,
+        1,
+        1,
+        vk::SampleCountFlags::_1,
+        vk::Format::R8G8B8A8_UNORM,
+        vk::ImageTiling::OPTIMAL,
+        vk::ImageCreateFlags::empty(),
+        vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST,
+        vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        "gui-font",
+    )?;
+
+    transition_image_layout(
+        device,
+        uploader.transfer_pool,
+        uploader.transfer_queue,
+        font_image,
+        vk::Format::R8G8B8A8_UNORM,
+        vk::ImageLayout::UNDEFINED,
+        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+        0,
+        1,
+        0,
+        1,
+    )?;
+
+    copy_buffer_to_image(device, uploader, staging_buffer, font_image, texture.width, texture.height, 1, 1)?;
+
+    // the copy leaves the image owned by the graphics family
+    transition_image_layout(
+        device,
+        uploader.graphics_pool,
+        uploader.graphics_queue,
+        font_image,
+        vk::Format::R8G8B8A8_UNORM,
+        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+        vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        0,
+        1,
+        0,
+        1,
+    )?;
+
+    device.destroy_buffer(staging_buffer, None);
+    allocator.free(device, staging_buffer_memory);
+
+    Ok((font_image, font_image_memory))
+}
+
+unsafe fn create_font_sampler(device: &Device) -> Result<vk::Sampler> {
+    let info = vk::SamplerCreateInfo::builder()
+        .mag_filter(vk::Filter::LINEAR)
+        .min_filter(vk::Filter::LINEAR)
+        .address_mode_u(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+        .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+        .address_mode_w(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+        .mipmap_mode(vk::SamplerMipmapMode::LINEAR)
+        .max_lod(1.0);
+
+    Ok(device.create_sampler(&info, None)?)
+}
+
+unsafe fn create_descriptor_set_layout(device: &Device) -> Result<vk::DescriptorSetLayout> {
+    let font_binding = vk::DescriptorSetLayoutBinding::builder()
+        .binding(0)
+        .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+        .descriptor_count(1)
+        .stage_flags(vk::ShaderStageFlags::FRAGMENT);
+
+    let bindings = &[font_binding];
+    let info = vk::DescriptorSetLayoutCreateInfo::builder()
+        .bindings(bindings);
+
+    Ok(device.create_descriptor_set_layout(&info, None)?)
+}
+
+unsafe fn create_descriptor_set(
+    device: &Device,
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    font_image_view: vk::ImageView,
+    font_sampler: vk::Sampler,
+) -> Result<(vk::DescriptorPool, vk::DescriptorSet)> {
+    let font_size = vk::DescriptorPoolSize::builder()
+        .type_(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+        .descriptor_count(1);
+
+    let pool_sizes = &[font_size];
+    let info = vk::DescriptorPoolCreateInfo::builder()
+        .pool_sizes(pool_sizes)
+        .max_sets(1);
+    let descriptor_pool = device.create_descriptor_pool(&info, None)?;
+
+    let layouts = &[descriptor_set_layout];
+    let info = vk::DescriptorSetAllocateInfo::builder()
+        .descriptor_pool(descriptor_pool)
+        .set_layouts(layouts);
+    let descriptor_set = device.allocate_descriptor_sets(&info)?[0];
+
+    let info = vk::DescriptorImageInfo::builder()
+        .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+        .image_view(font_image_view)
+        .sampler(font_sampler);
+
+    let image_info = &[info];
+    let font_write = vk::WriteDescriptorSet::builder()
+        .dst_set(descriptor_set)
+        .dst_binding(0)
+        .dst_array_element(0)
+        .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+        .image_info(image_info);
+
+    device.update_descriptor_sets(&[font_write], &[] as &[vk::CopyDescriptorSet]);
+
+    Ok((descriptor_pool, descriptor_set))
+}
+
+// alpha blended triangles in the single sampled post-processing subpass
+unsafe fn create_pipeline(
+    device: &Device,
+    pipeline_cache: vk::PipelineCache,
+    target: PipelineTarget,
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    swapchain_format: vk::Format,
+) -> Result<(vk::PipelineLayout, vk::Pipeline)> {
+    let vert_shader_module = create_shader_module(device, &shaders::load(shaders::GUI_VERT)?)?;
+    let frag_shader_module = match shaders::load(shaders::GUI_FRAG)
+        .and_then(|bytecode| create_shader_module(device, &bytecode))
+    {
+        Ok(module) => module,
+        Err(e) => {
+            device.destroy_shader_module(vert_shader_module, None);
+            return Err(e);
+        }
+    };
+
+    // SRGB_TARGET in gui.vert
+    let srgb_target = if FormatPreference::Srgb.matches(swapchain_format) { vk::TRUE } else { vk::FALSE };
+    let map_entries = &[vk::SpecializationMapEntry::builder()
+        .constant_id(0)
+        .offset(0)
+        .size(4)];
+    let data = srgb_target.to_ne_bytes();
+    let specialization_info = vk::SpecializationInfo::builder()
+        .map_entries(map_entries)
+        .data(&data);
+
+    let vert_stage = vk::PipelineShaderStageCreateInfo::builder()
+        .stage(vk::ShaderStageFlags::VERTEX)
+        .module(vert_shader_module)
+        .name(b"main\0")
+        .specialization_info(&specialization_info);
+
+    let frag_stage = vk::PipelineShaderStageCreateInfo::builder()
+        .stage(vk::ShaderStageFlags::FRAGMENT)
+        .module(frag_shader_module)
+        .name(b"main\0");
+
+    // position, uv and packed color
+    let binding_description = vk::VertexInputBindingDescription::builder()
+        .binding(0)
+        .stride(size_of::<DrawVert>() as u32)
+        .input_rate(vk::VertexInputRate::VERTEX);
+    let attribute_descriptions = [
+        (0, vk::Format::R32G32_SFLOAT, 0),
+        (1, vk::Format::R32G32_SFLOAT, 8),
+        (2, vk::Format::R8G8B8A8_UNORM, 16),
+    ]
+    .map(|(location, format, offset)| {
+        vk::VertexInputAttributeDescription::builder()
+            .binding(0)
+            .location(location)
+            .format(format)
+            .offset(offset)
+            .build()
+    });
+
+    let binding_descriptions = &[binding_description];
+    let vertex_input_state = vk::PipelineVertexInputStateCreateInfo::builder()
+        .vertex_binding_descriptions(binding_descriptions)
+        .vertex_attribute_descriptions(&attribute_descriptions);
+
+    let input_assembly_state = vk::PipelineInputAssemblyStateCreateInfo::builder()
+        .topology(vk::PrimitiveTopology::TRIANGLE_LIST)
+        .primitive_restart_enable(false);
+
+    let viewport_state = vk::PipelineViewportStateCreateInfo::builder()
+        .viewport_count(1)
+        .scissor_count(1);
+
+    let dynamic_states = &[vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+    let dynamic_state = vk::PipelineDynamicStateCreateInfo::builder()
+        .dynamic_states(dynamic_states);
+
+    let rasterization_state = vk::PipelineRasterizationStateCreateInfo::builder()
+        .depth_clamp_enable(false)
+        .rasterizer_discard_enable(false)
+        .polygon_mode(vk::PolygonMode::FILL)
+        .line_width(1.0)
+        .cull_mode(vk::CullModeFlags::NONE)
+        .front_face(vk::FrontFace::COUNTER_CLOCKWISE)
+        .depth_bias_enable(false);
+
+    let multisample_state = vk::PipelineMultisampleStateCreateInfo::builder()
+        .sample_shading_enable(false)
+        .rasterization_samples(vk::SampleCountFlags::_1);
+
+    let attachment = vk::PipelineColorBlendAttachmentState::builder()
+        .color_write_mask(vk::ColorComponentFlags::all())
+        .blend_enable(true)
+        .src_color_blend_factor(vk::BlendFactor::SRC_ALPHA)
+        .dst_color_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+        .color_blend_op(vk::BlendOp::ADD)
+        .src_alpha_blend_factor(vk::BlendFactor::ONE)
+        .dst_alpha_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+        .alpha_blend_op(vk::BlendOp::ADD);
+
+    let attachments = &[attachment];
+    let color_blend_state = vk::PipelineColorBlendStateCreateInfo::builder()
+        .logic_op_enable(false)
+        .logic_op(vk::LogicOp::COPY)
+        .attachments(attachments)
+        .blend_constants([0.0, 0.0, 0.0, 0.0]);
+
+    // scale and translate from display to clip space
+    let push_constant_range = vk::PushConstantRange::builder()
+        .stage_flags(vk::ShaderStageFlags::VERTEX)
+        .offset(0)
+        .size(4 * size_of::<f32>() as u32);
+
+    let set_layouts = &[descriptor_set_layout];
+    let push_constant_ranges = &[push_constant_range];
+    let layout_info = vk::PipelineLayoutCreateInfo::builder()
+        .set_layouts(set_layouts)
+        .push_constant_ranges(push_constant_ranges);
+    let pipeline_layout = device.create_pipeline_layout(&layout_info, None)?;
+
+    let stages = &[vert_stage, frag_stage];
+    let mut rendering_info = target.rendering_info();
+    let mut info = vk::GraphicsPipelineCreateInfo::builder()
+        .stages(stages)
+        .vertex_input_state(&vertex_input_state)
+        .input_assembly_state(&input_assembly_state)
+        .viewport_state(&viewport_state)
+        .rasterization_state(&rasterization_state)
+        .multisample_state(&multisample_state)
+        .color_blend_state(&color_blend_state)
+        .dynamic_state(&dynamic_state)
+        .layout(pipeline_layout)
+        .render_pass(target.render_pass())
+        .subpass(target.subpass());
+    if let Some(rendering_info) = &mut rendering_info {
+        info = info.push_next(rendering_info);
+    }
+
+    let pipeline = device
+        .create_graphics_pipelines(pipeline_cache, &[info], None)?
+        .0[0];
+
+    device.destroy_shader_module(vert_shader_module, None);
+    device.destroy_shader_module(frag_shader_module, None);
+
+    Ok((pipeline_layout, pipeline))
+}
@@ -1,186 +1,8034 @@
+pub mod allocator;
+pub mod bindless;
+pub mod bloom;
 pub mod constants;
+pub mod debug_lines;
+pub mod gltf_model;
+pub mod gui;
+pub mod particles;
+pub mod passes;
+pub mod picking;
+pub mod shaders;
+pub mod shadows;
+pub mod skybox;
 
 pub mod app {
     /*
      * imports
      */
 
+    use super::allocator::{Allocation, Allocator};
+    use super::bindless::BindlessTextures;
+    use super::bloom::{self, Bloom, BLOOM_FORMAT};
     use super::constants::*;
-    use data::QueueFamilyIndices;
+    use super::debug_lines::DebugLines;
+    use super::gltf_model::{self, Material};
+    use super::gui::Gui;
+    use super::particles::{Particle, Particles};
+    use super::passes::{ClosurePass, Frame, PassScheduler, RenderPassDesc, Target};
+    use super::picking::{Picker, BACKGROUND_ID};
+    use super::shaders::{self, ShaderWatcher};
+    use super::shadows::ShadowMap;
+    use super::skybox::{self, Skybox};
+    use data::{
+        Aabb, BlendMode, Camera, DeviceCapabilities, Frustum, Index, InstanceData, Light, LightUniform, Mat4,
+        ObjectUniform, PipelineTarget, PostConstants, PostEffect, QueueFamilyIndices, RenderObject, SwapchainSupport,
+        UniformBufferObject, Uploader, Vec3, Vertex,
+    };
 
     use thiserror::Error;
     use anyhow::{anyhow, Result};
     use log::*;
+
+    use rayon::prelude::*;
     
-    use winit::window::Window;
+    use winit::{event::Event, window::Window};
 
     use vulkanalia::{
         loader::{LibloadingLoader, LIBRARY},
         window as vk_window,
         prelude::v1_0::*,
-        vk::{DebugUtilsMessengerEXT, ExtDebugUtilsExtension},
+        vk::{
+            DebugUtilsMessengerEXT, DeviceV1_2, ExtDebugUtilsExtension, InstanceV1_1, KhrDynamicRenderingExtension,
+            KhrSurfaceExtension, KhrSwapchainExtension,
+        },
         Instance,
+        Version,
     };
 
+    use cgmath::{vec2, vec3, vec4, Deg, EuclideanSpace, InnerSpace, Point3, SquareMatrix};
+
     use std::{
-        collections::HashSet,
-        ffi::CStr,
+        collections::{HashMap, HashSet, VecDeque},
+        env,
+        ffi::{CStr, CString},
+        fs::File,
+        io::{BufReader, BufWriter},
+        mem::{self, size_of, size_of_val},
         os::raw::c_void,
+        path::{Path, PathBuf},
+        ptr::copy_nonoverlapping as memcpy,
+        time::{Duration, Instant},
     };
 
+    /*
+     * app configuration
+     */
+
+    // which kind of swapchain format to look for, unorm is for doing gamma correction in the shader
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub enum FormatPreference {
+        Srgb,
+        Unorm,
+        FirstAvailable,
+    }
+
+    impl FormatPreference {
+        pub fn matches(self, format: vk::Format) -> bool {
+            match self {
+                Self::Srgb => matches!(
+                    format,
+                    vk::Format::B8G8R8A8_SRGB | vk::Format::R8G8B8A8_SRGB | vk::Format::A8B8G8R8_SRGB_PACK32
+                ),
+                Self::Unorm => matches!(
+                    format,
+                    vk::Format::B8G8R8A8_UNORM
+                        | vk::Format::R8G8B8A8_UNORM
+                        | vk::Format::A8B8G8R8_UNORM_PACK32
+                        | vk::Format::A2B10G10R10_UNORM_PACK32
+                        | vk::Format::A2R10G10B10_UNORM_PACK32
+                ),
+                Self::FirstAvailable => true,
+            }
+        }
+    }
+
+    // curve the post pass maps the hdr scene with, values match post.frag
+    #[repr(u32)]
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub enum Tonemap {
+        Reinhard = 1,
+        Aces = 2,
+    }
+
+    #[derive(Clone, Debug)]
+    pub struct AppConfig {
+        pub window_title: String,
+        pub window_size: (u32, u32),
+        pub application_name: String,
+        pub engine_name: String,
+        pub api_version: u32,
+        pub preferred_present_mode: vk::PresentModeKHR,
+        pub format_preference: FormatPreference,
+        pub preferred_color_space: vk::ColorSpaceKHR,
+        pub validation: bool,
+        pub line_width: f32,
+        pub debug_line_width: f32,
+        pub sample_shading: Option<f32>,
+        pub anisotropy: f32,
+        pub tonemap: Option<Tonemap>,
+        pub exposure: f32,
+        pub bloom_threshold: f32,
+        pub bloom_intensity: f32,
+        pub shadows: bool,
+        pub shadow_map_size: u32,
+        pub shadow_depth_bias: (f32, f32),
+        pub stencil_test_enable: bool,
+        pub stencil_front: vk::StencilOpState,
+        pub stencil_back: vk::StencilOpState,
+        pub outline: Option<(f32, [f32; 3])>,
+        pub gui: bool,
+        pub recording_threads: usize,
+        pub dynamic_rendering: bool,
+        pub swapchain_image_count: Option<u32>,
+        pub depth_prepass: bool,
+        pub reverse_z: bool,
+        pub render_scale: f32,
+        pub sort_blended: bool,
+        pub bindless: bool,
+        pub buffer_device_address: bool,
+        pub timeline_semaphores: bool,
+        pub normal_map: Option<String>,
+        pub flat_normals: bool,
+    }
+
+    impl Default for AppConfig {
+        fn default() -> Self {
+            Self {
+                window_title: "Vulkan Testing".to_string(),
+                window_size: (800, 800),
+                application_name: "Vulkan Testing".to_string(),
+                engine_name: "No Engine".to_string(),
+                api_version: vk::make_version(1, 0, 0),
+                preferred_present_mode: vk::PresentModeKHR::MAILBOX,
+                format_preference: FormatPreference::Srgb,
+                preferred_color_space: vk::ColorSpaceKHR::SRGB_NONLINEAR,
+                validation: VALIDATION_ENABLED,
+                line_width: 1.0,
+                debug_line_width: 1.0,
+                sample_shading: None,
+                anisotropy: 16.0,
+                tonemap: None,
+                exposure: 1.0,
+                bloom_threshold: 1.0,
+                bloom_intensity: 0.0,
+                shadows: true,
+                shadow_map_size: 2048,
+                shadow_depth_bias: (1.25, 1.75),
+                stencil_test_enable: false,
+                stencil_front: vk::StencilOpState::default(),
+                stencil_back: vk::StencilOpState::default(),
+                outline: None,
+                gui: false,
+                recording_threads: 0,
+                dynamic_rendering: false,
+                swapchain_image_count: None,
+                depth_prepass: false,
+                reverse_z: false,
+                render_scale: 1.0,
+                sort_blended: true,
+                bindless: false,
+                buffer_device_address: false,
+                timeline_semaphores: false,
+                normal_map: None,
+                flat_normals: false,
+            }
+        }
+    }
+
+    #[derive(Clone, Debug, Default)]
+    pub struct AppBuilder {
+        config: AppConfig,
+    }
+
+    impl AppBuilder {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        pub fn window_title(mut self, title: &str) -> Self {
+            self.config.window_title = title.to_string();
+            self
+        }
+
+        pub fn window_size(mut self, width: u32, height: u32) -> Self {
+            self.config.window_size = (width, height);
+            self
+        }
+
+        pub fn application_name(mut self, name: &str) -> Self {
+            self.config.application_name = name.to_string();
+            self
+        }
+
+        pub fn engine_name(mut self, name: &str) -> Self {
+            self.config.engine_name = name.to_string();
+            self
+        }
+
+        // what the instance is created for, made with vk::make_version, the loader has to support at least this much
+        pub fn api_version(mut self, version: u32) -> Self {
+            self.config.api_version = version;
+            self
+        }
+
+        // falls back to fifo if the surface doesn't support it
+        pub fn preferred_present_mode(mut self, present_mode: vk::PresentModeKHR) -> Self {
+            self.config.preferred_present_mode = present_mode;
+            self
+        }
+
+        // falls back to the same kind in another color space, then anything in the preferred color space
+        pub fn format_preference(mut self, preference: FormatPreference) -> Self {
+            self.config.format_preference = preference;
+            self
+        }
+
+        pub fn preferred_color_space(mut self, color_space: vk::ColorSpaceKHR) -> Self {
+            self.config.preferred_color_space = color_space;
+            self
+        }
+
+        pub fn enable_validation(mut self, enabled: bool) -> Self {
+            self.config.validation = enabled;
+            self
+        }
+
+        // used for wireframe rendering, anything above 1.0 needs wide lines
+        pub fn line_width(mut self, width: f32) -> Self {
+            self.config.line_width = width;
+            self
+        }
+
+        // of lines from draw_line and draw_aabb, anything above 1.0 needs wide lines
+        pub fn debug_line_width(mut self, width: f32) -> Self {
+            self.config.debug_line_width = width;
+            self
+        }
+
+        // shades at least this fraction of each pixel's samples on their own, smoothing textures inside triangles
+        // with msaa
+        pub fn sample_shading(mut self, min_sample_shading: f32) -> Self {
+            self.config.sample_shading = Some(min_sample_shading);
+            self
+        }
+
+        // of the texture sampler, capped by the device, 1.0 or less turns anisotropic filtering off
+        pub fn anisotropy(mut self, level: f32) -> Self {
+            self.config.anisotropy = level;
+            self
+        }
+
+        // maps the hdr scene down in the post pass, scaling it by exposure first, without it the scene is clamped
+        pub fn tonemap(mut self, curve: Tonemap, exposure: f32) -> Self {
+            self.config.tonemap = Some(curve);
+            self.config.exposure = exposure;
+            self
+        }
+
+        // blurs whatever is brighter than the threshold and adds it back scaled by the intensity, 0 turns it off,
+        // turns on dynamic rendering since the blur runs between the scene and post rendering
+        pub fn bloom(mut self, threshold: f32, intensity: f32) -> Self {
+            self.config.bloom_threshold = threshold;
+            self.config.bloom_intensity = intensity;
+            self.config.dynamic_rendering |= intensity > 0.0;
+            self
+        }
+
+        // without them the shadow pass isn't drawn and everything is lit as if nothing blocked the light
+        pub fn shadows(mut self, enabled: bool) -> Self {
+            self.config.shadows = enabled;
+            self
+        }
+
+        // resolution of the light's depth map and the constant and slope scaled bias it's drawn with
+        pub fn shadow_map(mut self, size: u32, constant_bias: f32, slope_bias: f32) -> Self {
+            self.config.shadow_map_size = size;
+            self.config.shadow_depth_bias = (constant_bias, slope_bias);
+            self
+        }
+
+        // stencil state of the main pipeline, front and back faces can be treated differently
+        pub fn stencil(mut self, enabled: bool, front: vk::StencilOpState, back: vk::StencilOpState) -> Self {
+            self.config.stencil_test_enable = enabled;
+            self.config.stencil_front = front;
+            self.config.stencil_back = back;
+            self
+        }
+
+        // draws the scene again scaled up in a flat color wherever the stencil isn't the front reference,
+        // so the main pipeline has to write the reference for an outline
+        pub fn outline(mut self, scale: f32, color: [f32; 3]) -> Self {
+            self.config.outline = Some((scale, color));
+            self
+        }
+
+        // an imgui overlay built with App::gui_frame, windowed apps only
+        pub fn enable_gui(mut self, enabled: bool) -> Self {
+            self.config.gui = enabled;
+            self
+        }
+
+        // the scene is split across this many secondary command buffers recorded in parallel, 0 records inline
+        pub fn recording_threads(mut self, threads: usize) -> Self {
+            self.config.recording_threads = threads;
+            self
+        }
+
+        // begin rendering without render passes or framebuffers when the device supports it, needs vulkan 1.1
+        pub fn dynamic_rendering(mut self, enabled: bool) -> Self {
+            self.config.dynamic_rendering = enabled;
+            self
+        }
+
+        // index every texture from one descriptor array when the device supports it, needs vulkan 1.1
+        pub fn bindless(mut self, enabled: bool) -> Self {
+            self.config.bindless = enabled;
+            self
+        }
+
+        // let buffers be addressed from shaders when the device supports it, needs vulkan 1.2
+        pub fn buffer_device_address(mut self, enabled: bool) -> Self {
+            self.config.buffer_device_address = enabled;
+            self
+        }
+
+        // a tangent space normal map every object's surface is lit with, objects without tangents use their normals
+        pub fn normal_map(mut self, path: &str) -> Self {
+            self.config.normal_map = Some(path.to_string());
+            self
+        }
+
+        // one normal per face for models loaded without normals, or in place of the ones they have
+        pub fn flat_normals(mut self, enabled: bool) -> Self {
+            self.config.flat_normals = enabled;
+            self
+        }
+
+        // track frames in flight on a timeline semaphore instead of fences when the device supports it, needs vulkan 1.2
+        pub fn timeline_semaphores(mut self, enabled: bool) -> Self {
+            self.config.timeline_semaphores = enabled;
+            self
+        }
+
+        // 2 for double buffering, 3 for triple, clamped to what the surface allows
+        pub fn swapchain_image_count(mut self, count: u32) -> Self {
+            self.config.swapchain_image_count = Some(count);
+            self
+        }
+
+        // lays down depth before shading so each pixel is only shaded once, can be toggled while running
+        pub fn depth_prepass(mut self, enabled: bool) -> Self {
+            self.config.depth_prepass = enabled;
+            self
+        }
+
+        // clears depth to 0 and keeps what's greater, with the projection mapping near to 1 and far to 0, only more
+        // precise when the depth format is float, which it is unless the device only has D24_UNORM_S8_UINT
+        pub fn reverse_z(mut self, enabled: bool) -> Self {
+            self.config.reverse_z = enabled;
+            self
+        }
+
+        // renders at this multiple of the window size and blits down, 2.0 or 4.0 for smoother screenshots
+        pub fn render_scale(mut self, scale: f32) -> Self {
+            self.config.render_scale = scale;
+            self
+        }
+
+        // blended objects are drawn furthest from the camera first, turn off if the order doesn't matter
+        pub fn sort_blended(mut self, enabled: bool) -> Self {
+            self.config.sort_blended = enabled;
+            self
+        }
+
+        pub fn build(self) -> AppConfig {
+            self.config
+        }
+    }
+
     /*
      * the vulkan app
      */
 
+    // what the app presents its frames to
+    #[derive(Copy, Clone, Debug)]
+    enum RenderTarget<'a> {
+        Window(&'a Window),
+        Offscreen(vk::Extent2D),
+    }
+
+    // what drawing the scene needs, copied to the recording threads since the app can't be shared
+    #[derive(Copy, Clone)]
+    struct SceneDraws<'a> {
+        device: &'a Device,
+        objects: &'a [RenderObject],
+        // indexed by BlendMode, for passes that pick a pipeline per object
+        blend_pipelines: [vk::Pipeline; 3],
+        pipeline_layout: vk::PipelineLayout,
+        descriptor_set: vk::DescriptorSet,
+        bindless_set: Option<vk::DescriptorSet>,
+        instance_buffer: vk::Buffer,
+        instance_count: u32,
+        // the model's indirect draws and how many, a count of 0 draws it directly
+        draw_commands_buffer: vk::Buffer,
+        draw_command_count: u32,
+        multi_draw_indirect: bool,
+        object_alignment: vk::DeviceSize,
+        skybox: Option<&'a Skybox>,
+        skybox_view_proj: Mat4,
+        particles: Option<&'a Particles>,
+        debug_lines: &'a DebugLines,
+        image_index: usize,
+        view_proj: Mat4,
+    }
+
+    // what each thread records into one secondary, in order
     #[derive(Clone, Debug)]
+    enum ScenePass {
+        // a pipeline and the objects it draws in order, without one each object's blend mode picks it
+        Objects { pipeline: Option<vk::Pipeline>, objects: Vec<usize> },
+        // one draw each, only the first thread records them
+        Particles,
+        Skybox,
+        DebugLines,
+    }
+
+    impl SceneDraws<'_> {
+        // the objects are split evenly between the threads
+        unsafe fn record_pass(&self, command_buffer: vk::CommandBuffer, pass: &ScenePass, thread: usize, threads: usize) {
+            match pass {
+                ScenePass::Objects { pipeline, objects } => {
+                    let chunk = objects.len().div_ceil(threads);
+                    let start = (thread * chunk).min(objects.len());
+                    let end = (start + chunk).min(objects.len());
+                    self.record(command_buffer, *pipeline, &objects[start..end]);
+                }
+                ScenePass::Particles => {
+                    if let (0, Some(particles)) = (thread, self.particles) {
+                        particles.record(self.device, command_buffer, self.view_proj);
+                    }
+                }
+                ScenePass::Skybox => {
+                    if let (0, Some(skybox)) = (thread, self.skybox) {
+                        skybox.record(self.device, command_buffer, self.skybox_view_proj);
+                    }
+                }
+                ScenePass::DebugLines => {
+                    if thread == 0 {
+                        self.debug_lines.record(self.device, command_buffer, self.image_index, self.view_proj);
+                    }
+                }
+            }
+        }
+
+        // draws some of the scene objects, only binding a pipeline when it changes
+        unsafe fn record(&self, command_buffer: vk::CommandBuffer, pipeline: Option<vk::Pipeline>, objects: &[usize]) {
+            let mut bound = None;
+
+            // set 1 never changes, but the skybox's layout disturbs it so every pass binds it again
+            if let Some(bindless_set) = self.bindless_set {
+                self.device.cmd_bind_descriptor_sets(
+                    command_buffer,
+                    vk::PipelineBindPoint::GRAPHICS,
+                    self.pipeline_layout,
+                    1,
+                    &[bindless_set],
+                    &[],
+                );
+            }
+
+            // the set is rebound per object, only the dynamic offset into the transforms changes
+            for &i in objects {
+                let object = &self.objects[i];
+                let pipeline = pipeline.unwrap_or(self.blend_pipelines[object.blend_mode as usize]);
+                if bound != Some(pipeline) {
+                    self.device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::GRAPHICS, pipeline);
+                    bound = Some(pipeline);
+                }
+
+                let offset = i as u64 * self.object_alignment;
+                self.device.cmd_bind_descriptor_sets(
+                    command_buffer,
+                    vk::PipelineBindPoint::GRAPHICS,
+                    self.pipeline_layout,
+                    0,
+                    &[self.descriptor_set],
+                    &[offset as u32],
+                );
+                self.device.cmd_bind_vertex_buffers(
+                    command_buffer,
+                    0,
+                    &[object.vertex_buffer, self.instance_buffer],
+                    &[0, 0],
+                );
+                self.device.cmd_bind_index_buffer(command_buffer, object.index_buffer, 0, object.index_type);
+                self.device.cmd_push_constants(
+                    command_buffer,
+                    self.pipeline_layout,
+                    vk::ShaderStageFlags::FRAGMENT,
+                    0,
+                    &object.texture_index.to_ne_bytes(),
+                );
+
+                // without multi draw indirect each command is its own call
+                if i == 0 && self.draw_command_count > 0 {
+                    let stride = size_of::<vk::DrawIndexedIndirectCommand>() as u32;
+                    if self.multi_draw_indirect {
+                        self.device.cmd_draw_indexed_indirect(
+                            command_buffer,
+                            self.draw_commands_buffer,
+                            0,
+                            self.draw_command_count,
+                            stride,
+                        );
+                    } else {
+                        for command in 0..self.draw_command_count {
+                            let offset = command as u64 * stride as u64;
+                            self.device.cmd_draw_indexed_indirect(command_buffer, self.draw_commands_buffer, offset, 1, stride);
+                        }
+                    }
+                    continue;
+                }
+
+                // only the model is instanced, everything else draws the plain first instance
+                let (instance_count, first_instance) = if i == 0 && self.instance_count > 0 {
+                    (self.instance_count, 1)
+                } else {
+                    (1, 0)
+                };
+                self.device.cmd_draw_indexed(command_buffer, object.index_count, instance_count, 0, 0, first_instance);
+            }
+        }
+    }
+
+    // not Clone, the watcher can't be shared and a copy would destroy the same handles twice
+    #[derive(Debug)]
     pub struct App {
+        pub config: AppConfig,
         pub entry: Entry,
         pub instance: Instance,
         pub debug_messenger: Option<DebugUtilsMessengerEXT>,
         pub phys_device: vk::PhysicalDevice,
+        pub phys_device_properties: vk::PhysicalDeviceProperties,
+        // what the device supports, the optional features the app uses are enabled whenever they're here
+        pub phys_device_features: vk::PhysicalDeviceFeatures,
+        pub msaa_samples: vk::SampleCountFlags,
+        pub device: Device,
+        pub allocator: Allocator,
+        pub graphics_queue: vk::Queue,
+        pub present_queue: vk::Queue,
+        pub compute_queue: vk::Queue,
+        pub transfer_queue: vk::Queue,
+        pub queue_families: QueueFamilyIndices,
+        // the main window first, or the offscreen image when headless, see add_window for the rest
+        pub windows: Vec<WindowContext>,
+        // every window's swapchain has it, the pipelines are built for it
+        pub swapchain_format: vk::Format,
+        pub headless: bool,
+        // of the scene color and resolve images
+        pub scene_format: vk::Format,
+        // the main window renders bigger and blits down, the render pass leaves its target for that so without
+        // dynamic rendering the other windows have to match
+        pub supersampling: bool,
+        // null when rendering dynamically, framebuffers are empty too
+        pub render_pass: vk::RenderPass,
+        pub dynamic_rendering: bool,
+        // buffers can be created with SHADER_DEVICE_ADDRESS usage and their addresses queried
+        pub buffer_device_address: bool,
+        pub descriptor_set_layout: vk::DescriptorSetLayout,
+        pub pipeline_cache: vk::PipelineCache,
+        pub pipeline_layout: vk::PipelineLayout,
+        pub pipeline: vk::Pipeline,
+        pub wireframe_pipeline: vk::Pipeline,
+        pub outline_pipeline: vk::Pipeline,
+        pub depth_prepass_pipeline: vk::Pipeline,
+        pub depth_equal_pipeline: vk::Pipeline,
+        pub alpha_blend_pipeline: vk::Pipeline,
+        pub additive_pipeline: vk::Pipeline,
+        pub post_descriptor_set_layout: vk::DescriptorSetLayout,
+        pub post_pipeline_layout: vk::PipelineLayout,
+        pub post_pipeline: vk::Pipeline,
+        pub post_effect: PostEffect,
+        pub shader_watcher: Option<ShaderWatcher>,
+        pub wireframe_line_width: Option<f32>,
+        // none without msaa, when not asked for or when the device can't
+        pub min_sample_shading: Option<f32>,
+        pub wireframe: bool,
+        pub depth_prepass: bool,
+        pub clear_color: [f32; 4],
+        pub gui: Option<Gui>,
+        pub skybox: Option<Skybox>,
+        pub particles: Option<Particles>,
+        // each window has its own, sized to what it renders at
+        pub bloom_enabled: bool,
+        pub debug_lines: DebugLines,
+        pub shadow_map: ShadowMap,
+        // made on the first pick, destroyed when the main window's render extent changes
+        pub picker: Option<Picker>,
+        // a render target pixel to draw ids for in the main window's next frame
+        pub pick: Option<(u32, u32)>,
+        // which of the main window's frames in flight copy an id into their readback buffer
+        pub picks_in_flight: [bool; MAX_FRAMES_IN_FLIGHT],
+        // the last resolved pick, some of none when it hit nothing
+        pub picked: Option<Option<u32>>,
+        // taken from the main window's next frame, see save_screenshot
+        pub screenshot_request: Option<PathBuf>,
+        // copied into by a frame in flight and saved once it finishes, one at a time
+        pub screenshot: Option<Screenshot>,
+        pub command_pool: vk::CommandPool,
+        pub transfer_command_pool: vk::CommandPool,
+        pub texture_image: vk::Image,
+        pub texture_image_memory: Allocation,
+        pub texture_image_view: vk::ImageView,
+        pub mip_levels: u32,
+        pub texture_layers: u32,
+        pub texture_filter: vk::Filter,
+        // what the sampler actually uses, none when anisotropic filtering is off
+        pub anisotropy: Option<f32>,
+        pub texture_sampler: vk::Sampler,
+        // sampled with the texture sampler, a single flat normal without a configured normal map
+        pub normal_map_image: vk::Image,
+        pub normal_map_image_memory: Allocation,
+        pub normal_map_image_view: vk::ImageView,
+        // in place of the texture array when the device supports descriptor indexing
+        pub bindless: Option<BindlessTextures>,
+        pub model_path: String,
+        pub vertices: Vec<Vertex>,
+        pub indices: Vec<u32>,
+        pub model_bounds: Aabb,
+        pub vertex_buffer: vk::Buffer,
+        pub vertex_buffer_memory: Allocation,
+        pub index_buffer: vk::Buffer,
+        pub index_buffer_memory: Allocation,
+        pub index_type: vk::IndexType,
+        pub index_count: u32,
+        pub objects: Vec<RenderObject>,
+        pub instance_buffer: vk::Buffer,
+        pub instance_buffer_memory: Allocation,
+        pub instance_count: u32,
+        // draws the model in place of its direct draw when there are any, device local and only read by the gpu
+        pub draw_commands_buffer: vk::Buffer,
+        pub draw_commands_buffer_memory: Allocation,
+        pub draw_command_count: u32,
+        pub object_alignment: vk::DeviceSize,
+        pub post_sampler: vk::Sampler,
+        pub secondary_command_pools: Vec<vk::CommandPool>,
+        // each window waits on its own timeline in place of fences when enabled
+        pub timeline_semaphores: bool,
+        pub timestamp_properties: Option<(f32, u64)>,
+        pub compute_command_pool: vk::CommandPool,
+        pub compute_buffer: vk::Buffer,
+        pub compute_buffer_memory: Allocation,
+        pub compute_descriptor_set_layout: vk::DescriptorSetLayout,
+        pub compute_descriptor_pool: vk::DescriptorPool,
+        pub compute_descriptor_set: vk::DescriptorSet,
+        pub compute_pipeline_layout: vk::PipelineLayout,
+        pub compute_pipeline: vk::Pipeline,
+        pub model: Mat4,
+        // what every window without a camera of its own looks through
+        pub camera: Camera,
+        pub light: Light,
+        pub destroyed: bool,
+        pub start: Instant,
+        // what the scene animates by in place of the time since start, so captures come out the same every run
+        pub fixed_time: Option<f32>,
+        pub last_frame: Instant,
+        pub frame_delta: Duration,
+        pub last_report: Instant,
+        pub frame_times: VecDeque<Duration>,
+        pub gpu_frame_times: VecDeque<Duration>,
     }
 
     impl App {
-        pub unsafe fn create(window: &Window) -> Result<Self> {
+        pub fn builder() -> AppBuilder {
+            AppBuilder::new()
+        }
+
+        /// what a physical device can do, for deciding on a config before creating the app with it, the instance has
+        /// to be created for at least the device's api version for the later features to be queried
+        ///
+        /// # Safety
+        ///
+        /// `phys_device` has to come from `instance`.
+        pub unsafe fn probe_capabilities(instance: &Instance, phys_device: vk::PhysicalDevice) -> DeviceCapabilities {
+            let properties = instance.get_physical_device_properties(phys_device);
+            let features = instance.get_physical_device_features(phys_device);
+            let version = properties.api_version;
+
+            DeviceCapabilities {
+                name: properties.device_name.to_string(),
+                api_version: properties.api_version,
+                max_msaa: get_max_msaa_samples(instance, phys_device),
+                supports_anisotropy: features.sampler_anisotropy == vk::TRUE,
+                max_anisotropy: properties.limits.max_sampler_anisotropy,
+                supports_geometry_shader: features.geometry_shader == vk::TRUE,
+                supports_wide_lines: features.wide_lines == vk::TRUE,
+                max_texture_size: properties.limits.max_image_dimension_2d,
+                supports_dynamic_rendering: query_dynamic_rendering_support(instance, phys_device, version),
+                supports_descriptor_indexing: query_bindless_support(instance, phys_device, version),
+                supports_buffer_device_address: query_buffer_device_address_support(instance, phys_device, version),
+                supports_timeline_semaphores: query_timeline_semaphore_support(instance, phys_device, version),
+            }
+        }
+
+        /// # Safety
+        ///
+        /// `window` has to outlive the app.
+        pub unsafe fn create(window: &Window, config: AppConfig) -> Result<Self> {
+            Self::create_for_target(RenderTarget::Window(window), config)
+        }
+
+        /// renders into an offscreen image instead of a swapchain, read back with render_offscreen
+        ///
+        /// # Safety
+        ///
+        /// The Vulkan library loaded here has to be a conforming one, its entry points are trusted as is.
+        pub unsafe fn create_headless(width: u32, height: u32, config: AppConfig) -> Result<Self> {
+            Self::create_for_target(RenderTarget::Offscreen(vk::Extent2D { width, height }), config)
+        }
+
+        unsafe fn create_for_target(target: RenderTarget, config: AppConfig) -> Result<Self> {
+            let window = match target {
+                RenderTarget::Window(window) => Some(window),
+                RenderTarget::Offscreen(_) => None,
+            };
+            let headless = window.is_none();
+
             // create loader, entry, and instance
             let loader = LibloadingLoader::new(LIBRARY)?;
             let entry = Entry::new(loader).map_err(|b| anyhow!("{}", b))?;
-            let (instance, debug_messenger) = create_instance(window, &entry)?;
+            let (instance, debug_messenger) = create_instance(window, &entry, &config)?;
+
+            // create window surface, headless apps have nothing to present to
+            let surface = match window {
+                Some(window) => vk_window::create_surface(&instance, &window, &window)?,
+                None => vk::SurfaceKHR::null(),
+            };
+
+            let phys_device = choose_physical_device(&instance, surface)?;
+            let phys_device_properties = instance.get_physical_device_properties(phys_device);
+            let phys_device_features = instance.get_physical_device_features(phys_device);
+            log_device_info(&instance, phys_device);
+            check_device_version(&instance, phys_device, &config);
+            let msaa_samples = get_max_msaa_samples(&instance, phys_device);
+            check_push_constants_size(&instance, phys_device)?;
+            let dynamic_rendering = get_dynamic_rendering_support(&instance, phys_device, &config);
+            if config.dynamic_rendering && !dynamic_rendering {
+                warn!("Dynamic rendering is not supported, falling back to render passes.");
+            }
+            let bindless = get_bindless_support(&instance, phys_device, &config);
+            if config.bindless && !bindless {
+                warn!("Descriptor indexing is not supported, falling back to the texture array.");
+            }
+            let buffer_device_address = get_buffer_device_address_support(&instance, phys_device, &config);
+            if config.buffer_device_address && !buffer_device_address {
+                warn!("Buffer device address is not supported.");
+            }
+            let timeline_semaphores = get_timeline_semaphore_support(&instance, phys_device, &config);
+            if config.timeline_semaphores && !timeline_semaphores {
+                warn!("Timeline semaphores are not supported, falling back to fences.");
+            }
+
+            // create logical device and get queue handles
+            let queue_families = QueueFamilyIndices::get(&instance, &[surface], phys_device)?;
+            let (device, graphics_queue, present_queue, compute_queue, transfer_queue) = create_logical_device(
+                &instance,
+                phys_device,
+                &queue_families,
+                &config,
+                headless,
+                dynamic_rendering,
+                bindless,
+                buffer_device_address,
+                timeline_semaphores,
+            )?;
+            let mut allocator = Allocator::new();
+
+            // create the main window's swapchain and get its images, or a single offscreen image
+            let mut main = WindowContext { surface, ..Default::default() };
+            let swapchain_format = match target {
+                RenderTarget::Window(window) => {
+                    main.window_extent = window_extent(window);
+                    let (swapchain, images, format, extent) = create_swapchain(
+                        main.window_extent,
+                        &instance,
+                        &device,
+                        surface,
+                        phys_device,
+                        &queue_families,
+                        &config,
+                        vk::SwapchainKHR::null(),
+                    )?;
+                    (main.swapchain, main.images, main.extent) = (swapchain, images, extent);
+                    format
+                }
+                RenderTarget::Offscreen(extent) => {
+                    let (image, memory) = create_offscreen_image(
+                        &instance,
+                        &device,
+                        phys_device,
+                        &mut allocator,
+                        extent,
+                    )?;
+                    (main.images, main.offscreen_image_memory) = (vec![image], memory);
+                    (main.extent, main.window_extent) = (extent, extent);
+                    OFFSCREEN_FORMAT
+                }
+            };
+            main.render_extent = get_render_extent(
+                &instance,
+                phys_device,
+                surface,
+                swapchain_format,
+                main.extent,
+                config.render_scale,
+            )?;
+            let supersampling = main.render_extent != main.extent;
+
+            // hdr, only the post pass writes the swapchain
+            let scene_format = get_scene_format(&instance, phys_device)?;
+
+            // still correct, just no more precise than the usual way round
+            let depth_format = get_depth_format(&instance, phys_device)?;
+            if config.reverse_z && depth_format != vk::Format::D32_SFLOAT_S8_UINT {
+                warn!("Reversing depth with a fixed point depth format ({:?}) gains no precision.", depth_format);
+            }
+
+            let final_layout = get_final_layout(headless, supersampling);
+            let render_pass = if dynamic_rendering {
+                vk::RenderPass::null()
+            } else {
+                create_render_pass(
+                    &instance,
+                    &device,
+                    phys_device,
+                    swapchain_format,
+                    scene_format,
+                    msaa_samples,
+                    final_layout,
+                )?
+            };
+            let (scene_target, post_target) = get_pipeline_targets(
+                &instance,
+                phys_device,
+                dynamic_rendering,
+                render_pass,
+                swapchain_format,
+                scene_format,
+            )?;
+            let descriptor_set_layout = create_descriptor_set_layout(&device)?;
+            let mut bindless = if bindless { Some(BindlessTextures::new(&device)?) } else { None };
+            let post_descriptor_set_layout = create_post_descriptor_set_layout(&device, dynamic_rendering)?;
+            let wireframe_line_width = get_wireframe_line_width(&instance, phys_device, config.line_width);
+            let min_sample_shading = get_min_sample_shading(&instance, phys_device, &config, msaa_samples);
+            let pipeline_cache = create_pipeline_cache(&instance, &device, phys_device)?;
+            // hot reloading is a nicety, shaders still load from the directory without it
+            let shader_watcher = shaders::shader_dir().and_then(|dir| {
+                info!("Loading shaders from ({}) and watching for changes.", dir.display());
+                ShaderWatcher::new(&dir)
+                    .map_err(|e| warn!("Failed to watch shader directory ({}): {}", dir.display(), e))
+                    .ok()
+            });
+            let (
+                pipeline_layout,
+                pipeline,
+                wireframe_pipeline,
+                outline_pipeline,
+                depth_prepass_pipeline,
+                depth_equal_pipeline,
+                alpha_blend_pipeline,
+                additive_pipeline,
+            ) = create_pipeline(
+                &device,
+                &config,
+                pipeline_cache,
+                scene_target,
+                descriptor_set_layout,
+                bindless.as_ref().map(|b| b.descriptor_set_layout()),
+                msaa_samples,
+                wireframe_line_width,
+                min_sample_shading,
+            )?;
+            let (post_pipeline_layout, post_pipeline) =
+                create_post_pipeline(&device, pipeline_cache, post_target, post_descriptor_set_layout)?;
+            let debug_lines = DebugLines::new(
+                &device,
+                pipeline_cache,
+                scene_target,
+                msaa_samples,
+                config.reverse_z,
+                get_line_width(&instance, phys_device, config.debug_line_width),
+                main.images.len(),
+            )?;
+            let depth_prepass = config.depth_prepass;
+
+            // create command pool and record a command buffer per framebuffer
+            let command_pool = create_command_pool(&instance, &device, surface, phys_device)?;
+
+            // uploads go through the transfer queue when there is a dedicated one
+            let transfer_command_pool = create_transfer_command_pool(&device, &queue_families)?;
+            let uploader = Uploader {
+                transfer_family: queue_families.transfer.unwrap_or(queue_families.graphics),
+                transfer_pool: transfer_command_pool,
+                transfer_queue,
+                graphics_family: queue_families.graphics,
+                graphics_pool: command_pool,
+                graphics_queue,
+            };
+
+            let gui = match window {
+                Some(window) if config.gui => Some(Gui::new(
+                    window,
+                    &instance,
+                    &device,
+                    phys_device,
+                    &mut allocator,
+                    &uploader,
+                    pipeline_cache,
+                    post_target,
+                    swapchain_format,
+                    main.images.len(),
+                )?),
+                _ => None,
+            };
+
+            let (texture_image, texture_image_memory, mip_levels) =
+                create_texture_image(&instance, &device, phys_device, &mut allocator, &uploader, TEXTURE_PATHS)?;
+            let texture_layers = TEXTURE_PATHS.len() as u32;
+            let texture_image_view = create_texture_image_view(&device, texture_image, mip_levels, texture_layers)?;
+            let texture_filter = vk::Filter::LINEAR;
+            let anisotropy = get_anisotropy(&instance, phys_device, config.anisotropy);
+            let texture_sampler = create_texture_sampler(&device, texture_filter, anisotropy, mip_levels)?;
+            let (normal_map_image, normal_map_image_memory, normal_map_image_view) = create_normal_map(
+                &instance,
+                &device,
+                phys_device,
+                &mut allocator,
+                &uploader,
+                config.normal_map.as_deref(),
+            )?;
+            // loaded in the same order as the array's layers so an index means the same texture either way
+            if let Some(bindless) = &mut bindless {
+                for path in TEXTURE_PATHS {
+                    bindless.add(&instance, &device, phys_device, &mut allocator, &uploader, texture_sampler, path)?;
+                }
+            }
+
+            // load the model and upload it
+            let model_path = MODEL_PATH.to_string();
+            let (vertices, indices) = load_model(&model_path, config.flat_normals)?;
+            let (vertex_buffer, vertex_buffer_memory) = create_vertex_buffer(
+                &instance,
+                &device,
+                phys_device,
+                &mut allocator,
+                &uploader,
+                &vertices,
+                "model-vertices",
+            )?;
+            let (index_buffer, index_buffer_memory) = create_index_buffer(
+                &instance,
+                &device,
+                phys_device,
+                &mut allocator,
+                &uploader,
+                &indices,
+                "model-indices",
+            )?;
+            let index_type = u32::INDEX_TYPE;
+            let index_count = indices.len() as u32;
+            let (instance_buffer, instance_buffer_memory) = create_vertex_buffer(
+                &instance,
+                &device,
+                phys_device,
+                &mut allocator,
+                &uploader,
+                &[InstanceData::default()],
+                "instances",
+            )?;
+            let object_alignment = get_object_alignment(&instance, phys_device);
+            let shadow_map = ShadowMap::new(
+                &instance,
+                &device,
+                phys_device,
+                &mut allocator,
+                pipeline_cache,
+                descriptor_set_layout,
+                get_shadow_map_format(&instance, phys_device)?,
+                config.shadow_map_size,
+                config.shadow_depth_bias,
+            )?;
+            let post_sampler = if dynamic_rendering {
+                create_post_sampler(&device)?
+            } else {
+                vk::Sampler::null()
+            };
+            let bloom_enabled = get_bloom_support(&instance, phys_device, &config, dynamic_rendering)?;
 
-            let phys_device = choose_physical_device(&instance)?;
+            // command pools can't be used from more than one thread at a time
+            let secondary_command_pools = (0..config.recording_threads)
+                .map(|_| create_secondary_command_pool(&device, &queue_families))
+                .collect::<Result<Vec<_>>>()?;
 
-            Ok(Self {
+            // two timestamps per swapchain image, around the render pass
+            let timestamp_properties = get_timestamp_properties(&instance, phys_device, queue_families.graphics);
+
+            // compute work gets its own pool since the family may differ from graphics
+            let compute_command_pool = create_compute_command_pool(&device, &queue_families)?;
+            let (compute_buffer, compute_buffer_memory) = create_compute_buffer(
+                &instance,
+                &device,
+                phys_device,
+                &mut allocator,
+                buffer_device_address,
+            )?;
+            let compute_descriptor_set_layout = create_compute_descriptor_set_layout(&device)?;
+            let compute_descriptor_pool = create_compute_descriptor_pool(&device)?;
+            let compute_descriptor_set = create_compute_descriptor_set(
+                &device,
+                compute_descriptor_set_layout,
+                compute_descriptor_pool,
+                compute_buffer,
+            )?;
+            let compute_pipeline_layout = create_compute_pipeline_layout(&device, compute_descriptor_set_layout)?;
+            let compute_pipeline = create_compute_pipeline(&device, pipeline_cache, compute_pipeline_layout)?;
+
+            let mut app = Self {
+                config,
                 entry,
                 instance,
                 debug_messenger,
                 phys_device,
-            })
+                phys_device_properties,
+                phys_device_features,
+                msaa_samples,
+                device,
+                allocator,
+                graphics_queue,
+                present_queue,
+                compute_queue,
+                transfer_queue,
+                queue_families,
+                windows: Vec::new(),
+                swapchain_format,
+                headless,
+                scene_format,
+                supersampling,
+                render_pass,
+                dynamic_rendering,
+                buffer_device_address,
+                descriptor_set_layout,
+                pipeline_cache,
+                pipeline_layout,
+                pipeline,
+                wireframe_pipeline,
+                outline_pipeline,
+                depth_prepass_pipeline,
+                depth_equal_pipeline,
+                alpha_blend_pipeline,
+                additive_pipeline,
+                post_descriptor_set_layout,
+                post_pipeline_layout,
+                post_pipeline,
+                post_effect: PostEffect::None,
+                shader_watcher,
+                wireframe_line_width,
+                min_sample_shading,
+                wireframe: false,
+                depth_prepass,
+                clear_color: [0.0, 0.0, 0.0, 1.0],
+                gui,
+                skybox: None,
+                particles: None,
+                bloom_enabled,
+                debug_lines,
+                shadow_map,
+                picker: None,
+                pick: None,
+                picks_in_flight: [false; MAX_FRAMES_IN_FLIGHT],
+                picked: None,
+                screenshot_request: None,
+                screenshot: None,
+                command_pool,
+                transfer_command_pool,
+                texture_image,
+                texture_image_memory,
+                texture_image_view,
+                normal_map_image,
+                normal_map_image_memory,
+                normal_map_image_view,
+                mip_levels,
+                texture_layers,
+                texture_filter,
+                anisotropy,
+                texture_sampler,
+                bindless,
+                model_path,
+                model_bounds: Aabb::from_vertices(&vertices),
+                vertices,
+                indices,
+                vertex_buffer,
+                vertex_buffer_memory,
+                index_buffer,
+                index_buffer_memory,
+                index_type,
+                index_count,
+                objects: Vec::new(),
+                instance_buffer,
+                instance_buffer_memory,
+                instance_count: 0,
+                draw_commands_buffer: vk::Buffer::null(),
+                draw_commands_buffer_memory: Allocation::default(),
+                draw_command_count: 0,
+                object_alignment,
+                post_sampler,
+                secondary_command_pools,
+                timeline_semaphores,
+                timestamp_properties,
+                compute_command_pool,
+                compute_buffer,
+                compute_buffer_memory,
+                compute_descriptor_set_layout,
+                compute_descriptor_pool,
+                compute_descriptor_set,
+                compute_pipeline_layout,
+                compute_pipeline,
+                model: Mat4::identity(),
+                camera: Camera::default(),
+                light: Light::default(),
+                destroyed: false,
+                start: Instant::now(),
+                fixed_time: None,
+                last_frame: Instant::now(),
+                frame_delta: Duration::ZERO,
+                last_report: Instant::now(),
+                frame_times: VecDeque::new(),
+                gpu_frame_times: VecDeque::new(),
+            };
+
+            app.create_window_sync(&mut main)?;
+            app.create_window_objects(&mut main)?;
+            app.windows.push(main);
+
+            app.set_debug_names()?;
+
+            Ok(app)
         }
 
+        /// # Safety
+        ///
+        /// `window` has to be the one the app was created with.
         pub unsafe fn render(&mut self, window: &Window) -> Result<()> {
+            if self.headless {
+                return Err(anyhow!("Headless apps have no swapchain, use render_offscreen."));
+            }
+
+            // nothing to render to while minimized, the first frame after restoring shouldn't count the wait
+            if is_minimized(window) {
+                self.last_frame = Instant::now();
+                return Ok(());
+            }
+
+            if self.shader_watcher.as_mut().is_some_and(ShaderWatcher::poll) {
+                self.reload_shaders()?;
+            }
+
+            // the other windows keep the size from their own resize events
+            self.windows[0].window_extent = window_extent(window);
+            for index in 0..self.windows.len() {
+                self.render_window(index)?;
+            }
+
+            self.update_frame_times(window);
+
             Ok(())
         }
 
-        pub unsafe fn destroy(&mut self) {
-            // destroy the debug messener if it exists
-            match self.debug_messenger {
-                Some(messenger) => self.instance.destroy_debug_utils_messenger_ext(messenger, None),
-                _ => {}
+        // records and presents a frame of the window, which is skipped while it's minimized or can't be recreated
+        unsafe fn render_window(&mut self, index: usize) -> Result<()> {
+            if self.windows[index].resized {
+                self.recreate_window(index)?;
+            }
+            if self.windows[index].resized || self.windows[index].is_minimized() {
+                return Ok(());
+            }
+
+            // wait for this frame's previous submission to finish, on the timeline that's MAX_FRAMES_IN_FLIGHT values back
+            let window = &self.windows[index];
+            let frame = window.frame;
+            let in_flight_fence = window.in_flight_fences[frame];
+            let timeline_semaphore = window.timeline_semaphore;
+            let timeline_value = window.timeline_value + 1;
+            if timeline_semaphore.is_null() {
+                self.device
+                    .wait_for_fences(&[in_flight_fence], true, u64::MAX)
+                    .map_err(render_error)?;
+            } else {
+                self.wait_timeline(timeline_semaphore, timeline_value.saturating_sub(MAX_FRAMES_IN_FLIGHT as u64))?;
+            }
+
+            let result = self.device.acquire_next_image_khr(
+                window.swapchain,
+                u64::MAX,
+                window.image_available_semaphores[frame],
+                vk::Fence::null(),
+            );
+
+            let image_index = match result {
+                Ok((image_index, _)) => image_index as usize,
+                Err(vk::ErrorCode::OUT_OF_DATE_KHR) => {
+                    self.windows[index].resized = true;
+                    return self.recreate_window(index);
+                }
+                Err(e) => return Err(render_error(e)),
             };
 
-            self.instance.destroy_instance(None);
+            // the acquired image may still be in use by another frame
+            if timeline_semaphore.is_null() {
+                let image_in_flight = window.images_in_flight[image_index];
+                if !image_in_flight.is_null() {
+                    self.device
+                        .wait_for_fences(&[image_in_flight], true, u64::MAX)
+                        .map_err(render_error)?;
+                }
+
+                self.windows[index].images_in_flight[image_index] = in_flight_fence;
+            } else {
+                self.wait_timeline(timeline_semaphore, window.images_in_flight_values[image_index])?;
+                self.windows[index].images_in_flight_values[image_index] = timeline_value;
+            }
+
+            self.read_timestamps(index, image_index)?;
+            // the overlay, lines and ids are only drawn over the main window
+            if index == 0 {
+                self.resolve_pick(frame)?;
+                self.finish_screenshot(frame);
+                self.begin_screenshot(frame)?;
+                if let Some(gui) = &mut self.gui {
+                    gui.upload(&self.instance, &self.device, self.phys_device, &mut self.allocator, image_index)?;
+                }
+                self.debug_lines
+                    .upload(&self.instance, &self.device, self.phys_device, &mut self.allocator, image_index)?;
+            }
+            self.update_command_buffer(index, image_index)?;
+            if index == 0 && self.picker.is_some() && self.pick.take().is_some() {
+                self.picks_in_flight[frame] = true;
+            }
+            let window = &mut self.windows[index];
+            window.timestamps_written[image_index] = !window.timestamp_query_pool.is_null();
+            self.update_uniform_buffer(&self.windows[index], image_index)?;
+
+            // wait for the image before writing colors, signal when rendering is done
+            let window = &self.windows[index];
+            let wait_semaphores = &[window.image_available_semaphores[frame]];
+            // blitting a supersampled frame writes the image before any color attachment output
+            let wait_stages = if window.is_scaled() {
+                &[vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT | vk::PipelineStageFlags::TRANSFER]
+            } else {
+                &[vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT]
+            };
+            let command_buffers = &[window.command_buffers[image_index]];
+            let signal_semaphores = &[window.render_finished_semaphores[frame]];
+            let mut submit_info = vk::SubmitInfo::builder()
+                .wait_semaphores(wait_semaphores)
+                .wait_dst_stage_mask(wait_stages)
+                .command_buffers(command_buffers)
+                .signal_semaphores(signal_semaphores);
+
+            // the timeline is signalled alongside the binary semaphore, presenting can only wait on binary ones
+            let timeline_signal_semaphores = &[signal_semaphores[0], timeline_semaphore];
+            let timeline_signal_values = &[0, timeline_value];
+            let mut timeline_info = vk::TimelineSemaphoreSubmitInfo::builder()
+                .signal_semaphore_values(timeline_signal_values);
+
+            if timeline_semaphore.is_null() {
+                self.device.reset_fences(&[in_flight_fence])?;
+                self.device
+                    .queue_submit(self.graphics_queue, &[submit_info], in_flight_fence)
+                    .map_err(render_error)?;
+            } else {
+                submit_info = submit_info
+                    .signal_semaphores(timeline_signal_semaphores)
+                    .push_next(&mut timeline_info);
+                self.device
+                    .queue_submit(self.graphics_queue, &[submit_info], vk::Fence::null())
+                    .map_err(render_error)?;
+            }
+
+            let swapchains = &[window.swapchain];
+            let image_indices = &[image_index as u32];
+            let present_info = vk::PresentInfoKHR::builder()
+                .wait_semaphores(signal_semaphores)
+                .swapchains(swapchains)
+                .image_indices(image_indices);
+
+            let result = self.device.queue_present_khr(self.present_queue, &present_info);
+
+            // nothing else is worth trying once the device is gone
+            if result == Err(vk::ErrorCode::DEVICE_LOST) {
+                return Err(anyhow!(RenderError::DeviceLost));
+            }
+
+            // recreate if the swapchain no longer matches the surface
+            let changed = result == Ok(vk::SuccessCode::SUBOPTIMAL_KHR)
+                || result == Err(vk::ErrorCode::OUT_OF_DATE_KHR);
+
+            let window = &mut self.windows[index];
+            window.frame = (frame + 1) % MAX_FRAMES_IN_FLIGHT;
+            if !timeline_semaphore.is_null() {
+                window.timeline_value = timeline_value;
+            }
+
+            if window.resized || changed {
+                window.resized = true;
+                window.last_image_index = None;
+                self.recreate_window(index)?;
+            } else if let Err(e) = result {
+                return Err(anyhow!(e));
+            } else {
+                window.last_image_index = Some(image_index);
+            }
+
+            Ok(())
         }
-    }
 
-    /*
-     * creation functions
-     */
+        // blocks until the timeline reaches the value, values already reached return straight away
+        unsafe fn wait_timeline(&self, semaphore: vk::Semaphore, value: u64) -> Result<()> {
+            let semaphores = &[semaphore];
+            let values = &[value];
+            let info = vk::SemaphoreWaitInfo::builder()
+                .semaphores(semaphores)
+                .values(values);
 
-    unsafe fn create_instance(window: &Window, entry: &Entry) -> Result<(Instance, Option<DebugUtilsMessengerEXT>)> {
-        // create application info struct
-        let application_info = vk::ApplicationInfo::builder()
-            .application_name(b"Vulkan Testing\0")
-            .application_version(vk::make_version(1, 0, 0))
-            .engine_name(b"No Engine\0")
-            .engine_version(vk::make_version(1, 0, 0))
-            .api_version(vk::make_version(1, 0, 0));
+            self.device.wait_semaphores(&info, u64::MAX).map_err(render_error)?;
 
-        // get available layer names in a hashset
-        let available_layers = entry
-            .enumerate_instance_layer_properties()?
-            .iter()
-            .map(|l| l.layer_name)
-            .collect::<HashSet<_>>();
+            Ok(())
+        }
+
+        fn update_frame_times(&mut self, window: &Window) {
+            let now = Instant::now();
+            self.frame_delta = now - self.last_frame;
+            self.frame_times.push_back(self.frame_delta);
+            self.last_frame = now;
+
+            // report roughly once a second
+            let elapsed = now - self.last_report;
+            if elapsed < Duration::from_secs(1) {
+                return;
+            }
+
+            let frames = self.frame_times.len() as f64;
+            let fps = frames / elapsed.as_secs_f64();
+            let mean = self.frame_times.iter().sum::<Duration>().as_secs_f64() / frames;
+            let worst = self.frame_times.iter().max().copied().unwrap_or_default();
+
+            info!(
+                "{:.1} fps, mean frame time {:.2}ms, worst {:.2}ms",
+                fps,
+                mean * 1000.0,
+                worst.as_secs_f64() * 1000.0,
+            );
+
+            if !self.gpu_frame_times.is_empty() {
+                let gpu_frames = self.gpu_frame_times.len() as f64;
+                let gpu_mean = self.gpu_frame_times.iter().sum::<Duration>().as_secs_f64() / gpu_frames;
+                info!("mean gpu frame time {:.2}ms", gpu_mean * 1000.0);
+                self.gpu_frame_times.clear();
+            }
+            window.set_title(&format!("{} ({:.0} fps)", self.config.window_title, fps));
+
+            self.frame_times.clear();
+            self.last_report = now;
+        }
+
+        // the previous frame recorded for this image has already been waited on, so this never stalls
+        unsafe fn read_timestamps(&mut self, index: usize, image_index: usize) -> Result<()> {
+            let Some((period, mask)) = self.timestamp_properties else {
+                return Ok(());
+            };
+
+            let window = &self.windows[index];
+            if !window.timestamps_written[image_index] {
+                return Ok(());
+            }
+
+            let mut timestamps = [0u64; 2];
+            let data = std::slice::from_raw_parts_mut(timestamps.as_mut_ptr().cast::<u8>(), size_of_val(&timestamps));
+            self.device.get_query_pool_results(
+                window.timestamp_query_pool,
+                image_index as u32 * 2,
+                2,
+                data,
+                size_of::<u64>() as u64,
+                vk::QueryResultFlags::_64,
+            )?;
+
+            // period is in nanoseconds per tick
+            let ticks = (timestamps[1] & mask).wrapping_sub(timestamps[0] & mask) & mask;
+            self.gpu_frame_times.push_back(Duration::from_nanos((ticks as f64 * period as f64) as u64));
+
+            Ok(())
+        }
+
+        /// renders a single frame and reads it back as tightly packed rgba rows
+        ///
+        /// # Safety
+        ///
+        /// Any handles taken from the app have to be done with, the frame's resources are reused once it finishes.
+        pub unsafe fn render_offscreen(&mut self) -> Result<Vec<u8>> {
+            if !self.headless {
+                return Err(anyhow!("Only headless apps can render offscreen."));
+            }
+
+            self.debug_lines
+                .upload(&self.instance, &self.device, self.phys_device, &mut self.allocator, 0)?;
+            let frame = self.windows[0].frame;
+            self.begin_screenshot(frame)?;
+            self.update_command_buffer(0, 0)?;
+            self.update_uniform_buffer(&self.windows[0], 0)?;
+            if self.picker.is_some() && self.pick.take().is_some() {
+                self.picks_in_flight[frame] = true;
+            }
+
+            let fence = self.windows[0].in_flight_fences[0];
+            self.device.reset_fences(&[fence])?;
+
+            let command_buffers = &[self.windows[0].command_buffers[0]];
+            let submit_info = vk::SubmitInfo::builder().command_buffers(command_buffers);
+            self.device.queue_submit(self.graphics_queue, &[submit_info], fence)?;
+            self.device.wait_for_fences(&[fence], true, u64::MAX)?;
+            self.resolve_pick(frame)?;
+            self.finish_screenshot(frame);
+
+            let pixels = read_image(
+                &self.instance,
+                &self.device,
+                self.phys_device,
+                &mut self.allocator,
+                self.command_pool,
+                self.graphics_queue,
+                self.windows[0].images[0],
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                self.windows[0].extent,
+            )?;
+            self.windows[0].last_image_index = Some(0);
+
+            Ok(pixels)
+        }
+
+        /// draws ids in the main window's next frame to find the scene object under a window pixel, picked_object
+        /// has it once that frame finishes
+        ///
+        /// # Safety
+        ///
+        /// The app has to keep rendering the main window for the pick to resolve, its frame records the ids.
+        pub unsafe fn pick_object(&mut self, x: u32, y: u32) -> Result<()> {
+            let main = &self.windows[0];
+            if x >= main.extent.width || y >= main.extent.height {
+                return Ok(());
+            }
+
+            // window pixels to render target pixels, both with y going down since the projection flips it
+            let render_extent = main.render_extent;
+            let scale = |p: u32, from: u32, to: u32| (p as u64 * to as u64 / from as u64) as u32;
+            self.pick = Some((
+                scale(x, main.extent.width, render_extent.width),
+                scale(y, main.extent.height, render_extent.height),
+            ));
+
+            if self.picker.is_none() {
+                let picker = Picker::new(
+                    &self.instance,
+                    &self.device,
+                    self.phys_device,
+                    &mut self.allocator,
+                    self.pipeline_cache,
+                    self.descriptor_set_layout,
+                    get_depth_format(&self.instance, self.phys_device)?,
+                    render_extent,
+                    self.config.reverse_z,
+                )?;
+                picker.set_debug_names(&self.instance, &self.device)?;
+                self.picker = Some(picker);
+            }
+
+            Ok(())
+        }
+
+        // the last pick's scene object, 0 for the model and one past their index into objects for the rest, some of
+        // none when nothing was under it, taken so each pick is only seen once
+        pub fn picked_object(&mut self) -> Option<Option<u32>> {
+            self.picked.take()
+        }
+
+        // reads the frame's id once its fence or timeline value has been waited on
+        unsafe fn resolve_pick(&mut self, frame: usize) -> Result<()> {
+            if !mem::take(&mut self.picks_in_flight[frame]) {
+                return Ok(());
+            }
+            let Some(picker) = &self.picker else {
+                return Ok(());
+            };
+
+            let id = picker.read(&self.device, frame)?;
+            self.picked = Some((id != BACKGROUND_ID).then(|| id - 1));
+
+            Ok(())
+        }
+
+        // the device has to be idle, picks still in flight are resolved first
+        unsafe fn destroy_picker(&mut self) -> Result<()> {
+            for frame in 0..MAX_FRAMES_IN_FLIGHT {
+                self.resolve_pick(frame)?;
+            }
+            if let Some(mut picker) = self.picker.take() {
+                picker.destroy(&self.device, &mut self.allocator);
+            }
+            self.pick = None;
+
+            Ok(())
+        }
+
+        /// the main window's next frame copies its image before presenting, it's saved once that frame finishes
+        ///
+        /// # Safety
+        ///
+        /// The app has to keep rendering the main window, the copy is recorded into its next frame.
+        pub unsafe fn save_screenshot(&mut self, path: &Path) -> Result<()> {
+            if !self.headless {
+                let support = SwapchainSupport::get(&self.instance, self.windows[0].surface, self.phys_device)?;
+                if !support.capabilities.supported_usage_flags.contains(vk::ImageUsageFlags::TRANSFER_SRC) {
+                    return Err(anyhow!("Surface does not support copying from swapchain images."));
+                }
+            }
+
+            self.screenshot_request = Some(path.to_path_buf());
+
+            Ok(())
+        }
+
+        // a buffer for the frame about to be recorded to copy into, waits for the last screenshot to be saved
+        unsafe fn begin_screenshot(&mut self, frame: usize) -> Result<()> {
+            if self.screenshot.is_some() {
+                return Ok(());
+            }
+            let Some(path) = self.screenshot_request.take() else {
+                return Ok(());
+            };
+
+            let extent = self.windows[0].extent;
+            let (buffer, buffer_memory) = create_buffer(
+                &self.instance,
+                &self.device,
+                self.phys_device,
+                &mut self.allocator,
+                (extent.width * extent.height * 4) as u64,
+                vk::BufferUsageFlags::TRANSFER_DST,
+                vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+                "screenshot",
+            )?;
+            self.screenshot = Some(Screenshot { path, buffer, buffer_memory, extent, frame });
+
+            Ok(())
+        }
+
+        // once the frame that copied it has been waited on, a failed save is logged rather than stopping rendering
+        unsafe fn finish_screenshot(&mut self, frame: usize) {
+            if self.screenshot.as_ref().is_none_or(|s| s.frame != frame) {
+                return;
+            }
+            let Some(screenshot) = self.screenshot.take() else {
+                return;
+            };
+
+            match self.write_screenshot(&screenshot) {
+                Ok(()) => info!("Saved screenshot ({}).", screenshot.path.display()),
+                Err(e) => error!("Failed to save screenshot ({}): {}", screenshot.path.display(), e),
+            }
+            self.device.destroy_buffer(screenshot.buffer, None);
+            self.allocator.free(&self.device, screenshot.buffer_memory);
+        }
+
+        unsafe fn write_screenshot(&self, screenshot: &Screenshot) -> Result<()> {
+            let Screenshot { buffer_memory, extent, .. } = *screenshot;
+            let size = (extent.width * extent.height * 4) as u64;
+
+            let memory = self.device.map_memory(
+                buffer_memory.memory,
+                buffer_memory.offset,
+                size,
+                vk::MemoryMapFlags::empty(),
+            )?;
+            let mut pixels = vec![0u8; size as usize];
+            memcpy(memory.cast(), pixels.as_mut_ptr(), pixels.len());
+            self.device.unmap_memory(buffer_memory.memory);
+
+            let pixels = convert_to_rgba8(self.swapchain_format, pixels)?;
+            save_png(&screenshot.path, &pixels, extent.width, extent.height)
+        }
+
+        /// # Safety
+        ///
+        /// `window` has to be the one the app was created with.
+        pub unsafe fn recreate_swapchain(&mut self, window: &Window) -> Result<()> {
+            self.windows[0].window_extent = window_extent(window);
+            self.windows[0].resized = true;
+            self.recreate_window(0)
+        }
+
+        // a zero sized swapchain can't be made, a minimized window keeps its old one and stays resized until it's
+        // restored
+        unsafe fn recreate_window(&mut self, index: usize) -> Result<()> {
+            if self.windows[index].is_minimized() {
+                return Ok(());
+            }
+
+            self.device.device_wait_idle()?;
+            // the ids are drawn at the main window's render extent, a screenshot still in flight can be saved now
+            if index == 0 {
+                self.destroy_picker()?;
+                if let Some(frame) = self.screenshot.as_ref().map(|s| s.frame) {
+                    self.finish_screenshot(frame);
+                }
+            }
+            let mut window = mem::take(&mut self.windows[index]);
+            self.destroy_window_objects(&mut window);
+            let result = self
+                .create_window_swapchain(&mut window, index == 0)
+                .and_then(|_| self.create_window_objects(&mut window));
+            window.resized = result.is_err();
+            self.windows[index] = window;
+            result?;
+
+            // only the main window draws the overlay and lines, their buffers go by its image indices
+            if index == 0 {
+                let image_count = self.windows[0].images.len();
+                if let Some(gui) = &mut self.gui {
+                    gui.recreate_buffers(&self.device, &mut self.allocator, image_count);
+                }
+                self.debug_lines.recreate_buffers(&self.device, &mut self.allocator, image_count);
+            }
+
+            self.set_debug_names()
+        }
+
+        // the main window's format and scaling decide what the render pass and pipelines are built for, the other
+        // windows are recreated to match them and fail when they can't
+        unsafe fn create_window_swapchain(&mut self, window: &mut WindowContext, main: bool) -> Result<()> {
+            // handed to the new one so the driver can reuse what it can, only destroyed once it's replaced
+            let old_swapchain = window.swapchain;
+            let result = create_swapchain(
+                window.window_extent,
+                &self.instance,
+                &self.device,
+                window.surface,
+                self.phys_device,
+                &self.queue_families,
+                &self.config,
+                old_swapchain,
+            );
+            // it's retired even when creating the new one fails, and nulled so it's never destroyed twice
+            self.device.destroy_swapchain_khr(old_swapchain, None);
+            window.swapchain = vk::SwapchainKHR::null();
+            window.images.clear();
+            let (swapchain, images, format, extent) = result?;
+            window.swapchain = swapchain;
+            window.images = images;
+            window.extent = extent;
+            window.render_extent = get_render_extent(
+                &self.instance,
+                self.phys_device,
+                window.surface,
+                format,
+                extent,
+                self.config.render_scale,
+            )?;
+            let scaled = window.render_extent != extent;
+
+            if main && (format != self.swapchain_format || scaled != self.supersampling) {
+                self.swapchain_format = format;
+                self.supersampling = scaled;
+                self.recreate_render_pass()?;
+                // their targets and framebuffers were made for the old formats and render pass
+                self.windows.iter_mut().for_each(|w| w.resized = true);
+            } else if format != self.swapchain_format {
+                return Err(anyhow!(
+                    "The window's swapchain format ({:?}) doesn't match the main window's ({:?}).",
+                    format,
+                    self.swapchain_format,
+                ));
+            } else if scaled != self.supersampling && !self.dynamic_rendering {
+                // the render pass leaves its target ready to be blitted down or presented, not either
+                return Err(anyhow!("The window can't be supersampled when the main window isn't, or the other way."));
+            }
+
+            Ok(())
+        }
+
+        // viewport and scissor are dynamic, so only a new format or final layout needs a new render pass
+        unsafe fn recreate_render_pass(&mut self) -> Result<()> {
+            self.destroy_render_pass();
+            self.render_pass = vk::RenderPass::null();
+            if !self.dynamic_rendering {
+                self.render_pass = create_render_pass(
+                    &self.instance,
+                    &self.device,
+                    self.phys_device,
+                    self.swapchain_format,
+                    self.scene_format,
+                    self.msaa_samples,
+                    get_final_layout(self.headless, self.supersampling),
+                )?;
+            }
+            let (scene_target, post_target) = self.pipeline_targets()?;
+            (
+                self.pipeline_layout,
+                self.pipeline,
+                self.wireframe_pipeline,
+                self.outline_pipeline,
+                self.depth_prepass_pipeline,
+                self.depth_equal_pipeline,
+                self.alpha_blend_pipeline,
+                self.additive_pipeline,
+            ) = create_pipeline(
+                &self.device,
+                &self.config,
+                self.pipeline_cache,
+                scene_target,
+                self.descriptor_set_layout,
+                self.bindless.as_ref().map(|b| b.descriptor_set_layout()),
+                self.msaa_samples,
+                self.wireframe_line_width,
+                self.min_sample_shading,
+            )?;
+            (self.post_pipeline_layout, self.post_pipeline) = create_post_pipeline(
+                &self.device,
+                self.pipeline_cache,
+                post_target,
+                self.post_descriptor_set_layout,
+            )?;
+            if let Some(gui) = &mut self.gui {
+                gui.recreate_pipeline(&self.device, self.pipeline_cache, post_target, self.swapchain_format)?;
+            }
+            if let Some(skybox) = &mut self.skybox {
+                skybox.recreate_pipeline(&self.device, self.pipeline_cache, scene_target, self.msaa_samples)?;
+            }
+            if let Some(particles) = &mut self.particles {
+                particles.recreate_pipeline(&self.device, self.pipeline_cache, scene_target, self.msaa_samples)?;
+            }
+            self.debug_lines
+                .recreate_pipeline(&self.device, self.pipeline_cache, scene_target, self.msaa_samples)?;
+
+            Ok(())
+        }
+
+        // what a window waits on and signals, made once and kept however often its swapchain is recreated
+        unsafe fn create_window_sync(&self, window: &mut WindowContext) -> Result<()> {
+            (
+                window.image_available_semaphores,
+                window.render_finished_semaphores,
+                window.in_flight_fences,
+                window.images_in_flight,
+            ) = create_sync_objects(&self.device, window.images.len())?;
+            if self.timeline_semaphores {
+                window.timeline_semaphore = create_timeline_semaphore(&self.device)?;
+            }
+            window.images_in_flight_values = vec![0; window.images.len()];
+
+            Ok(())
+        }
+
+        // everything the window renders with at its size and image count, after its swapchain and render extent
+        unsafe fn create_window_objects(&mut self, window: &mut WindowContext) -> Result<()> {
+            window.image_views = create_swapchain_image_views(&self.device, &window.images, self.swapchain_format)?;
+            (window.scaled_image, window.scaled_image_memory, window.scaled_image_view) = create_scaled_objects(
+                &self.instance,
+                &self.device,
+                self.phys_device,
+                &mut self.allocator,
+                self.swapchain_format,
+                window.extent,
+                window.render_extent,
+            )?;
+
+            // multisampled targets that get resolved into the scene image
+            (window.color_image, window.color_image_memory, window.color_image_view) = create_color_objects(
+                &self.instance,
+                &self.device,
+                self.phys_device,
+                &mut self.allocator,
+                self.scene_format,
+                window.render_extent,
+                self.msaa_samples,
+            )?;
+            (window.depth_image, window.depth_image_memory, window.depth_image_view) = create_depth_objects(
+                &self.instance,
+                &self.device,
+                self.phys_device,
+                &mut self.allocator,
+                window.render_extent,
+                self.msaa_samples,
+            )?;
+            (window.scene_image, window.scene_image_memory, window.scene_image_view) = create_scene_objects(
+                &self.instance,
+                &self.device,
+                self.phys_device,
+                &mut self.allocator,
+                self.scene_format,
+                window.render_extent,
+                self.dynamic_rendering,
+            )?;
+            match &mut window.bloom {
+                Some(bloom) => bloom.resize(
+                    &self.instance,
+                    &self.device,
+                    self.phys_device,
+                    &mut self.allocator,
+                    window.scene_image_view,
+                    window.render_extent,
+                )?,
+                None if self.bloom_enabled => {
+                    window.bloom = Some(Bloom::new(
+                        &self.instance,
+                        &self.device,
+                        self.phys_device,
+                        &mut self.allocator,
+                        self.pipeline_cache,
+                        window.scene_image_view,
+                        window.render_extent,
+                        self.config.bloom_threshold,
+                    )?)
+                }
+                None => {}
+            }
+
+            if !self.dynamic_rendering {
+                window.framebuffers = create_framebuffers(
+                    &self.device,
+                    self.render_pass,
+                    &window.image_views,
+                    window.scaled_image_view,
+                    window.color_image_view,
+                    window.depth_image_view,
+                    window.scene_image_view,
+                    window.render_extent,
+                )?;
+            }
+
+            (window.uniform_buffers, window.uniform_buffers_memory) = create_uniform_buffers::<UniformBufferObject>(
+                &self.instance,
+                &self.device,
+                self.phys_device,
+                &mut self.allocator,
+                &window.images,
+                "uniform-buffer",
+            )?;
+            (window.light_buffers, window.light_buffers_memory) = create_uniform_buffers::<LightUniform>(
+                &self.instance,
+                &self.device,
+                self.phys_device,
+                &mut self.allocator,
+                &window.images,
+                "light-buffer",
+            )?;
+            (window.object_buffers, window.object_buffers_memory) = create_object_buffers(
+                &self.instance,
+                &self.device,
+                self.phys_device,
+                &mut self.allocator,
+                &window.images,
+                self.object_alignment,
+            )?;
+            window.descriptor_pool = create_descriptor_pool(&self.device, &window.images)?;
+            window.descriptor_sets = create_descriptor_sets(
+                &self.device,
+                self.descriptor_set_layout,
+                window.descriptor_pool,
+                &window.uniform_buffers,
+                &window.object_buffers,
+                &window.light_buffers,
+                self.texture_image_view,
+                self.normal_map_image_view,
+                self.texture_sampler,
+                self.shadow_map.image_view(),
+                self.shadow_map.sampler(),
+            )?;
+            window.post_descriptor_pool = create_post_descriptor_pool(&self.device, self.dynamic_rendering)?;
+            window.post_descriptor_set = create_post_descriptor_set(
+                &self.device,
+                self.post_descriptor_set_layout,
+                window.post_descriptor_pool,
+                window.scene_image_view,
+                self.post_sampler,
+                window.bloom.as_ref(),
+            )?;
+
+            window.command_buffers = create_command_buffers(&self.device, self.command_pool, window.images.len())?;
+            window.secondary_command_buffers = create_secondary_command_buffers(
+                &self.device,
+                &self.secondary_command_pools,
+                window.images.len(),
+            )?;
+
+            // the image count may have changed
+            window.images_in_flight.resize(window.images.len(), vk::Fence::null());
+            window.images_in_flight_values.resize(window.images.len(), 0);
+
+            // two timestamps per swapchain image, around the render pass
+            if self.timestamp_properties.is_some() {
+                window.timestamp_query_pool = create_timestamp_query_pool(&self.device, window.images.len())?;
+            }
+            window.timestamps_written = vec![false; window.images.len()];
+
+            Ok(())
+        }
+
+        /// a shader that fails to load or compile keeps the old pipelines, command buffers pick up new ones next frame
+        ///
+        /// # Safety
+        ///
+        /// No handle to the old pipelines can be kept outside the app, they're destroyed once replaced.
+        pub unsafe fn reload_shaders(&mut self) -> Result<()> {
+            self.device.device_wait_idle()?;
+
+            let (scene_target, post_target) = self.pipeline_targets()?;
+            let result = create_pipeline(
+                &self.device,
+                &self.config,
+                self.pipeline_cache,
+                scene_target,
+                self.descriptor_set_layout,
+                self.bindless.as_ref().map(|b| b.descriptor_set_layout()),
+                self.msaa_samples,
+                self.wireframe_line_width,
+                self.min_sample_shading,
+            );
+
+            let (
+                pipeline_layout,
+                pipeline,
+                wireframe_pipeline,
+                outline_pipeline,
+                depth_prepass_pipeline,
+                depth_equal_pipeline,
+                alpha_blend_pipeline,
+                additive_pipeline,
+            ) = match result {
+                Ok(pipelines) => pipelines,
+                Err(e) => {
+                    error!("Failed to reload shaders, keeping the old pipeline: {}", e);
+                    return Ok(());
+                }
+            };
+
+            let result =
+                create_post_pipeline(&self.device, self.pipeline_cache, post_target, self.post_descriptor_set_layout);
+            let (post_pipeline_layout, post_pipeline) = match result {
+                Ok(pipelines) => pipelines,
+                Err(e) => {
+                    error!("Failed to reload post-processing shaders, keeping the old pipelines: {}", e);
+                    [
+                        pipeline,
+                        wireframe_pipeline,
+                        outline_pipeline,
+                        depth_prepass_pipeline,
+                        depth_equal_pipeline,
+                        alpha_blend_pipeline,
+                        additive_pipeline,
+                    ]
+                    .into_iter()
+                        .filter(|p| !p.is_null())
+                        .for_each(|p| self.device.destroy_pipeline(p, None));
+                    self.device.destroy_pipeline_layout(pipeline_layout, None);
+                    return Ok(());
+                }
+            };
+
+            self.destroy_pipelines();
+
+            self.pipeline_layout = pipeline_layout;
+            self.pipeline = pipeline;
+            self.wireframe_pipeline = wireframe_pipeline;
+            self.outline_pipeline = outline_pipeline;
+            self.depth_prepass_pipeline = depth_prepass_pipeline;
+            self.depth_equal_pipeline = depth_equal_pipeline;
+            self.alpha_blend_pipeline = alpha_blend_pipeline;
+            self.additive_pipeline = additive_pipeline;
+            self.post_pipeline_layout = post_pipeline_layout;
+            self.post_pipeline = post_pipeline;
+
+            if let Some(gui) = &mut self.gui {
+                if let Err(e) = gui.recreate_pipeline(&self.device, self.pipeline_cache, post_target, self.swapchain_format) {
+                    // the old pipeline is already gone, so there's no overlay until the next successful reload
+                    error!("Failed to reload gui shaders: {}", e);
+                }
+            }
+            if let Some(skybox) = &mut self.skybox {
+                if let Err(e) = skybox.recreate_pipeline(&self.device, self.pipeline_cache, scene_target, self.msaa_samples) {
+                    error!("Failed to reload skybox shaders: {}", e);
+                }
+            }
+            if let Some(particles) = &mut self.particles {
+                let result = particles
+                    .recreate_pipeline(&self.device, self.pipeline_cache, scene_target, self.msaa_samples)
+                    .and_then(|_| particles.recreate_compute_pipeline(&self.device, self.pipeline_cache));
+                if let Err(e) = result {
+                    error!("Failed to reload particle shaders: {}", e);
+                }
+            }
+            for bloom in self.windows.iter_mut().filter_map(|w| w.bloom.as_mut()) {
+                if let Err(e) = bloom.recreate_pipelines(&self.device, self.pipeline_cache) {
+                    error!("Failed to reload bloom shaders: {}", e);
+                }
+            }
+            let result = self
+                .debug_lines
+                .recreate_pipeline(&self.device, self.pipeline_cache, scene_target, self.msaa_samples);
+            if let Err(e) = result {
+                error!("Failed to reload debug line shaders: {}", e);
+            }
+            let result = self
+                .shadow_map
+                .recreate_pipeline(&self.device, self.pipeline_cache, self.descriptor_set_layout);
+            if let Err(e) = result {
+                error!("Failed to reload shadow shaders: {}", e);
+            }
+            // rebuilt with the new shaders on the next pick
+            self.destroy_picker()?;
+            info!("Reloaded shaders.");
+
+            self.set_debug_names()
+        }
+
+        // names the objects recreated with the swapchain so validation messages are readable
+        unsafe fn set_debug_names(&self) -> Result<()> {
+            if self.headless {
+                set_debug_name(&self.instance, &self.device, self.windows[0].images[0], "offscreen-image")?;
+            }
+
+            if !self.render_pass.is_null() {
+                set_debug_name(&self.instance, &self.device, self.render_pass, "main-render-pass")?;
+            }
+            set_debug_name(&self.instance, &self.device, self.pipeline, "main-pipeline")?;
+            set_debug_name(&self.instance, &self.device, self.depth_prepass_pipeline, "depth-prepass-pipeline")?;
+            set_debug_name(&self.instance, &self.device, self.post_pipeline, "post-pipeline")?;
+            if let Some(gui) = &self.gui {
+                gui.set_debug_names(&self.instance, &self.device)?;
+            }
+            if let Some(skybox) = &self.skybox {
+                skybox.set_debug_names(&self.instance, &self.device)?;
+            }
+            if let Some(particles) = &self.particles {
+                particles.set_debug_names(&self.instance, &self.device)?;
+            }
+            self.debug_lines.set_debug_names(&self.instance, &self.device)?;
+            self.shadow_map.set_debug_names(&self.instance, &self.device)?;
+            if let Some(bindless) = &self.bindless {
+                bindless.set_debug_names(&self.instance, &self.device)?;
+            }
+            if !self.outline_pipeline.is_null() {
+                set_debug_name(&self.instance, &self.device, self.outline_pipeline, "outline-pipeline")?;
+            }
+
+            for (w, window) in self.windows.iter().enumerate() {
+                // the main window keeps the names it had alone
+                let prefix = if w == 0 { String::new() } else { format!("window-{}-", w) };
+                if !window.swapchain.is_null() {
+                    let name = if w == 0 { "main-swapchain".to_string() } else { format!("{}swapchain", prefix) };
+                    set_debug_name(&self.instance, &self.device, window.swapchain, &name)?;
+                }
+                if let Some(bloom) = &window.bloom {
+                    bloom.set_debug_names(&self.instance, &self.device)?;
+                }
+                for (i, command_buffer) in window.command_buffers.iter().enumerate() {
+                    let name = format!("{}command-buffer-{}", prefix, i);
+                    set_debug_name(&self.instance, &self.device, *command_buffer, &name)?;
+                }
+            }
+
+            Ok(())
+        }
+
+        /// # Safety
+        ///
+        /// The old model's buffers are destroyed, nothing outside the app can still reference them.
+        pub unsafe fn set_model(&mut self, path: &str) -> Result<()> {
+            // load first so a bad path leaves the current model in place
+            let (vertices, indices) = load_model(path, self.config.flat_normals)?;
+
+            // the new buffers are made before the old ones go, a failed upload leaves the current model in place
+            let uploader = self.uploader();
+            let (vertex_buffer, vertex_buffer_memory) = create_vertex_buffer(
+                &self.instance,
+                &self.device,
+                self.phys_device,
+                &mut self.allocator,
+                &uploader,
+                &vertices,
+                "model-vertices",
+            )?;
+            let (index_buffer, index_buffer_memory) = match create_index_buffer(
+                &self.instance,
+                &self.device,
+                self.phys_device,
+                &mut self.allocator,
+                &uploader,
+                &indices,
+                "model-indices",
+            ) {
+                Ok(index_buffer) => index_buffer,
+                Err(e) => {
+                    self.device.destroy_buffer(vertex_buffer, None);
+                    self.allocator.free(&self.device, vertex_buffer_memory);
+                    return Err(e);
+                }
+            };
+
+            self.device.device_wait_idle()?;
+            // they could point past the new model's indices
+            self.clear_draw_commands();
+
+            self.device.destroy_buffer(self.index_buffer, None);
+            self.allocator.free(&self.device, self.index_buffer_memory);
+            self.device.destroy_buffer(self.vertex_buffer, None);
+            self.allocator.free(&self.device, self.vertex_buffer_memory);
+            (self.vertex_buffer, self.vertex_buffer_memory) = (vertex_buffer, vertex_buffer_memory);
+            (self.index_buffer, self.index_buffer_memory) = (index_buffer, index_buffer_memory);
+
+            self.model_path = path.to_string();
+            self.index_type = u32::INDEX_TYPE;
+            self.index_count = indices.len() as u32;
+            self.model_bounds = Aabb::from_vertices(&vertices);
+            self.vertices = vertices;
+            self.indices = indices;
+
+            Ok(())
+        }
+
+        // pins the scene's animation to a time in seconds, none goes back to the time since the app was created
+        pub fn set_time(&mut self, time: Option<f32>) {
+            self.fixed_time = time;
+        }
+
+        // read by every frame from the next one recorded, the direction doesn't need to be normalized
+        pub fn set_light(&mut self, light: Light) {
+            self.light = light;
+        }
+
+        // the next frame recorded skips or draws the shadow pass
+        pub fn set_shadows(&mut self, enabled: bool) {
+            self.config.shadows = enabled;
+        }
+
+        // drawn in world space over the next frame only, lines are collected until it's rendered
+        pub fn draw_line(&mut self, a: Vec3, b: Vec3, color: Vec3) {
+            self.debug_lines.line(a, b, color);
+        }
+
+        // the edges of an axis aligned box, e.g. an object's bounds after its transform
+        pub fn draw_aabb(&mut self, min: Vec3, max: Vec3, color: Vec3) {
+            self.debug_lines.aabb(min, max, color);
+        }
+
+        /// uploads another mesh drawn alongside the model, returns its index into objects
+        ///
+        /// # Safety
+        ///
+        /// The app's queue can't be used elsewhere while the mesh uploads through it.
+        pub unsafe fn add_object<I: Index>(&mut self, vertices: &[Vertex], indices: &[I]) -> Result<usize> {
+            // the model takes the first transform slot
+            if self.objects.len() + 1 >= MAX_OBJECTS {
+                return Err(anyhow!("Can't add more than {} objects.", MAX_OBJECTS - 1));
+            }
+
+            let uploader = self.uploader();
+            let (vertex_buffer, vertex_buffer_memory) = create_vertex_buffer(
+                &self.instance,
+                &self.device,
+                self.phys_device,
+                &mut self.allocator,
+                &uploader,
+                vertices,
+                &format!("object-vertices-{}", self.objects.len()),
+            )?;
+            let (index_buffer, index_buffer_memory) = create_index_buffer(
+                &self.instance,
+                &self.device,
+                self.phys_device,
+                &mut self.allocator,
+                &uploader,
+                indices,
+                &format!("object-indices-{}", self.objects.len()),
+            )?;
+
+            self.objects.push(RenderObject {
+                vertex_buffer,
+                vertex_buffer_memory,
+                index_buffer,
+                index_buffer_memory,
+                index_type: I::INDEX_TYPE,
+                index_count: indices.len() as u32,
+                bounds: Aabb::from_vertices(vertices),
+                transform: Mat4::identity(),
+                blend_mode: BlendMode::Opaque,
+                texture_index: 0,
+            });
+
+            Ok(self.objects.len() - 1)
+        }
+
+        /// adds every triangle primitive in the model's scene as an object, returning their indices into objects
+        /// base color textures need bindless textures, without them every primitive samples the first texture layer
+        ///
+        /// # Safety
+        ///
+        /// The app's queue can't be used elsewhere while the meshes and textures upload through it.
+        pub unsafe fn load_gltf(&mut self, path: &str) -> Result<Vec<usize>> {
+            let model = gltf_model::load(path)?;
+            if self.objects.len() + 1 + model.primitives.len() > MAX_OBJECTS {
+                return Err(anyhow!(
+                    "glTF model ({}) has {} primitives, only {} more objects fit.",
+                    path,
+                    model.primitives.len(),
+                    MAX_OBJECTS - 1 - self.objects.len()
+                ));
+            }
+
+            // primitives without a material take the slot after the last one
+            let materials = model.materials.iter().copied().chain([Material::default()]).collect::<Vec<_>>();
+            let textures = self.add_gltf_textures(&model, &materials)?;
+
+            let mut objects = Vec::with_capacity(model.primitives.len());
+            for primitive in &model.primitives {
+                let material = primitive.material.unwrap_or(model.materials.len());
+                let object = self.add_object(&primitive.vertices, &primitive.indices)?;
+                self.objects[object].transform = primitive.transform;
+                self.objects[object].blend_mode = materials[material].blend_mode;
+                self.objects[object].texture_index = textures[material];
+                objects.push(object);
+            }
+
+            Ok(objects)
+        }
+
+        // a bindless slot for each material, shared between materials using the same image
+        unsafe fn add_gltf_textures(&mut self, model: &gltf_model::Model, materials: &[Material]) -> Result<Vec<u32>> {
+            if self.bindless.is_none() {
+                if materials.iter().any(|m| m.base_color_texture.is_some()) {
+                    warn!("glTF base color textures need bindless textures, the first texture layer is used.");
+                }
+                return Ok(vec![0; materials.len()]);
+            }
+
+            let mut images = HashMap::new();
+            let mut textures = Vec::with_capacity(materials.len());
+            for material in materials {
+                let index = match material.base_color_texture {
+                    Some(image) => match images.get(&image) {
+                        Some(index) => *index,
+                        None => {
+                            let (pixels, width, height) = &model.images[image];
+                            let index = self.add_texture_pixels(pixels, *width, *height)?;
+                            images.insert(image, index);
+                            index
+                        }
+                    },
+                    None => self.add_texture_pixels(&material.base_color_pixel(), 1, 1)?,
+                };
+                textures.push(index);
+            }
+
+            Ok(textures)
+        }
+
+        /// the first instance is always the plain one used by every other draw, so the uploaded buffer is one longer
+        /// draw commands already set are dropped, they could point past the new instances
+        ///
+        /// # Safety
+        ///
+        /// The old instance buffer is destroyed, nothing outside the app can still reference it.
+        pub unsafe fn set_instances(&mut self, instances: Vec<InstanceData>) -> Result<()> {
+            let instances = [InstanceData::default()].into_iter().chain(instances).collect::<Vec<_>>();
+
+            self.device.device_wait_idle()?;
+            self.clear_draw_commands();
+
+            self.device.destroy_buffer(self.instance_buffer, None);
+            self.allocator.free(&self.device, self.instance_buffer_memory);
+
+            let uploader = self.uploader();
+            (self.instance_buffer, self.instance_buffer_memory) = create_vertex_buffer(
+                &self.instance,
+                &self.device,
+                self.phys_device,
+                &mut self.allocator,
+                &uploader,
+                &instances,
+                "instances",
+            )?;
+            self.instance_count = instances.len() as u32 - 1;
+
+            Ok(())
+        }
+
+        /// replaces the model's draw, instances are read from the instance buffer so 0 is the plain model and 1 onwards
+        /// are from set_instances, an empty list goes back to drawing it directly, as do set_model and set_instances
+        ///
+        /// # Safety
+        ///
+        /// The old indirect buffer is destroyed, nothing outside the app can still reference it.
+        pub unsafe fn set_draw_commands(&mut self, commands: Vec<vk::DrawIndexedIndirectCommand>) -> Result<()> {
+            for command in &commands {
+                if command.first_index as u64 + command.index_count as u64 > self.index_count as u64 {
+                    return Err(anyhow!("Draw command indices are past the model's {} indices.", self.index_count));
+                }
+                if command.first_instance as u64 + command.instance_count as u64 > self.instance_count as u64 + 1 {
+                    return Err(anyhow!("Draw command instances are past the {} set instances.", self.instance_count));
+                }
+                if command.first_instance != 0 && self.phys_device_features.draw_indirect_first_instance != vk::TRUE {
+                    return Err(anyhow!("Draw commands can't start past the first instance on this device."));
+                }
+            }
+
+            let max_count = self.phys_device_properties.limits.max_draw_indirect_count;
+            if self.phys_device_features.multi_draw_indirect == vk::TRUE && commands.len() as u64 > max_count as u64 {
+                return Err(anyhow!("At most {} draw commands can be drawn at once, got {}.", max_count, commands.len()));
+            }
+
+            self.device.device_wait_idle()?;
+
+            self.clear_draw_commands();
+            if commands.is_empty() {
+                return Ok(());
+            }
+
+            let uploader = self.uploader();
+            (self.draw_commands_buffer, self.draw_commands_buffer_memory) = create_device_local_buffer(
+                &self.instance,
+                &self.device,
+                self.phys_device,
+                &mut self.allocator,
+                &uploader,
+                &commands,
+                vk::BufferUsageFlags::INDIRECT_BUFFER,
+                "draw-commands",
+            )?;
+            self.draw_command_count = commands.len() as u32;
+
+            Ok(())
+        }
+
+        // back to drawing the model directly, the device has to be done with the buffer
+        unsafe fn clear_draw_commands(&mut self) {
+            self.device.destroy_buffer(self.draw_commands_buffer, None);
+            self.allocator.free(&self.device, self.draw_commands_buffer_memory);
+            self.draw_commands_buffer = vk::Buffer::null();
+            self.draw_commands_buffer_memory = Allocation::default();
+            self.draw_command_count = 0;
+        }
+
+        /// faces in cube map order, see skybox::FACES, replaces any skybox already set
+        ///
+        /// # Safety
+        ///
+        /// Any skybox already set is destroyed, nothing outside the app can still reference its images.
+        pub unsafe fn set_skybox(&mut self, faces: [&str; skybox::FACES]) -> Result<()> {
+            let (scene_target, _) = self.pipeline_targets()?;
+            let uploader = self.uploader();
+            let skybox = Skybox::new(
+                &self.instance,
+                &self.device,
+                self.phys_device,
+                &mut self.allocator,
+                &uploader,
+                self.pipeline_cache,
+                scene_target,
+                self.msaa_samples,
+                self.config.reverse_z,
+                faces,
+            )?;
+
+            self.device.device_wait_idle()?;
+            if let Some(mut old) = self.skybox.replace(skybox) {
+                old.destroy(&self.device, &mut self.allocator);
+            }
+
+            Ok(())
+        }
+
+        /// returns the index objects use to sample it, only bindless textures can be added after creation
+        ///
+        /// # Safety
+        ///
+        /// The app's queue can't be used elsewhere while the texture uploads through it.
+        pub unsafe fn add_texture(&mut self, path: &str) -> Result<u32> {
+            let uploader = self.uploader();
+            let bindless = self
+                .bindless
+                .as_mut()
+                .ok_or_else(|| anyhow!("Textures can only be added with bindless textures enabled."))?;
+
+            // the set is update after bind, but not while a pending submission still uses it
+            self.device.device_wait_idle()?;
+            bindless.add(
+                &self.instance,
+                &self.device,
+                self.phys_device,
+                &mut self.allocator,
+                &uploader,
+                self.texture_sampler,
+                path,
+            )
+        }
+
+        /// the same from rgba pixels
+        ///
+        /// # Safety
+        ///
+        /// The app's queue can't be used elsewhere while the pixels upload through it.
+        pub unsafe fn add_texture_pixels(&mut self, pixels: &[u8], width: u32, height: u32) -> Result<u32> {
+            let uploader = self.uploader();
+            let bindless = self
+                .bindless
+                .as_mut()
+                .ok_or_else(|| anyhow!("Textures can only be added with bindless textures enabled."))?;
+
+            self.device.device_wait_idle()?;
+            bindless.add_pixels(
+                &self.instance,
+                &self.device,
+                self.phys_device,
+                &mut self.allocator,
+                &uploader,
+                self.texture_sampler,
+                pixels,
+                width,
+                height,
+            )
+        }
+
+        /// replaces any particles already set, they're integrated on the gpu every frame from here
+        ///
+        /// # Safety
+        ///
+        /// Any particles already set are destroyed, nothing outside the app can still reference their buffers.
+        pub unsafe fn set_particles(&mut self, particles: &[Particle]) -> Result<()> {
+            if particles.is_empty() {
+                return Err(anyhow!("A particle system needs at least one particle."));
+            }
+
+            // wider points need the large points feature, it's enabled whenever it's there
+            let range = self.phys_device_properties.limits.point_size_range;
+            let point_size = if self.phys_device_features.large_points == vk::TRUE {
+                PARTICLE_POINT_SIZE.clamp(range[0], range[1])
+            } else {
+                1.0
+            };
+
+            let (scene_target, _) = self.pipeline_targets()?;
+            let uploader = self.uploader();
+            let new = Particles::new(
+                &self.instance,
+                &self.device,
+                self.phys_device,
+                &mut self.allocator,
+                &uploader,
+                self.pipeline_cache,
+                scene_target,
+                self.msaa_samples,
+                self.config.reverse_z,
+                point_size,
+                particles,
+            )?;
+
+            self.device.device_wait_idle()?;
+            if let Some(mut old) = self.particles.replace(new) {
+                old.destroy(&self.device, &mut self.allocator);
+            }
+
+            Ok(())
+        }
+
+        fn uploader(&self) -> Uploader {
+            Uploader {
+                transfer_family: self.queue_families.transfer.unwrap_or(self.queue_families.graphics),
+                transfer_pool: self.transfer_command_pool,
+                transfer_queue: self.transfer_queue,
+                graphics_family: self.queue_families.graphics,
+                graphics_pool: self.command_pool,
+                graphics_queue: self.graphics_queue,
+            }
+        }
+
+        /// stored in the config so it survives device recreation, the swapchain is rebuilt to apply it
+        ///
+        /// # Safety
+        ///
+        /// `window` has to be the one the app was created with.
+        pub unsafe fn set_present_mode(&mut self, window: &Window, present_mode: vk::PresentModeKHR) -> Result<()> {
+            if self.headless {
+                return Err(anyhow!("Headless apps have no swapchain to present with."));
+            }
+
+            self.config.preferred_present_mode = present_mode;
+            self.windows.iter_mut().for_each(|w| w.resized = true);
+            self.recreate_swapchain(window)
+        }
+
+        /// supersampling is set up again for the new scale, headless apps keep the one they were made with
+        ///
+        /// # Safety
+        ///
+        /// `window` has to be the one the app was created with.
+        pub unsafe fn set_render_scale(&mut self, window: &Window, scale: f32) -> Result<()> {
+            if self.headless {
+                return Err(anyhow!("Headless apps can't change their render scale."));
+            }
+
+            self.config.render_scale = scale;
+            self.windows.iter_mut().for_each(|w| w.resized = true);
+            self.recreate_swapchain(window)
+        }
+
+        /// immediate when off, fifo when on, mailbox also waits for vblank so it counts as on
+        ///
+        /// # Safety
+        ///
+        /// `window` has to be the one the app was created with.
+        pub unsafe fn set_vsync(&mut self, window: &Window, enabled: bool) -> Result<()> {
+            let present_mode = if enabled {
+                vk::PresentModeKHR::FIFO
+            } else {
+                vk::PresentModeKHR::IMMEDIATE
+            };
+
+            self.set_present_mode(window, present_mode)
+        }
+
+        pub fn vsync(&self) -> bool {
+            self.config.preferred_present_mode != vk::PresentModeKHR::IMMEDIATE
+        }
+
+        /// another window rendering the scene at its own size, with its own targets, command buffers and sync objects
+        /// the surface has to be presentable from the present family the device was made with and take the main
+        /// swapchain's format, returns its index in windows
+        ///
+        /// # Safety
+        ///
+        /// `window` has to outlive its context, which remove_window or dropping the app destroys.
+        pub unsafe fn add_window(&mut self, window: &Window) -> Result<usize> {
+            if self.headless {
+                return Err(anyhow!("Headless apps have no frames to show in other windows."));
+            }
+
+            let mut context = WindowContext {
+                surface: vk_window::create_surface(&self.instance, &window, &window)?,
+                window_extent: window_extent(window),
+                ..Default::default()
+            };
+
+            // the surface and anything made after it go when any of it fails
+            if let Err(e) = self.create_window(&mut context) {
+                self.destroy_window(&mut context);
+                return Err(e);
+            }
+
+            self.windows.push(context);
+            self.set_debug_names()?;
+
+            Ok(self.windows.len() - 1)
+        }
+
+        unsafe fn create_window(&mut self, window: &mut WindowContext) -> Result<()> {
+            let present = self.queue_families.present;
+            if !self.instance.get_physical_device_surface_support_khr(self.phys_device, present, window.surface)? {
+                return Err(anyhow!("The window can't be presented to from the present queue family ({}).", present));
+            }
+
+            let support = SwapchainSupport::get(&self.instance, window.surface, self.phys_device)?;
+            if support.formats.is_empty() || support.present_modes.is_empty() {
+                return Err(anyhow!(DeviceError::SurfaceUnsupported));
+            }
+
+            self.create_window_sync(window)?;
+
+            // a window that starts minimized gets its swapchain once it's restored
+            if window.is_minimized() {
+                window.resized = true;
+                return Ok(());
+            }
+
+            self.create_window_swapchain(window, false)?;
+            self.create_window_objects(window)
+        }
+
+        /// waits for the device, the other windows' indices past it move down by one
+        ///
+        /// # Safety
+        ///
+        /// The window at `index` has to still be alive, its surface is destroyed here.
+        pub unsafe fn remove_window(&mut self, index: usize) -> Result<()> {
+            if index == 0 {
+                return Err(anyhow!("The main window can't be removed."));
+            }
+            if index >= self.windows.len() {
+                return Err(anyhow!("No window at index {}, there are {}.", index, self.windows.len()));
+            }
+
+            self.device.device_wait_idle()?;
+            let mut window = self.windows.remove(index);
+            self.destroy_window(&mut window);
+
+            Ok(())
+        }
+
+        pub fn physical_device_name(&self) -> String {
+            self.phys_device_properties.device_name.to_string()
+        }
+
+        pub fn queue_family_indices(&self) -> QueueFamilyIndices {
+            self.queue_families
+        }
+
+        // the highest version the device supports, not the version the instance asked for
+        pub fn api_version(&self) -> Version {
+            Version::from(self.phys_device_properties.api_version)
+        }
+
+        /// the buffer has to be created with SHADER_DEVICE_ADDRESS usage, the compute buffer is when this is enabled
+        ///
+        /// # Safety
+        ///
+        /// `buffer` has to come from the app's device with shader device address usage.
+        pub unsafe fn buffer_device_address(&self, buffer: vk::Buffer) -> Result<vk::DeviceAddress> {
+            if !self.buffer_device_address {
+                return Err(anyhow!(
+                    "Buffer device addresses need vulkan 1.2 with the bufferDeviceAddress feature, and enabling with \
+                     AppBuilder::buffer_device_address."
+                ));
+            }
+
+            let info = vk::BufferDeviceAddressInfo::builder()
+                .buffer(buffer);
+
+            Ok(self.device.get_buffer_device_address(&info))
+        }
+
+        // rgba, takes effect on the next frame
+        pub fn set_clear_color(&mut self, color: [f32; 4]) {
+            self.clear_color = color;
+        }
+
+        // forwards window input to imgui, every event should go through here when the gui is enabled
+        pub fn handle_gui_event<T>(&mut self, window: &Window, event: &Event<T>) {
+            if let Some(gui) = &mut self.gui {
+                gui.handle_event(window, event);
+            }
+        }
+
+        // builds the overlay drawn by the next render, does nothing without a gui
+        pub fn gui_frame(&mut self, window: &Window, build: impl FnOnce(&mut imgui::Ui)) -> Result<()> {
+            match &mut self.gui {
+                Some(gui) => gui.frame(window, self.frame_delta, build),
+                None => Ok(()),
+            }
+        }
+
+        pub fn set_post_effect(&mut self, effect: PostEffect) {
+            self.post_effect = effect;
+            info!("Post-processing effect set to {:?}.", effect);
+        }
+
+        // scales the scene before it's tonemapped, does nothing without tonemapping
+        pub fn set_exposure(&mut self, exposure: f32) {
+            self.config.exposure = exposure;
+            info!("Exposure set to {}.", exposure);
+        }
+
+        // does nothing without bloom
+        pub fn set_bloom_threshold(&mut self, threshold: f32) {
+            self.config.bloom_threshold = threshold;
+            for bloom in self.windows.iter_mut().filter_map(|w| w.bloom.as_mut()) {
+                bloom.set_threshold(threshold);
+            }
+        }
+
+        // command buffers are recorded every frame so the next one picks this up
+        pub fn toggle_wireframe(&mut self) {
+            if self.wireframe_pipeline.is_null() {
+                warn!("Wireframe rendering is not supported on this device.");
+                return;
+            }
+
+            self.wireframe = !self.wireframe;
+            info!("Wireframe rendering {}.", if self.wireframe { "enabled" } else { "disabled" });
+        }
+
+        // wireframe rendering skips the prepass, lines don't hide anything worth shading
+        pub fn toggle_depth_prepass(&mut self) {
+            self.depth_prepass = !self.depth_prepass;
+            info!("Depth prepass {}.", if self.depth_prepass { "enabled" } else { "disabled" });
+        }
+
+        /// runs the compute pipeline over the compute buffer and waits for it to finish
+        ///
+        /// # Safety
+        ///
+        /// The compute buffer can't be in use outside the app, this waits on the dispatch before returning.
+        pub unsafe fn dispatch_compute(&self, groups_x: u32, groups_y: u32, groups_z: u32) -> Result<()> {
+            let commands = SingleTimeCommands::new(&self.device, self.compute_command_pool, self.compute_queue)?;
+            let command_buffer = commands.command_buffer;
+
+            self.device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::COMPUTE, self.compute_pipeline);
+            self.device.cmd_bind_descriptor_sets(
+                command_buffer,
+                vk::PipelineBindPoint::COMPUTE,
+                self.compute_pipeline_layout,
+                0,
+                &[self.compute_descriptor_set],
+                &[],
+            );
+            self.device.cmd_dispatch(command_buffer, groups_x, groups_y, groups_z);
+
+            commands.submit()
+        }
+
+        /// # Safety
+        ///
+        /// The old sampler is destroyed, nothing outside the app can still reference it.
+        pub unsafe fn set_texture_filter(&mut self, filter: vk::Filter) -> Result<()> {
+            self.texture_filter = filter;
+            self.recreate_texture_sampler()
+        }
+
+        /// capped by the device like the configured level
+        ///
+        /// # Safety
+        ///
+        /// The old sampler is destroyed, nothing outside the app can still reference it.
+        pub unsafe fn set_anisotropy(&mut self, level: f32) -> Result<()> {
+            self.config.anisotropy = level;
+            self.anisotropy = get_anisotropy(&self.instance, self.phys_device, level);
+            self.recreate_texture_sampler()
+        }
+
+        unsafe fn recreate_texture_sampler(&mut self) -> Result<()> {
+            self.device.device_wait_idle()?;
+
+            // swap the sampler and point every set at the new one
+            self.device.destroy_sampler(self.texture_sampler, None);
+            self.texture_sampler =
+                create_texture_sampler(&self.device, self.texture_filter, self.anisotropy, self.mip_levels)?;
+
+            for window in &self.windows {
+                update_texture_descriptors(
+                    &self.device,
+                    &window.descriptor_sets,
+                    self.texture_image_view,
+                    self.normal_map_image_view,
+                    self.texture_sampler,
+                );
+            }
+            if let Some(bindless) = &self.bindless {
+                bindless.update_sampler(&self.device, self.texture_sampler);
+            }
+
+            Ok(())
+        }
+
+        unsafe fn update_command_buffer(&self, index: usize, image_index: usize) -> Result<()> {
+            let window = &self.windows[index];
+            let command_buffer = window.command_buffers[image_index];
+
+            // re-recorded every frame since the model transform is pushed
+            self.device.reset_command_buffer(command_buffer, vk::CommandBufferResetFlags::empty())?;
+
+            let info = vk::CommandBufferBeginInfo::builder()
+                .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+            self.device.begin_command_buffer(command_buffer, &info)?;
+
+            let first_query = image_index as u32 * 2;
+            if !window.timestamp_query_pool.is_null() {
+                self.device.cmd_reset_query_pool(command_buffer, window.timestamp_query_pool, first_query, 2);
+                self.device.cmd_write_timestamp(
+                    command_buffer,
+                    vk::PipelineStageFlags::TOP_OF_PIPE,
+                    window.timestamp_query_pool,
+                    first_query,
+                );
+            }
+
+            let render_area = vk::Rect2D::builder()
+                .offset(vk::Offset2D::default())
+                .extent(window.render_extent);
+
+            // viewport and scissor cover the whole render target
+            let viewport = vk::Viewport::builder()
+                .x(0.0)
+                .y(0.0)
+                .width(window.render_extent.width as f32)
+                .height(window.render_extent.height as f32)
+                .min_depth(0.0)
+                .max_depth(1.0);
+
+            let scissor = vk::Rect2D::builder()
+                .offset(vk::Offset2D { x: 0, y: 0 })
+                .extent(window.render_extent);
+
+            let frame = Frame {
+                image_index,
+                render_area: render_area.build(),
+                viewport: viewport.build(),
+                scissor: scissor.build(),
+                view_proj: self.view_proj(window),
+            };
+
+            // the main window's frame steps the particles and has the overlay and lines, the others draw what it left
+            let main = index == 0;
+
+            // moved before the scene that draws them, the same queue runs both so barriers are enough
+            let mut scheduler = PassScheduler::new();
+            if self.particles.is_some() && main {
+                scheduler.add(self.particles_pass());
+            }
+            if self.config.shadows {
+                scheduler.add(self.shadow_pass(window));
+            } else {
+                scheduler.add(self.no_shadow_pass());
+            }
+            scheduler.add(self.scene_pass(window, &frame, main));
+            if window.bloom.is_some() {
+                scheduler.add(self.bloom_pass(window));
+            }
+            scheduler.add(self.post_pass(window));
+            if self.gui.is_some() && main {
+                scheduler.add(self.gui_pass(window));
+            }
+            if let (Some((x, y)), Some(picker)) = (self.pick, &self.picker) {
+                if main {
+                    scheduler.add(self.picking_pass(window, picker, x, y));
+                }
+            }
+            scheduler.record(&self.instance, command_buffer, &frame, |command_buffer, from, to, frame| {
+                self.cmd_switch_target(command_buffer, window, from, to, frame)
+            })?;
+
+            if window.is_scaled() {
+                self.cmd_blit_scaled_image(command_buffer, window, image_index);
+            }
+            // taken before presenting, the image is put back in the layout it's presented from
+            if let Some(screenshot) = self.screenshot.as_ref().filter(|s| main && s.frame == window.frame) {
+                cmd_read_image(
+                    &self.device,
+                    command_buffer,
+                    window.images[image_index],
+                    get_final_layout(self.headless, false),
+                    screenshot.extent,
+                    screenshot.buffer,
+                );
+            }
+
+            if !window.timestamp_query_pool.is_null() {
+                self.device.cmd_write_timestamp(
+                    command_buffer,
+                    vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+                    window.timestamp_query_pool,
+                    first_query + 1,
+                );
+            }
+
+            self.device.end_command_buffer(command_buffer)?;
+
+            Ok(())
+        }
+
+        // the render pass path has the scene and post subpasses in one render pass, so nothing can go between them
+        unsafe fn cmd_switch_target(
+            &self,
+            command_buffer: vk::CommandBuffer,
+            window: &WindowContext,
+            from: Option<Target>,
+            to: Option<Target>,
+            frame: &Frame,
+        ) -> Result<()> {
+            if self.dynamic_rendering {
+                match from {
+                    Some(Target::Scene) => self.cmd_end_scene_rendering(command_buffer, window),
+                    Some(Target::Post) => {
+                        self.device.cmd_end_rendering_khr(command_buffer);
+                        self.cmd_end_post_rendering(command_buffer, window, frame.image_index);
+                    }
+                    None => {}
+                }
+                match to {
+                    Some(Target::Scene) => self.cmd_begin_scene_rendering(command_buffer, window, frame.render_area),
+                    Some(Target::Post) => {
+                        self.cmd_begin_post_rendering(command_buffer, window, frame.image_index, frame.render_area)
+                    }
+                    None => {}
+                }
+                return Ok(());
+            }
+
+            match (from, to) {
+                (None, Some(Target::Scene)) => {
+                    let clear_values = self.scene_clear_values();
+                    let info = vk::RenderPassBeginInfo::builder()
+                        .render_pass(self.render_pass)
+                        .framebuffer(window.framebuffers[frame.image_index])
+                        .render_area(frame.render_area)
+                        .clear_values(&clear_values);
+                    self.device.cmd_begin_render_pass(command_buffer, &info, self.scene_contents());
+                }
+                (Some(Target::Scene), Some(Target::Post)) => {
+                    self.device.cmd_next_subpass(command_buffer, vk::SubpassContents::INLINE);
+                }
+                (Some(Target::Post), None) => self.device.cmd_end_render_pass(command_buffer),
+                _ => return Err(anyhow!("The render pass can't go from {:?} to {:?}.", from, to)),
+            }
+
+            Ok(())
+        }
+
+        // the color and the far plane
+        fn scene_clear_values(&self) -> [vk::ClearValue; 2] {
+            let color_clear_value = vk::ClearValue {
+                color: vk::ClearColorValue {
+                    float32: self.clear_color,
+                },
+            };
+
+            let depth_clear_value = vk::ClearValue {
+                depth_stencil: vk::ClearDepthStencilValue {
+                    depth: if self.config.reverse_z { 0.0 } else { 1.0 },
+                    stencil: 0,
+                },
+            };
+
+            [color_clear_value, depth_clear_value]
+        }
+
+        // the scene is drawn by secondaries when there are recording threads
+        fn scene_contents(&self) -> vk::SubpassContents {
+            if self.secondary_command_pools.is_empty() {
+                vk::SubpassContents::INLINE
+            } else {
+                vk::SubpassContents::SECONDARY_COMMAND_BUFFERS
+            }
+        }
+
+        unsafe fn particles_pass(&self) -> ClosurePass<'_> {
+            let desc = RenderPassDesc::new("Particles", [1.0, 0.6, 0.2, 1.0]);
+
+            ClosurePass::new(desc, move |command_buffer, _| {
+                if let Some(particles) = &self.particles {
+                    particles.record_update(&self.device, command_buffer, self.frame_delta);
+                }
+
+                Ok(())
+            })
+        }
+
+        // every object's depth from the light, read back by the scene's fragment shaders
+        unsafe fn shadow_pass<'a>(&'a self, window: &'a WindowContext) -> ClosurePass<'a> {
+            let objects = self.scene_objects().collect::<Vec<_>>();
+            let desc = RenderPassDesc::new("Shadows", [0.4, 0.4, 0.4, 1.0]);
+
+            ClosurePass::new(desc, move |command_buffer, frame| {
+                let draws = SceneDraws {
+                    device: &self.device,
+                    objects: &objects,
+                    blend_pipelines: [self.shadow_map.pipeline(); 3],
+                    pipeline_layout: self.shadow_map.pipeline_layout(),
+                    descriptor_set: window.descriptor_sets[frame.image_index],
+                    bindless_set: None,
+                    instance_buffer: self.instance_buffer,
+                    instance_count: self.instance_count,
+                    draw_commands_buffer: self.draw_commands_buffer,
+                    draw_command_count: self.draw_command_count,
+                    multi_draw_indirect: self.phys_device_features.multi_draw_indirect == vk::TRUE,
+                    object_alignment: self.object_alignment,
+                    skybox: None,
+                    skybox_view_proj: Mat4::identity(),
+                    particles: None,
+                    debug_lines: &self.debug_lines,
+                    image_index: frame.image_index,
+                    view_proj: frame.view_proj,
+                };
+
+                self.shadow_map.cmd_begin(&self.device, command_buffer);
+                draws.record(command_buffer, Some(self.shadow_map.pipeline()), &(0..objects.len()).collect::<Vec<_>>());
+                self.shadow_map.cmd_end(&self.device, command_buffer);
+
+                Ok(())
+            })
+        }
+
+        // nothing is drawn, the shadow map is only made readable for the scene's descriptor sets
+        unsafe fn no_shadow_pass(&self) -> ClosurePass<'_> {
+            let desc = RenderPassDesc::new("No shadows", [0.4, 0.4, 0.4, 1.0]);
+
+            ClosurePass::new(desc, move |command_buffer, _| {
+                self.shadow_map.cmd_skip(&self.device, command_buffer);
+
+                Ok(())
+            })
+        }
+
+        // draws every scene pass into the scene rendering, spread over the recording threads if any
+        // the debug lines are only uploaded for the main window
+        unsafe fn scene_pass<'a>(&'a self, window: &'a WindowContext, frame: &Frame, main: bool) -> ClosurePass<'a> {
+            // the prepass fills the depth buffer, then only the nearest surface passes the equal test
+            let wireframe = self.wireframe && !self.wireframe_pipeline.is_null();
+            let objects = self.scene_objects().collect::<Vec<_>>();
+
+            // the instanced or indirectly drawn model can be anywhere its instances put it, so it's always drawn
+            let frustum = Frustum::from_view_proj(frame.view_proj);
+            let model_cullable = self.instance_count == 0 && self.draw_command_count == 0;
+            let visible = (0..objects.len())
+                .filter(|i| (*i == 0 && !model_cullable) || frustum.intersects(&objects[*i].world_bounds()))
+                .collect::<Vec<_>>();
+            debug!("Culled {} of {} objects.", objects.len() - visible.len(), objects.len());
+
+            // blended objects go over everything opaque, furthest first so each blends over what's behind it
+            let (opaque, mut blended): (Vec<_>, Vec<_>) = visible
+                .into_iter()
+                .partition(|i| wireframe || objects[*i].blend_mode == BlendMode::Opaque);
+            let camera = self.window_camera(window);
+            if self.config.sort_blended {
+                let distance = |i: usize| (objects[i].transform.w.truncate() - camera.position).magnitude2();
+                blended.sort_by(|a, b| distance(*b).total_cmp(&distance(*a)));
+            }
+
+            let pipelines = if wireframe {
+                vec![self.wireframe_pipeline]
+            } else if self.depth_prepass {
+                vec![self.depth_prepass_pipeline, self.depth_equal_pipeline]
+            } else {
+                vec![self.pipeline]
+            };
+            let mut passes = pipelines
+                .into_iter()
+                .map(|pipeline| ScenePass::Objects { pipeline: Some(pipeline), objects: opaque.clone() })
+                .collect::<Vec<_>>();
+
+            // another pass over the same objects, only drawn outside of what the main pass wrote to the stencil
+            if !self.outline_pipeline.is_null() {
+                passes.push(ScenePass::Objects { pipeline: Some(self.outline_pipeline), objects: opaque });
+            }
+            if self.particles.is_some() {
+                passes.push(ScenePass::Particles);
+            }
+            // after everything opaque so the depth test skips what's covered, before anything blended over it
+            if self.skybox.is_some() {
+                passes.push(ScenePass::Skybox);
+            }
+            // over the skybox, which would cover lines drawn before it since they don't write depth
+            if main {
+                passes.push(ScenePass::DebugLines);
+            }
+            if !blended.is_empty() {
+                passes.push(ScenePass::Objects { pipeline: None, objects: blended });
+            }
+
+            // the cube stays around the camera however it moves
+            let mut skybox_view = camera.view_matrix();
+            skybox_view.w = vec4(0.0, 0.0, 0.0, 1.0);
+            let skybox_view_proj = self.projection_matrix(window) * skybox_view;
+
+            let desc = RenderPassDesc::new("Scene", [0.2, 0.4, 1.0, 1.0]).target(Target::Scene);
+
+            ClosurePass::new(desc, move |command_buffer, frame| {
+                let image_index = frame.image_index;
+                let draws = SceneDraws {
+                    device: &self.device,
+                    objects: &objects,
+                    blend_pipelines: [self.pipeline, self.alpha_blend_pipeline, self.additive_pipeline],
+                    pipeline_layout: self.pipeline_layout,
+                    descriptor_set: window.descriptor_sets[image_index],
+                    bindless_set: self.bindless.as_ref().map(|b| b.descriptor_set()),
+                    instance_buffer: self.instance_buffer,
+                    instance_count: self.instance_count,
+                    draw_commands_buffer: self.draw_commands_buffer,
+                    draw_command_count: self.draw_command_count,
+                    multi_draw_indirect: self.phys_device_features.multi_draw_indirect == vk::TRUE,
+                    object_alignment: self.object_alignment,
+                    skybox: self.skybox.as_ref(),
+                    skybox_view_proj,
+                    particles: self.particles.as_ref(),
+                    debug_lines: &self.debug_lines,
+                    image_index,
+                    view_proj: frame.view_proj,
+                };
+
+                let secondaries = if self.secondary_command_pools.is_empty() {
+                    None
+                } else {
+                    let (scene_target, _) = self.pipeline_targets()?;
+                    Some(record_secondary_command_buffers(
+                        draws,
+                        &window.secondary_command_buffers[image_index],
+                        scene_target,
+                        window.framebuffers.get(image_index).copied().unwrap_or_default(),
+                        self.msaa_samples,
+                        &passes,
+                        frame.viewport,
+                        frame.scissor,
+                    )?)
+                };
+
+                match &secondaries {
+                    Some(secondaries) => self.device.cmd_execute_commands(command_buffer, secondaries),
+                    None => {
+                        self.device.cmd_set_viewport(command_buffer, 0, &[frame.viewport]);
+                        self.device.cmd_set_scissor(command_buffer, 0, &[frame.scissor]);
+
+                        for pass in &passes {
+                            draws.record_pass(command_buffer, pass, 0, 1);
+                        }
+                    }
+                }
+
+                Ok(())
+            })
+        }
+
+        // between the scene and the post pass, which adds it back in
+        unsafe fn bloom_pass<'a>(&'a self, window: &'a WindowContext) -> ClosurePass<'a> {
+            let desc = RenderPassDesc::new("Bloom", [1.0, 0.9, 0.3, 1.0]);
+
+            ClosurePass::new(desc, move |command_buffer, _| {
+                if let Some(bloom) = &window.bloom {
+                    bloom.record(&self.device, command_buffer);
+                }
+
+                Ok(())
+            })
+        }
+
+        // fullscreen triangle reading the resolved scene, secondaries leave the dynamic state undefined
+        unsafe fn post_pass<'a>(&'a self, window: &'a WindowContext) -> ClosurePass<'a> {
+            let desc = RenderPassDesc::new("Post", [0.6, 0.2, 1.0, 1.0]).target(Target::Post);
+
+            ClosurePass::new(desc, move |command_buffer, frame| {
+                self.device.cmd_set_viewport(command_buffer, 0, &[frame.viewport]);
+                self.device.cmd_set_scissor(command_buffer, 0, &[frame.scissor]);
+                self.device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::GRAPHICS, self.post_pipeline);
+                self.device.cmd_bind_descriptor_sets(
+                    command_buffer,
+                    vk::PipelineBindPoint::GRAPHICS,
+                    self.post_pipeline_layout,
+                    0,
+                    &[window.post_descriptor_set],
+                    &[],
+                );
+                // unorm targets get gamma corrected here, srgb ones encode on write
+                let constants = PostConstants {
+                    effect: self.post_effect as u32,
+                    tonemap: self.config.tonemap.map_or(0, |t| t as u32),
+                    exposure: self.config.exposure,
+                    gamma: !FormatPreference::Srgb.matches(self.swapchain_format) as u32,
+                    bloom_intensity: if window.bloom.is_some() { self.config.bloom_intensity } else { 0.0 },
+                };
+                self.device.cmd_push_constants(
+                    command_buffer,
+                    self.post_pipeline_layout,
+                    vk::ShaderStageFlags::FRAGMENT,
+                    0,
+                    std::slice::from_raw_parts((&constants as *const PostConstants).cast(), size_of::<PostConstants>()),
+                );
+                self.device.cmd_draw(command_buffer, 3, 1, 0, 0);
+
+                Ok(())
+            })
+        }
+
+        // the frame's objects again as ids, the one pixel is copied into the frame's readback buffer
+        unsafe fn picking_pass<'a>(
+            &'a self,
+            window: &'a WindowContext,
+            picker: &'a Picker,
+            x: u32,
+            y: u32,
+        ) -> ClosurePass<'a> {
+            // the id is pushed where the texture index would be
+            let objects = self
+                .scene_objects()
+                .enumerate()
+                .map(|(i, object)| RenderObject { texture_index: i as u32 + 1, ..object })
+                .collect::<Vec<_>>();
+            let desc = RenderPassDesc::new("Picking", [1.0, 0.2, 0.6, 1.0]);
+
+            ClosurePass::new(desc, move |command_buffer, frame| {
+                let draws = SceneDraws {
+                    device: &self.device,
+                    objects: &objects,
+                    blend_pipelines: [picker.pipeline(); 3],
+                    pipeline_layout: picker.pipeline_layout(),
+                    descriptor_set: window.descriptor_sets[frame.image_index],
+                    bindless_set: None,
+                    instance_buffer: self.instance_buffer,
+                    instance_count: self.instance_count,
+                    draw_commands_buffer: self.draw_commands_buffer,
+                    draw_command_count: self.draw_command_count,
+                    multi_draw_indirect: self.phys_device_features.multi_draw_indirect == vk::TRUE,
+                    object_alignment: self.object_alignment,
+                    skybox: None,
+                    skybox_view_proj: Mat4::identity(),
+                    particles: None,
+                    debug_lines: &self.debug_lines,
+                    image_index: frame.image_index,
+                    view_proj: frame.view_proj,
+                };
+
+                picker.cmd_begin(&self.device, command_buffer);
+                draws.record(command_buffer, Some(picker.pipeline()), &(0..objects.len()).collect::<Vec<_>>());
+                picker.cmd_end(&self.device, command_buffer, window.frame, x, y);
+
+                Ok(())
+            })
+        }
+
+        // the overlay goes on top, untouched by the effect
+        unsafe fn gui_pass<'a>(&'a self, window: &'a WindowContext) -> ClosurePass<'a> {
+            let desc = RenderPassDesc::new("GUI", [0.2, 1.0, 0.4, 1.0]).target(Target::Post);
+
+            ClosurePass::new(desc, move |command_buffer, frame| {
+                if let Some(gui) = &self.gui {
+                    gui.record(&self.device, command_buffer, frame.image_index, window.render_extent);
+                }
+
+                Ok(())
+            })
+        }
+
+        // without a render pass the attachments are transitioned here, matching its external dependency
+        unsafe fn cmd_begin_scene_rendering(
+            &self,
+            command_buffer: vk::CommandBuffer,
+            window: &WindowContext,
+            render_area: vk::Rect2D,
+        ) {
+            let color_barrier = image_barrier(
+                window.color_image,
+                vk::ImageAspectFlags::COLOR,
+                vk::ImageLayout::UNDEFINED,
+                vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+                vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+                vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+            );
+            let scene_barrier = image_barrier(
+                window.scene_image,
+                vk::ImageAspectFlags::COLOR,
+                vk::ImageLayout::UNDEFINED,
+                vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+                vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+                vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+            );
+            let depth_barrier = image_barrier(
+                window.depth_image,
+                vk::ImageAspectFlags::DEPTH | vk::ImageAspectFlags::STENCIL,
+                vk::ImageLayout::UNDEFINED,
+                vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+                vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+                vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+            );
+
+            // the last frame's post-processing read of the scene has to finish before it's resolved into again
+            self.device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+                    | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS
+                    | vk::PipelineStageFlags::FRAGMENT_SHADER,
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT | vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS,
+                vk::DependencyFlags::empty(),
+                &[] as &[vk::MemoryBarrier],
+                &[] as &[vk::BufferMemoryBarrier],
+                &[color_barrier, scene_barrier, depth_barrier],
+            );
+
+            let clear_values = self.scene_clear_values();
+
+            // the multisampled color is resolved into the scene image as the render pass did
+            let color_attachment = vk::RenderingAttachmentInfo::builder()
+                .image_view(window.color_image_view)
+                .image_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+                .resolve_mode(vk::ResolveModeFlags::AVERAGE)
+                .resolve_image_view(window.scene_image_view)
+                .resolve_image_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+                .load_op(vk::AttachmentLoadOp::CLEAR)
+                .store_op(vk::AttachmentStoreOp::DONT_CARE)
+                .clear_value(clear_values[0]);
+
+            let depth_stencil_attachment = vk::RenderingAttachmentInfo::builder()
+                .image_view(window.depth_image_view)
+                .image_layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL)
+                .load_op(vk::AttachmentLoadOp::CLEAR)
+                .store_op(vk::AttachmentStoreOp::DONT_CARE)
+                .clear_value(clear_values[1]);
+
+            let flags = if self.scene_contents() == vk::SubpassContents::SECONDARY_COMMAND_BUFFERS {
+                vk::RenderingFlags::CONTENTS_SECONDARY_COMMAND_BUFFERS
+            } else {
+                vk::RenderingFlags::empty()
+            };
+
+            let color_attachments = &[color_attachment];
+            let info = vk::RenderingInfo::builder()
+                .flags(flags)
+                .render_area(render_area)
+                .layer_count(1)
+                .color_attachments(color_attachments)
+                .depth_attachment(&depth_stencil_attachment)
+                .stencil_attachment(&depth_stencil_attachment);
+
+            self.device.cmd_begin_rendering_khr(command_buffer, &info);
+        }
+
+        // the resolved scene becomes readable by the post pass and bloom, like the subpass dependency
+        unsafe fn cmd_end_scene_rendering(&self, command_buffer: vk::CommandBuffer, window: &WindowContext) {
+            self.device.cmd_end_rendering_khr(command_buffer);
+
+            let scene_barrier = image_barrier(
+                window.scene_image,
+                vk::ImageAspectFlags::COLOR,
+                vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+                vk::AccessFlags::SHADER_READ,
+            );
+
+            self.device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                vk::PipelineStageFlags::FRAGMENT_SHADER | vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::DependencyFlags::empty(),
+                &[] as &[vk::MemoryBarrier],
+                &[] as &[vk::BufferMemoryBarrier],
+                &[scene_barrier],
+            );
+        }
+
+        // the swapchain image becomes writable, like the subpass dependency
+        unsafe fn cmd_begin_post_rendering(
+            &self,
+            command_buffer: vk::CommandBuffer,
+            window: &WindowContext,
+            image_index: usize,
+            render_area: vk::Rect2D,
+        ) {
+            let (target_image, target_image_view) = window.post_target(image_index);
+            let present_barrier = image_barrier(
+                target_image,
+                vk::ImageAspectFlags::COLOR,
+                vk::ImageLayout::UNDEFINED,
+                vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+                vk::AccessFlags::empty(),
+                vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+            );
+
+            self.device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                vk::DependencyFlags::empty(),
+                &[] as &[vk::MemoryBarrier],
+                &[] as &[vk::BufferMemoryBarrier],
+                &[present_barrier],
+            );
+
+            // every pixel is written by the fullscreen triangle so nothing is loaded
+            let color_attachment = vk::RenderingAttachmentInfo::builder()
+                .image_view(target_image_view)
+                .image_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+                .load_op(vk::AttachmentLoadOp::DONT_CARE)
+                .store_op(vk::AttachmentStoreOp::STORE);
+
+            let color_attachments = &[color_attachment];
+            let info = vk::RenderingInfo::builder()
+                .render_area(render_area)
+                .layer_count(1)
+                .color_attachments(color_attachments);
+
+            self.device.cmd_begin_rendering_khr(command_buffer, &info);
+        }
+
+        // presenting and reading back wait on the frame's semaphore or fence, only a blit needs a stage
+        unsafe fn cmd_end_post_rendering(
+            &self,
+            command_buffer: vk::CommandBuffer,
+            window: &WindowContext,
+            image_index: usize,
+        ) {
+            let scaled = window.is_scaled();
+            let (dst_stage_mask, dst_access_mask) = if scaled {
+                (vk::PipelineStageFlags::TRANSFER, vk::AccessFlags::TRANSFER_READ)
+            } else {
+                (vk::PipelineStageFlags::BOTTOM_OF_PIPE, vk::AccessFlags::empty())
+            };
+
+            let present_barrier = image_barrier(
+                window.post_target(image_index).0,
+                vk::ImageAspectFlags::COLOR,
+                vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+                get_final_layout(self.headless, scaled),
+                vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+                dst_access_mask,
+            );
+
+            self.device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                dst_stage_mask,
+                vk::DependencyFlags::empty(),
+                &[] as &[vk::MemoryBarrier],
+                &[] as &[vk::BufferMemoryBarrier],
+                &[present_barrier],
+            );
+        }
+
+        // downsamples the scaled render into the swapchain image, leaving it how the render pass would have
+        unsafe fn cmd_blit_scaled_image(
+            &self,
+            command_buffer: vk::CommandBuffer,
+            window: &WindowContext,
+            image_index: usize,
+        ) {
+            let swapchain_image = window.images[image_index];
+
+            // the render pass leaves the scaled image in transfer src, but its writes still have to land
+            let scaled_barrier = image_barrier(
+                window.scaled_image,
+                vk::ImageAspectFlags::COLOR,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+                vk::AccessFlags::TRANSFER_READ,
+            );
+            let swapchain_barrier = image_barrier(
+                swapchain_image,
+                vk::ImageAspectFlags::COLOR,
+                vk::ImageLayout::UNDEFINED,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                vk::AccessFlags::empty(),
+                vk::AccessFlags::TRANSFER_WRITE,
+            );
+            let barriers = if self.dynamic_rendering {
+                vec![swapchain_barrier]
+            } else {
+                vec![scaled_barrier, swapchain_barrier]
+            };
+
+            // the frame waits for the swapchain image at the transfer stage as well
+            self.device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT | vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::DependencyFlags::empty(),
+                &[] as &[vk::MemoryBarrier],
+                &[] as &[vk::BufferMemoryBarrier],
+                &barriers,
+            );
+
+            let subresource = vk::ImageSubresourceLayers::builder()
+                .aspect_mask(vk::ImageAspectFlags::COLOR)
+                .mip_level(0)
+                .base_array_layer(0)
+                .layer_count(1);
+
+            let corner = |extent: vk::Extent2D| vk::Offset3D {
+                x: extent.width as i32,
+                y: extent.height as i32,
+                z: 1,
+            };
+
+            let blit = vk::ImageBlit::builder()
+                .src_offsets([vk::Offset3D::default(), corner(window.render_extent)])
+                .src_subresource(subresource)
+                .dst_offsets([vk::Offset3D::default(), corner(window.extent)])
+                .dst_subresource(subresource);
+
+            self.device.cmd_blit_image(
+                command_buffer,
+                window.scaled_image,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                swapchain_image,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                &[blit],
+                vk::Filter::LINEAR,
+            );
+
+            // the next frame renders into the scaled image again once the blit has read it
+            let scaled_barrier = image_barrier(
+                window.scaled_image,
+                vk::ImageAspectFlags::COLOR,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                vk::AccessFlags::TRANSFER_READ,
+                vk::AccessFlags::empty(),
+            );
+            let swapchain_barrier = image_barrier(
+                swapchain_image,
+                vk::ImageAspectFlags::COLOR,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                get_final_layout(self.headless, false),
+                vk::AccessFlags::TRANSFER_WRITE,
+                vk::AccessFlags::empty(),
+            );
+
+            self.device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                vk::DependencyFlags::empty(),
+                &[] as &[vk::MemoryBarrier],
+                &[] as &[vk::BufferMemoryBarrier],
+                &[scaled_barrier, swapchain_barrier],
+            );
+        }
+
+        // what the scene is drawn with this frame, for culling and anything else drawn in world space
+        pub fn view_proj(&self, window: &WindowContext) -> Mat4 {
+            self.projection_matrix(window) * self.window_camera(window).view_matrix()
+        }
+
+        fn window_camera<'a>(&'a self, window: &'a WindowContext) -> &'a Camera {
+            window.camera.as_ref().unwrap_or(&self.camera)
+        }
+
+        // cgmath is made for opengl, flip y and map depth to 0..1 for vulkan, or 1..0 when reversed
+        fn clip_correction(reverse_z: bool) -> Mat4 {
+            let z = if reverse_z { -1.0 / 2.0 } else { 1.0 / 2.0 };
+            #[rustfmt::skip]
+            let correction = Mat4::new(
+                1.0,  0.0,       0.0, 0.0,
+                0.0, -1.0,       0.0, 0.0,
+                0.0,  0.0,         z, 0.0,
+                0.0,  0.0, 1.0 / 2.0, 1.0,
+            );
+
+            correction
+        }
+
+        fn projection_matrix(&self, window: &WindowContext) -> Mat4 {
+            let aspect = window.extent.width as f32 / window.extent.height as f32;
+            Self::clip_correction(self.config.reverse_z) * cgmath::perspective(Deg(45.0), aspect, 0.1, 10.0)
+        }
+
+        // normalizing a zero direction gives nans, it's straight down instead
+        fn light_direction(&self) -> Vec3 {
+            if self.light.direction.magnitude2() > 0.0 {
+                self.light.direction.normalize()
+            } else {
+                -Vec3::unit_z()
+            }
+        }
+
+        // an orthographic view down the light's direction around a sphere holding every object's bounds
+        fn light_view_proj(&self) -> Mat4 {
+            // empty meshes have inverted bounds, a unit box stands in when there's nothing else
+            let bounds = self
+                .scene_objects()
+                .map(|o| o.world_bounds())
+                .filter(|b| b.min.x <= b.max.x)
+                .reduce(|a, b| a.including(b.min).including(b.max))
+                .unwrap_or(Aabb { min: vec3(-1.0, -1.0, -1.0), max: vec3(1.0, 1.0, 1.0) });
+
+            let center = (bounds.min + bounds.max) / 2.0;
+            let radius = ((bounds.max - bounds.min).magnitude() / 2.0).max(0.01);
+
+            // z is up unless the light points nearly straight along it
+            let direction = self.light_direction();
+            let up = if direction.z.abs() > 0.99 { Vec3::unit_y() } else { Vec3::unit_z() };
+            let eye = center - direction * radius * 2.0;
+            let view = Mat4::look_at_rh(Point3::from_vec(eye), Point3::from_vec(center), up);
+
+            let proj = cgmath::ortho(-radius, radius, -radius, radius, 0.0, radius * 4.0);
+            // the shadow map has its own depth buffer, it's never reversed
+            Self::clip_correction(false) * proj * view
+        }
+
+        unsafe fn update_uniform_buffer(&self, window: &WindowContext, image_index: usize) -> Result<()> {
+            let camera = self.window_camera(window);
+            let ubo = UniformBufferObject { view: camera.view_matrix(), proj: self.projection_matrix(window) };
+
+            let memory = self.device.map_memory(
+                window.uniform_buffers_memory[image_index].memory,
+                window.uniform_buffers_memory[image_index].offset,
+                size_of::<UniformBufferObject>() as u64,
+                vk::MemoryMapFlags::empty(),
+            )?;
+
+            memcpy(&ubo, memory.cast(), 1);
+
+            self.device.unmap_memory(window.uniform_buffers_memory[image_index].memory);
+
+            let light = LightUniform {
+                direction: self.light_direction().extend(0.0),
+                color: self.light.color.extend(1.0),
+                ambient: self.light.ambient.extend(1.0),
+                camera_position: camera.position.extend(1.0),
+                view_proj: self.light_view_proj(),
+                shadows: self.config.shadows as u32,
+                _padding: [0; 3],
+            };
+
+            let memory = self.device.map_memory(
+                window.light_buffers_memory[image_index].memory,
+                window.light_buffers_memory[image_index].offset,
+                size_of::<LightUniform>() as u64,
+                vk::MemoryMapFlags::empty(),
+            )?;
+
+            memcpy(&light, memory.cast(), 1);
+
+            self.device.unmap_memory(window.light_buffers_memory[image_index].memory);
+
+            // each transform starts on its own aligned offset
+            let memory = self.device.map_memory(
+                window.object_buffers_memory[image_index].memory,
+                window.object_buffers_memory[image_index].offset,
+                self.object_alignment * MAX_OBJECTS as u64,
+                vk::MemoryMapFlags::empty(),
+            )?;
+
+            for (i, object) in self.scene_objects().enumerate() {
+                let offset = i as u64 * self.object_alignment;
+                let uniform = ObjectUniform::new(object.transform);
+                memcpy(&uniform, memory.cast::<u8>().add(offset as usize).cast(), 1);
+            }
+
+            self.device.unmap_memory(window.object_buffers_memory[image_index].memory);
+
+            Ok(())
+        }
+
+        // the spinning model first, then everything from add_object
+        fn scene_objects(&self) -> impl Iterator<Item = RenderObject> + '_ {
+            // spin around the z axis on top of the model's own transform
+            let time = self.fixed_time.unwrap_or_else(|| self.start.elapsed().as_secs_f32());
+            let model = RenderObject {
+                vertex_buffer: self.vertex_buffer,
+                vertex_buffer_memory: self.vertex_buffer_memory,
+                index_buffer: self.index_buffer,
+                index_buffer_memory: self.index_buffer_memory,
+                index_type: self.index_type,
+                index_count: self.index_count,
+                bounds: self.model_bounds,
+                transform: Mat4::from_axis_angle(vec3(0.0, 0.0, 1.0), Deg(90.0) * time) * self.model,
+                blend_mode: BlendMode::Opaque,
+                texture_index: 0,
+            };
+
+            std::iter::once(model).chain(self.objects.iter().copied())
+        }
+
+        // everything create_window_objects makes, handles are taken so whatever's left after a failed recreation is
+        // only destroyed once
+        unsafe fn destroy_window_objects(&mut self, window: &mut WindowContext) {
+            // destroying the pool frees its sets
+            self.device.destroy_descriptor_pool(mem::take(&mut window.descriptor_pool), None);
+            self.device.destroy_descriptor_pool(mem::take(&mut window.post_descriptor_pool), None);
+            window.descriptor_sets.clear();
+            window.uniform_buffers
+                .drain(..)
+                .for_each(|b| self.device.destroy_buffer(b, None));
+            window.uniform_buffers_memory
+                .drain(..)
+                .for_each(|m| self.allocator.free(&self.device, m));
+            window.light_buffers
+                .drain(..)
+                .for_each(|b| self.device.destroy_buffer(b, None));
+            window.light_buffers_memory
+                .drain(..)
+                .for_each(|m| self.allocator.free(&self.device, m));
+            window.object_buffers
+                .drain(..)
+                .for_each(|b| self.device.destroy_buffer(b, None));
+            window.object_buffers_memory
+                .drain(..)
+                .for_each(|m| self.allocator.free(&self.device, m));
+            window.framebuffers
+                .drain(..)
+                .for_each(|f| self.device.destroy_framebuffer(f, None));
+            if !window.command_buffers.is_empty() {
+                self.device.free_command_buffers(self.command_pool, &window.command_buffers);
+                window.command_buffers.clear();
+            }
+            for (thread, pool) in self.secondary_command_pools.iter().enumerate() {
+                let buffers = window
+                    .secondary_command_buffers
+                    .iter()
+                    .flat_map(|b| &b[thread * SCENE_PASSES..(thread + 1) * SCENE_PASSES])
+                    .copied()
+                    .collect::<Vec<_>>();
+                if !buffers.is_empty() {
+                    self.device.free_command_buffers(*pool, &buffers);
+                }
+            }
+            window.secondary_command_buffers.clear();
+            if !window.timestamp_query_pool.is_null() {
+                self.device.destroy_query_pool(mem::take(&mut window.timestamp_query_pool), None);
+            }
+            self.device.destroy_image_view(mem::take(&mut window.depth_image_view), None);
+            self.device.destroy_image(mem::take(&mut window.depth_image), None);
+            self.allocator.free(&self.device, mem::take(&mut window.depth_image_memory));
+            self.device.destroy_image_view(mem::take(&mut window.scene_image_view), None);
+            self.device.destroy_image(mem::take(&mut window.scene_image), None);
+            self.allocator.free(&self.device, mem::take(&mut window.scene_image_memory));
+            self.device.destroy_image_view(mem::take(&mut window.color_image_view), None);
+            self.device.destroy_image(mem::take(&mut window.color_image), None);
+            self.allocator.free(&self.device, mem::take(&mut window.color_image_memory));
+            if window.is_scaled() {
+                self.device.destroy_image_view(mem::take(&mut window.scaled_image_view), None);
+                self.device.destroy_image(mem::take(&mut window.scaled_image), None);
+                self.allocator.free(&self.device, mem::take(&mut window.scaled_image_memory));
+            }
+            window.image_views
+                .drain(..)
+                .for_each(|v| self.device.destroy_image_view(v, None));
+        }
+
+        // the device has to be idle
+        unsafe fn destroy_window(&mut self, window: &mut WindowContext) {
+            self.destroy_window_objects(window);
+            if let Some(mut bloom) = window.bloom.take() {
+                bloom.destroy(&self.device, &mut self.allocator);
+            }
+
+            window.in_flight_fences
+                .drain(..)
+                .for_each(|f| self.device.destroy_fence(f, None));
+            if !window.timeline_semaphore.is_null() {
+                self.device.destroy_semaphore(mem::take(&mut window.timeline_semaphore), None);
+            }
+            window.render_finished_semaphores
+                .drain(..)
+                .for_each(|s| self.device.destroy_semaphore(s, None));
+            window.image_available_semaphores
+                .drain(..)
+                .for_each(|s| self.device.destroy_semaphore(s, None));
+
+            // headless apps own their single image, a swapchain owns its images and they go with it
+            if window.surface.is_null() {
+                window.images
+                    .drain(..)
+                    .for_each(|i| self.device.destroy_image(i, None));
+                self.allocator.free(&self.device, mem::take(&mut window.offscreen_image_memory));
+            } else {
+                self.device.destroy_swapchain_khr(mem::take(&mut window.swapchain), None);
+                window.images.clear();
+                self.instance.destroy_surface_khr(mem::take(&mut window.surface), None);
+            }
+        }
+
+        // what pipelines are built against, also needed to rebuild them
+        unsafe fn pipeline_targets(&self) -> Result<(PipelineTarget, PipelineTarget)> {
+            get_pipeline_targets(
+                &self.instance,
+                self.phys_device,
+                self.dynamic_rendering,
+                self.render_pass,
+                self.swapchain_format,
+                self.scene_format,
+            )
+        }
+
+        // the pipelines are built for the render pass so they go with it
+        unsafe fn destroy_render_pass(&mut self) {
+            self.destroy_pipelines();
+            self.device.destroy_render_pass(self.render_pass, None);
+        }
+
+        unsafe fn destroy_pipelines(&mut self) {
+            self.device.destroy_pipeline(self.pipeline, None);
+            if !self.wireframe_pipeline.is_null() {
+                self.device.destroy_pipeline(self.wireframe_pipeline, None);
+            }
+            if !self.outline_pipeline.is_null() {
+                self.device.destroy_pipeline(self.outline_pipeline, None);
+            }
+            self.device.destroy_pipeline(self.depth_prepass_pipeline, None);
+            self.device.destroy_pipeline(self.depth_equal_pipeline, None);
+            self.device.destroy_pipeline(self.alpha_blend_pipeline, None);
+            self.device.destroy_pipeline(self.additive_pipeline, None);
+            self.device.destroy_pipeline_layout(self.pipeline_layout, None);
+            self.device.destroy_pipeline(self.post_pipeline, None);
+            self.device.destroy_pipeline_layout(self.post_pipeline_layout, None);
+        }
+
+        /// tears everything down and builds it again, for use after RenderError::DeviceLost
+        /// windows from add_window, objects from add_object, instances from set_instances, draw commands, the skybox,
+        /// particles and textures from add_texture lived on the old device and have to be set again
+        ///
+        /// # Safety
+        ///
+        /// `window` has to be the one the app was created with.
+        pub unsafe fn recreate_device(&mut self, window: &Window) -> Result<()> {
+            warn!("Recreating the device.");
+
+            // destroying takes the windows, only the main one is made again
+            let extent = self.windows[0].extent;
+            if self.windows.len() > 1 {
+                warn!("Dropping {} windows from add_window, they have to be added again.", self.windows.len() - 1);
+            }
+            self.destroy();
+
+            // if this fails the app stays destroyed and dropping it won't destroy it again
+            let config = self.config.clone();
+            *self = if self.headless {
+                Self::create_headless(extent.width, extent.height, config)?
+            } else {
+                Self::create(window, config)?
+            };
+
+            Ok(())
+        }
+
+        // the windows go first, then the device, then the instance
+        unsafe fn destroy(&mut self) {
+            if self.destroyed {
+                return;
+            }
+            self.destroyed = true;
+
+            // make sure the gpu is done with everything before tearing down, a lost device has nothing to wait on
+            if let Err(e) = self.device.device_wait_idle() {
+                warn!("Failed to wait for the device before destroying: {}", e);
+            }
+
+            for mut window in mem::take(&mut self.windows) {
+                self.destroy_window(&mut window);
+            }
+            self.destroy_render_pass();
+
+            self.device.destroy_pipeline(self.compute_pipeline, None);
+            if let Err(e) = save_pipeline_cache(&self.device, self.pipeline_cache) {
+                warn!("Failed to save pipeline cache: {}", e);
+            }
+            self.device.destroy_pipeline_cache(self.pipeline_cache, None);
+            self.device.destroy_pipeline_layout(self.compute_pipeline_layout, None);
+            self.device.destroy_descriptor_pool(self.compute_descriptor_pool, None);
+            self.device.destroy_descriptor_set_layout(self.compute_descriptor_set_layout, None);
+            self.device.destroy_buffer(self.compute_buffer, None);
+            self.allocator.free(&self.device, self.compute_buffer_memory);
+            self.device.destroy_command_pool(self.compute_command_pool, None);
+            self.device.destroy_sampler(self.texture_sampler, None);
+            self.device.destroy_image_view(self.texture_image_view, None);
+            self.device.destroy_image(self.texture_image, None);
+            self.allocator.free(&self.device, self.texture_image_memory);
+            self.device.destroy_image_view(self.normal_map_image_view, None);
+            self.device.destroy_image(self.normal_map_image, None);
+            self.allocator.free(&self.device, self.normal_map_image_memory);
+            self.device.destroy_descriptor_set_layout(self.descriptor_set_layout, None);
+            self.device.destroy_descriptor_set_layout(self.post_descriptor_set_layout, None);
+            if !self.post_sampler.is_null() {
+                self.device.destroy_sampler(self.post_sampler, None);
+            }
+            self.device.destroy_buffer(self.index_buffer, None);
+            self.allocator.free(&self.device, self.index_buffer_memory);
+            self.device.destroy_buffer(self.vertex_buffer, None);
+            self.allocator.free(&self.device, self.vertex_buffer_memory);
+            self.device.destroy_buffer(self.instance_buffer, None);
+            self.allocator.free(&self.device, self.instance_buffer_memory);
+            self.device.destroy_buffer(self.draw_commands_buffer, None);
+            self.allocator.free(&self.device, self.draw_commands_buffer_memory);
+            for object in &self.objects {
+                self.device.destroy_buffer(object.index_buffer, None);
+                self.allocator.free(&self.device, object.index_buffer_memory);
+                self.device.destroy_buffer(object.vertex_buffer, None);
+                self.allocator.free(&self.device, object.vertex_buffer_memory);
+            }
+            if let Some(mut gui) = self.gui.take() {
+                gui.destroy(&self.device, &mut self.allocator);
+            }
+            if let Some(mut skybox) = self.skybox.take() {
+                skybox.destroy(&self.device, &mut self.allocator);
+            }
+            if let Some(mut particles) = self.particles.take() {
+                particles.destroy(&self.device, &mut self.allocator);
+            }
+            self.debug_lines.destroy(&self.device, &mut self.allocator);
+            self.shadow_map.destroy(&self.device, &mut self.allocator);
+            if let Some(mut picker) = self.picker.take() {
+                picker.destroy(&self.device, &mut self.allocator);
+            }
+            if let Some(screenshot) = self.screenshot.take() {
+                self.device.destroy_buffer(screenshot.buffer, None);
+                self.allocator.free(&self.device, screenshot.buffer_memory);
+            }
+            if let Some(mut bindless) = self.bindless.take() {
+                bindless.destroy(&self.device, &mut self.allocator);
+            }
+            self.secondary_command_pools
+                .iter()
+                .for_each(|p| self.device.destroy_command_pool(*p, None));
+            self.device.destroy_command_pool(self.transfer_command_pool, None);
+            self.device.destroy_command_pool(self.command_pool, None);
+            self.allocator.destroy(&self.device);
+            self.device.destroy_device(None);
+
+            // destroy the debug messener if it exists
+            if let Some(messenger) = self.debug_messenger {
+                self.instance.destroy_debug_utils_messenger_ext(messenger, None);
+            }
+
+            self.instance.destroy_instance(None);
+        }
+    }
+
+    impl Drop for App {
+        fn drop(&mut self) {
+            unsafe { self.destroy() };
+        }
+    }
+
+    // the main window's image copied by a frame in flight, saved once that frame's fence or timeline value is reached
+    #[derive(Debug)]
+    pub struct Screenshot {
+        pub path: PathBuf,
+        pub buffer: vk::Buffer,
+        pub buffer_memory: Allocation,
+        pub extent: vk::Extent2D,
+        // the frame in flight that copies into it
+        pub frame: usize,
+    }
+
+    // everything a window renders the scene with, the first in App::windows is the main window or the offscreen
+    // image when headless, see add_window for the rest
+    #[derive(Debug, Default)]
+    pub struct WindowContext {
+        // null when headless
+        pub surface: vk::SurfaceKHR,
+        pub swapchain: vk::SwapchainKHR,
+        pub images: Vec<vk::Image>,
+        pub image_views: Vec<vk::ImageView>,
+        pub extent: vk::Extent2D,
+        // the window's size, used when the surface leaves it to the swapchain and to tell when it's minimized
+        pub window_extent: vk::Extent2D,
+        // only for the offscreen image, the swapchain owns its images
+        pub offscreen_image_memory: Allocation,
+        // what everything is rendered at, bigger than the swapchain when supersampling
+        pub render_extent: vk::Extent2D,
+        pub color_image: vk::Image,
+        pub color_image_memory: Allocation,
+        pub color_image_view: vk::ImageView,
+        pub depth_image: vk::Image,
+        pub depth_image_memory: Allocation,
+        pub depth_image_view: vk::ImageView,
+        pub scene_image: vk::Image,
+        pub scene_image_memory: Allocation,
+        pub scene_image_view: vk::ImageView,
+        // rendered to in place of the swapchain image then blitted down, null unless supersampling
+        pub scaled_image: vk::Image,
+        pub scaled_image_memory: Allocation,
+        pub scaled_image_view: vk::ImageView,
+        // none unless App::bloom_enabled
+        pub bloom: Option<Bloom>,
+        // empty when rendering dynamically
+        pub framebuffers: Vec<vk::Framebuffer>,
+        pub uniform_buffers: Vec<vk::Buffer>,
+        pub uniform_buffers_memory: Vec<Allocation>,
+        // the light and camera position, per swapchain image like the ubos
+        pub light_buffers: Vec<vk::Buffer>,
+        pub light_buffers_memory: Vec<Allocation>,
+        pub object_buffers: Vec<vk::Buffer>,
+        pub object_buffers_memory: Vec<Allocation>,
+        pub descriptor_pool: vk::DescriptorPool,
+        pub descriptor_sets: Vec<vk::DescriptorSet>,
+        pub post_descriptor_pool: vk::DescriptorPool,
+        pub post_descriptor_set: vk::DescriptorSet,
+        pub command_buffers: Vec<vk::CommandBuffer>,
+        // per swapchain image, a buffer for each scene pass of each recording thread
+        pub secondary_command_buffers: Vec<Vec<vk::CommandBuffer>>,
+        pub image_available_semaphores: Vec<vk::Semaphore>,
+        pub render_finished_semaphores: Vec<vk::Semaphore>,
+        pub in_flight_fences: Vec<vk::Fence>,
+        pub images_in_flight: Vec<vk::Fence>,
+        // null unless timeline semaphores are enabled, then it's waited on in place of the fences above
+        pub timeline_semaphore: vk::Semaphore,
+        // signalled by the last submission, one more each frame
+        pub timeline_value: u64,
+        // what each swapchain image's last submission signals
+        pub images_in_flight_values: Vec<u64>,
+        pub timestamp_query_pool: vk::QueryPool,
+        pub timestamps_written: Vec<bool>,
+        pub last_image_index: Option<usize>,
+        pub frame: usize,
+        // recreated before the next frame, skipped until then
+        pub resized: bool,
+        // looks through App::camera when none
+        pub camera: Option<Camera>,
+    }
+
+    impl WindowContext {
+        // for the window's resize events
+        pub fn resize(&mut self, width: u32, height: u32) {
+            self.window_extent = vk::Extent2D { width, height };
+            self.resized = true;
+        }
+
+        pub fn is_minimized(&self) -> bool {
+            self.window_extent.width == 0 || self.window_extent.height == 0
+        }
+
+        fn is_scaled(&self) -> bool {
+            !self.scaled_image.is_null()
+        }
+
+        // the image post-processing renders into, the scaled image when supersampling
+        fn post_target(&self, image_index: usize) -> (vk::Image, vk::ImageView) {
+            if self.scaled_image.is_null() {
+                (self.images[image_index], self.image_views[image_index])
+            } else {
+                (self.scaled_image, self.scaled_image_view)
+            }
+        }
+    }
+
+    /*
+     * creation functions
+     */
+
+    unsafe fn create_instance(
+        window: Option<&Window>,
+        entry: &Entry,
+        config: &AppConfig,
+    ) -> Result<(Instance, Option<DebugUtilsMessengerEXT>)> {
+        // a 1.0 loader reports 1.0 and fails on anything newer, the patch doesn't matter
+        let supported = entry.version()?;
+        let requested = Version::from(config.api_version);
+        if (requested.major, requested.minor) > (supported.major, supported.minor) {
+            return Err(anyhow!("Requested Vulkan {} but the instance only supports up to {}.", requested, supported));
+        }
+        info!("Creating a Vulkan {} instance, up to {} is supported.", requested, supported);
+
+        // create application info struct
+        let application_name = CString::new(config.application_name.as_str())?;
+        let engine_name = CString::new(config.engine_name.as_str())?;
+        let application_info = vk::ApplicationInfo::builder()
+            .application_name(application_name.as_bytes_with_nul())
+            .application_version(vk::make_version(1, 0, 0))
+            .engine_name(engine_name.as_bytes_with_nul())
+            .engine_version(vk::make_version(1, 0, 0))
+            .api_version(config.api_version);
+
+        // get available layer names in a hashset
+        let available_layers = entry
+            .enumerate_instance_layer_properties()?
+            .iter()
+            .map(|l| l.layer_name)
+            .collect::<HashSet<_>>();
+
+        // check every validation layer is supported if enabled
+        if config.validation {
+            if let Some(layer) = VALIDATION_LAYERS.iter().find(|l| !available_layers.contains(l)) {
+                return Err(anyhow!("Validation layer ({}) requested but not supported.", layer));
+            }
+        }
+
+        // add validation layers if enabled
+        let layers = if config.validation {
+            VALIDATION_LAYERS.iter().map(|l| l.as_ptr()).collect::<Vec<_>>()
+        } else {
+            Vec::new()
+        };
+
+        // get required extensions, headless needs no surface extensions
+        let mut extensions = window
+            .map(|w| vk_window::get_required_instance_extensions(w))
+            .unwrap_or(&[])
+            .iter()
+            .map(|e| e.as_ptr())
+            .collect::<Vec<_>>();
+
+        // get flags if target is macos
+        let flags = if cfg!(target_os = "macos") &&
+            entry.version()? >= PORTABILITY_MACOS_VERSION {
+            info!("Enabling extensions for MacOS portability.");
+            extensions.push(vk::KHR_GET_PHYSICAL_DEVICE_PROPERTIES2_EXTENSION.name.as_ptr());
+            extensions.push(vk::KHR_PORTABILITY_ENUMERATION_EXTENSION.name.as_ptr());
+            vk::InstanceCreateFlags::ENUMERATE_PORTABILITY_KHR
+        } else {
+            vk::InstanceCreateFlags::empty()
+        };
+
+        if config.validation {
+            extensions.push(vk::EXT_DEBUG_UTILS_EXTENSION.name.as_ptr());
+        }
+
+        let mut info = vk::InstanceCreateInfo::builder()
+            .application_info(&application_info)
+            .enabled_layer_names(&layers)
+            .enabled_extension_names(&extensions)
+            .flags(flags);
+
+        // set up validation for create instance call if enabled
+        let mut debug_messenger: Option<DebugUtilsMessengerEXT> = None;
+
+        let mut debug_info = vk::DebugUtilsMessengerCreateInfoEXT::builder()
+                .message_severity(VALIDATION_MIN_SEVERITY.flags())
+                .message_type(VALIDATION_TYPE_FLAGS)
+                .user_callback(Some(debug_callback));
+
+        if config.validation {
+            info = info.push_next(&mut debug_info);
+        }
+
+        let instance = entry.create_instance(&info, None)?;
+
+        // create debug messenger if validation is enabled
+        if config.validation {
+            debug_messenger = Some(instance.create_debug_utils_messenger_ext(&debug_info, None)?);
+        }
+        
+        Ok((instance, debug_messenger))
+    }
+
+    // used for GPU suitability
+    #[derive(Debug, Error)]
+    #[error("Missing {0}.")]
+    pub struct SuitabilityError(pub &'static str);
+
+    #[derive(Debug, Error)]
+    pub enum RenderError {
+        #[error("The device was lost.")]
+        DeviceLost,
+    }
+
+    // lets callers tell a lost device apart from other failures
+    fn render_error(error: vk::ErrorCode) -> anyhow::Error {
+        if error == vk::ErrorCode::DEVICE_LOST {
+            anyhow!(RenderError::DeviceLost)
+        } else {
+            anyhow!(error)
+        }
+    }
+
+    #[derive(Debug, Error)]
+    pub enum DeviceError {
+        #[error("Failed to find suitable physical device.")]
+        NoSuitableDevice,
+        #[error("Missing device extension ({0}).")]
+        MissingExtension(String),
+        #[error("Missing required queue families.")]
+        MissingQueueFamily,
+        #[error("Surface is not supported by the device.")]
+        SurfaceUnsupported,
+        #[error(transparent)]
+        Unsuitable(#[from] SuitabilityError),
+        #[error("Invalid device request: {0}")]
+        InvalidRequest(String),
+        #[error("Failed to find requested physical device ({0}).")]
+        RequestedDeviceNotFound(String),
+        #[error("Requested physical device ({name}) is unsuitable: {source}")]
+        RequestedDeviceUnsuitable { name: String, source: Box<DeviceError> },
+        #[error(transparent)]
+        Vulkan(#[from] vk::ErrorCode),
+    }
+
+    unsafe fn choose_physical_device(
+        instance: &Instance,
+        surface: vk::SurfaceKHR,
+    ) -> Result<vk::PhysicalDevice, DeviceError> {
+        // let the user force a device for debugging
+        if let Some(phys_device) = requested_physical_device(instance, surface)? {
+            return Ok(phys_device);
+        }
+
+        let mut candidates = Vec::new();
+
+        for phys_device in instance.enumerate_physical_devices()? {
+            let properties = instance.get_physical_device_properties(phys_device);
+
+            if let Err(error) = check_physical_device(instance, surface, phys_device) {
+                warn!("Skipping physical device ({}): {}", properties.device_name, error);
+                continue;
+            }
+
+            let score = rate_physical_device(instance, phys_device);
+            info!("Physical device ({}) scored {}.", properties.device_name, score);
+
+            if score > 0 {
+                candidates.push((score, phys_device));
+            }
+        }
+
+        // highest score wins, ties go to the first enumerated
+        let (_, phys_device) = candidates
+            .into_iter()
+            .rev()
+            .max_by_key(|(score, _)| *score)
+            .ok_or(DeviceError::NoSuitableDevice)?;
+
+        let properties = instance.get_physical_device_properties(phys_device);
+        info!("Selected physical device ({})", properties.device_name);
+
+        Ok(phys_device)
+    }
+
+    unsafe fn requested_physical_device(
+        instance: &Instance,
+        surface: vk::SurfaceKHR,
+    ) -> Result<Option<vk::PhysicalDevice>, DeviceError> {
+        let index = match env::var("VK_DEVICE_INDEX") {
+            Ok(value) => Some(
+                value
+                    .trim()
+                    .parse::<usize>()
+                    .map_err(|e| DeviceError::InvalidRequest(format!("VK_DEVICE_INDEX ({}): {}", value, e)))?,
+            ),
+            Err(_) => None,
+        };
+        let name = env::var("VK_DEVICE_NAME").ok();
+
+        if index.is_none() && name.is_none() {
+            return Ok(None);
+        }
+
+        // both have to match when both are set
+        let phys_device = instance
+            .enumerate_physical_devices()?
+            .into_iter()
+            .enumerate()
+            .find(|(i, phys_device)| {
+                let properties = instance.get_physical_device_properties(*phys_device);
+                index.is_none_or(|index| index == *i)
+                    && name
+                        .as_ref()
+                        .is_none_or(|name| properties.device_name.to_string().contains(name.as_str()))
+            })
+            .map(|(_, phys_device)| phys_device)
+            .ok_or_else(|| DeviceError::RequestedDeviceNotFound(format!("index: {:?}, name: {:?}", index, name)))?;
+
+        let properties = instance.get_physical_device_properties(phys_device);
+        check_physical_device(instance, surface, phys_device).map_err(|e| DeviceError::RequestedDeviceUnsuitable {
+            name: properties.device_name.to_string(),
+            source: Box::new(e),
+        })?;
+
+        info!("Selected requested physical device ({})", properties.device_name);
+
+        Ok(Some(phys_device))
+    }
+
+    unsafe fn log_device_info(instance: &Instance, phys_device: vk::PhysicalDevice) {
+        let properties = instance.get_physical_device_properties(phys_device);
+        let features = instance.get_physical_device_features(phys_device);
+        let limits = properties.limits;
+
+        let supported = |feature: vk::Bool32| if feature == vk::TRUE { "yes" } else { "no" };
+
+        let lines = [
+            format!("name:                   {}", properties.device_name),
+            format!("type:                   {:?}", properties.device_type),
+            format!("driver version:         {} ({:#x})", Version::from(properties.driver_version), properties.driver_version),
+            format!("api version:            {}", Version::from(properties.api_version)),
+            format!("max image dimension 2d: {}", limits.max_image_dimension_2d),
+            format!("max push constants:     {} bytes", limits.max_push_constants_size),
+            format!("max descriptor sets:    {}", limits.max_bound_descriptor_sets),
+            format!("sampler anisotropy:     {}", supported(features.sampler_anisotropy)),
+            format!("geometry shader:        {}", supported(features.geometry_shader)),
+            format!("tessellation shader:    {}", supported(features.tessellation_shader)),
+            format!("wide lines:             {}", supported(features.wide_lines)),
+            format!("sample rate shading:    {}", supported(features.sample_rate_shading)),
+        ];
+
+        info!("Physical device info:\n    {}", lines.join("\n    "));
+    }
+
+    unsafe fn rate_physical_device(instance: &Instance, phys_device: vk::PhysicalDevice) -> u32 {
+        let properties = instance.get_physical_device_properties(phys_device);
+
+        let mut score = 0;
+
+        // discrete gpus are almost always the faster option
+        if properties.device_type == vk::PhysicalDeviceType::DISCRETE_GPU {
+            score += 1000;
+        }
+
+        score += properties.limits.max_image_dimension_2d;
+
+        score
+    }
+
+    unsafe fn check_physical_device(
+        instance: &Instance,
+        surface: vk::SurfaceKHR,
+        phys_device: vk::PhysicalDevice,
+    ) -> Result<(), DeviceError> {
+        QueueFamilyIndices::get(instance, &[surface], phys_device)?;
+
+        // headless rendering never needs a swapchain
+        if !surface.is_null() {
+            check_physical_device_extensions(instance, phys_device)?;
+
+            // make sure the surface can actually be presented to
+            let support = SwapchainSupport::get(instance, surface, phys_device)?;
+            if support.formats.is_empty() || support.present_modes.is_empty() {
+                return Err(DeviceError::SurfaceUnsupported);
+            }
+        }
+
+        Ok(())
+    }
+
+    unsafe fn check_physical_device_extensions(
+        instance: &Instance,
+        phys_device: vk::PhysicalDevice,
+    ) -> Result<(), DeviceError> {
+        let extensions = instance
+            .enumerate_device_extension_properties(phys_device, None)?
+            .iter()
+            .map(|e| e.extension_name)
+            .collect::<HashSet<_>>();
+
+        match DEVICE_EXTENSIONS.iter().find(|e| !extensions.contains(e)) {
+            Some(missing) => Err(DeviceError::MissingExtension(missing.to_string())),
+            None => Ok(()),
+        }
+    }
+
+    // not an error, features from versions past the device's are left off like they would be without the instance's
+    unsafe fn check_device_version(instance: &Instance, phys_device: vk::PhysicalDevice, config: &AppConfig) {
+        let device = Version::from(instance.get_physical_device_properties(phys_device).api_version);
+        let requested = Version::from(config.api_version);
+        if (device.major, device.minor) < (requested.major, requested.minor) {
+            warn!("Device only supports Vulkan {}, not the requested {}.", device, requested);
+        }
+    }
+
+    unsafe fn check_push_constants_size(instance: &Instance, phys_device: vk::PhysicalDevice) -> Result<()> {
+        let max = instance.get_physical_device_properties(phys_device).limits.max_push_constants_size;
+
+        if PUSH_CONSTANTS_SIZE > max {
+            return Err(anyhow!("Push constants ({} bytes) exceed the device limit ({} bytes).", PUSH_CONSTANTS_SIZE, max));
+        }
+
+        Ok(())
+    }
+
+    // what the instance asked for capped by what the device has, later core features need both
+    unsafe fn get_effective_version(instance: &Instance, phys_device: vk::PhysicalDevice, config: &AppConfig) -> u32 {
+        let version = instance.get_physical_device_properties(phys_device).api_version;
+        version.min(config.api_version)
+    }
+
+    unsafe fn get_dynamic_rendering_support(
+        instance: &Instance,
+        phys_device: vk::PhysicalDevice,
+        config: &AppConfig,
+    ) -> bool {
+        let version = get_effective_version(instance, phys_device, config);
+        config.dynamic_rendering && query_dynamic_rendering_support(instance, phys_device, version)
+    }
+
+    // the extension is still listed by 1.3 devices, the features query needs 1.1 on both sides
+    unsafe fn query_dynamic_rendering_support(
+        instance: &Instance,
+        phys_device: vk::PhysicalDevice,
+        version: u32,
+    ) -> bool {
+        if version < vk::make_version(1, 1, 0) {
+            return false;
+        }
+
+        let extensions = match instance.enumerate_device_extension_properties(phys_device, None) {
+            Ok(extensions) => extensions.iter().map(|e| e.extension_name).collect::<HashSet<_>>(),
+            Err(_) => return false,
+        };
+        if !get_dynamic_rendering_extensions(version).iter().all(|e| extensions.contains(e)) {
+            return false;
+        }
+
+        let mut dynamic_rendering = vk::PhysicalDeviceDynamicRenderingFeatures::builder();
+        let mut features = vk::PhysicalDeviceFeatures2::builder().push_next(&mut dynamic_rendering);
+        instance.get_physical_device_features2(phys_device, &mut features);
+
+        dynamic_rendering.dynamic_rendering == vk::TRUE
+    }
+
+    // what it depends on is core from 1.2
+    fn get_dynamic_rendering_extensions(device_version: u32) -> Vec<vk::ExtensionName> {
+        let mut extensions = vec![vk::KHR_DYNAMIC_RENDERING_EXTENSION.name];
+        if device_version < vk::make_version(1, 2, 0) {
+            extensions.push(vk::KHR_DEPTH_STENCIL_RESOLVE_EXTENSION.name);
+            extensions.push(vk::KHR_CREATE_RENDERPASS2_EXTENSION.name);
+        }
+
+        extensions
+    }
+
+    unsafe fn get_bindless_support(instance: &Instance, phys_device: vk::PhysicalDevice, config: &AppConfig) -> bool {
+        let version = get_effective_version(instance, phys_device, config);
+        config.bindless && query_bindless_support(instance, phys_device, version)
+    }
+
+    // partially bound, update after bind sampled images in a runtime sized array, with room for the whole array
+    unsafe fn query_bindless_support(instance: &Instance, phys_device: vk::PhysicalDevice, version: u32) -> bool {
+        if version < vk::make_version(1, 1, 0) {
+            return false;
+        }
+
+        let extensions = match instance.enumerate_device_extension_properties(phys_device, None) {
+            Ok(extensions) => extensions.iter().map(|e| e.extension_name).collect::<HashSet<_>>(),
+            Err(_) => return false,
+        };
+        if !get_bindless_extensions(version).iter().all(|e| extensions.contains(e)) {
+            return false;
+        }
+
+        let mut descriptor_indexing = vk::PhysicalDeviceDescriptorIndexingFeatures::builder();
+        let mut features = vk::PhysicalDeviceFeatures2::builder().push_next(&mut descriptor_indexing);
+        instance.get_physical_device_features2(phys_device, &mut features);
+
+        let mut descriptor_indexing_properties = vk::PhysicalDeviceDescriptorIndexingProperties::builder();
+        let mut properties = vk::PhysicalDeviceProperties2::builder().push_next(&mut descriptor_indexing_properties);
+        instance.get_physical_device_properties2(phys_device, &mut properties);
+
+        descriptor_indexing.descriptor_binding_partially_bound == vk::TRUE
+            && descriptor_indexing.descriptor_binding_sampled_image_update_after_bind == vk::TRUE
+            && descriptor_indexing.runtime_descriptor_array == vk::TRUE
+            && descriptor_indexing_properties.max_per_stage_descriptor_update_after_bind_sampled_images
+                >= MAX_BINDLESS_TEXTURES
+            && descriptor_indexing_properties.max_per_stage_descriptor_update_after_bind_samplers >= MAX_BINDLESS_TEXTURES
+            && descriptor_indexing_properties.max_descriptor_set_update_after_bind_sampled_images >= MAX_BINDLESS_TEXTURES
+            && descriptor_indexing_properties.max_descriptor_set_update_after_bind_samplers >= MAX_BINDLESS_TEXTURES
+    }
+
+    // buffer device address and descriptor indexing among them, none unless 1.2 is there on both sides
+    unsafe fn get_vulkan12_features(
+        instance: &Instance,
+        phys_device: vk::PhysicalDevice,
+        version: u32,
+    ) -> Option<vk::PhysicalDeviceVulkan12Features> {
+        if version < vk::make_version(1, 2, 0) {
+            return None;
+        }
+
+        let mut vulkan12 = vk::PhysicalDeviceVulkan12Features::builder();
+        let mut features = vk::PhysicalDeviceFeatures2::builder().push_next(&mut vulkan12);
+        instance.get_physical_device_features2(phys_device, &mut features);
+
+        Some(vulkan12.build())
+    }
+
+    unsafe fn get_buffer_device_address_support(
+        instance: &Instance,
+        phys_device: vk::PhysicalDevice,
+        config: &AppConfig,
+    ) -> bool {
+        let version = get_effective_version(instance, phys_device, config);
+        config.buffer_device_address && query_buffer_device_address_support(instance, phys_device, version)
+    }
+
+    unsafe fn query_buffer_device_address_support(
+        instance: &Instance,
+        phys_device: vk::PhysicalDevice,
+        version: u32,
+    ) -> bool {
+        get_vulkan12_features(instance, phys_device, version).is_some_and(|f| f.buffer_device_address == vk::TRUE)
+    }
+
+    unsafe fn get_timeline_semaphore_support(
+        instance: &Instance,
+        phys_device: vk::PhysicalDevice,
+        config: &AppConfig,
+    ) -> bool {
+        let version = get_effective_version(instance, phys_device, config);
+        config.timeline_semaphores && query_timeline_semaphore_support(instance, phys_device, version)
+    }
+
+    unsafe fn query_timeline_semaphore_support(
+        instance: &Instance,
+        phys_device: vk::PhysicalDevice,
+        version: u32,
+    ) -> bool {
+        get_vulkan12_features(instance, phys_device, version).is_some_and(|f| f.timeline_semaphore == vk::TRUE)
+    }
+
+    // core from 1.2, what it depends on is core from 1.1
+    fn get_bindless_extensions(device_version: u32) -> Vec<vk::ExtensionName> {
+        if device_version < vk::make_version(1, 2, 0) {
+            vec![vk::EXT_DESCRIPTOR_INDEXING_EXTENSION.name]
+        } else {
+            Vec::new()
+        }
+    }
+
+    unsafe fn get_max_msaa_samples(instance: &Instance, phys_device: vk::PhysicalDevice) -> vk::SampleCountFlags {
+        let properties = instance.get_physical_device_properties(phys_device);
+
+        // has to be usable for both color and depth
+        let counts = properties.limits.framebuffer_color_sample_counts
+            & properties.limits.framebuffer_depth_sample_counts;
+
+        [
+            vk::SampleCountFlags::_64,
+            vk::SampleCountFlags::_32,
+            vk::SampleCountFlags::_16,
+            vk::SampleCountFlags::_8,
+            vk::SampleCountFlags::_4,
+            vk::SampleCountFlags::_2,
+        ]
+        .iter()
+        .cloned()
+        .find(|c| counts.contains(*c))
+        .unwrap_or(vk::SampleCountFlags::_1)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    unsafe fn create_logical_device(
+        instance: &Instance,
+        phys_device: vk::PhysicalDevice,
+        indices: &QueueFamilyIndices,
+        config: &AppConfig,
+        headless: bool,
+        dynamic_rendering: bool,
+        bindless: bool,
+        buffer_device_address: bool,
+        timeline_semaphores: bool,
+    ) -> Result<(Device, vk::Queue, vk::Queue, vk::Queue, vk::Queue)> {
+        let compute = indices.compute.unwrap_or(indices.graphics);
+        let transfer = indices.transfer.unwrap_or(indices.graphics);
+
+        // one queue from each unique family
+        let mut unique_indices = HashSet::new();
+        unique_indices.insert(indices.graphics);
+        unique_indices.insert(indices.present);
+        unique_indices.insert(compute);
+        unique_indices.insert(transfer);
+
+        let queue_priorities = &[1.0];
+        let queue_infos = unique_indices
+            .iter()
+            .map(|i| {
+                vk::DeviceQueueCreateInfo::builder()
+                    .queue_family_index(*i)
+                    .queue_priorities(queue_priorities)
+            })
+            .collect::<Vec<_>>();
+
+        // device layers are deprecated but older implementations still use them
+        let layers = if config.validation {
+            VALIDATION_LAYERS.iter().map(|l| l.as_ptr()).collect::<Vec<_>>()
+        } else {
+            Vec::new()
+        };
+
+        // swapchain is required unless headless, portability subset is required on macos
+        let mut extensions = Vec::new();
+        if !headless {
+            extensions.extend(DEVICE_EXTENSIONS.iter().map(|e| e.as_ptr()));
+        }
+        if cfg!(target_os = "macos") {
+            extensions.push(vk::KHR_PORTABILITY_SUBSET_EXTENSION.name.as_ptr());
+        }
+        let version = get_effective_version(instance, phys_device, config);
+        if dynamic_rendering {
+            extensions.extend(get_dynamic_rendering_extensions(version).iter().map(|e| e.as_ptr()));
+        }
+        if bindless {
+            extensions.extend(get_bindless_extensions(version).iter().map(|e| e.as_ptr()));
+        }
+
+        // optional features are enabled if available to back wireframe rendering, particle points, sample shading,
+        // anisotropic filtering and indirect draws
+        let supported = instance.get_physical_device_features(phys_device);
+        let features = vk::PhysicalDeviceFeatures::builder()
+            .sampler_anisotropy(supported.sampler_anisotropy == vk::TRUE)
+            .fill_mode_non_solid(supported.fill_mode_non_solid == vk::TRUE)
+            .wide_lines(supported.wide_lines == vk::TRUE)
+            .large_points(supported.large_points == vk::TRUE)
+            .sample_rate_shading(supported.sample_rate_shading == vk::TRUE)
+            .multi_draw_indirect(supported.multi_draw_indirect == vk::TRUE)
+            .draw_indirect_first_instance(supported.draw_indirect_first_instance == vk::TRUE)
+            .build();
+
+        let mut info = vk::DeviceCreateInfo::builder()
+            .queue_create_infos(&queue_infos)
+            .enabled_layer_names(&layers)
+            .enabled_extension_names(&extensions);
+
+        // from 1.1 the core features go at the head of the chain with everything newer after them
+        let mut features2 = vk::PhysicalDeviceFeatures2::builder().features(features);
+        if version >= vk::make_version(1, 1, 0) {
+            info = info.push_next(&mut features2);
+        } else {
+            info = info.enabled_features(&features);
+        }
+
+        let mut dynamic_rendering_features = vk::PhysicalDeviceDynamicRenderingFeatures::builder()
+            .dynamic_rendering(true);
+        if dynamic_rendering {
+            info = info.push_next(&mut dynamic_rendering_features);
+        }
+
+        // 1.2 takes descriptor indexing here as well, it can't be chained again on its own
+        let mut vulkan12_features = vk::PhysicalDeviceVulkan12Features::builder()
+            .descriptor_binding_partially_bound(bindless)
+            .descriptor_binding_sampled_image_update_after_bind(bindless)
+            .runtime_descriptor_array(bindless)
+            .buffer_device_address(buffer_device_address)
+            .timeline_semaphore(timeline_semaphores);
+        let mut descriptor_indexing_features = vk::PhysicalDeviceDescriptorIndexingFeatures::builder()
+            .descriptor_binding_partially_bound(true)
+            .descriptor_binding_sampled_image_update_after_bind(true)
+            .runtime_descriptor_array(true);
+        if version >= vk::make_version(1, 2, 0) {
+            info = info.push_next(&mut vulkan12_features);
+        } else if bindless {
+            info = info.push_next(&mut descriptor_indexing_features);
+        }
+
+        let device = instance.create_device(phys_device, &info, None)?;
+        let graphics_queue = device.get_device_queue(indices.graphics, 0);
+        let present_queue = device.get_device_queue(indices.present, 0);
+        let compute_queue = device.get_device_queue(compute, 0);
+        let transfer_queue = device.get_device_queue(transfer, 0);
+
+        Ok((device, graphics_queue, present_queue, compute_queue, transfer_queue))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    unsafe fn create_swapchain(
+        window_extent: vk::Extent2D,
+        instance: &Instance,
+        device: &Device,
+        surface: vk::SurfaceKHR,
+        phys_device: vk::PhysicalDevice,
+        indices: &QueueFamilyIndices,
+        config: &AppConfig,
+        old_swapchain: vk::SwapchainKHR,
+    ) -> Result<(vk::SwapchainKHR, Vec<vk::Image>, vk::Format, vk::Extent2D)> {
+        // queried every time, the modes and formats can change with the window's state, e.g. mailbox on wayland
+        let support = SwapchainSupport::get(instance, surface, phys_device)?;
+
+        let surface_format = get_swapchain_surface_format(
+            &support.formats,
+            config.format_preference,
+            config.preferred_color_space,
+        );
+        info!(
+            "Using surface format {:?} with color space {:?}.",
+            surface_format.format,
+            surface_format.color_space,
+        );
+        let present_mode = get_swapchain_present_mode(&support.present_modes, config.preferred_present_mode);
+        let extent = get_swapchain_extent(window_extent, support.capabilities);
+
+        let image_count = get_swapchain_image_count(support.capabilities, config.swapchain_image_count);
+
+        // screenshots copy out of the swapchain images and supersampling blits into them when the surface allows it
+        let mut image_usage = vk::ImageUsageFlags::COLOR_ATTACHMENT;
+        if support.capabilities.supported_usage_flags.contains(vk::ImageUsageFlags::TRANSFER_SRC) {
+            image_usage |= vk::ImageUsageFlags::TRANSFER_SRC;
+        }
+        if support.capabilities.supported_usage_flags.contains(vk::ImageUsageFlags::TRANSFER_DST) {
+            image_usage |= vk::ImageUsageFlags::TRANSFER_DST;
+        }
+
+        // rendered to on the graphics queue and presented on the present queue, the only resource used by both
+        let queue_family_indices = &[indices.graphics, indices.present];
+        let (image_sharing_mode, queue_family_indices): (_, &[u32]) = if indices.graphics != indices.present {
+            (vk::SharingMode::CONCURRENT, queue_family_indices)
+        } else {
+            (vk::SharingMode::EXCLUSIVE, &[])
+        };
+
+        let info = vk::SwapchainCreateInfoKHR::builder()
+            .surface(surface)
+            .min_image_count(image_count)
+            .image_format(surface_format.format)
+            .image_color_space(surface_format.color_space)
+            .image_extent(extent)
+            .image_array_layers(1)
+            .image_usage(image_usage)
+            .image_sharing_mode(image_sharing_mode)
+            .queue_family_indices(queue_family_indices)
+            .pre_transform(support.capabilities.current_transform)
+            .composite_alpha(vk::CompositeAlphaFlagsKHR::OPAQUE)
+            .present_mode(present_mode)
+            .clipped(true)
+            .old_swapchain(old_swapchain);
+
+        let swapchain = device.create_swapchain_khr(&info, None)?;
+        let images = device.get_swapchain_images_khr(swapchain)?;
+
+        // the driver is free to create more than asked for
+        info!("Using {} swapchain images ({} requested).", images.len(), image_count);
+
+        Ok((swapchain, images, surface_format.format, extent))
+    }
+
+    unsafe fn create_offscreen_image(
+        instance: &Instance,
+        device: &Device,
+        phys_device: vk::PhysicalDevice,
+        allocator: &mut Allocator,
+        extent: vk::Extent2D,
+    ) -> Result<(vk::Image, Allocation)> {
+        // stands in for the swapchain image, copied out after rendering
+        create_image(
+            instance,
+            device,
+            phys_device,
+            allocator,
+            extent.width,
+            extent.height,
+            1,
+            1,
+            vk::SampleCountFlags::_1,
+            OFFSCREEN_FORMAT,
+            vk::ImageTiling::OPTIMAL,
+            vk::ImageCreateFlags::empty(),
+            vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSFER_SRC | vk::ImageUsageFlags::TRANSFER_DST,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            "offscreen-image",
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    unsafe fn read_image(
+        instance: &Instance,
+        device: &Device,
+        phys_device: vk::PhysicalDevice,
+        allocator: &mut Allocator,
+        command_pool: vk::CommandPool,
+        queue: vk::Queue,
+        image: vk::Image,
+        layout: vk::ImageLayout,
+        extent: vk::Extent2D,
+    ) -> Result<Vec<u8>> {
+        // only used with 4 byte formats
+        let size = (extent.width * extent.height * 4) as u64;
+
+        let (buffer, buffer_memory) = create_buffer(
+            instance,
+            device,
+            phys_device,
+            allocator,
+            size,
+            vk::BufferUsageFlags::TRANSFER_DST,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+            "readback",
+        )?;
+
+        let commands = SingleTimeCommands::new(device, command_pool, queue)?;
+        cmd_read_image(device, commands.command_buffer, image, layout, extent, buffer);
+        commands.submit()?;
+
+        let memory = device.map_memory(buffer_memory.memory, buffer_memory.offset, size, vk::MemoryMapFlags::empty())?;
+        let mut pixels = vec![0u8; size as usize];
+        memcpy(memory.cast(), pixels.as_mut_ptr(), pixels.len());
+        device.unmap_memory(buffer_memory.memory);
+
+        device.destroy_buffer(buffer, None);
+        allocator.free(device, buffer_memory);
+
+        Ok(pixels)
+    }
+
+    // copies a 4 byte per pixel image into a host visible buffer and puts it back in the layout it was in
+    unsafe fn cmd_read_image(
+        device: &Device,
+        command_buffer: vk::CommandBuffer,
+        image: vk::Image,
+        layout: vk::ImageLayout,
+        extent: vk::Extent2D,
+        buffer: vk::Buffer,
+    ) {
+        // wait for the render pass or blit writes and move the image into transfer src
+        let subresource_range = vk::ImageSubresourceRange::builder()
+            .aspect_mask(vk::ImageAspectFlags::COLOR)
+            .base_mip_level(0)
+            .level_count(1)
+            .base_array_layer(0)
+            .layer_count(1);
+
+        let barrier = vk::ImageMemoryBarrier::builder()
+            .old_layout(layout)
+            .new_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .image(image)
+            .subresource_range(subresource_range)
+            .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE | vk::AccessFlags::TRANSFER_WRITE)
+            .dst_access_mask(vk::AccessFlags::TRANSFER_READ);
+
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT | vk::PipelineStageFlags::TRANSFER,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::DependencyFlags::empty(),
+            &[] as &[vk::MemoryBarrier],
+            &[] as &[vk::BufferMemoryBarrier],
+            &[barrier],
+        );
+
+        let subresource = vk::ImageSubresourceLayers::builder()
+            .aspect_mask(vk::ImageAspectFlags::COLOR)
+            .mip_level(0)
+            .base_array_layer(0)
+            .layer_count(1);
+
+        let region = vk::BufferImageCopy::builder()
+            .buffer_offset(0)
+            .buffer_row_length(0)
+            .buffer_image_height(0)
+            .image_subresource(subresource)
+            .image_offset(vk::Offset3D { x: 0, y: 0, z: 0 })
+            .image_extent(vk::Extent3D { width: extent.width, height: extent.height, depth: 1 });
+
+        device.cmd_copy_image_to_buffer(
+            command_buffer,
+            image,
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            buffer,
+            &[region],
+        );
+
+        // put the image back how we found it
+        if layout != vk::ImageLayout::TRANSFER_SRC_OPTIMAL {
+            let barrier = vk::ImageMemoryBarrier::builder()
+                .old_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL)
+                .new_layout(layout)
+                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .image(image)
+                .subresource_range(subresource_range)
+                .src_access_mask(vk::AccessFlags::TRANSFER_READ)
+                .dst_access_mask(vk::AccessFlags::empty());
+
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+                vk::DependencyFlags::empty(),
+                &[] as &[vk::MemoryBarrier],
+                &[] as &[vk::BufferMemoryBarrier],
+                &[barrier],
+            );
+        }
+
+        // made visible to the host mapping once the commands are waited on
+        let buffer_barrier = vk::BufferMemoryBarrier::builder()
+            .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+            .dst_access_mask(vk::AccessFlags::HOST_READ)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .buffer(buffer)
+            .offset(0)
+            .size(vk::WHOLE_SIZE as u64);
+
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::PipelineStageFlags::HOST,
+            vk::DependencyFlags::empty(),
+            &[] as &[vk::MemoryBarrier],
+            &[buffer_barrier],
+            &[] as &[vk::ImageMemoryBarrier],
+        );
+    }
+
+    unsafe fn create_swapchain_image_views(
+        device: &Device,
+        swapchain_images: &[vk::Image],
+        swapchain_format: vk::Format,
+    ) -> Result<Vec<vk::ImageView>> {
+        swapchain_images
+            .iter()
+            .map(|i| create_image_view(device, *i, swapchain_format, vk::ImageAspectFlags::COLOR, 1))
+            .collect()
+    }
+
+    /// # Safety
+    ///
+    /// `image` has to come from `device` and have `format` and the mip levels asked for.
+    pub unsafe fn create_image_view(
+        device: &Device,
+        image: vk::Image,
+        format: vk::Format,
+        aspects: vk::ImageAspectFlags,
+        mip_levels: u32,
+    ) -> Result<vk::ImageView> {
+        let components = vk::ComponentMapping::builder()
+            .r(vk::ComponentSwizzle::IDENTITY)
+            .g(vk::ComponentSwizzle::IDENTITY)
+            .b(vk::ComponentSwizzle::IDENTITY)
+            .a(vk::ComponentSwizzle::IDENTITY);
+
+        let subresource_range = vk::ImageSubresourceRange::builder()
+            .aspect_mask(aspects)
+            .base_mip_level(0)
+            .level_count(mip_levels)
+            .base_array_layer(0)
+            .layer_count(1);
+
+        let info = vk::ImageViewCreateInfo::builder()
+            .image(image)
+            .view_type(vk::ImageViewType::_2D)
+            .format(format)
+            .components(components)
+            .subresource_range(subresource_range);
+
+        Ok(device.create_image_view(&info, None)?)
+    }
+
+    unsafe fn create_render_pass(
+        instance: &Instance,
+        device: &Device,
+        phys_device: vk::PhysicalDevice,
+        swapchain_format: vk::Format,
+        scene_format: vk::Format,
+        msaa_samples: vk::SampleCountFlags,
+        final_layout: vk::ImageLayout,
+    ) -> Result<vk::RenderPass> {
+        // multisampled color attachment, cleared on load and resolved at the end
+        let color_attachment = vk::AttachmentDescription::builder()
+            .format(scene_format)
+            .samples(msaa_samples)
+            .load_op(vk::AttachmentLoadOp::CLEAR)
+            .store_op(vk::AttachmentStoreOp::DONT_CARE)
+            .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+            .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+            .initial_layout(vk::ImageLayout::UNDEFINED)
+            .final_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL);
+
+        let color_attachment_ref = vk::AttachmentReference::builder()
+            .attachment(0)
+            .layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL);
+
+        // depth and stencil attachment, contents aren't needed after the pass
+        let depth_stencil_attachment = vk::AttachmentDescription::builder()
+            .format(get_depth_format(instance, phys_device)?)
+            .samples(msaa_samples)
+            .load_op(vk::AttachmentLoadOp::CLEAR)
+            .store_op(vk::AttachmentStoreOp::DONT_CARE)
+            .stencil_load_op(vk::AttachmentLoadOp::CLEAR)
+            .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+            .initial_layout(vk::ImageLayout::UNDEFINED)
+            .final_layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL);
+
+        let depth_stencil_attachment_ref = vk::AttachmentReference::builder()
+            .attachment(1)
+            .layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL);
+
+        // single sampled swapchain image the post-processing subpass writes
+        let present_attachment = vk::AttachmentDescription::builder()
+            .format(swapchain_format)
+            .samples(vk::SampleCountFlags::_1)
+            .load_op(vk::AttachmentLoadOp::DONT_CARE)
+            .store_op(vk::AttachmentStoreOp::STORE)
+            .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+            .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+            .initial_layout(vk::ImageLayout::UNDEFINED)
+            .final_layout(final_layout);
+
+        let present_attachment_ref = vk::AttachmentReference::builder()
+            .attachment(2)
+            .layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL);
+
+        // single sampled scene the color attachment resolves into, only read by the next subpass
+        let color_resolve_attachment = vk::AttachmentDescription::builder()
+            .format(scene_format)
+            .samples(vk::SampleCountFlags::_1)
+            .load_op(vk::AttachmentLoadOp::DONT_CARE)
+            .store_op(vk::AttachmentStoreOp::DONT_CARE)
+            .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+            .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+            .initial_layout(vk::ImageLayout::UNDEFINED)
+            .final_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL);
+
+        let color_resolve_attachment_ref = vk::AttachmentReference::builder()
+            .attachment(3)
+            .layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL);
+
+        let scene_input_ref = vk::AttachmentReference::builder()
+            .attachment(3)
+            .layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL);
+
+        // subpass 0 draws the scene, subpass 1 post-processes it into the swapchain image
+        let color_attachments = &[color_attachment_ref];
+        let resolve_attachments = &[color_resolve_attachment_ref];
+        let scene_subpass = vk::SubpassDescription::builder()
+            .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
+            .color_attachments(color_attachments)
+            .depth_stencil_attachment(&depth_stencil_attachment_ref)
+            .resolve_attachments(resolve_attachments);
+
+        let input_attachments = &[scene_input_ref];
+        let post_color_attachments = &[present_attachment_ref];
+        let post_subpass = vk::SubpassDescription::builder()
+            .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
+            .input_attachments(input_attachments)
+            .color_attachments(post_color_attachments);
+
+        // wait for the swapchain image to be available and the previous depth use before writing
+        let dependency = vk::SubpassDependency::builder()
+            .src_subpass(vk::SUBPASS_EXTERNAL)
+            .dst_subpass(0)
+            .src_stage_mask(
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+                    | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS,
+            )
+            .src_access_mask(vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE)
+            .dst_stage_mask(
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+                    | vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS,
+            )
+            .dst_access_mask(
+                vk::AccessFlags::COLOR_ATTACHMENT_WRITE
+                    | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+            );
+
+        // the swapchain image is first written in the post-processing subpass
+        let present_dependency = vk::SubpassDependency::builder()
+            .src_subpass(vk::SUBPASS_EXTERNAL)
+            .dst_subpass(1)
+            .src_stage_mask(vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+            .src_access_mask(vk::AccessFlags::empty())
+            .dst_stage_mask(vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+            .dst_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE);
+
+        // the resolved scene has to be written before it's read, each pixel only reads its own
+        let scene_dependency = vk::SubpassDependency::builder()
+            .src_subpass(0)
+            .dst_subpass(1)
+            .src_stage_mask(vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+            .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+            .dst_stage_mask(vk::PipelineStageFlags::FRAGMENT_SHADER)
+            .dst_access_mask(vk::AccessFlags::INPUT_ATTACHMENT_READ)
+            .dependency_flags(vk::DependencyFlags::BY_REGION);
+
+        let attachments = &[color_attachment, depth_stencil_attachment, present_attachment, color_resolve_attachment];
+        let subpasses = &[scene_subpass, post_subpass];
+        let dependencies = &[dependency, present_dependency, scene_dependency];
+        let info = vk::RenderPassCreateInfo::builder()
+            .attachments(attachments)
+            .subpasses(subpasses)
+            .dependencies(dependencies);
+
+        Ok(device.create_render_pass(&info, None)?)
+    }
+
+    // the scene and post-processing subpasses, or the formats of what each renders to dynamically
+    unsafe fn get_pipeline_targets(
+        instance: &Instance,
+        phys_device: vk::PhysicalDevice,
+        dynamic_rendering: bool,
+        render_pass: vk::RenderPass,
+        swapchain_format: vk::Format,
+        scene_format: vk::Format,
+    ) -> Result<(PipelineTarget, PipelineTarget)> {
+        if !dynamic_rendering {
+            return Ok((PipelineTarget::Subpass(render_pass, 0), PipelineTarget::Subpass(render_pass, 1)));
+        }
+
+        let scene = PipelineTarget::Dynamic {
+            color_formats: [scene_format],
+            depth_format: get_depth_format(instance, phys_device)?,
+        };
+        let post = PipelineTarget::Dynamic { color_formats: [swapchain_format], depth_format: vk::Format::UNDEFINED };
+
+        Ok((scene, post))
+    }
+
+    unsafe fn create_descriptor_set_layout(device: &Device) -> Result<vk::DescriptorSetLayout> {
+        // mvp matrices for the vertex shader
+        let ubo_binding = vk::DescriptorSetLayoutBinding::builder()
+            .binding(0)
+            .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
+            .descriptor_count(1)
+            .stage_flags(vk::ShaderStageFlags::VERTEX);
+
+        // texture for the fragment shader
+        let sampler_binding = vk::DescriptorSetLayoutBinding::builder()
+            .binding(1)
+            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .descriptor_count(1)
+            .stage_flags(vk::ShaderStageFlags::FRAGMENT);
+
+        // per object model matrix, picked with a dynamic offset at bind time
+        let object_binding = vk::DescriptorSetLayoutBinding::builder()
+            .binding(2)
+            .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC)
+            .descriptor_count(1)
+            .stage_flags(vk::ShaderStageFlags::VERTEX);
+
+        // tangent space normals, bound even when normal mapping is off
+        let normal_map_binding = vk::DescriptorSetLayoutBinding::builder()
+            .binding(3)
+            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .descriptor_count(1)
+            .stage_flags(vk::ShaderStageFlags::FRAGMENT);
+
+        // the light, the shadow pass's vertex shader takes its matrix
+        let light_binding = vk::DescriptorSetLayoutBinding::builder()
+            .binding(4)
+            .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
+            .descriptor_count(1)
+            .stage_flags(vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT);
+
+        // the light's depth, sampled with a compare sampler
+        let shadow_map_binding = vk::DescriptorSetLayoutBinding::builder()
+            .binding(5)
+            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .descriptor_count(1)
+            .stage_flags(vk::ShaderStageFlags::FRAGMENT);
+
+        let bindings = &[
+            ubo_binding,
+            sampler_binding,
+            object_binding,
+            normal_map_binding,
+            light_binding,
+            shadow_map_binding,
+        ];
+        let info = vk::DescriptorSetLayoutCreateInfo::builder()
+            .bindings(bindings);
+
+        Ok(device.create_descriptor_set_layout(&info, None)?)
+    }
+
+    unsafe fn create_descriptor_pool(device: &Device, swapchain_images: &[vk::Image]) -> Result<vk::DescriptorPool> {
+        // the mvp matrices and the light
+        let ubo_size = vk::DescriptorPoolSize::builder()
+            .type_(vk::DescriptorType::UNIFORM_BUFFER)
+            .descriptor_count(swapchain_images.len() as u32 * 2);
+
+        // the texture, the normal map and the shadow map
+        let sampler_size = vk::DescriptorPoolSize::builder()
+            .type_(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .descriptor_count(swapchain_images.len() as u32 * 3);
+
+        let object_size = vk::DescriptorPoolSize::builder()
+            .type_(vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC)
+            .descriptor_count(swapchain_images.len() as u32);
+
+        let pool_sizes = &[ubo_size, sampler_size, object_size];
+        let info = vk::DescriptorPoolCreateInfo::builder()
+            .pool_sizes(pool_sizes)
+            .max_sets(swapchain_images.len() as u32);
+
+        Ok(device.create_descriptor_pool(&info, None)?)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    unsafe fn create_descriptor_sets(
+        device: &Device,
+        descriptor_set_layout: vk::DescriptorSetLayout,
+        descriptor_pool: vk::DescriptorPool,
+        uniform_buffers: &[vk::Buffer],
+        object_buffers: &[vk::Buffer],
+        light_buffers: &[vk::Buffer],
+        texture_image_view: vk::ImageView,
+        normal_map_image_view: vk::ImageView,
+        texture_sampler: vk::Sampler,
+        shadow_map_view: vk::ImageView,
+        shadow_sampler: vk::Sampler,
+    ) -> Result<Vec<vk::DescriptorSet>> {
+        // one set per uniform buffer, all with the same layout
+        let layouts = vec![descriptor_set_layout; uniform_buffers.len()];
+        let info = vk::DescriptorSetAllocateInfo::builder()
+            .descriptor_pool(descriptor_pool)
+            .set_layouts(&layouts);
+
+        let descriptor_sets = device.allocate_descriptor_sets(&info)?;
+
+        let buffers = uniform_buffers.iter().zip(object_buffers).zip(light_buffers);
+        for (descriptor_set, ((uniform_buffer, object_buffer), light_buffer)) in descriptor_sets.iter().zip(buffers) {
+            let info = vk::DescriptorBufferInfo::builder()
+                .buffer(*uniform_buffer)
+                .offset(0)
+                .range(size_of::<UniformBufferObject>() as u64);
+
+            let buffer_info = &[info];
+            let ubo_write = vk::WriteDescriptorSet::builder()
+                .dst_set(*descriptor_set)
+                .dst_binding(0)
+                .dst_array_element(0)
+                .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
+                .buffer_info(buffer_info);
+
+            // the range is a single object, the dynamic offset moves it along the buffer
+            let info = vk::DescriptorBufferInfo::builder()
+                .buffer(*object_buffer)
+                .offset(0)
+                .range(size_of::<ObjectUniform>() as u64);
+
+            let buffer_info = &[info];
+            let object_write = vk::WriteDescriptorSet::builder()
+                .dst_set(*descriptor_set)
+                .dst_binding(2)
+                .dst_array_element(0)
+                .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC)
+                .buffer_info(buffer_info);
+
+            let info = vk::DescriptorBufferInfo::builder()
+                .buffer(*light_buffer)
+                .offset(0)
+                .range(size_of::<LightUniform>() as u64);
+
+            let buffer_info = &[info];
+            let light_write = vk::WriteDescriptorSet::builder()
+                .dst_set(*descriptor_set)
+                .dst_binding(4)
+                .dst_array_element(0)
+                .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
+                .buffer_info(buffer_info);
+
+            // never changes, the shadow pass redraws it in place each frame
+            let info = vk::DescriptorImageInfo::builder()
+                .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+                .image_view(shadow_map_view)
+                .sampler(shadow_sampler);
+
+            let image_info = &[info];
+            let shadow_map_write = vk::WriteDescriptorSet::builder()
+                .dst_set(*descriptor_set)
+                .dst_binding(5)
+                .dst_array_element(0)
+                .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                .image_info(image_info);
+
+            let writes = &[ubo_write, object_write, light_write, shadow_map_write];
+            device.update_descriptor_sets(writes, &[] as &[vk::CopyDescriptorSet]);
+        }
+
+        update_texture_descriptors(device, &descriptor_sets, texture_image_view, normal_map_image_view, texture_sampler);
+
+        Ok(descriptor_sets)
+    }
+
+    // dynamic rendering can't read the scene as an input attachment, it's sampled at the same pixel instead
+    fn get_post_descriptor_type(dynamic_rendering: bool) -> vk::DescriptorType {
+        if dynamic_rendering {
+            vk::DescriptorType::COMBINED_IMAGE_SAMPLER
+        } else {
+            vk::DescriptorType::INPUT_ATTACHMENT
+        }
+    }
+
+    unsafe fn create_post_descriptor_set_layout(
+        device: &Device,
+        dynamic_rendering: bool,
+    ) -> Result<vk::DescriptorSetLayout> {
+        // the resolved scene, read at the same pixel
+        let scene_binding = vk::DescriptorSetLayoutBinding::builder()
+            .binding(0)
+            .descriptor_type(get_post_descriptor_type(dynamic_rendering))
+            .descriptor_count(1)
+            .stage_flags(vk::ShaderStageFlags::FRAGMENT);
+
+        // the bloom chain, only sampled when rendering dynamically
+        let bloom_binding = vk::DescriptorSetLayoutBinding::builder()
+            .binding(1)
+            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .descriptor_count(1)
+            .stage_flags(vk::ShaderStageFlags::FRAGMENT);
+
+        let bindings = if dynamic_rendering { &[scene_binding, bloom_binding][..] } else { &[scene_binding][..] };
+        let info = vk::DescriptorSetLayoutCreateInfo::builder()
+            .bindings(bindings);
+
+        Ok(device.create_descriptor_set_layout(&info, None)?)
+    }
+
+    unsafe fn create_post_descriptor_pool(device: &Device, dynamic_rendering: bool) -> Result<vk::DescriptorPool> {
+        // the bloom chain is a second sampler
+        let scene_size = vk::DescriptorPoolSize::builder()
+            .type_(get_post_descriptor_type(dynamic_rendering))
+            .descriptor_count(if dynamic_rendering { 2 } else { 1 });
+
+        let pool_sizes = &[scene_size];
+        let info = vk::DescriptorPoolCreateInfo::builder()
+            .pool_sizes(pool_sizes)
+            .max_sets(1);
+
+        Ok(device.create_descriptor_pool(&info, None)?)
+    }
+
+    // a single set, every framebuffer shares the same scene image
+    unsafe fn create_post_descriptor_set(
+        device: &Device,
+        post_descriptor_set_layout: vk::DescriptorSetLayout,
+        post_descriptor_pool: vk::DescriptorPool,
+        scene_image_view: vk::ImageView,
+        post_sampler: vk::Sampler,
+        bloom: Option<&Bloom>,
+    ) -> Result<vk::DescriptorSet> {
+        let layouts = &[post_descriptor_set_layout];
+        let info = vk::DescriptorSetAllocateInfo::builder()
+            .descriptor_pool(post_descriptor_pool)
+            .set_layouts(layouts);
+
+        let post_descriptor_set = device.allocate_descriptor_sets(&info)?[0];
+
+        // only rendering dynamically has a sampler
+        let info = vk::DescriptorImageInfo::builder()
+            .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+            .image_view(scene_image_view)
+            .sampler(post_sampler);
+
+        let image_info = &[info];
+        let scene_write = vk::WriteDescriptorSet::builder()
+            .dst_set(post_descriptor_set)
+            .dst_binding(0)
+            .dst_array_element(0)
+            .descriptor_type(get_post_descriptor_type(!post_sampler.is_null()))
+            .image_info(image_info);
+
+        if post_sampler.is_null() {
+            device.update_descriptor_sets(&[scene_write], &[] as &[vk::CopyDescriptorSet]);
+            return Ok(post_descriptor_set);
+        }
+
+        // the shader skips it without bloom, but it still has to be bound to something
+        let info = match bloom {
+            Some(bloom) => vk::DescriptorImageInfo::builder()
+                .image_layout(vk::ImageLayout::GENERAL)
+                .image_view(bloom.image_view())
+                .sampler(bloom.sampler()),
+            None => info,
+        };
+
+        let image_info = &[info];
+        let bloom_write = vk::WriteDescriptorSet::builder()
+            .dst_set(post_descriptor_set)
+            .dst_binding(1)
+            .dst_array_element(0)
+            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .image_info(image_info);
+
+        device.update_descriptor_sets(&[scene_write, bloom_write], &[] as &[vk::CopyDescriptorSet]);
+
+        Ok(post_descriptor_set)
+    }
+
+    // off unless it's asked for, checked once and every window makes its own at its render size
+    // the compute passes run between the scene and post rendering, which the render pass has no gap between
+    unsafe fn get_bloom_support(
+        instance: &Instance,
+        phys_device: vk::PhysicalDevice,
+        config: &AppConfig,
+        dynamic_rendering: bool,
+    ) -> Result<bool> {
+        if config.bloom_intensity <= 0.0 {
+            return Ok(false);
+        }
+
+        if !dynamic_rendering {
+            return Err(anyhow!("Bloom needs dynamic rendering, which is off or unsupported."));
+        }
+
+        if !bloom::is_supported(instance, phys_device) {
+            warn!("Device can't store or filter {:?} images, bloom is disabled.", BLOOM_FORMAT);
+            return Ok(false);
+        }
+
+        Ok(true)
+    }
+
+    // read with texelFetch so filtering never applies
+    unsafe fn create_post_sampler(device: &Device) -> Result<vk::Sampler> {
+        let info = vk::SamplerCreateInfo::builder()
+            .mag_filter(vk::Filter::NEAREST)
+            .min_filter(vk::Filter::NEAREST)
+            .address_mode_u(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .address_mode_w(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .mipmap_mode(vk::SamplerMipmapMode::NEAREST);
+
+        Ok(device.create_sampler(&info, None)?)
+    }
+
+    unsafe fn update_texture_descriptors(
+        device: &Device,
+        descriptor_sets: &[vk::DescriptorSet],
+        texture_image_view: vk::ImageView,
+        normal_map_image_view: vk::ImageView,
+        texture_sampler: vk::Sampler,
+    ) {
+        let info = vk::DescriptorImageInfo::builder()
+            .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+            .image_view(texture_image_view)
+            .sampler(texture_sampler);
+
+        let normal_map_info = vk::DescriptorImageInfo::builder()
+            .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+            .image_view(normal_map_image_view)
+            .sampler(texture_sampler);
+
+        let image_info = &[info];
+        let normal_map_image_info = &[normal_map_info];
+        for descriptor_set in descriptor_sets {
+            let sampler_write = vk::WriteDescriptorSet::builder()
+                .dst_set(*descriptor_set)
+                .dst_binding(1)
+                .dst_array_element(0)
+                .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                .image_info(image_info);
+
+            let normal_map_write = vk::WriteDescriptorSet::builder()
+                .dst_set(*descriptor_set)
+                .dst_binding(3)
+                .dst_array_element(0)
+                .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                .image_info(normal_map_image_info);
+
+            device.update_descriptor_sets(&[sampler_write, normal_map_write], &[] as &[vk::CopyDescriptorSet]);
+        }
+    }
+
+    // seeded from disk when the saved data was made by this exact device and driver
+    unsafe fn create_pipeline_cache(
+        instance: &Instance,
+        device: &Device,
+        phys_device: vk::PhysicalDevice,
+    ) -> Result<vk::PipelineCache> {
+        let data = match std::fs::read(PIPELINE_CACHE_PATH) {
+            Ok(data) if pipeline_cache_matches(instance, phys_device, &data) => {
+                info!("Loaded pipeline cache ({}).", PIPELINE_CACHE_PATH);
+                data
+            }
+            Ok(_) => {
+                warn!("Ignoring pipeline cache ({}) from a different device or driver.", PIPELINE_CACHE_PATH);
+                Vec::new()
+            }
+            Err(_) => Vec::new(),
+        };
+
+        let info = vk::PipelineCacheCreateInfo::builder()
+            .initial_data(&data);
+
+        Ok(device.create_pipeline_cache(&info, None)?)
+    }
+
+    // header is length, version, vendor id, device id then the cache uuid
+    unsafe fn pipeline_cache_matches(instance: &Instance, phys_device: vk::PhysicalDevice, data: &[u8]) -> bool {
+        if data.len() < 32 {
+            return false;
+        }
+
+        let read_u32 = |offset: usize| {
+            u32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]])
+        };
+        let properties = instance.get_physical_device_properties(phys_device);
+
+        read_u32(0) >= 32
+            && read_u32(4) == vk::PipelineCacheHeaderVersion::ONE.as_raw() as u32
+            && read_u32(8) == properties.vendor_id
+            && read_u32(12) == properties.device_id
+            && data[16..32] == *properties.pipeline_cache_uuid
+    }
+
+    unsafe fn save_pipeline_cache(device: &Device, pipeline_cache: vk::PipelineCache) -> Result<()> {
+        let data = device.get_pipeline_cache_data(pipeline_cache)?;
+        std::fs::write(PIPELINE_CACHE_PATH, data)?;
+
+        Ok(())
+    }
+
+    // layout, main, wireframe, outline, depth prepass, main after a prepass, alpha blended and additive
+    type ScenePipelines = (
+        vk::PipelineLayout,
+        vk::Pipeline,
+        vk::Pipeline,
+        vk::Pipeline,
+        vk::Pipeline,
+        vk::Pipeline,
+        vk::Pipeline,
+        vk::Pipeline,
+    );
+
+    #[allow(clippy::too_many_arguments)]
+    unsafe fn create_pipeline(
+        device: &Device,
+        config: &AppConfig,
+        pipeline_cache: vk::PipelineCache,
+        target: PipelineTarget,
+        descriptor_set_layout: vk::DescriptorSetLayout,
+        bindless_layout: Option<vk::DescriptorSetLayout>,
+        msaa_samples: vk::SampleCountFlags,
+        wireframe_line_width: Option<f32>,
+        min_sample_shading: Option<f32>,
+    ) -> Result<ScenePipelines> {
+        // bindless textures swap the texture array for set 1
+        let frag_shader = if bindless_layout.is_some() { shaders::BINDLESS_FRAG } else { shaders::FRAG };
+        let vert_shader_module = create_shader_module(device, &shaders::load(shaders::VERT)?)?;
+        let frag_shader_module = match shaders::load(frag_shader)
+            .and_then(|bytecode| create_shader_module(device, &bytecode))
+        {
+            Ok(module) => module,
+            Err(e) => {
+                device.destroy_shader_module(vert_shader_module, None);
+                return Err(e);
+            }
+        };
+
+        let vert_stage = vk::PipelineShaderStageCreateInfo::builder()
+            .stage(vk::ShaderStageFlags::VERTEX)
+            .module(vert_shader_module)
+            .name(b"main\0");
+
+        // the outline specialization below replaces this, its flat color isn't shaded
+        let normal_mapping_entries = &[vk::SpecializationMapEntry::builder()
+            .constant_id(5)
+            .offset(0)
+            .size(4)
+            .build()];
+        let normal_mapping_data = (config.normal_map.is_some() as vk::Bool32).to_ne_bytes();
+        let normal_mapping_info = vk::SpecializationInfo::builder()
+            .map_entries(normal_mapping_entries)
+            .data(&normal_mapping_data);
+
+        let frag_stage = vk::PipelineShaderStageCreateInfo::builder()
+            .stage(vk::ShaderStageFlags::FRAGMENT)
+            .module(frag_shader_module)
+            .name(b"main\0")
+            .specialization_info(&normal_mapping_info);
+
+        // per vertex data in binding 0, per instance data in binding 1
+        let binding_descriptions = &[Vertex::binding_description(), InstanceData::binding_description()];
+        let attribute_descriptions = Vertex::attribute_descriptions()
+            .into_iter()
+            .chain(InstanceData::attribute_descriptions())
+            .collect::<Vec<_>>();
+        let vertex_input_state = vk::PipelineVertexInputStateCreateInfo::builder()
+            .vertex_binding_descriptions(binding_descriptions)
+            .vertex_attribute_descriptions(&attribute_descriptions);
+
+        let input_assembly_state = vk::PipelineInputAssemblyStateCreateInfo::builder()
+            .topology(vk::PrimitiveTopology::TRIANGLE_LIST)
+            .primitive_restart_enable(false);
+
+        // viewport and scissor are set while recording so resizes keep the pipeline
+        let viewport_state = vk::PipelineViewportStateCreateInfo::builder()
+            .viewport_count(1)
+            .scissor_count(1);
+
+        let dynamic_states = &[vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+        let dynamic_state = vk::PipelineDynamicStateCreateInfo::builder()
+            .dynamic_states(dynamic_states);
+
+        let rasterization_state = vk::PipelineRasterizationStateCreateInfo::builder()
+            .depth_clamp_enable(false)
+            .rasterizer_discard_enable(false)
+            .polygon_mode(vk::PolygonMode::FILL)
+            .line_width(1.0)
+            .cull_mode(vk::CullModeFlags::BACK)
+            .front_face(vk::FrontFace::COUNTER_CLOCKWISE)
+            .depth_bias_enable(false);
+
+        let multisample_state = vk::PipelineMultisampleStateCreateInfo::builder()
+            .sample_shading_enable(min_sample_shading.is_some())
+            .min_sample_shading(min_sample_shading.unwrap_or(0.0))
+            .rasterization_samples(msaa_samples);
+
+        let depth_stencil_state = vk::PipelineDepthStencilStateCreateInfo::builder()
+            .depth_test_enable(true)
+            .depth_write_enable(true)
+            .depth_compare_op(if config.reverse_z { vk::CompareOp::GREATER } else { vk::CompareOp::LESS })
+            .depth_bounds_test_enable(false)
+            .stencil_test_enable(config.stencil_test_enable)
+            .front(config.stencil_front)
+            .back(config.stencil_back);
+
+        let attachment = vk::PipelineColorBlendAttachmentState::builder()
+            .color_write_mask(vk::ColorComponentFlags::all())
+            .blend_enable(false);
+
+        let attachments = &[attachment];
+        let color_blend_state = vk::PipelineColorBlendStateCreateInfo::builder()
+            .logic_op_enable(false)
+            .logic_op(vk::LogicOp::COPY)
+            .attachments(attachments)
+            .blend_constants([0.0, 0.0, 0.0, 0.0]);
+
+        // texture index per draw
+        let push_constant_range = vk::PushConstantRange::builder()
+            .stage_flags(vk::ShaderStageFlags::FRAGMENT)
+            .offset(0)
+            .size(PUSH_CONSTANTS_SIZE);
+
+        let set_layouts = [descriptor_set_layout].into_iter().chain(bindless_layout).collect::<Vec<_>>();
+        let push_constant_ranges = &[push_constant_range];
+        let layout_info = vk::PipelineLayoutCreateInfo::builder()
+            .set_layouts(&set_layouts)
+            .push_constant_ranges(push_constant_ranges);
+        let pipeline_layout = device.create_pipeline_layout(&layout_info, None)?;
+
+        let stages = &[vert_stage, frag_stage];
+        let mut rendering_info = target.rendering_info();
+        let mut info = vk::GraphicsPipelineCreateInfo::builder()
+            .stages(stages)
+            .vertex_input_state(&vertex_input_state)
+            .input_assembly_state(&input_assembly_state)
+            .viewport_state(&viewport_state)
+            .rasterization_state(&rasterization_state)
+            .multisample_state(&multisample_state)
+            .depth_stencil_state(&depth_stencil_state)
+            .color_blend_state(&color_blend_state)
+            .dynamic_state(&dynamic_state)
+            .layout(pipeline_layout)
+            .render_pass(target.render_pass())
+            .subpass(target.subpass());
+        if let Some(rendering_info) = &mut rendering_info {
+            info = info.push_next(rendering_info);
+        }
+
+        let pipeline = device
+            .create_graphics_pipelines(pipeline_cache, &[info], None)?
+            .0[0];
+
+        // same pipeline drawing lines, null if the device can't
+        let wireframe_pipeline = match wireframe_line_width {
+            Some(line_width) => {
+                let rasterization_state = rasterization_state
+                    .polygon_mode(vk::PolygonMode::LINE)
+                    .line_width(line_width);
+                let info = info.rasterization_state(&rasterization_state);
+
+                device
+                    .create_graphics_pipelines(pipeline_cache, &[info], None)?
+                    .0[0]
+            }
+            None => vk::Pipeline::null(),
+        };
+
+        // same pipeline again with the outline specialization, null unless configured
+        let outline_pipeline = match config.outline {
+            Some((scale, [r, g, b])) => {
+                // constant ids from shader.vert and shader.frag, each stage only reads its own
+                let map_entries = (0..5)
+                    .map(|i| {
+                        vk::SpecializationMapEntry::builder()
+                            .constant_id(i)
+                            .offset(i * 4)
+                            .size(4)
+                            .build()
+                    })
+                    .collect::<Vec<_>>();
+                let data = [scale.to_bits(), vk::TRUE, r.to_bits(), g.to_bits(), b.to_bits()]
+                    .into_iter()
+                    .flat_map(u32::to_ne_bytes)
+                    .collect::<Vec<_>>();
+                let specialization_info = vk::SpecializationInfo::builder()
+                    .map_entries(&map_entries)
+                    .data(&data);
+
+                let stages = &[
+                    vert_stage.specialization_info(&specialization_info),
+                    frag_stage.specialization_info(&specialization_info),
+                ];
+
+                // only where the main pipeline didn't write the reference, on top of everything
+                let stencil = vk::StencilOpState::builder()
+                    .fail_op(vk::StencilOp::KEEP)
+                    .pass_op(vk::StencilOp::KEEP)
+                    .depth_fail_op(vk::StencilOp::KEEP)
+                    .compare_op(vk::CompareOp::NOT_EQUAL)
+                    .compare_mask(0xff)
+                    .write_mask(0)
+                    .reference(config.stencil_front.reference)
+                    .build();
+                let depth_stencil_state = depth_stencil_state
+                    .depth_test_enable(false)
+                    .depth_write_enable(false)
+                    .stencil_test_enable(true)
+                    .front(stencil)
+                    .back(stencil);
+                let info = info.stages(stages).depth_stencil_state(&depth_stencil_state);
+
+                device
+                    .create_graphics_pipelines(pipeline_cache, &[info], None)?
+                    .0[0]
+            }
+            None => vk::Pipeline::null(),
+        };
+
+        // depth only, nothing is shaded and no color is written
+        let depth_prepass_pipeline = {
+            let stages = &[vert_stage];
+            let depth_stencil_state = depth_stencil_state.stencil_test_enable(false);
+            let attachment = attachment.color_write_mask(vk::ColorComponentFlags::empty());
+            let attachments = &[attachment];
+            let color_blend_state = color_blend_state.attachments(attachments);
+            let info = info
+                .stages(stages)
+                .depth_stencil_state(&depth_stencil_state)
+                .color_blend_state(&color_blend_state);
+
+            device
+                .create_graphics_pipelines(pipeline_cache, &[info], None)?
+                .0[0]
+        };
+
+        // the main pipeline after a prepass, the same vertex shader gives exactly the same depth
+        let depth_equal_pipeline = {
+            let depth_stencil_state = depth_stencil_state
+                .depth_write_enable(false)
+                .depth_compare_op(vk::CompareOp::EQUAL);
+            let info = info.depth_stencil_state(&depth_stencil_state);
+
+            device
+                .create_graphics_pipelines(pipeline_cache, &[info], None)?
+                .0[0]
+        };
+
+        // drawn after everything opaque, tested against its depth without writing any
+        let blend_pipelines = [BlendMode::AlphaBlend, BlendMode::Additive]
+            .into_iter()
+            .map(|blend_mode| {
+                let attachments = &[blend_mode.attachment()];
+                let color_blend_state = color_blend_state.attachments(attachments);
+                let depth_stencil_state = depth_stencil_state
+                    .depth_write_enable(false)
+                    .stencil_test_enable(false);
+                let info = info
+                    .color_blend_state(&color_blend_state)
+                    .depth_stencil_state(&depth_stencil_state);
+
+                Ok(device.create_graphics_pipelines(pipeline_cache, &[info], None)?.0[0])
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        // modules are no longer needed once the pipeline is built
+        device.destroy_shader_module(vert_shader_module, None);
+        device.destroy_shader_module(frag_shader_module, None);
+
+        Ok((
+            pipeline_layout,
+            pipeline,
+            wireframe_pipeline,
+            outline_pipeline,
+            depth_prepass_pipeline,
+            depth_equal_pipeline,
+            blend_pipelines[0],
+            blend_pipelines[1],
+        ))
+    }
+
+    // runs in the second subpass, no vertex input and no depth
+    unsafe fn create_post_pipeline(
+        device: &Device,
+        pipeline_cache: vk::PipelineCache,
+        target: PipelineTarget,
+        post_descriptor_set_layout: vk::DescriptorSetLayout,
+    ) -> Result<(vk::PipelineLayout, vk::Pipeline)> {
+        // there's no input attachment without a render pass so the scene is sampled
+        let frag_shader = match target {
+            PipelineTarget::Subpass(..) => shaders::POST_FRAG,
+            PipelineTarget::Dynamic { .. } => shaders::POST_SAMPLED_FRAG,
+        };
+
+        let vert_shader_module = create_shader_module(device, &shaders::load(shaders::POST_VERT)?)?;
+        let frag_shader_module = match shaders::load(frag_shader)
+            .and_then(|bytecode| create_shader_module(device, &bytecode))
+        {
+            Ok(module) => module,
+            Err(e) => {
+                device.destroy_shader_module(vert_shader_module, None);
+                return Err(e);
+            }
+        };
+
+        let vert_stage = vk::PipelineShaderStageCreateInfo::builder()
+            .stage(vk::ShaderStageFlags::VERTEX)
+            .module(vert_shader_module)
+            .name(b"main\0");
+
+        let frag_stage = vk::PipelineShaderStageCreateInfo::builder()
+            .stage(vk::ShaderStageFlags::FRAGMENT)
+            .module(frag_shader_module)
+            .name(b"main\0");
+
+        // the triangle comes from the vertex index
+        let vertex_input_state = vk::PipelineVertexInputStateCreateInfo::builder();
+
+        let input_assembly_state = vk::PipelineInputAssemblyStateCreateInfo::builder()
+            .topology(vk::PrimitiveTopology::TRIANGLE_LIST)
+            .primitive_restart_enable(false);
+
+        let viewport_state = vk::PipelineViewportStateCreateInfo::builder()
+            .viewport_count(1)
+            .scissor_count(1);
+
+        let dynamic_states = &[vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+        let dynamic_state = vk::PipelineDynamicStateCreateInfo::builder()
+            .dynamic_states(dynamic_states);
+
+        let rasterization_state = vk::PipelineRasterizationStateCreateInfo::builder()
+            .depth_clamp_enable(false)
+            .rasterizer_discard_enable(false)
+            .polygon_mode(vk::PolygonMode::FILL)
+            .line_width(1.0)
+            .cull_mode(vk::CullModeFlags::NONE)
+            .front_face(vk::FrontFace::COUNTER_CLOCKWISE)
+            .depth_bias_enable(false);
+
+        let multisample_state = vk::PipelineMultisampleStateCreateInfo::builder()
+            .sample_shading_enable(false)
+            .rasterization_samples(vk::SampleCountFlags::_1);
+
+        let attachment = vk::PipelineColorBlendAttachmentState::builder()
+            .color_write_mask(vk::ColorComponentFlags::all())
+            .blend_enable(false);
+
+        let attachments = &[attachment];
+        let color_blend_state = vk::PipelineColorBlendStateCreateInfo::builder()
+            .logic_op_enable(false)
+            .logic_op(vk::LogicOp::COPY)
+            .attachments(attachments)
+            .blend_constants([0.0, 0.0, 0.0, 0.0]);
+
+        // the effect, tonemapping and gamma, see PostConstants
+        let push_constant_range = vk::PushConstantRange::builder()
+            .stage_flags(vk::ShaderStageFlags::FRAGMENT)
+            .offset(0)
+            .size(size_of::<PostConstants>() as u32);
+
+        let set_layouts = &[post_descriptor_set_layout];
+        let push_constant_ranges = &[push_constant_range];
+        let layout_info = vk::PipelineLayoutCreateInfo::builder()
+            .set_layouts(set_layouts)
+            .push_constant_ranges(push_constant_ranges);
+        let pipeline_layout = device.create_pipeline_layout(&layout_info, None)?;
+
+        let stages = &[vert_stage, frag_stage];
+        let mut rendering_info = target.rendering_info();
+        let mut info = vk::GraphicsPipelineCreateInfo::builder()
+            .stages(stages)
+            .vertex_input_state(&vertex_input_state)
+            .input_assembly_state(&input_assembly_state)
+            .viewport_state(&viewport_state)
+            .rasterization_state(&rasterization_state)
+            .multisample_state(&multisample_state)
+            .color_blend_state(&color_blend_state)
+            .dynamic_state(&dynamic_state)
+            .layout(pipeline_layout)
+            .render_pass(target.render_pass())
+            .subpass(target.subpass());
+        if let Some(rendering_info) = &mut rendering_info {
+            info = info.push_next(rendering_info);
+        }
+
+        let pipeline = device
+            .create_graphics_pipelines(pipeline_cache, &[info], None)?
+            .0[0];
+
+        device.destroy_shader_module(vert_shader_module, None);
+        device.destroy_shader_module(frag_shader_module, None);
+
+        Ok((pipeline_layout, pipeline))
+    }
+
+    // none if wireframe rendering isn't possible on this device
+    unsafe fn get_wireframe_line_width(
+        instance: &Instance,
+        phys_device: vk::PhysicalDevice,
+        line_width: f32,
+    ) -> Option<f32> {
+        let features = instance.get_physical_device_features(phys_device);
+
+        if features.fill_mode_non_solid != vk::TRUE {
+            warn!("Device does not support non-solid fill modes, wireframe rendering is disabled.");
+            return None;
+        }
+
+        Some(get_line_width(instance, phys_device, line_width))
+    }
+
+    // clamped to 0..1, none if it wasn't asked for or can't be used
+    unsafe fn get_min_sample_shading(
+        instance: &Instance,
+        phys_device: vk::PhysicalDevice,
+        config: &AppConfig,
+        msaa_samples: vk::SampleCountFlags,
+    ) -> Option<f32> {
+        let min_sample_shading = config.sample_shading?;
+
+        if msaa_samples == vk::SampleCountFlags::_1 {
+            warn!("Sample shading needs msaa, it's disabled.");
+            return None;
+        }
+
+        if instance.get_physical_device_features(phys_device).sample_rate_shading != vk::TRUE {
+            warn!("Device does not support sample rate shading, it's disabled.");
+            return None;
+        }
+
+        Some(min_sample_shading.clamp(0.0, 1.0))
+    }
+
+    // only widths above 1.0 need wide lines, without them it falls back to 1.0
+    unsafe fn get_line_width(instance: &Instance, phys_device: vk::PhysicalDevice, line_width: f32) -> f32 {
+        let features = instance.get_physical_device_features(phys_device);
+
+        if line_width > 1.0 && features.wide_lines != vk::TRUE {
+            warn!("Device does not support wide lines, using a line width of 1.0.");
+            return 1.0;
+        }
+
+        let range = instance.get_physical_device_properties(phys_device).limits.line_width_range;
+        line_width.clamp(range[0], range[1])
+    }
+
+    // the scaled image stands in for every swapchain image when supersampling
+    #[allow(clippy::too_many_arguments)]
+    unsafe fn create_framebuffers(
+        device: &Device,
+        render_pass: vk::RenderPass,
+        swapchain_image_views: &[vk::ImageView],
+        scaled_image_view: vk::ImageView,
+        color_image_view: vk::ImageView,
+        depth_image_view: vk::ImageView,
+        scene_image_view: vk::ImageView,
+        render_extent: vk::Extent2D,
+    ) -> Result<Vec<vk::Framebuffer>> {
+        swapchain_image_views
+            .iter()
+            .map(|i| {
+                let target = if scaled_image_view.is_null() { *i } else { scaled_image_view };
+                let attachments = &[color_image_view, depth_image_view, target, scene_image_view];
+                create_framebuffer(device, render_pass, attachments, render_extent)
+            })
+            .collect()
+    }
+
+    unsafe fn create_framebuffer(
+        device: &Device,
+        render_pass: vk::RenderPass,
+        attachments: &[vk::ImageView],
+        extent: vk::Extent2D,
+    ) -> Result<vk::Framebuffer> {
+        let info = vk::FramebufferCreateInfo::builder()
+            .render_pass(render_pass)
+            .attachments(attachments)
+            .width(extent.width)
+            .height(extent.height)
+            .layers(1);
+
+        Ok(device.create_framebuffer(&info, None)?)
+    }
+
+    unsafe fn create_command_pool(
+        instance: &Instance,
+        device: &Device,
+        surface: vk::SurfaceKHR,
+        phys_device: vk::PhysicalDevice,
+    ) -> Result<vk::CommandPool> {
+        let indices = QueueFamilyIndices::get(instance, &[surface], phys_device)?;
+
+        let info = vk::CommandPoolCreateInfo::builder()
+            .flags(vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER)
+            .queue_family_index(indices.graphics);
+
+        Ok(device.create_command_pool(&info, None)?)
+    }
+
+    // each thread records into its own pool, the buffers are reset one at a time
+    unsafe fn create_secondary_command_pool(device: &Device, indices: &QueueFamilyIndices) -> Result<vk::CommandPool> {
+        let info = vk::CommandPoolCreateInfo::builder()
+            .flags(vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER)
+            .queue_family_index(indices.graphics);
+
+        Ok(device.create_command_pool(&info, None)?)
+    }
+
+    // indexed by image then thread * 2 + pass, pass 0 is the main draws and 1 the outline
+    unsafe fn create_secondary_command_buffers(
+        device: &Device,
+        pools: &[vk::CommandPool],
+        image_count: usize,
+    ) -> Result<Vec<Vec<vk::CommandBuffer>>> {
+        let mut buffers = vec![Vec::with_capacity(pools.len() * SCENE_PASSES); image_count];
+        for pool in pools {
+            let allocate_info = vk::CommandBufferAllocateInfo::builder()
+                .command_pool(*pool)
+                .level(vk::CommandBufferLevel::SECONDARY)
+                .command_buffer_count((image_count * SCENE_PASSES) as u32);
+
+            let allocated = device.allocate_command_buffers(&allocate_info)?;
+            for (image, passes) in allocated.chunks(SCENE_PASSES).enumerate() {
+                buffers[image].extend_from_slice(passes);
+            }
+        }
+
+        Ok(buffers)
+    }
+
+    // the objects are split into one chunk per thread, returns every thread's buffer for a pass before the next pass
+    #[allow(clippy::too_many_arguments)]
+    unsafe fn record_secondary_command_buffers(
+        draws: SceneDraws,
+        command_buffers: &[vk::CommandBuffer],
+        target: PipelineTarget,
+        framebuffer: vk::Framebuffer,
+        samples: vk::SampleCountFlags,
+        passes: &[ScenePass],
+        viewport: vk::Viewport,
+        scissor: vk::Rect2D,
+    ) -> Result<Vec<vk::CommandBuffer>> {
+        let threads = command_buffers.len() / SCENE_PASSES;
+
+        let recorded = (0..threads)
+            .into_par_iter()
+            .map(|thread| {
+                passes
+                    .iter()
+                    .enumerate()
+                    .map(|(i, pass)| {
+                        let command_buffer = command_buffers[thread * SCENE_PASSES + i];
+                        draws.device.reset_command_buffer(command_buffer, vk::CommandBufferResetFlags::empty())?;
+
+                        let mut rendering_info = target.inheritance_rendering_info(samples);
+                        let mut inheritance_info = vk::CommandBufferInheritanceInfo::builder()
+                            .render_pass(target.render_pass())
+                            .subpass(target.subpass())
+                            .framebuffer(framebuffer);
+                        if let Some(rendering_info) = &mut rendering_info {
+                            inheritance_info = inheritance_info.push_next(rendering_info);
+                        }
+                        let info = vk::CommandBufferBeginInfo::builder()
+                            .flags(
+                                vk::CommandBufferUsageFlags::RENDER_PASS_CONTINUE
+                                    | vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT,
+                            )
+                            .inheritance_info(&inheritance_info);
+                        draws.device.begin_command_buffer(command_buffer, &info)?;
+
+                        // dynamic state isn't inherited from the primary
+                        draws.device.cmd_set_viewport(command_buffer, 0, &[viewport]);
+                        draws.device.cmd_set_scissor(command_buffer, 0, &[scissor]);
+                        draws.record_pass(command_buffer, pass, thread, threads);
+
+                        draws.device.end_command_buffer(command_buffer)?;
+                        Ok(command_buffer)
+                    })
+                    .collect::<Result<Vec<_>>>()
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        // the main pass tests against the whole prepass, the outline against the whole main pass
+        Ok((0..passes.len()).flat_map(|pass| recorded.iter().map(move |r| r[pass])).collect())
+    }
+
+    unsafe fn create_command_buffers(
+        device: &Device,
+        command_pool: vk::CommandPool,
+        count: usize,
+    ) -> Result<Vec<vk::CommandBuffer>> {
+        // recorded each frame in update_command_buffer
+        let allocate_info = vk::CommandBufferAllocateInfo::builder()
+            .command_pool(command_pool)
+            .level(vk::CommandBufferLevel::PRIMARY)
+            .command_buffer_count(count as u32);
+
+        Ok(device.allocate_command_buffers(&allocate_info)?)
+    }
+
+    /// also used for per instance data, anything copyable can go in a vertex buffer
+    ///
+    /// # Safety
+    ///
+    /// `device` and `phys_device` have to come from `instance`, `allocator` and `uploader` from `device`.
+    pub unsafe fn create_vertex_buffer<T: Copy>(
+        instance: &Instance,
+        device: &Device,
+        phys_device: vk::PhysicalDevice,
+        allocator: &mut Allocator,
+        uploader: &Uploader,
+        vertices: &[T],
+        name: &str,
+    ) -> Result<(vk::Buffer, Allocation)> {
+        create_device_local_buffer(
+            instance,
+            device,
+            phys_device,
+            allocator,
+            uploader,
+            vertices,
+            vk::BufferUsageFlags::VERTEX_BUFFER,
+            name,
+        )
+    }
+
+    /// uploaded through a staging buffer, the usage also decides what the graphics queue acquires it for
+    ///
+    /// # Safety
+    ///
+    /// `device` and `phys_device` have to come from `instance`, `allocator` and `uploader` from `device`.
+    #[allow(clippy::too_many_arguments)]
+    pub unsafe fn create_device_local_buffer<T: Copy>(
+        instance: &Instance,
+        device: &Device,
+        phys_device: vk::PhysicalDevice,
+        allocator: &mut Allocator,
+        uploader: &Uploader,
+        data: &[T],
+        usage: vk::BufferUsageFlags,
+        name: &str,
+    ) -> Result<(vk::Buffer, Allocation)> {
+        let size = size_of_val(data) as u64;
+
+        // host visible staging buffer the data gets copied into first
+        let (staging_buffer, staging_buffer_memory) = create_buffer(
+            instance,
+            device,
+            phys_device,
+            allocator,
+            size,
+            vk::BufferUsageFlags::TRANSFER_SRC,
+            vk::MemoryPropertyFlags::HOST_COHERENT | vk::MemoryPropertyFlags::HOST_VISIBLE,
+            &format!("{}-staging", name),
+        )?;
+
+        let memory = device.map_memory(
+            staging_buffer_memory.memory,
+            staging_buffer_memory.offset,
+            size,
+            vk::MemoryMapFlags::empty(),
+        )?;
+        memcpy(data.as_ptr(), memory.cast(), data.len());
+        device.unmap_memory(staging_buffer_memory.memory);
+
+        let (buffer, buffer_memory) = create_buffer(
+            instance,
+            device,
+            phys_device,
+            allocator,
+            size,
+            vk::BufferUsageFlags::TRANSFER_DST | usage,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            name,
+        )?;
+
+        copy_buffer(device, uploader, staging_buffer, buffer, size, usage)?;
+
+        device.destroy_buffer(staging_buffer, None);
+        allocator.free(device, staging_buffer_memory);
+
+        Ok((buffer, buffer_memory))
+    }
+
+    unsafe fn create_index_buffer<I: Index>(
+        instance: &Instance,
+        device: &Device,
+        phys_device: vk::PhysicalDevice,
+        allocator: &mut Allocator,
+        uploader: &Uploader,
+        indices: &[I],
+        name: &str,
+    ) -> Result<(vk::Buffer, Allocation)> {
+        let size = size_of_val(indices) as u64;
+
+        let (staging_buffer, staging_buffer_memory) = create_buffer(
+            instance,
+            device,
+            phys_device,
+            allocator,
+            size,
+            vk::BufferUsageFlags::TRANSFER_SRC,
+            vk::MemoryPropertyFlags::HOST_COHERENT | vk::MemoryPropertyFlags::HOST_VISIBLE,
+            &format!("{}-staging", name),
+        )?;
+
+        let memory = device.map_memory(
+            staging_buffer_memory.memory,
+            staging_buffer_memory.offset,
+            size,
+            vk::MemoryMapFlags::empty(),
+        )?;
+        memcpy(indices.as_ptr(), memory.cast(), indices.len());
+        device.unmap_memory(staging_buffer_memory.memory);
+
+        let (index_buffer, index_buffer_memory) = create_buffer(
+            instance,
+            device,
+            phys_device,
+            allocator,
+            size,
+            vk::BufferUsageFlags::TRANSFER_DST | vk::BufferUsageFlags::INDEX_BUFFER,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            name,
+        )?;
+
+        copy_buffer(device, uploader, staging_buffer, index_buffer, size, vk::BufferUsageFlags::INDEX_BUFFER)?;
+
+        device.destroy_buffer(staging_buffer, None);
+        allocator.free(device, staging_buffer_memory);
+
+        Ok((index_buffer, index_buffer_memory))
+    }
+
+    // each holds one T
+    unsafe fn create_uniform_buffers<T>(
+        instance: &Instance,
+        device: &Device,
+        phys_device: vk::PhysicalDevice,
+        allocator: &mut Allocator,
+        swapchain_images: &[vk::Image],
+        name: &str,
+    ) -> Result<(Vec<vk::Buffer>, Vec<Allocation>)> {
+        let mut uniform_buffers = Vec::with_capacity(swapchain_images.len());
+        let mut uniform_buffers_memory = Vec::with_capacity(swapchain_images.len());
+
+        // one per swapchain image so we never write to one that is being read
+        for i in 0..swapchain_images.len() {
+            let (uniform_buffer, uniform_buffer_memory) = create_buffer(
+                instance,
+                device,
+                phys_device,
+                allocator,
+                size_of::<T>() as u64,
+                vk::BufferUsageFlags::UNIFORM_BUFFER,
+                vk::MemoryPropertyFlags::HOST_COHERENT | vk::MemoryPropertyFlags::HOST_VISIBLE,
+                &format!("{}-{}", name, i),
+            )?;
+
+            uniform_buffers.push(uniform_buffer);
+            uniform_buffers_memory.push(uniform_buffer_memory);
+        }
+
+        Ok((uniform_buffers, uniform_buffers_memory))
+    }
+
+    // per swapchain image like the ubos, each holding MAX_OBJECTS aligned object uniforms
+    unsafe fn create_object_buffers(
+        instance: &Instance,
+        device: &Device,
+        phys_device: vk::PhysicalDevice,
+        allocator: &mut Allocator,
+        swapchain_images: &[vk::Image],
+        object_alignment: vk::DeviceSize,
+    ) -> Result<(Vec<vk::Buffer>, Vec<Allocation>)> {
+        let mut object_buffers = Vec::with_capacity(swapchain_images.len());
+        let mut object_buffers_memory = Vec::with_capacity(swapchain_images.len());
+
+        for i in 0..swapchain_images.len() {
+            let (object_buffer, object_buffer_memory) = create_buffer(
+                instance,
+                device,
+                phys_device,
+                allocator,
+                object_alignment * MAX_OBJECTS as u64,
+                vk::BufferUsageFlags::UNIFORM_BUFFER,
+                vk::MemoryPropertyFlags::HOST_COHERENT | vk::MemoryPropertyFlags::HOST_VISIBLE,
+                &format!("object-buffer-{}", i),
+            )?;
+
+            object_buffers.push(object_buffer);
+            object_buffers_memory.push(object_buffer_memory);
+        }
+
+        Ok((object_buffers, object_buffers_memory))
+    }
+
+    // stride between object uniforms, one bigger than the limit only needs rounding up to a multiple of it
+    unsafe fn get_object_alignment(instance: &Instance, phys_device: vk::PhysicalDevice) -> vk::DeviceSize {
+        let min_alignment = instance
+            .get_physical_device_properties(phys_device)
+            .limits
+            .min_uniform_buffer_offset_alignment;
+        let size = size_of::<ObjectUniform>() as vk::DeviceSize;
+
+        // the limit is always a power of two
+        if min_alignment > 0 {
+            (size + min_alignment - 1) & !(min_alignment - 1)
+        } else {
+            size
+        }
+    }
+
+    // normals are computed when a mesh has none, or always when flat, before identical vertices are merged
+    fn load_model(path: &str, flat_normals: bool) -> Result<(Vec<Vertex>, Vec<u32>)> {
+        let mut reader = BufReader::new(
+            File::open(path).map_err(|e| anyhow!("Failed to open model ({}): {}", path, e))?,
+        );
+
+        let (models, _) = tobj::load_obj_buf(
+            &mut reader,
+            &tobj::LoadOptions {
+                triangulate: true,
+                single_index: true,
+                ..Default::default()
+            },
+            |_| Ok(Default::default()),
+        )?;
+
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+        let mut unique_vertices = HashMap::new();
+
+        for model in &models {
+            let mesh = &model.mesh;
+
+            let position = |index: u32| {
+                let offset = (3 * index) as usize;
+                vec3(mesh.positions[offset], mesh.positions[offset + 1], mesh.positions[offset + 2])
+            };
+            let computed_normals = if flat_normals || mesh.normals.is_empty() {
+                info!("Computing {} normals for mesh ({}).", if flat_normals { "flat" } else { "smooth" }, model.name);
+                let positions = mesh.indices.iter().map(|i| position(*i)).collect::<Vec<_>>();
+                compute_normals(&positions, flat_normals)
+            } else {
+                Vec::new()
+            };
+
+            for (corner, index) in mesh.indices.iter().enumerate() {
+                let pos_offset = (3 * index) as usize;
+                let tex_coord_offset = (2 * index) as usize;
+
+                // obj has v going up, vulkan has it going down
+                let tex_coord = if mesh.texcoords.is_empty() {
+                    vec2(0.0, 0.0)
+                } else {
+                    vec2(
+                        mesh.texcoords[tex_coord_offset],
+                        1.0 - mesh.texcoords[tex_coord_offset + 1],
+                    )
+                };
+
+                // single_index lines the normals up with the positions
+                let normal = if let Some(normal) = computed_normals.get(corner) {
+                    *normal
+                } else {
+                    vec3(mesh.normals[pos_offset], mesh.normals[pos_offset + 1], mesh.normals[pos_offset + 2])
+                };
+
+                let vertex = Vertex::new(position(*index), vec3(1.0, 1.0, 1.0), tex_coord, normal);
+
+                // reuse identical vertices
+                if let Some(index) = unique_vertices.get(&vertex) {
+                    indices.push(*index);
+                } else {
+                    let index = vertices.len() as u32;
+                    unique_vertices.insert(vertex, index);
+                    vertices.push(vertex);
+                    indices.push(index);
+                }
+            }
+        }
+
+        compute_tangents(&mut vertices, &indices);
+
+        info!("Loaded model ({}) with {} vertices and {} indices.", path, vertices.len(), indices.len());
+
+        Ok((vertices, indices))
+    }
+
+    // a normal per corner of a triangle list, smooth ones sum the faces at the same position so uv seams don't split
+    // them, bigger faces count for more, degenerate faces get a zero normal
+    pub fn compute_normals(positions: &[Vec3], flat: bool) -> Vec<Vec3> {
+        let normalize = |n: Vec3| if n.magnitude2() > 0.0 { n.normalize() } else { vec3(0.0, 0.0, 0.0) };
+        let faces = positions
+            .chunks_exact(3)
+            .map(|t| (t[1] - t[0]).cross(t[2] - t[0]))
+            .collect::<Vec<_>>();
+        let face = |corner: usize| faces.get(corner / 3).copied().unwrap_or(vec3(0.0, 0.0, 0.0));
+
+        if flat {
+            return (0..positions.len()).map(|i| normalize(face(i))).collect();
+        }
+
+        let key = |p: &Vec3| [p.x.to_bits(), p.y.to_bits(), p.z.to_bits()];
+        let mut sums = HashMap::new();
+        for (i, p) in positions.iter().enumerate() {
+            *sums.entry(key(p)).or_insert(vec3(0.0, 0.0, 0.0)) += face(i);
+        }
+
+        positions.iter().map(|p| normalize(sums[&key(p)])).collect()
+    }
+
+    // per vertex tangents from the direction u increases across each triangle, averaged where triangles meet
+    // vertices without a normal or with no change in uv across their triangles keep a zero tangent
+    pub fn compute_tangents(vertices: &mut [Vertex], indices: &[u32]) {
+        let mut tangents = vec![vec3(0.0, 0.0, 0.0); vertices.len()];
+        let mut bitangents = vec![vec3(0.0, 0.0, 0.0); vertices.len()];
+
+        for triangle in indices.chunks_exact(3) {
+            let [a, b, c] = [triangle[0] as usize, triangle[1] as usize, triangle[2] as usize];
+            if a.max(b).max(c) >= vertices.len() {
+                continue;
+            }
+
+            let (v0, v1, v2) = (vertices[a], vertices[b], vertices[c]);
+            let (edge1, edge2) = (v1.pos - v0.pos, v2.pos - v0.pos);
+            let (duv1, duv2) = (v1.tex_coord - v0.tex_coord, v2.tex_coord - v0.tex_coord);
+            let det = duv1.x * duv2.y - duv2.x * duv1.y;
+            if det.abs() < f32::EPSILON {
+                continue;
+            }
+
+            let tangent = (edge1 * duv2.y - edge2 * duv1.y) / det;
+            let bitangent = (edge2 * duv1.x - edge1 * duv2.x) / det;
+            for i in [a, b, c] {
+                tangents[i] += tangent;
+                bitangents[i] += bitangent;
+            }
+        }
+
+        // made perpendicular to the normal, the sign says which way the bitangent goes from normal x tangent
+        for ((vertex, tangent), bitangent) in vertices.iter_mut().zip(tangents).zip(bitangents) {
+            let normal = vertex.normal;
+            let tangent = tangent - normal * normal.dot(tangent);
+            if normal.magnitude2() == 0.0 || tangent.magnitude2() < f32::EPSILON {
+                vertex.tangent = vec4(0.0, 0.0, 0.0, 0.0);
+                continue;
+            }
+
+            let handedness = if normal.cross(tangent).dot(bitangent) < 0.0 { -1.0 } else { 1.0 };
+            vertex.tangent = tangent.normalize().extend(handedness);
+        }
+    }
+
+    /// # Safety
+    ///
+    /// `device` and `phys_device` have to come from `instance`, `allocator` from `device`.
+    #[allow(clippy::too_many_arguments)]
+    pub unsafe fn create_buffer(
+        instance: &Instance,
+        device: &Device,
+        phys_device: vk::PhysicalDevice,
+        allocator: &mut Allocator,
+        size: vk::DeviceSize,
+        usage: vk::BufferUsageFlags,
+        properties: vk::MemoryPropertyFlags,
+        name: &str,
+    ) -> Result<(vk::Buffer, Allocation)> {
+        let buffer_info = vk::BufferCreateInfo::builder()
+            .size(size)
+            .usage(usage)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE);
+
+        let buffer = device.create_buffer(&buffer_info, None)?;
+
+        let requirements = device.get_buffer_memory_requirements(buffer);
+        let memory_type = get_memory_type_index(instance, phys_device, properties, requirements)?;
+        // buffers whose address is taken need memory allocated for it
+        let flags = if usage.contains(vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS) {
+            vk::MemoryAllocateFlags::DEVICE_ADDRESS
+        } else {
+            vk::MemoryAllocateFlags::empty()
+        };
+        let buffer_memory = allocator.allocate(device, requirements, memory_type, true, flags, name)?;
+        device.bind_buffer_memory(buffer, buffer_memory.memory, buffer_memory.offset)?;
+
+        Ok((buffer, buffer_memory))
+    }
+
+    // the destination's usage picks the stages and access the graphics family acquires it for
+    unsafe fn copy_buffer(
+        device: &Device,
+        uploader: &Uploader,
+        source: vk::Buffer,
+        destination: vk::Buffer,
+        size: vk::DeviceSize,
+        usage: vk::BufferUsageFlags,
+    ) -> Result<()> {
+        let commands = SingleTimeCommands::new(device, uploader.transfer_pool, uploader.transfer_queue)?;
+        let command_buffer = commands.command_buffer;
+
+        let regions = vk::BufferCopy::builder().size(size);
+        device.cmd_copy_buffer(command_buffer, source, destination, &[regions]);
+
+        // release on the transfer family, acquire on the graphics family
+        let barrier = vk::BufferMemoryBarrier::builder()
+            .src_queue_family_index(uploader.transfer_family)
+            .dst_queue_family_index(uploader.graphics_family)
+            .buffer(destination)
+            .offset(0)
+            .size(size);
+
+        if uploader.needs_ownership_transfer() {
+            let release = barrier
+                .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+                .dst_access_mask(vk::AccessFlags::empty());
+
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+                vk::DependencyFlags::empty(),
+                &[] as &[vk::MemoryBarrier],
+                &[release],
+                &[] as &[vk::ImageMemoryBarrier],
+            );
+        }
+
+        commands.submit()?;
+
+        if uploader.needs_ownership_transfer() {
+            let commands = SingleTimeCommands::new(device, uploader.graphics_pool, uploader.graphics_queue)?;
+            let command_buffer = commands.command_buffer;
+
+            let (dst_stage, dst_access) = get_buffer_access(usage);
+            let acquire = barrier
+                .src_access_mask(vk::AccessFlags::empty())
+                .dst_access_mask(dst_access);
+
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TOP_OF_PIPE,
+                dst_stage,
+                vk::DependencyFlags::empty(),
+                &[] as &[vk::MemoryBarrier],
+                &[acquire],
+                &[] as &[vk::ImageMemoryBarrier],
+            );
+
+            commands.submit()?;
+        }
+
+        Ok(())
+    }
+
+    // where and how a buffer with this usage is first read after an upload
+    fn get_buffer_access(usage: vk::BufferUsageFlags) -> (vk::PipelineStageFlags, vk::AccessFlags) {
+        [
+            (vk::BufferUsageFlags::VERTEX_BUFFER, vk::PipelineStageFlags::VERTEX_INPUT, vk::AccessFlags::VERTEX_ATTRIBUTE_READ),
+            (vk::BufferUsageFlags::INDEX_BUFFER, vk::PipelineStageFlags::VERTEX_INPUT, vk::AccessFlags::INDEX_READ),
+            (
+                vk::BufferUsageFlags::INDIRECT_BUFFER,
+                vk::PipelineStageFlags::DRAW_INDIRECT,
+                vk::AccessFlags::INDIRECT_COMMAND_READ,
+            ),
+            (
+                vk::BufferUsageFlags::STORAGE_BUFFER,
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::AccessFlags::SHADER_READ | vk::AccessFlags::SHADER_WRITE,
+            ),
+        ]
+        .iter()
+        .filter(|(flag, ..)| usage.contains(*flag))
+        .fold(
+            (vk::PipelineStageFlags::empty(), vk::AccessFlags::empty()),
+            |(stages, access), (_, stage, flags)| (stages | *stage, access | *flags),
+        )
+    }
+
+    unsafe fn create_color_objects(
+        instance: &Instance,
+        device: &Device,
+        phys_device: vk::PhysicalDevice,
+        allocator: &mut Allocator,
+        scene_format: vk::Format,
+        swapchain_extent: vk::Extent2D,
+        msaa_samples: vk::SampleCountFlags,
+    ) -> Result<(vk::Image, Allocation, vk::ImageView)> {
+        // only lives within the render pass so it can be transient
+        let (color_image, color_image_memory) = create_image(
+            instance,
+            device,
+            phys_device,
+            allocator,
+            swapchain_extent.width,
+            swapchain_extent.height,
+            1,
+            1,
+            msaa_samples,
+            scene_format,
+            vk::ImageTiling::OPTIMAL,
+            vk::ImageCreateFlags::empty(),
+            vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSIENT_ATTACHMENT,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            "color-image",
+        )?;
+
+        let color_image_view =
+            create_image_view(device, color_image, scene_format, vk::ImageAspectFlags::COLOR, 1)?;
+
+        Ok((color_image, color_image_memory, color_image_view))
+    }
+
+    // null handles unless the render extent differs from the swapchain
+    unsafe fn create_scaled_objects(
+        instance: &Instance,
+        device: &Device,
+        phys_device: vk::PhysicalDevice,
+        allocator: &mut Allocator,
+        swapchain_format: vk::Format,
+        swapchain_extent: vk::Extent2D,
+        render_extent: vk::Extent2D,
+    ) -> Result<(vk::Image, Allocation, vk::ImageView)> {
+        if render_extent == swapchain_extent {
+            return Ok((vk::Image::null(), Allocation::default(), vk::ImageView::null()));
+        }
+
+        let (scaled_image, scaled_image_memory) = create_image(
+            instance,
+            device,
+            phys_device,
+            allocator,
+            render_extent.width,
+            render_extent.height,
+            1,
+            1,
+            vk::SampleCountFlags::_1,
+            swapchain_format,
+            vk::ImageTiling::OPTIMAL,
+            vk::ImageCreateFlags::empty(),
+            vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSFER_SRC,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            "scaled-image",
+        )?;
+
+        let scaled_image_view =
+            create_image_view(device, scaled_image, swapchain_format, vk::ImageAspectFlags::COLOR, 1)?;
+
+        Ok((scaled_image, scaled_image_memory, scaled_image_view))
+    }
+
+    unsafe fn create_scene_objects(
+        instance: &Instance,
+        device: &Device,
+        phys_device: vk::PhysicalDevice,
+        allocator: &mut Allocator,
+        scene_format: vk::Format,
+        swapchain_extent: vk::Extent2D,
+        dynamic_rendering: bool,
+    ) -> Result<(vk::Image, Allocation, vk::ImageView)> {
+        // resolved into and read back within the render pass, never stored, or sampled when rendering dynamically
+        let usage = if dynamic_rendering {
+            vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::SAMPLED
+        } else {
+            vk::ImageUsageFlags::COLOR_ATTACHMENT
+                | vk::ImageUsageFlags::INPUT_ATTACHMENT
+                | vk::ImageUsageFlags::TRANSIENT_ATTACHMENT
+        };
+        let (scene_image, scene_image_memory) = create_image(
+            instance,
+            device,
+            phys_device,
+            allocator,
+            swapchain_extent.width,
+            swapchain_extent.height,
+            1,
+            1,
+            vk::SampleCountFlags::_1,
+            scene_format,
+            vk::ImageTiling::OPTIMAL,
+            vk::ImageCreateFlags::empty(),
+            usage,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            "scene-image",
+        )?;
+
+        let scene_image_view =
+            create_image_view(device, scene_image, scene_format, vk::ImageAspectFlags::COLOR, 1)?;
+
+        Ok((scene_image, scene_image_memory, scene_image_view))
+    }
+
+    unsafe fn create_depth_objects(
+        instance: &Instance,
+        device: &Device,
+        phys_device: vk::PhysicalDevice,
+        allocator: &mut Allocator,
+        swapchain_extent: vk::Extent2D,
+        msaa_samples: vk::SampleCountFlags,
+    ) -> Result<(vk::Image, Allocation, vk::ImageView)> {
+        let format = get_depth_format(instance, phys_device)?;
+
+        let (depth_image, depth_image_memory) = create_image(
+            instance,
+            device,
+            phys_device,
+            allocator,
+            swapchain_extent.width,
+            swapchain_extent.height,
+            1,
+            1,
+            msaa_samples,
+            format,
+            vk::ImageTiling::OPTIMAL,
+            vk::ImageCreateFlags::empty(),
+            vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            "depth-image",
+        )?;
+
+        // the render pass takes care of the layout transition
+        let aspects = vk::ImageAspectFlags::DEPTH | vk::ImageAspectFlags::STENCIL;
+        let depth_image_view = create_image_view(device, depth_image, format, aspects, 1)?;
+
+        Ok((depth_image, depth_image_memory, depth_image_view))
+    }
+
+    // depth only, sampled by the scene with a compare sampler
+    unsafe fn get_shadow_map_format(instance: &Instance, phys_device: vk::PhysicalDevice) -> Result<vk::Format> {
+        let candidates = &[vk::Format::D32_SFLOAT, vk::Format::D16_UNORM];
+
+        get_supported_format(
+            instance,
+            phys_device,
+            candidates,
+            vk::ImageTiling::OPTIMAL,
+            vk::FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT | vk::FormatFeatureFlags::SAMPLED_IMAGE,
+        )
+    }
+
+    // always has a stencil component, one of these is supported by every device
+    unsafe fn get_depth_format(instance: &Instance, phys_device: vk::PhysicalDevice) -> Result<vk::Format> {
+        let candidates = &[vk::Format::D32_SFLOAT_S8_UINT, vk::Format::D24_UNORM_S8_UINT];
+
+        get_supported_format(
+            instance,
+            phys_device,
+            candidates,
+            vk::ImageTiling::OPTIMAL,
+            vk::FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT,
+        )
+    }
+
+    // a float format the scene can be blended, resolved and sampled in, it's linear so the post pass only has to
+    // encode it for unorm swapchains, the first is required to support all of it
+    unsafe fn get_scene_format(instance: &Instance, phys_device: vk::PhysicalDevice) -> Result<vk::Format> {
+        let features = vk::FormatFeatureFlags::COLOR_ATTACHMENT
+            | vk::FormatFeatureFlags::COLOR_ATTACHMENT_BLEND
+            | vk::FormatFeatureFlags::SAMPLED_IMAGE;
+        let format = get_supported_format(instance, phys_device, SCENE_FORMATS, vk::ImageTiling::OPTIMAL, features)?;
+        info!("Rendering the scene in {:?}.", format);
+
+        Ok(format)
+    }
+
+    unsafe fn get_supported_format(
+        instance: &Instance,
+        phys_device: vk::PhysicalDevice,
+        candidates: &[vk::Format],
+        tiling: vk::ImageTiling,
+        features: vk::FormatFeatureFlags,
+    ) -> Result<vk::Format> {
+        candidates
+            .iter()
+            .cloned()
+            .find(|f| {
+                let properties = instance.get_physical_device_format_properties(phys_device, *f);
+                match tiling {
+                    vk::ImageTiling::LINEAR => properties.linear_tiling_features.contains(features),
+                    vk::ImageTiling::OPTIMAL => properties.optimal_tiling_features.contains(features),
+                    _ => false,
+                }
+            })
+            .ok_or_else(|| anyhow!("Failed to find supported format."))
+    }
+
+    /// # Safety
+    ///
+    /// `device` and `phys_device` have to come from `instance`, `allocator` and `uploader` from `device`.
+    pub unsafe fn create_texture_image(
+        instance: &Instance,
+        device: &Device,
+        phys_device: vk::PhysicalDevice,
+        allocator: &mut Allocator,
+        uploader: &Uploader,
+        paths: &[&str],
+    ) -> Result<(vk::Image, Allocation, u32)> {
+        // capped by what one stage can sample too, so the layers fit however they end up bound
+        let limits = instance.get_physical_device_properties(phys_device).limits;
+        let max_layers = limits.max_image_array_layers.min(limits.max_per_stage_descriptor_sampled_images);
+        if paths.is_empty() || paths.len() as u32 > max_layers {
+            return Err(anyhow!("Texture arrays need between 1 and {} layers, got {}.", max_layers, paths.len()));
+        }
+
+        // one layer per image, they all have to be the same size
+        let images = paths
+            .iter()
+            .map(|path| load_png(path))
+            .collect::<Result<Vec<_>>>()?;
+        let (_, width, height) = images[0];
+        if let Some(((_, w, h), path)) = images.iter().zip(paths).find(|((_, w, h), _)| (*w, *h) != (width, height)) {
+            return Err(anyhow!("Texture ({}) is {}x{}, the first layer is {}x{}.", path, w, h, width, height));
+        }
+
+        let layers = images.len() as u32;
+        let pixels = images.into_iter().flat_map(|(pixels, ..)| pixels).collect::<Vec<_>>();
+
+        create_texture_image_from_pixels(
+            instance,
+            device,
+            phys_device,
+            allocator,
+            uploader,
+            &pixels,
+            width,
+            height,
+            layers,
+            vk::Format::R8G8B8A8_SRGB,
+        )
+    }
+
+    /// rgba layers packed one after another, for textures that weren't loaded from a png or aren't colors
+    ///
+    /// # Safety
+    ///
+    /// `device` and `phys_device` have to come from `instance`, `allocator` and `uploader` from `device`.
+    #[allow(clippy::too_many_arguments)]
+    pub unsafe fn create_texture_image_from_pixels(
+        instance: &Instance,
+        device: &Device,
+        phys_device: vk::PhysicalDevice,
+        allocator: &mut Allocator,
+        uploader: &Uploader,
+        pixels: &[u8],
+        width: u32,
+        height: u32,
+        layers: u32,
+        format: vk::Format,
+    ) -> Result<(vk::Image, Allocation, u32)> {
+        let expected = width as usize * height as usize * 4 * layers as usize;
+        if pixels.len() != expected || expected == 0 {
+            return Err(anyhow!(
+                "A {}x{} texture with {} layers needs {} bytes, got {}.",
+                width,
+                height,
+                layers,
+                expected,
+                pixels.len()
+            ));
+        }
+
+        let size = pixels.len() as u64;
+
+        // halve until 1x1
+        let mip_levels = (width.max(height) as f32).log2().floor() as u32 + 1;
+
+        let (staging_buffer, staging_buffer_memory) = create_buffer(
+            instance,
+            device,
+            phys_device,
+            allocator,
+            size,
+            vk::BufferUsageFlags::TRANSFER_SRC,
+            vk::MemoryPropertyFlags::HOST_COHERENT | vk::MemoryPropertyFlags::HOST_VISIBLE,
+            "texture-staging",
+        )?;
+
+        let memory = device.map_memory(
+            staging_buffer_memory.memory,
+            staging_buffer_memory.offset,
+            size,
+            vk::MemoryMapFlags::empty(),
+        )?;
+        memcpy(pixels.as_ptr(), memory.cast(), pixels.len());
+        device.unmap_memory(staging_buffer_memory.memory);
+
+        let (texture_image, texture_image_memory) = create_image(
+            instance,
+            device,
+            phys_device,
+            allocator,
+            width,
+            height,
+            mip_levels,
+            layers,
+            vk::SampleCountFlags::_1,
+            format,
+            vk::ImageTiling::OPTIMAL,
+            vk::ImageCreateFlags::empty(),
+            vk::ImageUsageFlags::SAMPLED
+                | vk::ImageUsageFlags::TRANSFER_DST
+                | vk::ImageUsageFlags::TRANSFER_SRC,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            "texture",
+        )?;
+
+        transition_image_layout(
+            device,
+            uploader.transfer_pool,
+            uploader.transfer_queue,
+            texture_image,
+            format,
+            vk::ImageLayout::UNDEFINED,
+            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            0,
+            mip_levels,
+            0,
+            layers,
+        )?;
+
+        copy_buffer_to_image(device, uploader, staging_buffer, texture_image, width, height, mip_levels, layers)?;
+
+        device.destroy_buffer(staging_buffer, None);
+        allocator.free(device, staging_buffer_memory);
+
+        // fills the other levels and leaves every level ready for sampling
+        generate_mipmaps(
+            instance,
+            device,
+            phys_device,
+            uploader.graphics_pool,
+            uploader.graphics_queue,
+            texture_image,
+            format,
+            width,
+            height,
+            mip_levels,
+            layers,
+        )?;
+
+        Ok((texture_image, texture_image_memory, mip_levels))
+    }
+
+    // every layer is visible, objects pick theirs with RenderObject::texture_index
+    unsafe fn create_texture_image_view(
+        device: &Device,
+        texture_image: vk::Image,
+        mip_levels: u32,
+        layers: u32,
+    ) -> Result<vk::ImageView> {
+        let subresource_range = vk::ImageSubresourceRange::builder()
+            .aspect_mask(vk::ImageAspectFlags::COLOR)
+            .base_mip_level(0)
+            .level_count(mip_levels)
+            .base_array_layer(0)
+            .layer_count(layers);
+
+        let info = vk::ImageViewCreateInfo::builder()
+            .image(texture_image)
+            .view_type(vk::ImageViewType::_2D_ARRAY)
+            .format(vk::Format::R8G8B8A8_SRGB)
+            .subresource_range(subresource_range);
+
+        Ok(device.create_image_view(&info, None)?)
+    }
+
+    // linear rather than srgb since the texels are vectors, a flat normal pointing straight out without a path
+    unsafe fn create_normal_map(
+        instance: &Instance,
+        device: &Device,
+        phys_device: vk::PhysicalDevice,
+        allocator: &mut Allocator,
+        uploader: &Uploader,
+        path: Option<&str>,
+    ) -> Result<(vk::Image, Allocation, vk::ImageView)> {
+        let (pixels, width, height) = match path {
+            Some(path) => load_png(path)?,
+            None => (vec![128, 128, 255, 255], 1, 1),
+        };
+
+        let format = vk::Format::R8G8B8A8_UNORM;
+        let (image, image_memory, mip_levels) = create_texture_image_from_pixels(
+            instance,
+            device,
+            phys_device,
+            allocator,
+            uploader,
+            &pixels,
+            width,
+            height,
+            1,
+            format,
+        )?;
+        let image_view = match create_image_view(device, image, format, vk::ImageAspectFlags::COLOR, mip_levels) {
+            Ok(view) => view,
+            Err(e) => {
+                device.destroy_image(image, None);
+                allocator.free(device, image_memory);
+                return Err(e);
+            }
+        };
+
+        Ok((image, image_memory, image_view))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    unsafe fn generate_mipmaps(
+        instance: &Instance,
+        device: &Device,
+        phys_device: vk::PhysicalDevice,
+        command_pool: vk::CommandPool,
+        queue: vk::Queue,
+        image: vk::Image,
+        format: vk::Format,
+        width: u32,
+        height: u32,
+        mip_levels: u32,
+        array_layers: u32,
+    ) -> Result<()> {
+        // blitting with a linear filter isn't supported for every format
+        if !instance
+            .get_physical_device_format_properties(phys_device, format)
+            .optimal_tiling_features
+            .contains(vk::FormatFeatureFlags::SAMPLED_IMAGE_FILTER_LINEAR)
+        {
+            return Err(anyhow!("Texture image format ({:?}) does not support linear blitting.", format));
+        }
+
+        let commands = SingleTimeCommands::new(device, command_pool, queue)?;
+        let command_buffer = commands.command_buffer;
+
+        let mut mip_width = width;
+        let mut mip_height = height;
+
+        for i in 1..mip_levels {
+            // previous level becomes the blit source
+            cmd_transition_image_layout(
+                device,
+                command_buffer,
+                image,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                i - 1,
+                1,
+                0,
+                array_layers,
+            )?;
+
+            let src_subresource = vk::ImageSubresourceLayers::builder()
+                .aspect_mask(vk::ImageAspectFlags::COLOR)
+                .mip_level(i - 1)
+                .base_array_layer(0)
+                .layer_count(array_layers);
+
+            let dst_subresource = vk::ImageSubresourceLayers::builder()
+                .aspect_mask(vk::ImageAspectFlags::COLOR)
+                .mip_level(i)
+                .base_array_layer(0)
+                .layer_count(array_layers);
+
+            let next_width = if mip_width > 1 { mip_width / 2 } else { 1 };
+            let next_height = if mip_height > 1 { mip_height / 2 } else { 1 };
+
+            let blit = vk::ImageBlit::builder()
+                .src_offsets([
+                    vk::Offset3D { x: 0, y: 0, z: 0 },
+                    vk::Offset3D { x: mip_width as i32, y: mip_height as i32, z: 1 },
+                ])
+                .src_subresource(src_subresource)
+                .dst_offsets([
+                    vk::Offset3D { x: 0, y: 0, z: 0 },
+                    vk::Offset3D { x: next_width as i32, y: next_height as i32, z: 1 },
+                ])
+                .dst_subresource(dst_subresource);
+
+            device.cmd_blit_image(
+                command_buffer,
+                image,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                image,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                &[blit],
+                vk::Filter::LINEAR,
+            );
+
+            // previous level is done, hand it to the fragment shader
+            cmd_transition_image_layout(
+                device,
+                command_buffer,
+                image,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                i - 1,
+                1,
+                0,
+                array_layers,
+            )?;
+
+            mip_width = next_width;
+            mip_height = next_height;
+        }
+
+        // the last level was only ever written to
+        cmd_transition_image_layout(
+            device,
+            command_buffer,
+            image,
+            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            mip_levels - 1,
+            1,
+            0,
+            array_layers,
+        )?;
+
+        commands.submit()?;
+
+        Ok(())
+    }
+
+    unsafe fn create_texture_sampler(
+        device: &Device,
+        filter: vk::Filter,
+        anisotropy: Option<f32>,
+        mip_levels: u32,
+    ) -> Result<vk::Sampler> {
+        let mipmap_mode = if filter == vk::Filter::NEAREST {
+            vk::SamplerMipmapMode::NEAREST
+        } else {
+            vk::SamplerMipmapMode::LINEAR
+        };
+
+        let info = vk::SamplerCreateInfo::builder()
+            .mag_filter(filter)
+            .min_filter(filter)
+            .address_mode_u(vk::SamplerAddressMode::REPEAT)
+            .address_mode_v(vk::SamplerAddressMode::REPEAT)
+            .address_mode_w(vk::SamplerAddressMode::REPEAT)
+            .anisotropy_enable(anisotropy.is_some())
+            .max_anisotropy(anisotropy.unwrap_or(1.0))
+            .border_color(vk::BorderColor::INT_OPAQUE_BLACK)
+            .unnormalized_coordinates(false)
+            .compare_enable(false)
+            .compare_op(vk::CompareOp::ALWAYS)
+            .mipmap_mode(mipmap_mode)
+            .mip_lod_bias(0.0)
+            .min_lod(0.0)
+            .max_lod(mip_levels as f32);
+
+        Ok(device.create_sampler(&info, None)?)
+    }
+
+    // clamped to the device's limit, none when it's 1.0 or less or the device can't filter anisotropically
+    unsafe fn get_anisotropy(instance: &Instance, phys_device: vk::PhysicalDevice, level: f32) -> Option<f32> {
+        if level <= 1.0 {
+            info!("Anisotropic filtering is off.");
+            return None;
+        }
+
+        if instance.get_physical_device_features(phys_device).sampler_anisotropy != vk::TRUE {
+            warn!("Device does not support sampler anisotropy, anisotropic filtering is off.");
+            return None;
+        }
+
+        let max = instance.get_physical_device_properties(phys_device).limits.max_sampler_anisotropy;
+        let level = level.min(max);
+        info!("Using {}x anisotropic filtering.", level);
+
+        Some(level)
+    }
+
+    pub fn load_png(path: &str) -> Result<(Vec<u8>, u32, u32)> {
+        let file = File::open(path).map_err(|e| anyhow!("Failed to open image ({}): {}", path, e))?;
+
+        // expand palettes and low bit depths to 8 bits per channel
+        let mut decoder = png::Decoder::new(file);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+
+        let mut reader = decoder.read_info()?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buffer)?;
+        buffer.truncate(info.buffer_size());
+
+        // convert everything to rgba
+        let pixels = match info.color_type {
+            png::ColorType::Rgba => buffer,
+            png::ColorType::Rgb => buffer
+                .chunks_exact(3)
+                .flat_map(|p| [p[0], p[1], p[2], 255])
+                .collect(),
+            png::ColorType::GrayscaleAlpha => buffer
+                .chunks_exact(2)
+                .flat_map(|p| [p[0], p[0], p[0], p[1]])
+                .collect(),
+            png::ColorType::Grayscale => buffer
+                .iter()
+                .flat_map(|p| [*p, *p, *p, 255])
+                .collect(),
+            png::ColorType::Indexed => return Err(anyhow!("Unexpected indexed image after expansion ({}).", path)),
+        };
+
+        Ok((pixels, info.width, info.height))
+    }
+
+    fn convert_to_rgba8(format: vk::Format, pixels: Vec<u8>) -> Result<Vec<u8>> {
+        match format {
+            vk::Format::R8G8B8A8_SRGB | vk::Format::R8G8B8A8_UNORM => Ok(pixels),
+            vk::Format::B8G8R8A8_SRGB | vk::Format::B8G8R8A8_UNORM => Ok(pixels
+                .chunks_exact(4)
+                .flat_map(|p| [p[2], p[1], p[0], p[3]])
+                .collect()),
+            // packed 10 bit formats, drop the low bits of each channel
+            vk::Format::A2B10G10R10_UNORM_PACK32 | vk::Format::A2R10G10B10_UNORM_PACK32 => Ok(pixels
+                .chunks_exact(4)
+                .flat_map(|p| {
+                    let packed = u32::from_le_bytes([p[0], p[1], p[2], p[3]]);
+                    let low = ((packed & 0x3ff) >> 2) as u8;
+                    let mid = (((packed >> 10) & 0x3ff) >> 2) as u8;
+                    let high = (((packed >> 20) & 0x3ff) >> 2) as u8;
+                    let alpha = ((packed >> 30) * 85) as u8;
+
+                    if format == vk::Format::A2B10G10R10_UNORM_PACK32 {
+                        [low, mid, high, alpha]
+                    } else {
+                        [high, mid, low, alpha]
+                    }
+                })
+                .collect()),
+            _ => Err(anyhow!("Unsupported format for conversion to rgba ({:?}).", format)),
+        }
+    }
+
+    fn save_png(path: &Path, pixels: &[u8], width: u32, height: u32) -> Result<()> {
+        let file = File::create(path).map_err(|e| anyhow!("Failed to create image ({}): {}", path.display(), e))?;
+
+        let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(pixels)?;
+
+        Ok(())
+    }
+
+    /// # Safety
+    ///
+    /// `device` and `phys_device` have to come from `instance`, `allocator` from `device`.
+    #[allow(clippy::too_many_arguments)]
+    pub unsafe fn create_image(
+        instance: &Instance,
+        device: &Device,
+        phys_device: vk::PhysicalDevice,
+        allocator: &mut Allocator,
+        width: u32,
+        height: u32,
+        mip_levels: u32,
+        array_layers: u32,
+        samples: vk::SampleCountFlags,
+        format: vk::Format,
+        tiling: vk::ImageTiling,
+        flags: vk::ImageCreateFlags,
+        usage: vk::ImageUsageFlags,
+        properties: vk::MemoryPropertyFlags,
+        name: &str,
+    ) -> Result<(vk::Image, Allocation)> {
+        let info = vk::ImageCreateInfo::builder()
+            .flags(flags)
+            .image_type(vk::ImageType::_2D)
+            .extent(vk::Extent3D { width, height, depth: 1 })
+            .mip_levels(mip_levels)
+            .array_layers(array_layers)
+            .format(format)
+            .tiling(tiling)
+            .initial_layout(vk::ImageLayout::UNDEFINED)
+            .usage(usage)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE)
+            .samples(samples);
+
+        let image = device.create_image(&info, None)?;
+
+        // linear images could share blocks with buffers, optimal ones never do
+        let requirements = device.get_image_memory_requirements(image);
+        let memory_type = get_memory_type_index(instance, phys_device, properties, requirements)?;
+        let linear = tiling == vk::ImageTiling::LINEAR;
+        let image_memory =
+            allocator.allocate(device, requirements, memory_type, linear, vk::MemoryAllocateFlags::empty(), name)?;
+        device.bind_image_memory(image, image_memory.memory, image_memory.offset)?;
+
+        Ok((image, image_memory))
+    }
+
+    // for barriers recorded into a frame's command buffer rather than submitted on their own
+    fn image_barrier(
+        image: vk::Image,
+        aspect_mask: vk::ImageAspectFlags,
+        old_layout: vk::ImageLayout,
+        new_layout: vk::ImageLayout,
+        src_access_mask: vk::AccessFlags,
+        dst_access_mask: vk::AccessFlags,
+    ) -> vk::ImageMemoryBarrier {
+        let subresource = vk::ImageSubresourceRange::builder()
+            .aspect_mask(aspect_mask)
+            .base_mip_level(0)
+            .level_count(1)
+            .base_array_layer(0)
+            .layer_count(1);
+
+        vk::ImageMemoryBarrier::builder()
+            .old_layout(old_layout)
+            .new_layout(new_layout)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .image(image)
+            .subresource_range(subresource)
+            .src_access_mask(src_access_mask)
+            .dst_access_mask(dst_access_mask)
+            .build()
+    }
+
+    /// # Safety
+    ///
+    /// `image` has to be in `old_layout` and not in use, `command_pool` has to be for `queue`.
+    #[allow(clippy::too_many_arguments)]
+    pub unsafe fn transition_image_layout(
+        device: &Device,
+        command_pool: vk::CommandPool,
+        queue: vk::Queue,
+        image: vk::Image,
+        _format: vk::Format,
+        old_layout: vk::ImageLayout,
+        new_layout: vk::ImageLayout,
+        base_mip_level: u32,
+        level_count: u32,
+        base_array_layer: u32,
+        layer_count: u32,
+    ) -> Result<()> {
+        let commands = SingleTimeCommands::new(device, command_pool, queue)?;
+
+        cmd_transition_image_layout(
+            device,
+            commands.command_buffer,
+            image,
+            old_layout,
+            new_layout,
+            base_mip_level,
+            level_count,
+            base_array_layer,
+            layer_count,
+        )?;
+
+        commands.submit()?;
+
+        Ok(())
+    }
+
+    /// records the barrier for a range of levels and layers, for use alongside other commands
+    ///
+    /// # Safety
+    ///
+    /// `command_buffer` has to be recording outside any rendering, with `image` in `old_layout` by then.
+    #[allow(clippy::too_many_arguments)]
+    pub unsafe fn cmd_transition_image_layout(
+        device: &Device,
+        command_buffer: vk::CommandBuffer,
+        image: vk::Image,
+        old_layout: vk::ImageLayout,
+        new_layout: vk::ImageLayout,
+        base_mip_level: u32,
+        level_count: u32,
+        base_array_layer: u32,
+        layer_count: u32,
+    ) -> Result<()> {
+        let (src_access_mask, dst_access_mask, src_stage_mask, dst_stage_mask) = match (old_layout, new_layout) {
+            (vk::ImageLayout::UNDEFINED, vk::ImageLayout::TRANSFER_DST_OPTIMAL) => (
+                vk::AccessFlags::empty(),
+                vk::AccessFlags::TRANSFER_WRITE,
+                vk::PipelineStageFlags::TOP_OF_PIPE,
+                vk::PipelineStageFlags::TRANSFER,
+            ),
+            (vk::ImageLayout::TRANSFER_DST_OPTIMAL, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL) => (
+                vk::AccessFlags::TRANSFER_WRITE,
+                vk::AccessFlags::SHADER_READ,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::FRAGMENT_SHADER,
+            ),
+            // a written level becoming the source of the next one's blit
+            (vk::ImageLayout::TRANSFER_DST_OPTIMAL, vk::ImageLayout::TRANSFER_SRC_OPTIMAL) => (
+                vk::AccessFlags::TRANSFER_WRITE,
+                vk::AccessFlags::TRANSFER_READ,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::TRANSFER,
+            ),
+            (vk::ImageLayout::TRANSFER_SRC_OPTIMAL, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL) => (
+                vk::AccessFlags::TRANSFER_READ,
+                vk::AccessFlags::SHADER_READ,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::FRAGMENT_SHADER,
+            ),
+            _ => return Err(anyhow!("Unsupported image layout transition ({:?} -> {:?}).", old_layout, new_layout)),
+        };
+
+        let subresource = vk::ImageSubresourceRange::builder()
+            .aspect_mask(vk::ImageAspectFlags::COLOR)
+            .base_mip_level(base_mip_level)
+            .level_count(level_count)
+            .base_array_layer(base_array_layer)
+            .layer_count(layer_count);
+
+        let barrier = vk::ImageMemoryBarrier::builder()
+            .old_layout(old_layout)
+            .new_layout(new_layout)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .image(image)
+            .subresource_range(subresource)
+            .src_access_mask(src_access_mask)
+            .dst_access_mask(dst_access_mask);
+
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            src_stage_mask,
+            dst_stage_mask,
+            vk::DependencyFlags::empty(),
+            &[] as &[vk::MemoryBarrier],
+            &[] as &[vk::BufferMemoryBarrier],
+            &[barrier],
+        );
+
+        Ok(())
+    }
+
+    /// leaves the image in transfer dst, owned by the graphics family, layers are packed one after another
+    ///
+    /// # Safety
+    ///
+    /// `buffer` has to hold every layer copied and `image` has to be in transfer dst layout.
+    #[allow(clippy::too_many_arguments)]
+    pub unsafe fn copy_buffer_to_image(
+        device: &Device,
+        uploader: &Uploader,
+        buffer: vk::Buffer,
+        image: vk::Image,
+        width: u32,
+        height: u32,
+        mip_levels: u32,
+        layers: u32,
+    ) -> Result<()> {
+        let commands = SingleTimeCommands::new(device, uploader.transfer_pool, uploader.transfer_queue)?;
+        let command_buffer = commands.command_buffer;
+
+        let subresource = vk::ImageSubresourceLayers::builder()
+            .aspect_mask(vk::ImageAspectFlags::COLOR)
+            .mip_level(0)
+            .base_array_layer(0)
+            .layer_count(layers);
+
+        // tightly packed, whole image
+        let region = vk::BufferImageCopy::builder()
+            .buffer_offset(0)
+            .buffer_row_length(0)
+            .buffer_image_height(0)
+            .image_subresource(subresource)
+            .image_offset(vk::Offset3D { x: 0, y: 0, z: 0 })
+            .image_extent(vk::Extent3D { width, height, depth: 1 });
+
+        device.cmd_copy_buffer_to_image(
+            command_buffer,
+            buffer,
+            image,
+            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            &[region],
+        );
+
+        // release on the transfer family, acquire on the graphics family for mipmapping
+        let subresource_range = vk::ImageSubresourceRange::builder()
+            .aspect_mask(vk::ImageAspectFlags::COLOR)
+            .base_mip_level(0)
+            .level_count(mip_levels)
+            .base_array_layer(0)
+            .layer_count(layers);
+
+        let barrier = vk::ImageMemoryBarrier::builder()
+            .old_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+            .new_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+            .src_queue_family_index(uploader.transfer_family)
+            .dst_queue_family_index(uploader.graphics_family)
+            .image(image)
+            .subresource_range(subresource_range);
+
+        if uploader.needs_ownership_transfer() {
+            let release = barrier
+                .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+                .dst_access_mask(vk::AccessFlags::empty());
+
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+                vk::DependencyFlags::empty(),
+                &[] as &[vk::MemoryBarrier],
+                &[] as &[vk::BufferMemoryBarrier],
+                &[release],
+            );
+        }
+
+        commands.submit()?;
+
+        if uploader.needs_ownership_transfer() {
+            let commands = SingleTimeCommands::new(device, uploader.graphics_pool, uploader.graphics_queue)?;
+            let command_buffer = commands.command_buffer;
+
+            let acquire = barrier
+                .src_access_mask(vk::AccessFlags::empty())
+                .dst_access_mask(vk::AccessFlags::TRANSFER_READ | vk::AccessFlags::TRANSFER_WRITE);
+
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TOP_OF_PIPE,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::DependencyFlags::empty(),
+                &[] as &[vk::MemoryBarrier],
+                &[] as &[vk::BufferMemoryBarrier],
+                &[acquire],
+            );
+
+            commands.submit()?;
+        }
+
+        Ok(())
+    }
+
+    // a one time submit command buffer, submitted and waited on by submit() or when dropped
+    struct SingleTimeCommands<'a> {
+        device: &'a Device,
+        command_pool: vk::CommandPool,
+        queue: vk::Queue,
+        command_buffer: vk::CommandBuffer,
+        submitted: bool,
+    }
+
+    impl<'a> SingleTimeCommands<'a> {
+        unsafe fn new(device: &'a Device, command_pool: vk::CommandPool, queue: vk::Queue) -> Result<Self> {
+            let info = vk::CommandBufferAllocateInfo::builder()
+                .level(vk::CommandBufferLevel::PRIMARY)
+                .command_pool(command_pool)
+                .command_buffer_count(1);
+
+            let command_buffer = device.allocate_command_buffers(&info)?[0];
+
+            let info = vk::CommandBufferBeginInfo::builder()
+                .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+
+            if let Err(e) = device.begin_command_buffer(command_buffer, &info) {
+                device.free_command_buffers(command_pool, &[command_buffer]);
+                return Err(e.into());
+            }
+
+            Ok(Self { device, command_pool, queue, command_buffer, submitted: false })
+        }
+
+        // dropping submits too, but can only log what goes wrong
+        unsafe fn submit(mut self) -> Result<()> {
+            self.finish()
+        }
+
+        // the buffer is freed even if submitting fails
+        unsafe fn finish(&mut self) -> Result<()> {
+            self.submitted = true;
+
+            let command_buffers = &[self.command_buffer];
+            let result = submit_and_wait(self.device, self.queue, command_buffers);
+            self.device.free_command_buffers(self.command_pool, command_buffers);
+
+            result
+        }
+    }
+
+    impl Drop for SingleTimeCommands<'_> {
+        fn drop(&mut self) {
+            if self.submitted {
+                return;
+            }
+
+            if let Err(e) = unsafe { self.finish() } {
+                error!("Failed to submit single time commands: {}", e);
+            }
+        }
+    }
+
+    unsafe fn submit_and_wait(device: &Device, queue: vk::Queue, command_buffers: &[vk::CommandBuffer]) -> Result<()> {
+        device.end_command_buffer(command_buffers[0])?;
+
+        // submit and wait for the commands to finish
+        let info = vk::SubmitInfo::builder().command_buffers(command_buffers);
+
+        device.queue_submit(queue, &[info], vk::Fence::null())?;
+        device.queue_wait_idle(queue)?;
+
+        Ok(())
+    }
+
+    unsafe fn get_memory_type_index(
+        instance: &Instance,
+        phys_device: vk::PhysicalDevice,
+        properties: vk::MemoryPropertyFlags,
+        requirements: vk::MemoryRequirements,
+    ) -> Result<u32> {
+        let memory = instance.get_physical_device_memory_properties(phys_device);
+
+        // first memory type allowed by the requirements with all the wanted properties
+        (0..memory.memory_type_count)
+            .find(|i| {
+                let suitable = (requirements.memory_type_bits & (1 << i)) != 0;
+                let memory_type = memory.memory_types[*i as usize];
+                suitable && memory_type.property_flags.contains(properties)
+            })
+            .ok_or_else(|| anyhow!("Failed to find suitable memory type."))
+    }
+
+    // nanoseconds per tick and a mask of the valid bits, none when the graphics queue can't write timestamps
+    unsafe fn get_timestamp_properties(
+        instance: &Instance,
+        phys_device: vk::PhysicalDevice,
+        graphics_family: u32,
+    ) -> Option<(f32, u64)> {
+        let limits = instance.get_physical_device_properties(phys_device).limits;
+        let valid_bits = instance.get_physical_device_queue_family_properties(phys_device)[graphics_family as usize]
+            .timestamp_valid_bits;
+
+        // timestamp_compute_and_graphics promises support, otherwise the family has to report valid bits itself
+        if limits.timestamp_compute_and_graphics != vk::TRUE && valid_bits == 0 {
+            warn!("GPU timestamps are not supported, GPU frame times won't be reported.");
+            return None;
+        }
+
+        let mask = match valid_bits {
+            1..=63 => (1 << valid_bits) - 1,
+            _ => u64::MAX,
+        };
+
+        Some((limits.timestamp_period, mask))
+    }
+
+    unsafe fn create_timestamp_query_pool(device: &Device, image_count: usize) -> Result<vk::QueryPool> {
+        let info = vk::QueryPoolCreateInfo::builder()
+            .query_type(vk::QueryType::TIMESTAMP)
+            .query_count(image_count as u32 * 2);
+
+        Ok(device.create_query_pool(&info, None)?)
+    }
+
+    type SyncObjects = (Vec<vk::Semaphore>, Vec<vk::Semaphore>, Vec<vk::Fence>, Vec<vk::Fence>);
+
+    unsafe fn create_sync_objects(device: &Device, image_count: usize) -> Result<SyncObjects> {
+        let semaphore_info = vk::SemaphoreCreateInfo::builder();
+
+        // fences start signaled so the first wait on each frame doesn't block forever
+        let fence_info = vk::FenceCreateInfo::builder()
+            .flags(vk::FenceCreateFlags::SIGNALED);
+
+        let mut image_available_semaphores = Vec::with_capacity(MAX_FRAMES_IN_FLIGHT);
+        let mut render_finished_semaphores = Vec::with_capacity(MAX_FRAMES_IN_FLIGHT);
+        let mut in_flight_fences = Vec::with_capacity(MAX_FRAMES_IN_FLIGHT);
+
+        for _ in 0..MAX_FRAMES_IN_FLIGHT {
+            image_available_semaphores.push(device.create_semaphore(&semaphore_info, None)?);
+            render_finished_semaphores.push(device.create_semaphore(&semaphore_info, None)?);
+            in_flight_fences.push(device.create_fence(&fence_info, None)?);
+        }
+
+        // no image is in flight yet
+        let images_in_flight = vec![vk::Fence::null(); image_count];
+
+        Ok((image_available_semaphores, render_finished_semaphores, in_flight_fences, images_in_flight))
+    }
+
+    // starts at 0, which every wait on an unused frame or image asks for
+    unsafe fn create_timeline_semaphore(device: &Device) -> Result<vk::Semaphore> {
+        let mut type_info = vk::SemaphoreTypeCreateInfo::builder()
+            .semaphore_type(vk::SemaphoreType::TIMELINE)
+            .initial_value(0);
+        let info = vk::SemaphoreCreateInfo::builder()
+            .push_next(&mut type_info);
+
+        Ok(device.create_semaphore(&info, None)?)
+    }
+
+    unsafe fn create_transfer_command_pool(device: &Device, indices: &QueueFamilyIndices) -> Result<vk::CommandPool> {
+        let info = vk::CommandPoolCreateInfo::builder()
+            .flags(vk::CommandPoolCreateFlags::TRANSIENT)
+            .queue_family_index(indices.transfer.unwrap_or(indices.graphics));
+
+        Ok(device.create_command_pool(&info, None)?)
+    }
+
+    unsafe fn create_compute_command_pool(device: &Device, indices: &QueueFamilyIndices) -> Result<vk::CommandPool> {
+        let info = vk::CommandPoolCreateInfo::builder()
+            .flags(vk::CommandPoolCreateFlags::TRANSIENT)
+            .queue_family_index(indices.compute.unwrap_or(indices.graphics));
+
+        Ok(device.create_command_pool(&info, None)?)
+    }
+
+    unsafe fn create_compute_buffer(
+        instance: &Instance,
+        device: &Device,
+        phys_device: vk::PhysicalDevice,
+        allocator: &mut Allocator,
+        buffer_device_address: bool,
+    ) -> Result<(vk::Buffer, Allocation)> {
+        let size = (size_of::<f32>() * COMPUTE_BUFFER_ELEMENTS) as u64;
+
+        // shaders can reach it through its address as well as the descriptor when device addresses are on
+        let mut usage = vk::BufferUsageFlags::STORAGE_BUFFER;
+        if buffer_device_address {
+            usage |= vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS;
+        }
+
+        // host visible so results can be read back directly
+        let (buffer, buffer_memory) = create_buffer(
+            instance,
+            device,
+            phys_device,
+            allocator,
+            size,
+            usage,
+            vk::MemoryPropertyFlags::HOST_COHERENT | vk::MemoryPropertyFlags::HOST_VISIBLE,
+            "compute-buffer",
+        )?;
+
+        let memory = device.map_memory(buffer_memory.memory, buffer_memory.offset, size, vk::MemoryMapFlags::empty())?;
+        std::ptr::write_bytes(memory.cast::<u8>(), 0, size as usize);
+        device.unmap_memory(buffer_memory.memory);
+
+        Ok((buffer, buffer_memory))
+    }
+
+    unsafe fn create_compute_descriptor_set_layout(device: &Device) -> Result<vk::DescriptorSetLayout> {
+        let storage_binding = vk::DescriptorSetLayoutBinding::builder()
+            .binding(0)
+            .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+            .descriptor_count(1)
+            .stage_flags(vk::ShaderStageFlags::COMPUTE);
+
+        let bindings = &[storage_binding];
+        let info = vk::DescriptorSetLayoutCreateInfo::builder()
+            .bindings(bindings);
+
+        Ok(device.create_descriptor_set_layout(&info, None)?)
+    }
+
+    unsafe fn create_compute_descriptor_pool(device: &Device) -> Result<vk::DescriptorPool> {
+        let storage_size = vk::DescriptorPoolSize::builder()
+            .type_(vk::DescriptorType::STORAGE_BUFFER)
+            .descriptor_count(1);
+
+        let pool_sizes = &[storage_size];
+        let info = vk::DescriptorPoolCreateInfo::builder()
+            .pool_sizes(pool_sizes)
+            .max_sets(1);
+
+        Ok(device.create_descriptor_pool(&info, None)?)
+    }
+
+    unsafe fn create_compute_descriptor_set(
+        device: &Device,
+        descriptor_set_layout: vk::DescriptorSetLayout,
+        descriptor_pool: vk::DescriptorPool,
+        compute_buffer: vk::Buffer,
+    ) -> Result<vk::DescriptorSet> {
+        let layouts = &[descriptor_set_layout];
+        let info = vk::DescriptorSetAllocateInfo::builder()
+            .descriptor_pool(descriptor_pool)
+            .set_layouts(layouts);
+
+        let descriptor_set = device.allocate_descriptor_sets(&info)?[0];
+
+        let info = vk::DescriptorBufferInfo::builder()
+            .buffer(compute_buffer)
+            .offset(0)
+            .range(vk::WHOLE_SIZE as u64);
+
+        let buffer_info = &[info];
+        let storage_write = vk::WriteDescriptorSet::builder()
+            .dst_set(descriptor_set)
+            .dst_binding(0)
+            .dst_array_element(0)
+            .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+            .buffer_info(buffer_info);
+
+        device.update_descriptor_sets(&[storage_write], &[] as &[vk::CopyDescriptorSet]);
+
+        Ok(descriptor_set)
+    }
+
+    unsafe fn create_compute_pipeline_layout(
+        device: &Device,
+        descriptor_set_layout: vk::DescriptorSetLayout,
+    ) -> Result<vk::PipelineLayout> {
+        let set_layouts = &[descriptor_set_layout];
+        let info = vk::PipelineLayoutCreateInfo::builder()
+            .set_layouts(set_layouts);
+
+        Ok(device.create_pipeline_layout(&info, None)?)
+    }
+
+    unsafe fn create_compute_pipeline(
+        device: &Device,
+        pipeline_cache: vk::PipelineCache,
+        layout: vk::PipelineLayout,
+    ) -> Result<vk::Pipeline> {
+        let comp_shader_module = create_shader_module(device, &shaders::load(shaders::COMP)?)?;
+
+        let comp_stage = vk::PipelineShaderStageCreateInfo::builder()
+            .stage(vk::ShaderStageFlags::COMPUTE)
+            .module(comp_shader_module)
+            .name(b"main\0");
+
+        let info = vk::ComputePipelineCreateInfo::builder()
+            .stage(comp_stage)
+            .layout(layout);
+
+        let pipeline = device
+            .create_compute_pipelines(pipeline_cache, &[info], None)?
+            .0[0];
+
+        device.destroy_shader_module(comp_shader_module, None);
 
-        // check if validation enabled and supported
-        if VALIDATION_ENABLED && !available_layers.contains(&VALIDATION_LAYER) {
-            return Err(anyhow!("Validation layer requested but not supported."));
-        }
+        Ok(pipeline)
+    }
 
-        // add validation layer if enabled
-        let layers = if VALIDATION_ENABLED {
-            vec![VALIDATION_LAYER.as_ptr()]
-        } else {
-            Vec::new()
-        };
+    /// # Safety
+    ///
+    /// `bytecode` has to be SPIR-V the device accepts.
+    pub unsafe fn create_shader_module(device: &Device, bytecode: &[u8]) -> Result<vk::ShaderModule> {
+        // drivers crash on malformed modules rather than returning an error
+        let code = shaders::spirv_words(bytecode)?;
+
+        let info = vk::ShaderModuleCreateInfo::builder()
+            .code_size(bytecode.len())
+            .code(&code);
+
+        Ok(device.create_shader_module(&info, None)?)
+    }
 
-        // get required extensions
-        let mut extensions = vk_window::get_required_instance_extensions(window)
+    fn get_swapchain_surface_format(
+        formats: &[vk::SurfaceFormatKHR],
+        preference: FormatPreference,
+        color_space: vk::ColorSpaceKHR,
+    ) -> vk::SurfaceFormatKHR {
+        // the preferred kind in the preferred color space, then either on its own, then whatever is first
+        let find = |predicate: &dyn Fn(&vk::SurfaceFormatKHR) -> bool| formats.iter().cloned().find(predicate);
+        find(&|f| preference.matches(f.format) && f.color_space == color_space)
+            .or_else(|| find(&|f| preference.matches(f.format)))
+            .or_else(|| find(&|f| f.color_space == color_space))
+            .unwrap_or_else(|| {
+                warn!("No surface format matches ({:?}, {:?}), using the first one.", preference, color_space);
+                formats[0]
+            })
+    }
+
+    fn get_swapchain_present_mode(
+        present_modes: &[vk::PresentModeKHR],
+        preferred: vk::PresentModeKHR,
+    ) -> vk::PresentModeKHR {
+        // use the preferred mode if possible, fifo is always supported, chosen again with every swapchain since
+        // a mode can be there for one and gone for the next
+        let present_mode = present_modes
             .iter()
-            .map(|e| e.as_ptr())
-            .collect::<Vec<_>>();
+            .cloned()
+            .find(|m| *m == preferred)
+            .unwrap_or_else(|| {
+                warn!("Present mode ({:?}) is not supported, falling back to FIFO.", preferred);
+                vk::PresentModeKHR::FIFO
+            });
 
-        // get flags if target is macos
-        let flags = if cfg!(target_os = "macos") &&
-            entry.version()? >= PORTABILITY_MACOS_VERSION {
-            info!("Enabling extensions for MacOS portability.");
-            extensions.push(vk::KHR_GET_PHYSICAL_DEVICE_PROPERTIES2_EXTENSION.name.as_ptr());
-            extensions.push(vk::KHR_PORTABILITY_ENUMERATION_EXTENSION.name.as_ptr());
-            vk::InstanceCreateFlags::ENUMERATE_PORTABILITY_KHR
-        } else {
-            vk::InstanceCreateFlags::empty()
-        };
+        info!("Using present mode {:?}.", present_mode);
+        present_mode
+    }
 
-        if VALIDATION_ENABLED {
-            extensions.push(vk::EXT_DEBUG_UTILS_EXTENSION.name.as_ptr());
+    // one more than the minimum by default so we don't wait on the driver
+    fn get_swapchain_image_count(capabilities: vk::SurfaceCapabilitiesKHR, requested: Option<u32>) -> u32 {
+        let min = capabilities.min_image_count;
+        // zero means there is no maximum, it can also equal the minimum leaving no choice
+        let max = if capabilities.max_image_count == 0 { u32::MAX } else { capabilities.max_image_count };
+
+        let count = requested.unwrap_or(min.saturating_add(1));
+        let clamped = count.clamp(min, max.max(min));
+        if requested.is_some_and(|requested| requested != clamped) {
+            warn!("Swapchain image count ({}) is not supported, clamped to {}.", count, clamped);
         }
 
-        let mut info = vk::InstanceCreateInfo::builder()
-            .application_info(&application_info)
-            .enabled_layer_names(&layers)
-            .enabled_extension_names(&extensions)
-            .flags(flags);
+        clamped
+    }
 
-        // set up validation for create instance call if enabled
-        let mut debug_messenger: Option<DebugUtilsMessengerEXT> = None;
+    // the swapchain extent scaled and clamped to the largest image, unscaled if the result can't be blitted down
+    unsafe fn get_render_extent(
+        instance: &Instance,
+        phys_device: vk::PhysicalDevice,
+        surface: vk::SurfaceKHR,
+        format: vk::Format,
+        extent: vk::Extent2D,
+        scale: f32,
+    ) -> Result<vk::Extent2D> {
+        if scale == 1.0 {
+            return Ok(extent);
+        }
 
-        let mut debug_info = vk::DebugUtilsMessengerCreateInfoEXT::builder()
-                .message_severity(vk::DebugUtilsMessageSeverityFlagsEXT::all())
-                .message_type(vk::DebugUtilsMessageTypeFlagsEXT::all())
-                .user_callback(Some(debug_callback));
+        let max = instance.get_physical_device_properties(phys_device).limits.max_image_dimension_2d;
+        let scaled = |size: u32| ((size as f32 * scale).round() as u32).clamp(1, max);
+        let render_extent = vk::Extent2D { width: scaled(extent.width), height: scaled(extent.height) };
 
-        if VALIDATION_ENABLED {
-            info = info.push_next(&mut debug_info);
+        // headless apps make their own image to blit into
+        let features = instance.get_physical_device_format_properties(phys_device, format).optimal_tiling_features;
+        let blittable = features.contains(
+            vk::FormatFeatureFlags::BLIT_SRC
+                | vk::FormatFeatureFlags::BLIT_DST
+                | vk::FormatFeatureFlags::SAMPLED_IMAGE_FILTER_LINEAR,
+        );
+        let copyable = surface.is_null()
+            || SwapchainSupport::get(instance, surface, phys_device)?
+                .capabilities
+                .supported_usage_flags
+                .contains(vk::ImageUsageFlags::TRANSFER_DST);
+
+        if !blittable || !copyable {
+            warn!("Render scale ({}) is not supported with format ({:?}), rendering at window size.", scale, format);
+            return Ok(extent);
         }
 
-        let instance = entry.create_instance(&info, None)?;
+        Ok(render_extent)
+    }
 
-        // create debug messenger if validation is enabled
-        if VALIDATION_ENABLED {
-            debug_messenger = Some(instance.create_debug_utils_messenger_ext(&debug_info, None)?);
+    // ready to present, read back or blit down once rendering is done
+    fn get_final_layout(headless: bool, scaled: bool) -> vk::ImageLayout {
+        if headless || scaled {
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL
+        } else {
+            vk::ImageLayout::PRESENT_SRC_KHR
         }
-        
-        Ok((instance, debug_messenger))
     }
 
-    // used for GPU suitability
-    #[derive(Debug, Error)]
-    #[error("Missing {0}.")]
-    pub struct SuitabilityError(pub &'static str);
+    // the framebuffer has no area, so there's nothing to render to or make a swapchain for
+    pub fn is_minimized(window: &Window) -> bool {
+        let size = window.inner_size();
+        size.width == 0 || size.height == 0
+    }
 
-    unsafe fn choose_physical_device(instance: &Instance) -> Result<vk::PhysicalDevice> {
-        for phys_device in instance.enumerate_physical_devices()? {
-            let properties = instance.get_physical_device_properties(phys_device);
+    fn window_extent(window: &Window) -> vk::Extent2D {
+        let size = window.inner_size();
+        vk::Extent2D { width: size.width, height: size.height }
+    }
 
-            if let Err(error) = check_physical_device(instance, phys_device) {
-                warn!("Skipping physical device ({}): {}", properties.device_name, error)
-            } else {
-                info!("Selected physical device ({})", properties.device_name);
-                return Ok(phys_device);
-            }
+    // window_extent is only used when the surface leaves the size to the swapchain
+    fn get_swapchain_extent(window_extent: vk::Extent2D, capabilities: vk::SurfaceCapabilitiesKHR) -> vk::Extent2D {
+        // u32::MAX means the surface size is determined by the swapchain
+        if capabilities.current_extent.width != u32::MAX {
+            capabilities.current_extent
+        } else {
+            let size = window_extent;
+            vk::Extent2D::builder()
+                .width(size.width.clamp(
+                    capabilities.min_image_extent.width,
+                    capabilities.max_image_extent.width,
+                ))
+                .height(size.height.clamp(
+                    capabilities.min_image_extent.height,
+                    capabilities.max_image_extent.height,
+                ))
+                .build()
         }
-        
-        Err(anyhow!("Failed to find suitable physical device."))
     }
 
-    unsafe fn check_physical_device(
-        instance: &Instance,
-        phys_device: vk::PhysicalDevice
-    ) -> Result<()> {
-        QueueFamilyIndices::get(instance, phys_device)?;
+    /// no-op unless debug utils were enabled with validation
+    ///
+    /// # Safety
+    ///
+    /// `object` has to come from `device`, created from `instance`.
+    pub unsafe fn set_debug_name<T: vk::Handle>(instance: &Instance, device: &Device, object: T, name: &str) -> Result<()>
+    where
+        T::Repr: TryInto<u64>,
+    {
+        if !instance.extensions().contains(&vk::EXT_DEBUG_UTILS_EXTENSION.name) {
+            return Ok(());
+        }
+
+        // dispatchable handles are pointers, the rest are already u64
+        let handle = object
+            .as_raw()
+            .try_into()
+            .map_err(|_| anyhow!("Handle for ({}) does not fit in 64 bits.", name))?;
+
+        let name = CString::new(name)?;
+        let info = vk::DebugUtilsObjectNameInfoEXT::builder()
+            .object_type(T::TYPE)
+            .object_handle(handle)
+            .object_name(name.as_bytes_with_nul());
+
+        instance.set_debug_utils_object_name_ext(device.handle(), &info)?;
+
         Ok(())
     }
 
+    /// labeled regions for renderdoc and friends, compiled out without validation
+    ///
+    /// # Safety
+    ///
+    /// `command_buffer` has to be recording, and the label ended in it with cmd_end_debug_label.
+    #[inline]
+    pub unsafe fn cmd_begin_debug_label(
+        instance: &Instance,
+        command_buffer: vk::CommandBuffer,
+        label: &str,
+        color: [f32; 4],
+    ) {
+        if VALIDATION_ENABLED && instance.extensions().contains(&vk::EXT_DEBUG_UTILS_EXTENSION.name) {
+            // labels are only for debugging, drop any interior nul rather than fail
+            let label = CString::new(label.replace('\0', "")).unwrap_or_default();
+            let info = vk::DebugUtilsLabelEXT::builder()
+                .label_name(label.as_bytes_with_nul())
+                .color(color);
+
+            instance.cmd_begin_debug_utils_label_ext(command_buffer, &info);
+        }
+    }
+
+    /// # Safety
+    ///
+    /// `command_buffer` has to have a label open from cmd_begin_debug_label.
+    #[inline]
+    pub unsafe fn cmd_end_debug_label(instance: &Instance, command_buffer: vk::CommandBuffer) {
+        if VALIDATION_ENABLED && instance.extensions().contains(&vk::EXT_DEBUG_UTILS_EXTENSION.name) {
+            instance.cmd_end_debug_utils_label_ext(command_buffer);
+        }
+    }
+
     // debug callback for validation layer
     extern "system" fn debug_callback(
         severity: vk::DebugUtilsMessageSeverityFlagsEXT,
@@ -188,6 +8036,11 @@ pub mod app {
         data: *const vk::DebugUtilsMessengerCallbackDataEXT,
         _: *mut c_void,
     ) -> vk::Bool32 {
+        // the messenger should already filter these, but be safe
+        if !VALIDATION_TYPE_FLAGS.intersects(type_) || !VALIDATION_MIN_SEVERITY.flags().intersects(severity) {
+            return vk::FALSE;
+        }
+
         let data = unsafe { *data };
         let message = unsafe { CStr::from_ptr(data.message) }.to_string_lossy();
 
@@ -205,20 +8058,45 @@ pub mod app {
     }
     
     pub mod data {
-        use super::SuitabilityError;
-        use vulkanalia::{Instance, vk, prelude::v1_0::*};
-        use anyhow::{Result, anyhow};
+        use super::{Allocation, DeviceError};
+        use vulkanalia::{Instance, vk, prelude::v1_0::*, vk::KhrSurfaceExtension};
+        use cgmath::{
+            vec3, vec4, Angle, Deg, EuclideanSpace, InnerSpace, Matrix, Matrix4, Point3, SquareMatrix, Vector2, Vector3,
+            Vector4,
+        };
+        use std::{
+            hash::{Hash, Hasher},
+            mem::size_of,
+            time::Duration,
+        };
+
+        pub type Vec2 = Vector2<f32>;
+        pub type Vec3 = Vector3<f32>;
+        pub type Vec4 = Vector4<f32>;
+        pub type Mat4 = Matrix4<f32>;
 
+        // graphics and present may well be the same family, uploads go through graphics without a transfer family
         #[derive(Copy, Clone, Debug)]
         pub struct QueueFamilyIndices {
             pub graphics: u32,
+            // equal to graphics when headless
+            pub present: u32,
+            pub compute: Option<u32>,
+            pub transfer: Option<u32>,
         }
 
         impl QueueFamilyIndices {
+            /// present has to be able to present to every surface, null surfaces are ignored and with none left it's
+            /// headless, windows added later are checked against the family picked here since the device can't change
+            ///
+            /// # Safety
+            ///
+            /// `phys_device` and the surfaces have to come from `instance`.
             pub unsafe fn get(
                 instance: &Instance,
+                surfaces: &[vk::SurfaceKHR],
                 phys_device: vk::PhysicalDevice,
-            ) -> Result<Self> {
+            ) -> Result<Self, DeviceError> {
                 let properties = instance
                     .get_physical_device_queue_family_properties(phys_device);
 
@@ -227,11 +8105,627 @@ pub mod app {
                     .position(|p| p.queue_flags.contains(vk::QueueFlags::GRAPHICS))
                     .map(|i| i as u32);
 
-                if let Some(graphics) = graphics {
-                    Ok(Self{ graphics })
+                // prefer a dedicated compute family, graphics families always support compute
+                let compute = properties
+                    .iter()
+                    .position(|p| {
+                        p.queue_flags.contains(vk::QueueFlags::COMPUTE)
+                            && !p.queue_flags.contains(vk::QueueFlags::GRAPHICS)
+                    })
+                    .map(|i| i as u32)
+                    .or(graphics);
+
+                // prefer a family that only does transfers, then any without graphics
+                let transfer_only = properties
+                    .iter()
+                    .position(|p| {
+                        p.queue_flags.contains(vk::QueueFlags::TRANSFER)
+                            && !p.queue_flags.intersects(vk::QueueFlags::GRAPHICS | vk::QueueFlags::COMPUTE)
+                    });
+                let transfer = transfer_only
+                    .or_else(|| {
+                        properties.iter().position(|p| {
+                            p.queue_flags.contains(vk::QueueFlags::TRANSFER)
+                                && !p.queue_flags.contains(vk::QueueFlags::GRAPHICS)
+                        })
+                    })
+                    .map(|i| i as u32);
+
+                // headless never presents, so graphics stands in for present
+                let surfaces = surfaces.iter().filter(|s| !s.is_null()).copied().collect::<Vec<_>>();
+                if surfaces.is_empty() {
+                    return graphics
+                        .map(|graphics| Self { graphics, present: graphics, compute, transfer })
+                        .ok_or(DeviceError::MissingQueueFamily);
+                }
+
+                // find a family that can present to all of the surfaces
+                let mut present = None;
+                'families: for (index, _) in properties.iter().enumerate() {
+                    for surface in &surfaces {
+                        if !instance.get_physical_device_surface_support_khr(phys_device, index as u32, *surface)? {
+                            continue 'families;
+                        }
+                    }
+
+                    present = Some(index as u32);
+                    break;
+                }
+
+                if let (Some(graphics), Some(present)) = (graphics, present) {
+                    Ok(Self{ graphics, present, compute, transfer })
                 } else {
-                    Err(anyhow!(SuitabilityError("Missing required queue families.")))
+                    Err(DeviceError::MissingQueueFamily)
+                }
+            }
+        }
+
+        // where uploads are recorded, and which queue takes ownership of the results
+        #[derive(Copy, Clone, Debug)]
+        pub struct Uploader {
+            pub transfer_family: u32,
+            pub transfer_pool: vk::CommandPool,
+            pub transfer_queue: vk::Queue,
+            pub graphics_family: u32,
+            pub graphics_pool: vk::CommandPool,
+            pub graphics_queue: vk::Queue,
+        }
+
+        impl Uploader {
+            pub fn needs_ownership_transfer(&self) -> bool {
+                self.transfer_family != self.graphics_family
+            }
+        }
+
+        #[derive(Clone, Debug)]
+        pub struct SwapchainSupport {
+            pub capabilities: vk::SurfaceCapabilitiesKHR,
+            pub formats: Vec<vk::SurfaceFormatKHR>,
+            pub present_modes: Vec<vk::PresentModeKHR>,
+        }
+
+        impl SwapchainSupport {
+            /// # Safety
+            ///
+            /// `phys_device` and `surface` have to come from `instance`.
+            pub unsafe fn get(
+                instance: &Instance,
+                surface: vk::SurfaceKHR,
+                phys_device: vk::PhysicalDevice,
+            ) -> Result<Self, vk::ErrorCode> {
+                Ok(Self {
+                    capabilities: instance
+                        .get_physical_device_surface_capabilities_khr(phys_device, surface)?,
+                    formats: instance
+                        .get_physical_device_surface_formats_khr(phys_device, surface)?,
+                    present_modes: instance
+                        .get_physical_device_surface_present_modes_khr(phys_device, surface)?,
+                })
+            }
+        }
+
+        // from App::probe_capabilities, the optional parts of the config each one decides
+        #[derive(Clone, Debug)]
+        pub struct DeviceCapabilities {
+            pub name: String,
+            pub api_version: u32,
+            // usable for both color and depth
+            pub max_msaa: vk::SampleCountFlags,
+            pub supports_anisotropy: bool,
+            pub max_anisotropy: f32,
+            pub supports_geometry_shader: bool,
+            pub supports_wide_lines: bool,
+            // the largest 2d image either side
+            pub max_texture_size: u32,
+            pub supports_dynamic_rendering: bool,
+            // with everything the bindless textures need, see AppBuilder::bindless
+            pub supports_descriptor_indexing: bool,
+            pub supports_buffer_device_address: bool,
+            pub supports_timeline_semaphores: bool,
+        }
+
+        // a mesh drawn with its transform from the dynamic object uniform buffer, its texture index is a push constant
+        #[derive(Copy, Clone, Debug)]
+        pub struct RenderObject {
+            pub vertex_buffer: vk::Buffer,
+            pub vertex_buffer_memory: Allocation,
+            pub index_buffer: vk::Buffer,
+            pub index_buffer_memory: Allocation,
+            pub index_type: vk::IndexType,
+            pub index_count: u32,
+            // in the mesh's own space, before the transform
+            pub bounds: Aabb,
+            pub transform: Mat4,
+            pub blend_mode: BlendMode,
+            // a layer of the texture array, clamped to the last, or a slot in the bindless textures when in use
+            pub texture_index: u32,
+        }
+
+        impl RenderObject {
+            pub fn world_bounds(&self) -> Aabb {
+                self.bounds.transformed(self.transform)
+            }
+        }
+
+        // axis aligned bounds, an empty mesh's are inverted and never visible
+        #[derive(Copy, Clone, Debug, PartialEq)]
+        pub struct Aabb {
+            pub min: Vec3,
+            pub max: Vec3,
+        }
+
+        impl Default for Aabb {
+            fn default() -> Self {
+                Self { min: vec3(f32::MAX, f32::MAX, f32::MAX), max: vec3(f32::MIN, f32::MIN, f32::MIN) }
+            }
+        }
+
+        impl Aabb {
+            pub fn from_vertices(vertices: &[Vertex]) -> Self {
+                vertices.iter().fold(Self::default(), |bounds, v| bounds.including(v.pos))
+            }
+
+            pub fn including(self, point: Vec3) -> Self {
+                Self {
+                    min: vec3(self.min.x.min(point.x), self.min.y.min(point.y), self.min.z.min(point.z)),
+                    max: vec3(self.max.x.max(point.x), self.max.y.max(point.y), self.max.z.max(point.z)),
+                }
+            }
+
+            pub fn corners(&self) -> [Vec3; 8] {
+                std::array::from_fn(|i| {
+                    vec3(
+                        if i & 1 == 0 { self.min.x } else { self.max.x },
+                        if i & 2 == 0 { self.min.y } else { self.max.y },
+                        if i & 4 == 0 { self.min.z } else { self.max.z },
+                    )
+                })
+            }
+
+            // the bounds of the transformed corners, looser than the mesh's own bounds after rotating
+            pub fn transformed(&self, transform: Mat4) -> Self {
+                if self.min.x > self.max.x {
+                    return *self;
                 }
+
+                self.corners()
+                    .iter()
+                    .fold(Self::default(), |bounds, c| bounds.including((transform * c.extend(1.0)).truncate()))
+            }
+        }
+
+        // planes facing inwards as (normal, distance), a point is inside when it's in front of all six
+        #[derive(Copy, Clone, Debug)]
+        pub struct Frustum {
+            pub planes: [Vec4; 6],
+        }
+
+        impl Frustum {
+            // for a vulkan projection with depth from 0 to 1
+            pub fn from_view_proj(view_proj: Mat4) -> Self {
+                let row = |i: usize| vec4(view_proj.x[i], view_proj.y[i], view_proj.z[i], view_proj.w[i]);
+                let (x, y, z, w) = (row(0), row(1), row(2), row(3));
+
+                Self { planes: [w + x, w - x, w + y, w - y, z, w - z] }
+            }
+
+            // conservative, a box near a corner can pass while still being outside
+            pub fn intersects(&self, bounds: &Aabb) -> bool {
+                if bounds.min.x > bounds.max.x {
+                    return false;
+                }
+
+                // only the corner furthest along each plane's normal has to be checked
+                self.planes.iter().all(|plane| {
+                    let corner = vec3(
+                        if plane.x >= 0.0 { bounds.max.x } else { bounds.min.x },
+                        if plane.y >= 0.0 { bounds.max.y } else { bounds.min.y },
+                        if plane.z >= 0.0 { bounds.max.z } else { bounds.min.z },
+                    );
+                    plane.truncate().dot(corner) + plane.w >= 0.0
+                })
+            }
+        }
+
+        // how an object's color combines with what's already drawn, anything but opaque is drawn last
+        #[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+        pub enum BlendMode {
+            #[default]
+            Opaque,
+            AlphaBlend,
+            Additive,
+        }
+
+        impl BlendMode {
+            pub fn attachment(self) -> vk::PipelineColorBlendAttachmentState {
+                let attachment = vk::PipelineColorBlendAttachmentState::builder()
+                    .color_write_mask(vk::ColorComponentFlags::all())
+                    .color_blend_op(vk::BlendOp::ADD)
+                    .alpha_blend_op(vk::BlendOp::ADD);
+
+                match self {
+                    Self::Opaque => attachment.blend_enable(false),
+                    // over what's behind it by the source alpha
+                    Self::AlphaBlend => attachment
+                        .blend_enable(true)
+                        .src_color_blend_factor(vk::BlendFactor::SRC_ALPHA)
+                        .dst_color_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+                        .src_alpha_blend_factor(vk::BlendFactor::ONE)
+                        .dst_alpha_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA),
+                    // adds light, the alpha already there is kept
+                    Self::Additive => attachment
+                        .blend_enable(true)
+                        .src_color_blend_factor(vk::BlendFactor::SRC_ALPHA)
+                        .dst_color_blend_factor(vk::BlendFactor::ONE)
+                        .src_alpha_blend_factor(vk::BlendFactor::ZERO)
+                        .dst_alpha_blend_factor(vk::BlendFactor::ONE),
+                }
+                .build()
+            }
+        }
+
+        // first person camera, z is up and yaw is measured from +x towards +y
+        #[derive(Copy, Clone, Debug)]
+        pub struct Camera {
+            pub position: Vec3,
+            pub yaw: Deg<f32>,
+            pub pitch: Deg<f32>,
+            // world units per second
+            pub speed: f32,
+            // degrees per unit of mouse motion
+            pub sensitivity: f32,
+        }
+
+        impl Default for Camera {
+            // same view as the old fixed camera, looking at the origin from (2, 2, 2)
+            fn default() -> Self {
+                Self {
+                    position: vec3(2.0, 2.0, 2.0),
+                    yaw: Deg(-135.0),
+                    pitch: Deg(-35.26),
+                    speed: 2.0,
+                    sensitivity: 0.1,
+                }
+            }
+        }
+
+        impl Camera {
+            pub fn forward(&self) -> Vec3 {
+                let (yaw_sin, yaw_cos) = self.yaw.sin_cos();
+                let (pitch_sin, pitch_cos) = self.pitch.sin_cos();
+                vec3(yaw_cos * pitch_cos, yaw_sin * pitch_cos, pitch_sin)
+            }
+
+            pub fn right(&self) -> Vec3 {
+                self.forward().cross(Vec3::unit_z()).normalize()
+            }
+
+            pub fn view_matrix(&self) -> Mat4 {
+                Mat4::look_to_rh(Point3::from_vec(self.position), self.forward(), Vec3::unit_z())
+            }
+
+            // direction is (right, forward, up) relative to the camera
+            pub fn translate(&mut self, direction: Vec3, delta: Duration) {
+                if direction.magnitude2() == 0.0 {
+                    return;
+                }
+
+                let step = direction.normalize() * self.speed * delta.as_secs_f32();
+                self.position += self.right() * step.x + self.forward() * step.y + Vec3::unit_z() * step.z;
+            }
+
+            // clamp pitch so the view never flips over the up axis
+            pub fn rotate(&mut self, dx: f32, dy: f32) {
+                self.yaw -= Deg(dx * self.sensitivity);
+                self.pitch = Deg((self.pitch.0 - dy * self.sensitivity).clamp(-89.0, 89.0));
+            }
+        }
+
+        #[repr(C)]
+        #[derive(Copy, Clone, Debug)]
+        pub struct UniformBufferObject {
+            pub view: Mat4,
+            pub proj: Mat4,
+        }
+
+        // what each object's dynamic offset points at
+        #[repr(C)]
+        #[derive(Copy, Clone, Debug)]
+        pub struct ObjectUniform {
+            pub model: Mat4,
+            // inverse transpose of the model matrix for the normals, a mat4 to match std140's padded mat3 columns
+            pub normal: Mat4,
+        }
+
+        impl ObjectUniform {
+            pub fn new(model: Mat4) -> Self {
+                let normal = model.invert().map_or(Mat4::identity(), |m| m.transpose());
+                Self { model, normal }
+            }
+        }
+
+        // a directional light shining along its direction, blinn-phong with a fixed shininess
+        #[derive(Copy, Clone, Debug)]
+        pub struct Light {
+            pub direction: Vec3,
+            pub color: Vec3,
+            pub ambient: Vec3,
+        }
+
+        // from above and a little to the side
+        impl Default for Light {
+            fn default() -> Self {
+                Self {
+                    direction: vec3(0.3, 0.2, -1.0),
+                    color: vec3(1.0, 1.0, 1.0),
+                    ambient: vec3(0.2, 0.2, 0.2),
+                }
+            }
+        }
+
+        // std140 pads every vec3 to a vec4 so they're stored as vec4s
+        #[repr(C)]
+        #[derive(Copy, Clone, Debug)]
+        pub struct LightUniform {
+            pub direction: Vec4,
+            pub color: Vec4,
+            pub ambient: Vec4,
+            pub camera_position: Vec4,
+            // world to the shadow map's clip space
+            pub view_proj: Mat4,
+            // 0 when the shadow map isn't drawn, so it's not sampled
+            pub shadows: u32,
+            pub _padding: [u32; 3],
+        }
+
+        // index types that can be bound as an index buffer
+        pub trait Index: Copy {
+            const INDEX_TYPE: vk::IndexType;
+        }
+
+        impl Index for u16 {
+            const INDEX_TYPE: vk::IndexType = vk::IndexType::UINT16;
+        }
+
+        impl Index for u32 {
+            const INDEX_TYPE: vk::IndexType = vk::IndexType::UINT32;
+        }
+
+        // applied by the post-processing subpass, values match post.frag
+        #[repr(u32)]
+        #[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+        pub enum PostEffect {
+            #[default]
+            None = 0,
+            Invert = 1,
+            Grayscale = 2,
+        }
+
+        impl PostEffect {
+            pub fn next(self) -> Self {
+                match self {
+                    Self::None => Self::Invert,
+                    Self::Invert => Self::Grayscale,
+                    Self::Grayscale => Self::None,
+                }
+            }
+        }
+
+        // pushed to the post pass, matches post.frag
+        #[repr(C)]
+        #[derive(Copy, Clone, Debug, Default)]
+        pub struct PostConstants {
+            pub effect: u32,
+            // 0 when the scene isn't tonemapped
+            pub tonemap: u32,
+            pub exposure: f32,
+            // non-zero to encode to srgb in the shader
+            pub gamma: u32,
+            // 0 without bloom
+            pub bloom_intensity: f32,
+        }
+
+        // what a pipeline draws into, a subpass of the render pass or attachments of these formats
+        #[derive(Copy, Clone, Debug)]
+        pub enum PipelineTarget {
+            Subpass(vk::RenderPass, u32),
+            Dynamic { color_formats: [vk::Format; 1], depth_format: vk::Format },
+        }
+
+        impl PipelineTarget {
+            pub fn render_pass(&self) -> vk::RenderPass {
+                match self {
+                    Self::Subpass(render_pass, _) => *render_pass,
+                    Self::Dynamic { .. } => vk::RenderPass::null(),
+                }
+            }
+
+            pub fn subpass(&self) -> u32 {
+                match self {
+                    Self::Subpass(_, subpass) => *subpass,
+                    Self::Dynamic { .. } => 0,
+                }
+            }
+
+            // chained onto the pipeline info in place of a render pass
+            pub fn rendering_info(&self) -> Option<vk::PipelineRenderingCreateInfoBuilder<'_>> {
+                match self {
+                    Self::Subpass(..) => None,
+                    Self::Dynamic { color_formats, depth_format } => Some(
+                        vk::PipelineRenderingCreateInfo::builder()
+                            .color_attachment_formats(color_formats)
+                            .depth_attachment_format(*depth_format)
+                            .stencil_attachment_format(*depth_format),
+                    ),
+                }
+            }
+
+            // secondaries recorded for dynamic rendering inherit the formats instead of a subpass
+            pub fn inheritance_rendering_info(
+                &self,
+                samples: vk::SampleCountFlags,
+            ) -> Option<vk::CommandBufferInheritanceRenderingInfoBuilder<'_>> {
+                match self {
+                    Self::Subpass(..) => None,
+                    Self::Dynamic { color_formats, depth_format } => Some(
+                        vk::CommandBufferInheritanceRenderingInfo::builder()
+                            .color_attachment_formats(color_formats)
+                            .depth_attachment_format(*depth_format)
+                            .stencil_attachment_format(*depth_format)
+                            .rasterization_samples(samples),
+                    ),
+                }
+            }
+        }
+
+        #[repr(C)]
+        #[derive(Copy, Clone, Debug)]
+        pub struct Vertex {
+            pub pos: Vec3,
+            pub color: Vec3,
+            pub tex_coord: Vec2,
+            // zero when the mesh has none
+            pub normal: Vec3,
+            // w is the bitangent's handedness, zero until compute_tangents fills it in and then wherever it can't
+            pub tangent: Vec4,
+        }
+
+        impl Vertex {
+            pub const fn new(pos: Vec3, color: Vec3, tex_coord: Vec2, normal: Vec3) -> Self {
+                Self { pos, color, tex_coord, normal, tangent: Vec4::new(0.0, 0.0, 0.0, 0.0) }
+            }
+
+            pub fn binding_description() -> vk::VertexInputBindingDescription {
+                vk::VertexInputBindingDescription::builder()
+                    .binding(0)
+                    .stride(size_of::<Vertex>() as u32)
+                    .input_rate(vk::VertexInputRate::VERTEX)
+                    .build()
+            }
+
+            // the normal and tangent come after the instance attributes
+            pub fn attribute_descriptions() -> [vk::VertexInputAttributeDescription; 5] {
+                let pos = vk::VertexInputAttributeDescription::builder()
+                    .binding(0)
+                    .location(0)
+                    .format(vk::Format::R32G32B32_SFLOAT)
+                    .offset(0)
+                    .build();
+
+                let color = vk::VertexInputAttributeDescription::builder()
+                    .binding(0)
+                    .location(1)
+                    .format(vk::Format::R32G32B32_SFLOAT)
+                    .offset(size_of::<Vec3>() as u32)
+                    .build();
+
+                let tex_coord = vk::VertexInputAttributeDescription::builder()
+                    .binding(0)
+                    .location(2)
+                    .format(vk::Format::R32G32_SFLOAT)
+                    .offset((size_of::<Vec3>() + size_of::<Vec3>()) as u32)
+                    .build();
+
+                let normal = vk::VertexInputAttributeDescription::builder()
+                    .binding(0)
+                    .location(8)
+                    .format(vk::Format::R32G32B32_SFLOAT)
+                    .offset((size_of::<Vec3>() + size_of::<Vec3>() + size_of::<Vec2>()) as u32)
+                    .build();
+
+                let tangent = vk::VertexInputAttributeDescription::builder()
+                    .binding(0)
+                    .location(9)
+                    .format(vk::Format::R32G32B32A32_SFLOAT)
+                    .offset((size_of::<Vec3>() * 3 + size_of::<Vec2>()) as u32)
+                    .build();
+
+                [pos, color, tex_coord, normal, tangent]
+            }
+        }
+
+        // per instance vertex data, the transform takes up four attribute locations
+        #[repr(C)]
+        #[derive(Copy, Clone, Debug)]
+        pub struct InstanceData {
+            pub transform: Mat4,
+            pub color: Vec3,
+        }
+
+        impl Default for InstanceData {
+            fn default() -> Self {
+                Self {
+                    transform: Mat4::identity(),
+                    color: vec3(1.0, 1.0, 1.0),
+                }
+            }
+        }
+
+        impl InstanceData {
+            pub fn binding_description() -> vk::VertexInputBindingDescription {
+                vk::VertexInputBindingDescription::builder()
+                    .binding(1)
+                    .stride(size_of::<InstanceData>() as u32)
+                    .input_rate(vk::VertexInputRate::INSTANCE)
+                    .build()
+            }
+
+            pub fn attribute_descriptions() -> [vk::VertexInputAttributeDescription; 5] {
+                let column = |i: u32| {
+                    vk::VertexInputAttributeDescription::builder()
+                        .binding(1)
+                        .location(3 + i)
+                        .format(vk::Format::R32G32B32A32_SFLOAT)
+                        .offset(i * size_of::<Vec4>() as u32)
+                        .build()
+                };
+
+                let color = vk::VertexInputAttributeDescription::builder()
+                    .binding(1)
+                    .location(7)
+                    .format(vk::Format::R32G32B32_SFLOAT)
+                    .offset(size_of::<Mat4>() as u32)
+                    .build();
+
+                [column(0), column(1), column(2), column(3), color]
+            }
+        }
+
+        impl Vertex {
+            // what equality and hashing both go by, so 0.0 and -0.0 differ and NaN equals itself
+            fn bits(&self) -> [u32; 15] {
+                [
+                    self.pos[0].to_bits(),
+                    self.pos[1].to_bits(),
+                    self.pos[2].to_bits(),
+                    self.color[0].to_bits(),
+                    self.color[1].to_bits(),
+                    self.color[2].to_bits(),
+                    self.tex_coord[0].to_bits(),
+                    self.tex_coord[1].to_bits(),
+                    self.normal[0].to_bits(),
+                    self.normal[1].to_bits(),
+                    self.normal[2].to_bits(),
+                    self.tangent[0].to_bits(),
+                    self.tangent[1].to_bits(),
+                    self.tangent[2].to_bits(),
+                    self.tangent[3].to_bits(),
+                ]
+            }
+        }
+
+        // floats aren't Eq/Hash, compare the bits so vertices can be deduplicated
+        impl PartialEq for Vertex {
+            fn eq(&self, other: &Self) -> bool {
+                self.bits() == other.bits()
+            }
+        }
+
+        impl Eq for Vertex {}
+
+        impl Hash for Vertex {
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                self.bits().hash(state);
             }
         }
 
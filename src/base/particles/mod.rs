@@ -0,0 +1,489 @@
+use super::allocator::{Allocation, Allocator};
+use super::app::data::{Mat4, PipelineTarget, Uploader};
+use super::app::{create_shader_module, create_device_local_buffer, set_debug_name};
+use super::shaders;
+
+use anyhow::Result;
+
+use vulkanalia::{prelude::v1_0::*, Instance};
+
+use std::{mem::size_of, time::Duration};
+
+// has to match local_size_x in particles.comp
+const WORKGROUP_SIZE: u32 = 256;
+
+// a long frame would throw everything through the walls
+const MAX_DELTA: f32 = 0.05;
+
+// std430 layout, so every member is padded out to a vec4
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Particle {
+    pub position: [f32; 4],
+    pub velocity: [f32; 4],
+    pub color: [f32; 4],
+}
+
+// the view projection for the vertex shader followed by the point size
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+struct DrawConstants {
+    view_proj: Mat4,
+    point_size: f32,
+}
+
+// points moved by a compute shader each frame, then drawn straight from the same storage buffer
+#[derive(Debug)]
+pub struct Particles {
+    count: u32,
+    buffer: vk::Buffer,
+    buffer_memory: Allocation,
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    descriptor_pool: vk::DescriptorPool,
+    descriptor_set: vk::DescriptorSet,
+    compute_pipeline_layout: vk::PipelineLayout,
+    compute_pipeline: vk::Pipeline,
+    pipeline_layout: vk::PipelineLayout,
+    pipeline: vk::Pipeline,
+    point_size: f32,
+    // the far plane is at 0 instead of 1
+    reverse_z: bool,
+}
+
+impl Particles {
+    /// # Safety
+    ///
+    /// The handles passed in have to be valid and from one device, which has to outlive what's made here.
+    #[allow(clippy::too_many_arguments)]
+    pub unsafe fn new(
+        instance: &Instance,
+        device: &Device,
+        phys_device: vk::PhysicalDevice,
+        allocator: &mut Allocator,
+        uploader: &Uploader,
+        pipeline_cache: vk::PipelineCache,
+        target: PipelineTarget,
+        samples: vk::SampleCountFlags,
+        reverse_z: bool,
+        point_size: f32,
+        particles: &[Particle],
+    ) -> Result<Self> {
+        let (buffer, buffer_memory) = create_device_local_buffer(
+            instance,
+            device,
+            phys_device,
+            allocator,
+            uploader,
+            particles,
+            vk::BufferUsageFlags::VERTEX_BUFFER | vk::BufferUsageFlags::STORAGE_BUFFER,
+            "particles",
+        )?;
+
+        let descriptor_set_layout = create_descriptor_set_layout(device)?;
+        let (descriptor_pool, descriptor_set) = create_descriptor_set(device, descriptor_set_layout, buffer)?;
+        let (compute_pipeline_layout, compute_pipeline) =
+            create_compute_pipeline(device, pipeline_cache, descriptor_set_layout)?;
+        let (pipeline_layout, pipeline) = create_pipeline(device, pipeline_cache, target, samples, reverse_z)?;
+
+        Ok(Self {
+            count: particles.len() as u32,
+            buffer,
+            buffer_memory,
+            descriptor_set_layout,
+            descriptor_pool,
+            descriptor_set,
+            compute_pipeline_layout,
+            compute_pipeline,
+            pipeline_layout,
+            pipeline,
+            point_size,
+            reverse_z,
+        })
+    }
+
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    // the storage buffer the compute shader writes and the vertex input reads
+    pub fn buffer(&self) -> vk::Buffer {
+        self.buffer
+    }
+
+    /// records outside any render pass, before the scene that draws the particles
+    ///
+    /// # Safety
+    ///
+    /// `command_buffer` has to be recording outside any rendering.
+    pub unsafe fn record_update(&self, device: &Device, command_buffer: vk::CommandBuffer, delta: Duration) {
+        // the last frame's draw has to be done reading before this one's writes start
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::VERTEX_INPUT,
+            vk::PipelineStageFlags::COMPUTE_SHADER,
+            vk::DependencyFlags::empty(),
+            &[] as &[vk::MemoryBarrier],
+            &[] as &[vk::BufferMemoryBarrier],
+            &[] as &[vk::ImageMemoryBarrier],
+        );
+
+        device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::COMPUTE, self.compute_pipeline);
+        device.cmd_bind_descriptor_sets(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            self.compute_pipeline_layout,
+            0,
+            &[self.descriptor_set],
+            &[],
+        );
+        let delta = delta.as_secs_f32().min(MAX_DELTA);
+        device.cmd_push_constants(
+            command_buffer,
+            self.compute_pipeline_layout,
+            vk::ShaderStageFlags::COMPUTE,
+            0,
+            &delta.to_ne_bytes(),
+        );
+        device.cmd_dispatch(command_buffer, self.count.div_ceil(WORKGROUP_SIZE), 1, 1);
+
+        // the new positions are read as vertices
+        let barrier = vk::BufferMemoryBarrier::builder()
+            .src_access_mask(vk::AccessFlags::SHADER_WRITE)
+            .dst_access_mask(vk::AccessFlags::VERTEX_ATTRIBUTE_READ)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .buffer(self.buffer)
+            .offset(0)
+            .size(vk::WHOLE_SIZE as u64);
+
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::COMPUTE_SHADER,
+            vk::PipelineStageFlags::VERTEX_INPUT,
+            vk::DependencyFlags::empty(),
+            &[] as &[vk::MemoryBarrier],
+            &[barrier],
+            &[] as &[vk::ImageMemoryBarrier],
+        );
+    }
+
+    /// records inside the scene subpass
+    ///
+    /// # Safety
+    ///
+    /// `command_buffer` has to be recording inside the scene rendering.
+    pub unsafe fn record(&self, device: &Device, command_buffer: vk::CommandBuffer, view_proj: Mat4) {
+        let constants = DrawConstants { view_proj, point_size: self.point_size };
+
+        device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::GRAPHICS, self.pipeline);
+        device.cmd_bind_vertex_buffers(command_buffer, 0, &[self.buffer], &[0]);
+        device.cmd_push_constants(
+            command_buffer,
+            self.pipeline_layout,
+            vk::ShaderStageFlags::VERTEX,
+            0,
+            std::slice::from_raw_parts((&constants as *const DrawConstants).cast(), size_of::<DrawConstants>()),
+        );
+        device.cmd_draw(command_buffer, self.count, 1, 0, 0);
+    }
+
+    /// the compute pipeline doesn't depend on the render target, so only the draw is rebuilt with the render pass
+    ///
+    /// # Safety
+    ///
+    /// The old pipeline can't be in use by a pending command buffer.
+    pub unsafe fn recreate_pipeline(
+        &mut self,
+        device: &Device,
+        pipeline_cache: vk::PipelineCache,
+        target: PipelineTarget,
+        samples: vk::SampleCountFlags,
+    ) -> Result<()> {
+        device.destroy_pipeline(self.pipeline, None);
+        device.destroy_pipeline_layout(self.pipeline_layout, None);
+        (self.pipeline_layout, self.pipeline) =
+            create_pipeline(device, pipeline_cache, target, samples, self.reverse_z)?;
+
+        Ok(())
+    }
+
+    /// for shader reloads, the old pipeline is kept if the new one fails
+    ///
+    /// # Safety
+    ///
+    /// The old pipeline can't be in use by a pending command buffer.
+    pub unsafe fn recreate_compute_pipeline(&mut self, device: &Device, pipeline_cache: vk::PipelineCache) -> Result<()> {
+        let (layout, pipeline) = create_compute_pipeline(device, pipeline_cache, self.descriptor_set_layout)?;
+        device.destroy_pipeline(self.compute_pipeline, None);
+        device.destroy_pipeline_layout(self.compute_pipeline_layout, None);
+        (self.compute_pipeline_layout, self.compute_pipeline) = (layout, pipeline);
+
+        Ok(())
+    }
+
+    /// # Safety
+    ///
+    /// Nothing made here can still be in use on the gpu, and none of it can be used after.
+    pub unsafe fn destroy(&mut self, device: &Device, allocator: &mut Allocator) {
+        device.destroy_pipeline(self.pipeline, None);
+        device.destroy_pipeline_layout(self.pipeline_layout, None);
+        device.destroy_pipeline(self.compute_pipeline, None);
+        device.destroy_pipeline_layout(self.compute_pipeline_layout, None);
+        device.destroy_descriptor_pool(self.descriptor_pool, None);
+        device.destroy_descriptor_set_layout(self.descriptor_set_layout, None);
+        device.destroy_buffer(self.buffer, None);
+        allocator.free(device, self.buffer_memory);
+    }
+
+    /// # Safety
+    ///
+    /// `device` has to be the one these were made with, created from `instance`.
+    pub unsafe fn set_debug_names(&self, instance: &Instance, device: &Device) -> Result<()> {
+        set_debug_name(instance, device, self.buffer, "particles")?;
+        set_debug_name(instance, device, self.compute_pipeline, "particles-compute-pipeline")?;
+        set_debug_name(instance, device, self.pipeline, "particles-pipeline")
+    }
+}
+
+unsafe fn create_descriptor_set_layout(device: &Device) -> Result<vk::DescriptorSetLayout> {
+    let storage_binding = vk::DescriptorSetLayoutBinding::builder()
+        .binding(0)
+        .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+        .descriptor_count(1)
+        .stage_flags(vk::ShaderStageFlags::COMPUTE);
+
+    let bindings = &[storage_binding];
+    let info = vk::DescriptorSetLayoutCreateInfo::builder()
+        .bindings(bindings);
+
+    Ok(device.create_descriptor_set_layout(&info, None)?)
+}
+
+unsafe fn create_descriptor_set(
+    device: &Device,
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    buffer: vk::Buffer,
+) -> Result<(vk::DescriptorPool, vk::DescriptorSet)> {
+    let storage_size = vk::DescriptorPoolSize::builder()
+        .type_(vk::DescriptorType::STORAGE_BUFFER)
+        .descriptor_count(1);
+
+    let pool_sizes = &[storage_size];
+    let info = vk::DescriptorPoolCreateInfo::builder()
+        .pool_sizes(pool_sizes)
+        .max_sets(1);
+    let descriptor_pool = device.create_descriptor_pool(&info, None)?;
+
+    let layouts = &[descriptor_set_layout];
+    let info = vk::DescriptorSetAllocateInfo::builder()
+        .descriptor_pool(descriptor_pool)
+        .set_layouts(layouts);
+    let descriptor_set = device.allocate_descriptor_sets(&info)?[0];
+
+    let info = vk::DescriptorBufferInfo::builder()
+        .buffer(buffer)
+        .offset(0)
+        .range(vk::WHOLE_SIZE as u64);
+
+    let buffer_info = &[info];
+    let storage_write = vk::WriteDescriptorSet::builder()
+        .dst_set(descriptor_set)
+        .dst_binding(0)
+        .dst_array_element(0)
+        .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+        .buffer_info(buffer_info);
+
+    device.update_descriptor_sets(&[storage_write], &[] as &[vk::CopyDescriptorSet]);
+
+    Ok((descriptor_pool, descriptor_set))
+}
+
+unsafe fn create_compute_pipeline(
+    device: &Device,
+    pipeline_cache: vk::PipelineCache,
+    descriptor_set_layout: vk::DescriptorSetLayout,
+) -> Result<(vk::PipelineLayout, vk::Pipeline)> {
+    let comp_shader_module = create_shader_module(device, &shaders::load(shaders::PARTICLES_COMP)?)?;
+
+    let comp_stage = vk::PipelineShaderStageCreateInfo::builder()
+        .stage(vk::ShaderStageFlags::COMPUTE)
+        .module(comp_shader_module)
+        .name(b"main\0");
+
+    // seconds since the last frame
+    let push_constant_range = vk::PushConstantRange::builder()
+        .stage_flags(vk::ShaderStageFlags::COMPUTE)
+        .offset(0)
+        .size(size_of::<f32>() as u32);
+
+    let set_layouts = &[descriptor_set_layout];
+    let push_constant_ranges = &[push_constant_range];
+    let layout_info = vk::PipelineLayoutCreateInfo::builder()
+        .set_layouts(set_layouts)
+        .push_constant_ranges(push_constant_ranges);
+    let pipeline_layout = match device.create_pipeline_layout(&layout_info, None) {
+        Ok(layout) => layout,
+        Err(e) => {
+            device.destroy_shader_module(comp_shader_module, None);
+            return Err(e.into());
+        }
+    };
+
+    let info = vk::ComputePipelineCreateInfo::builder()
+        .stage(comp_stage)
+        .layout(pipeline_layout);
+
+    let result = device.create_compute_pipelines(pipeline_cache, &[info], None);
+    device.destroy_shader_module(comp_shader_module, None);
+
+    match result {
+        Ok((pipelines, _)) => Ok((pipeline_layout, pipelines[0])),
+        Err(e) => {
+            device.destroy_pipeline_layout(pipeline_layout, None);
+            Err(e.into())
+        }
+    }
+}
+
+// one point per particle, depth tested and written like any opaque object
+unsafe fn create_pipeline(
+    device: &Device,
+    pipeline_cache: vk::PipelineCache,
+    target: PipelineTarget,
+    samples: vk::SampleCountFlags,
+    reverse_z: bool,
+) -> Result<(vk::PipelineLayout, vk::Pipeline)> {
+    let vert_shader_module = create_shader_module(device, &shaders::load(shaders::PARTICLES_VERT)?)?;
+    let frag_shader_module = match shaders::load(shaders::PARTICLES_FRAG)
+        .and_then(|bytecode| create_shader_module(device, &bytecode))
+    {
+        Ok(module) => module,
+        Err(e) => {
+            device.destroy_shader_module(vert_shader_module, None);
+            return Err(e);
+        }
+    };
+
+    let vert_stage = vk::PipelineShaderStageCreateInfo::builder()
+        .stage(vk::ShaderStageFlags::VERTEX)
+        .module(vert_shader_module)
+        .name(b"main\0");
+
+    let frag_stage = vk::PipelineShaderStageCreateInfo::builder()
+        .stage(vk::ShaderStageFlags::FRAGMENT)
+        .module(frag_shader_module)
+        .name(b"main\0");
+
+    // position and color straight out of the storage buffer, the velocity between them is skipped
+    let binding_description = vk::VertexInputBindingDescription::builder()
+        .binding(0)
+        .stride(size_of::<Particle>() as u32)
+        .input_rate(vk::VertexInputRate::VERTEX);
+    let position_description = vk::VertexInputAttributeDescription::builder()
+        .binding(0)
+        .location(0)
+        .format(vk::Format::R32G32B32A32_SFLOAT)
+        .offset(0);
+    let color_description = vk::VertexInputAttributeDescription::builder()
+        .binding(0)
+        .location(1)
+        .format(vk::Format::R32G32B32A32_SFLOAT)
+        .offset((size_of::<[f32; 4]>() * 2) as u32);
+
+    let binding_descriptions = &[binding_description];
+    let attribute_descriptions = &[position_description, color_description];
+    let vertex_input_state = vk::PipelineVertexInputStateCreateInfo::builder()
+        .vertex_binding_descriptions(binding_descriptions)
+        .vertex_attribute_descriptions(attribute_descriptions);
+
+    let input_assembly_state = vk::PipelineInputAssemblyStateCreateInfo::builder()
+        .topology(vk::PrimitiveTopology::POINT_LIST)
+        .primitive_restart_enable(false);
+
+    let viewport_state = vk::PipelineViewportStateCreateInfo::builder()
+        .viewport_count(1)
+        .scissor_count(1);
+
+    let dynamic_states = &[vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+    let dynamic_state = vk::PipelineDynamicStateCreateInfo::builder()
+        .dynamic_states(dynamic_states);
+
+    let rasterization_state = vk::PipelineRasterizationStateCreateInfo::builder()
+        .depth_clamp_enable(false)
+        .rasterizer_discard_enable(false)
+        .polygon_mode(vk::PolygonMode::FILL)
+        .line_width(1.0)
+        .cull_mode(vk::CullModeFlags::NONE)
+        .front_face(vk::FrontFace::COUNTER_CLOCKWISE)
+        .depth_bias_enable(false);
+
+    let multisample_state = vk::PipelineMultisampleStateCreateInfo::builder()
+        .sample_shading_enable(false)
+        .rasterization_samples(samples);
+
+    let depth_stencil_state = vk::PipelineDepthStencilStateCreateInfo::builder()
+        .depth_test_enable(true)
+        .depth_write_enable(true)
+        .depth_compare_op(if reverse_z { vk::CompareOp::GREATER } else { vk::CompareOp::LESS })
+        .depth_bounds_test_enable(false)
+        .stencil_test_enable(false);
+
+    let attachment = vk::PipelineColorBlendAttachmentState::builder()
+        .color_write_mask(vk::ColorComponentFlags::all())
+        .blend_enable(false);
+
+    let attachments = &[attachment];
+    let color_blend_state = vk::PipelineColorBlendStateCreateInfo::builder()
+        .logic_op_enable(false)
+        .logic_op(vk::LogicOp::COPY)
+        .attachments(attachments)
+        .blend_constants([0.0, 0.0, 0.0, 0.0]);
+
+    let push_constant_range = vk::PushConstantRange::builder()
+        .stage_flags(vk::ShaderStageFlags::VERTEX)
+        .offset(0)
+        .size(size_of::<DrawConstants>() as u32);
+
+    let push_constant_ranges = &[push_constant_range];
+    let layout_info = vk::PipelineLayoutCreateInfo::builder()
+        .push_constant_ranges(push_constant_ranges);
+    let pipeline_layout = match device.create_pipeline_layout(&layout_info, None) {
+        Ok(layout) => layout,
+        Err(e) => {
+            device.destroy_shader_module(vert_shader_module, None);
+            device.destroy_shader_module(frag_shader_module, None);
+            return Err(e.into());
+        }
+    };
+
+    let stages = &[vert_stage, frag_stage];
+    let mut rendering_info = target.rendering_info();
+    let mut info = vk::GraphicsPipelineCreateInfo::builder()
+        .stages(stages)
+        .vertex_input_state(&vertex_input_state)
+        .input_assembly_state(&input_assembly_state)
+        .viewport_state(&viewport_state)
+        .rasterization_state(&rasterization_state)
+        .multisample_state(&multisample_state)
+        .depth_stencil_state(&depth_stencil_state)
+        .color_blend_state(&color_blend_state)
+        .dynamic_state(&dynamic_state)
+        .layout(pipeline_layout)
+        .render_pass(target.render_pass())
+        .subpass(target.subpass());
+    if let Some(rendering_info) = &mut rendering_info {
+        info = info.push_next(rendering_info);
+    }
+
+    let result = device.create_graphics_pipelines(pipeline_cache, &[info], None);
+    device.destroy_shader_module(vert_shader_module, None);
+    device.destroy_shader_module(frag_shader_module, None);
+
+    match result {
+        Ok((pipelines, _)) => Ok((pipeline_layout, pipelines[0])),
+        Err(e) => {
+            device.destroy_pipeline_layout(pipeline_layout, None);
+            Err(e.into())
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use super::app::data::Mat4;
+use super::app::{cmd_begin_debug_label, cmd_end_debug_label};
+
+use anyhow::Result;
+
+use vulkanalia::{prelude::v1_0::*, Instance};
+
+// what a pass draws into, consecutive passes with the same target share one rendering
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Target {
+    // the multisampled color and depth, resolved into the scene image
+    Scene,
+    // the swapchain image, or the scaled image when supersampling
+    Post,
+}
+
+#[derive(Clone, Debug)]
+pub struct RenderPassDesc {
+    pub name: &'static str,
+    // of the debug label around the pass
+    pub label_color: [f32; 4],
+    // none for passes that begin their own rendering or don't draw
+    pub target: Option<Target>,
+}
+
+impl RenderPassDesc {
+    pub fn new(name: &'static str, label_color: [f32; 4]) -> Self {
+        Self { name, label_color, target: None }
+    }
+
+    pub fn target(mut self, target: Target) -> Self {
+        self.target = Some(target);
+        self
+    }
+}
+
+// shared by every pass recorded into one frame's command buffer
+#[derive(Copy, Clone, Debug)]
+pub struct Frame {
+    pub image_index: usize,
+    pub render_area: vk::Rect2D,
+    pub viewport: vk::Viewport,
+    pub scissor: vk::Rect2D,
+    // the camera's, taken once so every pass sees the same one
+    pub view_proj: Mat4,
+}
+
+pub trait Pass {
+    fn desc(&self) -> &RenderPassDesc;
+
+    /// # Safety
+    ///
+    /// `command_buffer` has to be recording, inside the rendering of the pass's target if it has one.
+    unsafe fn record(&self, command_buffer: vk::CommandBuffer, frame: &Frame) -> Result<()>;
+}
+
+type RecordFn<'a> = Box<dyn Fn(vk::CommandBuffer, &Frame) -> Result<()> + 'a>;
+
+// a pass that's just a description and what to record, for passes that borrow the app
+pub struct ClosurePass<'a> {
+    desc: RenderPassDesc,
+    record: RecordFn<'a>,
+}
+
+impl<'a> ClosurePass<'a> {
+    pub fn new<F>(desc: RenderPassDesc, record: F) -> Self
+    where
+        F: Fn(vk::CommandBuffer, &Frame) -> Result<()> + 'a,
+    {
+        Self { desc, record: Box::new(record) }
+    }
+}
+
+impl Pass for ClosurePass<'_> {
+    fn desc(&self) -> &RenderPassDesc {
+        &self.desc
+    }
+
+    unsafe fn record(&self, command_buffer: vk::CommandBuffer, frame: &Frame) -> Result<()> {
+        (self.record)(command_buffer, frame)
+    }
+}
+
+// records passes in the order they were added, each inside its own debug label
+#[derive(Default)]
+pub struct PassScheduler<'a> {
+    passes: Vec<Box<dyn Pass + 'a>>,
+}
+
+impl<'a> PassScheduler<'a> {
+    pub fn new() -> Self {
+        Self { passes: Vec::new() }
+    }
+
+    pub fn add(&mut self, pass: impl Pass + 'a) -> &mut Self {
+        self.passes.push(Box::new(pass));
+        self
+    }
+
+    /// switch_target begins, moves between and ends the renderings, called whenever the target changes
+    ///
+    /// # Safety
+    ///
+    /// `command_buffer` has to be recording outside any rendering, `switch_target` begins and ends them.
+    pub unsafe fn record<F>(
+        &self,
+        instance: &Instance,
+        command_buffer: vk::CommandBuffer,
+        frame: &Frame,
+        switch_target: F,
+    ) -> Result<()>
+    where
+        F: Fn(vk::CommandBuffer, Option<Target>, Option<Target>, &Frame) -> Result<()>,
+    {
+        let mut target = None;
+        for pass in &self.passes {
+            let desc = pass.desc();
+            if desc.target != target {
+                switch_target(command_buffer, target, desc.target, frame)?;
+                target = desc.target;
+            }
+
+            cmd_begin_debug_label(instance, command_buffer, desc.name, desc.label_color);
+            let result = pass.record(command_buffer, frame);
+            cmd_end_debug_label(instance, command_buffer);
+            result?;
+        }
+
+        if target.is_some() {
+            switch_target(command_buffer, target, None, frame)?;
+        }
+
+        Ok(())
+    }
+}
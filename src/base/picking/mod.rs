@@ -0,0 +1,473 @@
+use super::allocator::{Allocation, Allocator};
+use super::app::data::{InstanceData, Vertex};
+use super::app::{create_buffer, create_image, create_image_view, create_shader_module, set_debug_name};
+use super::constants::MAX_FRAMES_IN_FLIGHT;
+use super::shaders;
+
+use anyhow::Result;
+
+use vulkanalia::{prelude::v1_0::*, Instance};
+
+use std::mem::size_of;
+
+pub const PICKING_FORMAT: vk::Format = vk::Format::R32_UINT;
+
+// what's cleared where nothing is drawn, ids are written one above the object's index
+pub const BACKGROUND_ID: u32 = 0;
+
+// a single sampled id image and depth buffer the size of the render target, drawn only when picking
+#[derive(Debug)]
+pub struct Picker {
+    extent: vk::Extent2D,
+    // drawn with the scene's projection, so its depth is reversed the same way
+    reverse_z: bool,
+    id_image: vk::Image,
+    id_image_memory: Allocation,
+    id_image_view: vk::ImageView,
+    depth_image: vk::Image,
+    depth_image_memory: Allocation,
+    depth_image_view: vk::ImageView,
+    render_pass: vk::RenderPass,
+    framebuffer: vk::Framebuffer,
+    pipeline_layout: vk::PipelineLayout,
+    pipeline: vk::Pipeline,
+    // the one id under the cursor, one per frame in flight so a pick is read once its frame's fence signals
+    readback_buffers: Vec<vk::Buffer>,
+    readback_buffer_memories: Vec<Allocation>,
+}
+
+impl Picker {
+    /// # Safety
+    ///
+    /// The handles passed in have to be valid and from one device, which has to outlive what's made here.
+    #[allow(clippy::too_many_arguments)]
+    pub unsafe fn new(
+        instance: &Instance,
+        device: &Device,
+        phys_device: vk::PhysicalDevice,
+        allocator: &mut Allocator,
+        pipeline_cache: vk::PipelineCache,
+        descriptor_set_layout: vk::DescriptorSetLayout,
+        depth_format: vk::Format,
+        extent: vk::Extent2D,
+        reverse_z: bool,
+    ) -> Result<Self> {
+        let (id_image, id_image_memory) = create_image(
+            instance,
+            device,
+            phys_device,
+            allocator,
+            extent.width,
+            extent.height,
+            1,
+            1,
+            vk::SampleCountFlags::_1,
+            PICKING_FORMAT,
+            vk::ImageTiling::OPTIMAL,
+            vk::ImageCreateFlags::empty(),
+            vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSFER_SRC,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            "picking-image",
+        )?;
+        let id_image_view = create_image_view(device, id_image, PICKING_FORMAT, vk::ImageAspectFlags::COLOR, 1)?;
+
+        let (depth_image, depth_image_memory) = create_image(
+            instance,
+            device,
+            phys_device,
+            allocator,
+            extent.width,
+            extent.height,
+            1,
+            1,
+            vk::SampleCountFlags::_1,
+            depth_format,
+            vk::ImageTiling::OPTIMAL,
+            vk::ImageCreateFlags::empty(),
+            vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            "picking-depth-image",
+        )?;
+        let depth_image_view = create_image_view(device, depth_image, depth_format, vk::ImageAspectFlags::DEPTH, 1)?;
+
+        let render_pass = create_render_pass(device, depth_format)?;
+        let attachments = &[id_image_view, depth_image_view];
+        let info = vk::FramebufferCreateInfo::builder()
+            .render_pass(render_pass)
+            .attachments(attachments)
+            .width(extent.width)
+            .height(extent.height)
+            .layers(1);
+        let framebuffer = device.create_framebuffer(&info, None)?;
+
+        let (pipeline_layout, pipeline) =
+            create_pipeline(device, pipeline_cache, render_pass, descriptor_set_layout, reverse_z)?;
+
+        let mut readback_buffers = Vec::with_capacity(MAX_FRAMES_IN_FLIGHT);
+        let mut readback_buffer_memories = Vec::with_capacity(MAX_FRAMES_IN_FLIGHT);
+        for _ in 0..MAX_FRAMES_IN_FLIGHT {
+            let (buffer, memory) = create_buffer(
+                instance,
+                device,
+                phys_device,
+                allocator,
+                size_of::<u32>() as u64,
+                vk::BufferUsageFlags::TRANSFER_DST,
+                vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+                "picking-readback",
+            )?;
+            readback_buffers.push(buffer);
+            readback_buffer_memories.push(memory);
+        }
+
+        Ok(Self {
+            extent,
+            reverse_z,
+            id_image,
+            id_image_memory,
+            id_image_view,
+            depth_image,
+            depth_image_memory,
+            depth_image_view,
+            render_pass,
+            framebuffer,
+            pipeline_layout,
+            pipeline,
+            readback_buffers,
+            readback_buffer_memories,
+        })
+    }
+
+    pub fn extent(&self) -> vk::Extent2D {
+        self.extent
+    }
+
+    pub fn pipeline(&self) -> vk::Pipeline {
+        self.pipeline
+    }
+
+    // the main descriptor sets bind to it, the id goes where the texture index would
+    pub fn pipeline_layout(&self) -> vk::PipelineLayout {
+        self.pipeline_layout
+    }
+
+    /// clears the ids to the background and sets the viewport and scissor to the whole image
+    ///
+    /// # Safety
+    ///
+    /// `command_buffer` has to be recording outside any rendering, cmd_end has to follow.
+    pub unsafe fn cmd_begin(&self, device: &Device, command_buffer: vk::CommandBuffer) {
+        let render_area = vk::Rect2D::builder()
+            .offset(vk::Offset2D::default())
+            .extent(self.extent);
+
+        let id_clear_value = vk::ClearValue {
+            color: vk::ClearColorValue {
+                uint32: [BACKGROUND_ID; 4],
+            },
+        };
+
+        let depth_clear_value = vk::ClearValue {
+            depth_stencil: vk::ClearDepthStencilValue {
+                depth: if self.reverse_z { 0.0 } else { 1.0 },
+                stencil: 0,
+            },
+        };
+
+        let clear_values = &[id_clear_value, depth_clear_value];
+        let info = vk::RenderPassBeginInfo::builder()
+            .render_pass(self.render_pass)
+            .framebuffer(self.framebuffer)
+            .render_area(render_area)
+            .clear_values(clear_values);
+        device.cmd_begin_render_pass(command_buffer, &info, vk::SubpassContents::INLINE);
+
+        let viewport = vk::Viewport::builder()
+            .x(0.0)
+            .y(0.0)
+            .width(self.extent.width as f32)
+            .height(self.extent.height as f32)
+            .min_depth(0.0)
+            .max_depth(1.0);
+        device.cmd_set_viewport(command_buffer, 0, &[viewport]);
+        device.cmd_set_scissor(command_buffer, 0, &[render_area]);
+    }
+
+    /// the render pass leaves the ids ready to copy into the frame's buffer, x and y have to be inside the extent
+    ///
+    /// # Safety
+    ///
+    /// `command_buffer` has to be inside the render pass cmd_begin began, `frame` below MAX_FRAMES_IN_FLIGHT.
+    pub unsafe fn cmd_end(&self, device: &Device, command_buffer: vk::CommandBuffer, frame: usize, x: u32, y: u32) {
+        device.cmd_end_render_pass(command_buffer);
+
+        let subresource = vk::ImageSubresourceLayers::builder()
+            .aspect_mask(vk::ImageAspectFlags::COLOR)
+            .mip_level(0)
+            .base_array_layer(0)
+            .layer_count(1);
+
+        let region = vk::BufferImageCopy::builder()
+            .buffer_offset(0)
+            .buffer_row_length(0)
+            .buffer_image_height(0)
+            .image_subresource(subresource)
+            .image_offset(vk::Offset3D { x: x as i32, y: y as i32, z: 0 })
+            .image_extent(vk::Extent3D { width: 1, height: 1, depth: 1 });
+
+        device.cmd_copy_image_to_buffer(
+            command_buffer,
+            self.id_image,
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            self.readback_buffers[frame],
+            &[region],
+        );
+
+        // made visible to the host mapping once the frame's fence is waited on
+        let barrier = vk::BufferMemoryBarrier::builder()
+            .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+            .dst_access_mask(vk::AccessFlags::HOST_READ)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .buffer(self.readback_buffers[frame])
+            .offset(0)
+            .size(vk::WHOLE_SIZE as u64);
+
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::PipelineStageFlags::HOST,
+            vk::DependencyFlags::empty(),
+            &[] as &[vk::MemoryBarrier],
+            &[barrier],
+            &[] as &[vk::ImageMemoryBarrier],
+        );
+    }
+
+    /// only valid after the frame that recorded cmd_end has finished
+    ///
+    /// # Safety
+    ///
+    /// The frame that recorded cmd_end for `frame` has to have finished.
+    pub unsafe fn read(&self, device: &Device, frame: usize) -> Result<u32> {
+        let readback_buffer_memory = self.readback_buffer_memories[frame];
+        let memory = device.map_memory(
+            readback_buffer_memory.memory,
+            readback_buffer_memory.offset,
+            size_of::<u32>() as u64,
+            vk::MemoryMapFlags::empty(),
+        )?;
+        let id = memory.cast::<u32>().read_unaligned();
+        device.unmap_memory(readback_buffer_memory.memory);
+
+        Ok(id)
+    }
+
+    /// # Safety
+    ///
+    /// Nothing made here can still be in use on the gpu, and none of it can be used after.
+    pub unsafe fn destroy(&mut self, device: &Device, allocator: &mut Allocator) {
+        for (buffer, memory) in self.readback_buffers.drain(..).zip(self.readback_buffer_memories.drain(..)) {
+            device.destroy_buffer(buffer, None);
+            allocator.free(device, memory);
+        }
+        device.destroy_pipeline(self.pipeline, None);
+        device.destroy_pipeline_layout(self.pipeline_layout, None);
+        device.destroy_framebuffer(self.framebuffer, None);
+        device.destroy_render_pass(self.render_pass, None);
+        device.destroy_image_view(self.depth_image_view, None);
+        device.destroy_image(self.depth_image, None);
+        allocator.free(device, self.depth_image_memory);
+        device.destroy_image_view(self.id_image_view, None);
+        device.destroy_image(self.id_image, None);
+        allocator.free(device, self.id_image_memory);
+    }
+
+    /// # Safety
+    ///
+    /// `device` has to be the one these were made with, created from `instance`.
+    pub unsafe fn set_debug_names(&self, instance: &Instance, device: &Device) -> Result<()> {
+        set_debug_name(instance, device, self.id_image, "picking-image")?;
+        set_debug_name(instance, device, self.render_pass, "picking-render-pass")?;
+        set_debug_name(instance, device, self.pipeline, "picking-pipeline")
+    }
+}
+
+// the ids end up in transfer src for the copy, depth is thrown away
+unsafe fn create_render_pass(device: &Device, depth_format: vk::Format) -> Result<vk::RenderPass> {
+    let id_attachment = vk::AttachmentDescription::builder()
+        .format(PICKING_FORMAT)
+        .samples(vk::SampleCountFlags::_1)
+        .load_op(vk::AttachmentLoadOp::CLEAR)
+        .store_op(vk::AttachmentStoreOp::STORE)
+        .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+        .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+        .initial_layout(vk::ImageLayout::UNDEFINED)
+        .final_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL);
+
+    let depth_attachment = vk::AttachmentDescription::builder()
+        .format(depth_format)
+        .samples(vk::SampleCountFlags::_1)
+        .load_op(vk::AttachmentLoadOp::CLEAR)
+        .store_op(vk::AttachmentStoreOp::DONT_CARE)
+        .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+        .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+        .initial_layout(vk::ImageLayout::UNDEFINED)
+        .final_layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL);
+
+    let id_attachment_ref = vk::AttachmentReference::builder()
+        .attachment(0)
+        .layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL);
+
+    let depth_attachment_ref = vk::AttachmentReference::builder()
+        .attachment(1)
+        .layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL);
+
+    let color_attachments = &[id_attachment_ref];
+    let subpass = vk::SubpassDescription::builder()
+        .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
+        .color_attachments(color_attachments)
+        .depth_stencil_attachment(&depth_attachment_ref);
+
+    // the copy waits for the ids to be written
+    let dependency = vk::SubpassDependency::builder()
+        .src_subpass(0)
+        .dst_subpass(vk::SUBPASS_EXTERNAL)
+        .src_stage_mask(vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+        .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+        .dst_stage_mask(vk::PipelineStageFlags::TRANSFER)
+        .dst_access_mask(vk::AccessFlags::TRANSFER_READ);
+
+    let attachments = &[id_attachment, depth_attachment];
+    let subpasses = &[subpass];
+    let dependencies = &[dependency];
+    let info = vk::RenderPassCreateInfo::builder()
+        .attachments(attachments)
+        .subpasses(subpasses)
+        .dependencies(dependencies);
+
+    Ok(device.create_render_pass(&info, None)?)
+}
+
+// the scene's vertex shader, so objects and instances land where they were drawn, with a flat id for a color
+unsafe fn create_pipeline(
+    device: &Device,
+    pipeline_cache: vk::PipelineCache,
+    render_pass: vk::RenderPass,
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    reverse_z: bool,
+) -> Result<(vk::PipelineLayout, vk::Pipeline)> {
+    let vert_shader_module = create_shader_module(device, &shaders::load(shaders::VERT)?)?;
+    let frag_shader_module = match shaders::load(shaders::PICKING_FRAG)
+        .and_then(|bytecode| create_shader_module(device, &bytecode))
+    {
+        Ok(module) => module,
+        Err(e) => {
+            device.destroy_shader_module(vert_shader_module, None);
+            return Err(e);
+        }
+    };
+
+    let vert_stage = vk::PipelineShaderStageCreateInfo::builder()
+        .stage(vk::ShaderStageFlags::VERTEX)
+        .module(vert_shader_module)
+        .name(b"main\0");
+
+    let frag_stage = vk::PipelineShaderStageCreateInfo::builder()
+        .stage(vk::ShaderStageFlags::FRAGMENT)
+        .module(frag_shader_module)
+        .name(b"main\0");
+
+    let binding_descriptions = &[Vertex::binding_description(), InstanceData::binding_description()];
+    let attribute_descriptions = Vertex::attribute_descriptions()
+        .into_iter()
+        .chain(InstanceData::attribute_descriptions())
+        .collect::<Vec<_>>();
+    let vertex_input_state = vk::PipelineVertexInputStateCreateInfo::builder()
+        .vertex_binding_descriptions(binding_descriptions)
+        .vertex_attribute_descriptions(&attribute_descriptions);
+
+    let input_assembly_state = vk::PipelineInputAssemblyStateCreateInfo::builder()
+        .topology(vk::PrimitiveTopology::TRIANGLE_LIST)
+        .primitive_restart_enable(false);
+
+    let viewport_state = vk::PipelineViewportStateCreateInfo::builder()
+        .viewport_count(1)
+        .scissor_count(1);
+
+    let dynamic_states = &[vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+    let dynamic_state = vk::PipelineDynamicStateCreateInfo::builder()
+        .dynamic_states(dynamic_states);
+
+    let rasterization_state = vk::PipelineRasterizationStateCreateInfo::builder()
+        .depth_clamp_enable(false)
+        .rasterizer_discard_enable(false)
+        .polygon_mode(vk::PolygonMode::FILL)
+        .line_width(1.0)
+        .cull_mode(vk::CullModeFlags::BACK)
+        .front_face(vk::FrontFace::COUNTER_CLOCKWISE)
+        .depth_bias_enable(false);
+
+    let multisample_state = vk::PipelineMultisampleStateCreateInfo::builder()
+        .sample_shading_enable(false)
+        .rasterization_samples(vk::SampleCountFlags::_1);
+
+    let depth_stencil_state = vk::PipelineDepthStencilStateCreateInfo::builder()
+        .depth_test_enable(true)
+        .depth_write_enable(true)
+        .depth_compare_op(if reverse_z { vk::CompareOp::GREATER } else { vk::CompareOp::LESS })
+        .depth_bounds_test_enable(false)
+        .stencil_test_enable(false);
+
+    // integer attachments can't blend
+    let attachment = vk::PipelineColorBlendAttachmentState::builder()
+        .color_write_mask(vk::ColorComponentFlags::R)
+        .blend_enable(false);
+
+    let attachments = &[attachment];
+    let color_blend_state = vk::PipelineColorBlendStateCreateInfo::builder()
+        .logic_op_enable(false)
+        .logic_op(vk::LogicOp::COPY)
+        .attachments(attachments)
+        .blend_constants([0.0, 0.0, 0.0, 0.0]);
+
+    // the object's id
+    let push_constant_range = vk::PushConstantRange::builder()
+        .stage_flags(vk::ShaderStageFlags::FRAGMENT)
+        .offset(0)
+        .size(size_of::<u32>() as u32);
+
+    let set_layouts = &[descriptor_set_layout];
+    let push_constant_ranges = &[push_constant_range];
+    let layout_info = vk::PipelineLayoutCreateInfo::builder()
+        .set_layouts(set_layouts)
+        .push_constant_ranges(push_constant_ranges);
+    let pipeline_layout = device.create_pipeline_layout(&layout_info, None)?;
+
+    let stages = &[vert_stage, frag_stage];
+    let info = vk::GraphicsPipelineCreateInfo::builder()
+        .stages(stages)
+        .vertex_input_state(&vertex_input_state)
+        .input_assembly_state(&input_assembly_state)
+        .viewport_state(&viewport_state)
+        .rasterization_state(&rasterization_state)
+        .multisample_state(&multisample_state)
+        .depth_stencil_state(&depth_stencil_state)
+        .color_blend_state(&color_blend_state)
+        .dynamic_state(&dynamic_state)
+        .layout(pipeline_layout)
+        .render_pass(render_pass)
+        .subpass(0);
+
+    let result = device.create_graphics_pipelines(pipeline_cache, &[info], None);
+    device.destroy_shader_module(vert_shader_module, None);
+    device.destroy_shader_module(frag_shader_module, None);
+
+    match result {
+        Ok((pipelines, _)) => Ok((pipeline_layout, pipelines[0])),
+        Err(e) => {
+            device.destroy_pipeline_layout(pipeline_layout, None);
+            Err(e.into())
+        }
+    }
+}
@@ -0,0 +1,113 @@
+#version 450
+#extension GL_EXT_nonuniform_qualifier : require
+
+// every texture the app has loaded, the array is only partially bound
+layout(set = 1, binding = 0) uniform sampler2D textures[];
+
+// which texture the object samples
+layout(push_constant) uniform ObjectConstants {
+    uint textureIndex;
+} object;
+
+// the outline pipeline writes a flat color instead of the texture
+layout(constant_id = 1) const bool OUTLINE = false;
+layout(constant_id = 2) const float OUTLINE_R = 1.0;
+layout(constant_id = 3) const float OUTLINE_G = 1.0;
+layout(constant_id = 4) const float OUTLINE_B = 1.0;
+
+// perturbs the normal with the normal map
+layout(constant_id = 5) const bool NORMAL_MAPPING = false;
+
+layout(set = 0, binding = 3) uniform sampler2D normalMap;
+
+// one directional light, vec3s are padded to vec4s
+layout(set = 0, binding = 4) uniform LightUniform {
+    vec4 direction;
+    vec4 color;
+    vec4 ambient;
+    vec4 cameraPosition;
+    // world to the shadow map's clip space
+    mat4 viewProj;
+    // 0 when the shadow map isn't drawn
+    uint shadows;
+} light;
+
+// compared against the depth of the nearest surface the light reaches
+layout(set = 0, binding = 5) uniform sampler2DShadow shadowMap;
+
+const float SHININESS = 32.0;
+
+layout(location = 0) in vec3 fragColor;
+layout(location = 1) in vec2 fragTexCoord;
+layout(location = 2) in vec3 fragNormal;
+layout(location = 3) in vec4 fragTangent;
+layout(location = 4) in vec3 fragPosition;
+
+layout(location = 0) out vec4 outColor;
+
+// the interpolated normal, turned by the normal map when the mesh has tangents
+vec3 surfaceNormal() {
+    vec3 normal = normalize(fragNormal);
+    if (!NORMAL_MAPPING || dot(fragTangent.xyz, fragTangent.xyz) == 0.0) {
+        return normal;
+    }
+
+    vec3 tangent = normalize(fragTangent.xyz - normal * dot(normal, fragTangent.xyz));
+    vec3 bitangent = cross(normal, tangent) * fragTangent.w;
+    vec3 mapped = texture(normalMap, fragTexCoord).xyz * 2.0 - 1.0;
+
+    return normalize(mat3(tangent, bitangent, normal) * mapped);
+}
+
+// 1 where the light reaches, averaged over the 3x3 shadow map texels around the fragment
+float lit() {
+    if (light.shadows == 0u) {
+        return 1.0;
+    }
+
+    vec4 position = light.viewProj * vec4(fragPosition, 1.0);
+    vec3 coords = position.xyz / position.w;
+    if (coords.z > 1.0) {
+        return 1.0;
+    }
+
+    vec2 uv = coords.xy * 0.5 + 0.5;
+    vec2 texel = 1.0 / vec2(textureSize(shadowMap, 0));
+    float total = 0.0;
+    for (int x = -1; x <= 1; x++) {
+        for (int y = -1; y <= 1; y++) {
+            total += texture(shadowMap, vec3(uv + vec2(x, y) * texel, coords.z));
+        }
+    }
+
+    return total / 9.0;
+}
+
+// blinn-phong, meshes without normals are left unlit
+vec4 shade(vec4 color) {
+    if (dot(fragNormal, fragNormal) == 0.0) {
+        return color;
+    }
+
+    vec3 normal = surfaceNormal();
+    vec3 toLight = -light.direction.xyz;
+    vec3 halfway = normalize(toLight + normalize(light.cameraPosition.xyz - fragPosition));
+
+    float diffuse = max(dot(normal, toLight), 0.0);
+    float specular = diffuse > 0.0 ? pow(max(dot(normal, halfway), 0.0), SHININESS) : 0.0;
+
+    // the ambient light still reaches shadowed surfaces
+    float shadow = lit();
+    diffuse *= shadow;
+    specular *= shadow;
+
+    return vec4(color.rgb * (light.ambient.rgb + light.color.rgb * diffuse) + light.color.rgb * specular, color.a);
+}
+
+void main() {
+    if (OUTLINE) {
+        outColor = vec4(OUTLINE_R, OUTLINE_G, OUTLINE_B, 1.0);
+    } else {
+        outColor = shade(texture(textures[object.textureIndex], fragTexCoord));
+    }
+}
@@ -0,0 +1,23 @@
+#!/bin/sh
+# recompile the embedded spir-v, run from this directory
+glslc shader.vert -o vert.spv
+glslc shader.frag -o frag.spv
+glslc shader.comp -o comp.spv
+glslc post.vert -o post_vert.spv
+glslc post.frag -o post_frag.spv
+glslc post_sampled.frag -o post_sampled_frag.spv
+glslc gui.vert -o gui_vert.spv
+glslc gui.frag -o gui_frag.spv
+glslc skybox.vert -o skybox_vert.spv
+glslc skybox.frag -o skybox_frag.spv
+glslc bindless.frag -o bindless_frag.spv
+glslc particles.comp -o particles_comp.spv
+glslc particles.vert -o particles_vert.spv
+glslc particles.frag -o particles_frag.spv
+glslc lines.vert -o lines_vert.spv
+glslc lines.frag -o lines_frag.spv
+glslc picking.frag -o picking_frag.spv
+glslc bloom_down.comp -o bloom_down_comp.spv
+glslc bloom_blur.comp -o bloom_blur_comp.spv
+glslc bloom_up.comp -o bloom_up_comp.spv
+glslc shadow.vert -o shadow_vert.spv
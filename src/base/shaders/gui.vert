@@ -0,0 +1,23 @@
+#version 450
+
+// imgui colors are srgb, they're decoded first when the swapchain encodes on write
+layout(constant_id = 0) const bool SRGB_TARGET = true;
+
+// display coordinates to clip space
+layout(push_constant) uniform GuiConstants {
+    vec2 scale;
+    vec2 translate;
+} gui;
+
+layout(location = 0) in vec2 inPosition;
+layout(location = 1) in vec2 inTexCoord;
+layout(location = 2) in vec4 inColor;
+
+layout(location = 0) out vec4 fragColor;
+layout(location = 1) out vec2 fragTexCoord;
+
+void main() {
+    gl_Position = vec4(inPosition * gui.scale + gui.translate, 0.0, 1.0);
+    fragColor = SRGB_TARGET ? vec4(pow(inColor.rgb, vec3(2.2)), inColor.a) : inColor;
+    fragTexCoord = inTexCoord;
+}
@@ -0,0 +1,15 @@
+#version 450
+
+layout(push_constant) uniform LineConstants {
+    mat4 viewProj;
+} constants;
+
+layout(location = 0) in vec3 inPosition;
+layout(location = 1) in vec3 inColor;
+
+layout(location = 0) out vec3 fragColor;
+
+void main() {
+    gl_Position = constants.viewProj * vec4(inPosition, 1.0);
+    fragColor = inColor;
+}
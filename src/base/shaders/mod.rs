@@ -0,0 +1,327 @@
+use super::constants::{SPIRV_MAGIC, SPIRV_MAX_ID_BOUND};
+
+use anyhow::{anyhow, Result};
+use log::*;
+
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use std::{
+    env,
+    fmt,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver},
+};
+
+// a shader's glsl source and where compile.sh writes its spir-v, the spir-v is also embedded
+#[derive(Copy, Clone, Debug)]
+pub struct Shader {
+    pub source: &'static str,
+    pub spirv: &'static str,
+    pub embedded: &'static [u8],
+}
+
+pub const VERT: Shader = Shader { source: "shader.vert", spirv: "vert.spv", embedded: include_bytes!("vert.spv") };
+pub const FRAG: Shader = Shader { source: "shader.frag", spirv: "frag.spv", embedded: include_bytes!("frag.spv") };
+pub const COMP: Shader = Shader { source: "shader.comp", spirv: "comp.spv", embedded: include_bytes!("comp.spv") };
+pub const POST_VERT: Shader =
+    Shader { source: "post.vert", spirv: "post_vert.spv", embedded: include_bytes!("post_vert.spv") };
+pub const POST_FRAG: Shader =
+    Shader { source: "post.frag", spirv: "post_frag.spv", embedded: include_bytes!("post_frag.spv") };
+pub const POST_SAMPLED_FRAG: Shader = Shader {
+    source: "post_sampled.frag",
+    spirv: "post_sampled_frag.spv",
+    embedded: include_bytes!("post_sampled_frag.spv"),
+};
+pub const GUI_VERT: Shader =
+    Shader { source: "gui.vert", spirv: "gui_vert.spv", embedded: include_bytes!("gui_vert.spv") };
+pub const GUI_FRAG: Shader =
+    Shader { source: "gui.frag", spirv: "gui_frag.spv", embedded: include_bytes!("gui_frag.spv") };
+pub const SKYBOX_VERT: Shader =
+    Shader { source: "skybox.vert", spirv: "skybox_vert.spv", embedded: include_bytes!("skybox_vert.spv") };
+pub const SKYBOX_FRAG: Shader =
+    Shader { source: "skybox.frag", spirv: "skybox_frag.spv", embedded: include_bytes!("skybox_frag.spv") };
+pub const BINDLESS_FRAG: Shader =
+    Shader { source: "bindless.frag", spirv: "bindless_frag.spv", embedded: include_bytes!("bindless_frag.spv") };
+pub const PARTICLES_COMP: Shader =
+    Shader { source: "particles.comp", spirv: "particles_comp.spv", embedded: include_bytes!("particles_comp.spv") };
+pub const PARTICLES_VERT: Shader =
+    Shader { source: "particles.vert", spirv: "particles_vert.spv", embedded: include_bytes!("particles_vert.spv") };
+pub const PARTICLES_FRAG: Shader =
+    Shader { source: "particles.frag", spirv: "particles_frag.spv", embedded: include_bytes!("particles_frag.spv") };
+pub const LINES_VERT: Shader =
+    Shader { source: "lines.vert", spirv: "lines_vert.spv", embedded: include_bytes!("lines_vert.spv") };
+pub const LINES_FRAG: Shader =
+    Shader { source: "lines.frag", spirv: "lines_frag.spv", embedded: include_bytes!("lines_frag.spv") };
+pub const PICKING_FRAG: Shader =
+    Shader { source: "picking.frag", spirv: "picking_frag.spv", embedded: include_bytes!("picking_frag.spv") };
+pub const BLOOM_DOWN_COMP: Shader = Shader {
+    source: "bloom_down.comp",
+    spirv: "bloom_down_comp.spv",
+    embedded: include_bytes!("bloom_down_comp.spv"),
+};
+pub const BLOOM_BLUR_COMP: Shader = Shader {
+    source: "bloom_blur.comp",
+    spirv: "bloom_blur_comp.spv",
+    embedded: include_bytes!("bloom_blur_comp.spv"),
+};
+pub const BLOOM_UP_COMP: Shader =
+    Shader { source: "bloom_up.comp", spirv: "bloom_up_comp.spv", embedded: include_bytes!("bloom_up_comp.spv") };
+pub const SHADOW_VERT: Shader =
+    Shader { source: "shadow.vert", spirv: "shadow_vert.spv", embedded: include_bytes!("shadow_vert.spv") };
+
+// shaders are read from here instead of the binary when set, as glsl source with the shaderc feature
+pub const SHADER_DIR_VAR: &str = "SHADER_DIR";
+
+// changes to these files in the shader directory trigger a reload
+#[cfg(not(feature = "shaderc"))]
+const WATCHED_EXTENSIONS: &[&str] = &["spv"];
+#[cfg(feature = "shaderc")]
+const WATCHED_EXTENSIONS: &[&str] = &["vert", "frag", "comp"];
+
+pub fn shader_dir() -> Option<PathBuf> {
+    env::var_os(SHADER_DIR_VAR).map(PathBuf::from)
+}
+
+pub fn load(shader: Shader) -> Result<Vec<u8>> {
+    match shader_dir() {
+        Some(dir) => load_from_dir(&dir, shader),
+        None => Ok(shader.embedded.to_vec()),
+    }
+}
+
+#[cfg(not(feature = "shaderc"))]
+fn load_from_dir(dir: &Path, shader: Shader) -> Result<Vec<u8>> {
+    let path = dir.join(shader.spirv);
+    std::fs::read(&path).map_err(|e| anyhow!("Failed to read shader ({}): {}", path.display(), e))
+}
+
+#[cfg(feature = "shaderc")]
+fn load_from_dir(dir: &Path, shader: Shader) -> Result<Vec<u8>> {
+    use shaderc::{CompileOptions, Compiler, ShaderKind};
+
+    let kind = match Path::new(shader.source).extension().and_then(|e| e.to_str()) {
+        Some("vert") => ShaderKind::Vertex,
+        Some("frag") => ShaderKind::Fragment,
+        Some("comp") => ShaderKind::Compute,
+        _ => return Err(anyhow!("Shader source ({}) has no known stage extension.", shader.source)),
+    };
+
+    let path = dir.join(shader.source);
+    let text = std::fs::read_to_string(&path)
+        .map_err(|e| anyhow!("Failed to read shader ({}): {}", path.display(), e))?;
+
+    let compiler = Compiler::new()?;
+    let mut options = CompileOptions::new()?;
+    options.set_generate_debug_info();
+
+    // the log is returned as is so errors point at lines in the source
+    let artifact = compiler
+        .compile_into_spirv(&text, kind, &path.to_string_lossy(), "main", Some(&options))
+        .map_err(|e| match e {
+            shaderc::Error::CompilationError(_, log) => {
+                anyhow!("Failed to compile shader ({}):\n{}", path.display(), log)
+            }
+            e => anyhow!("Failed to compile shader ({}): {}", path.display(), e),
+        })?;
+
+    if artifact.get_num_warnings() > 0 {
+        warn!("Shader ({}) compiled with warnings:\n{}", path.display(), artifact.get_warning_messages());
+    }
+
+    debug!("Compiled shader ({}).", path.display());
+    Ok(artifact.as_binary_u8().to_vec())
+}
+
+// magic, version, generator, id bound and schema
+const SPIRV_HEADER_WORDS: usize = 5;
+
+// checks the header and that every instruction fits before a driver sees it, big endian modules are swapped
+pub fn spirv_words(bytecode: &[u8]) -> Result<Vec<u32>> {
+    if !bytecode.len().is_multiple_of(4) {
+        return Err(anyhow!("Shader bytecode length ({}) is not a multiple of 4.", bytecode.len()));
+    }
+
+    // copy into u32 words, the byte slice isn't guaranteed to be 4-byte aligned
+    let mut words = bytecode
+        .chunks_exact(4)
+        .map(|w| u32::from_le_bytes([w[0], w[1], w[2], w[3]]))
+        .collect::<Vec<_>>();
+    if words.len() <= SPIRV_HEADER_WORDS {
+        return Err(anyhow!("Shader bytecode ({} words) is too short to be SPIR-V.", words.len()));
+    }
+
+    match words[0] {
+        SPIRV_MAGIC => {}
+        magic if magic.swap_bytes() == SPIRV_MAGIC => words.iter_mut().for_each(|w| *w = w.swap_bytes()),
+        magic => return Err(anyhow!("Shader bytecode starts with {:#010x}, not the SPIR-V magic number.", magic)),
+    }
+
+    // 0 | major | minor | 0, only 1.x exists
+    let version = words[1];
+    let (major, minor) = ((version >> 16) & 0xff, (version >> 8) & 0xff);
+    if version & 0xff00_00ff != 0 || major != 1 || minor > 6 {
+        return Err(anyhow!("Shader has an unknown SPIR-V version ({:#010x}).", version));
+    }
+
+    let bound = words[3];
+    if bound == 0 || bound > SPIRV_MAX_ID_BOUND {
+        return Err(anyhow!("Shader has an invalid SPIR-V id bound ({}).", bound));
+    }
+    if words[4] != 0 {
+        return Err(anyhow!("Shader has a non-zero SPIR-V schema ({}).", words[4]));
+    }
+
+    // each instruction's first word holds its length in words, a truncated module ends partway through one
+    let mut offset = SPIRV_HEADER_WORDS;
+    while offset < words.len() {
+        let length = (words[offset] >> 16) as usize;
+        if length == 0 {
+            return Err(anyhow!("Shader has a zero length SPIR-V instruction at word {}.", offset));
+        }
+        if offset + length > words.len() {
+            return Err(anyhow!(
+                "Shader's SPIR-V instruction at word {} runs {} words past the end, is it truncated?",
+                offset,
+                offset + length - words.len(),
+            ));
+        }
+        offset += length;
+    }
+
+    Ok(words)
+}
+
+// watches the directory for written shader files, events are drained once per frame on the render thread
+pub struct ShaderWatcher {
+    _watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<Event>>,
+}
+
+impl fmt::Debug for ShaderWatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ShaderWatcher").finish_non_exhaustive()
+    }
+}
+
+impl ShaderWatcher {
+    pub fn new(dir: &Path) -> Result<Self> {
+        let (sender, events) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(sender)?;
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+
+        Ok(Self { _watcher: watcher, events })
+    }
+
+    // true when any watched file was created or written since the last poll
+    pub fn poll(&mut self) -> bool {
+        let mut changed = false;
+        for event in self.events.try_iter() {
+            match event {
+                Ok(event) => {
+                    let written = matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_));
+                    let shader = event.paths.iter().any(|p| {
+                        p.extension().and_then(|e| e.to_str()).is_some_and(|e| WATCHED_EXTENSIONS.contains(&e))
+                    });
+                    changed |= written && shader;
+                }
+                Err(e) => warn!("Shader watcher error: {}", e),
+            }
+        }
+
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a header and OpCapability Shader
+    fn minimal_module() -> Vec<u32> {
+        vec![SPIRV_MAGIC, 0x0001_0000, 0, 1, 0, (2 << 16) | 17, 1]
+    }
+
+    fn bytes(words: &[u32]) -> Vec<u8> {
+        words.iter().flat_map(|w| w.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn accepts_a_minimal_module() {
+        assert_eq!(spirv_words(&bytes(&minimal_module())).unwrap(), minimal_module());
+    }
+
+    #[test]
+    fn accepts_the_embedded_shaders() {
+        for shader in [VERT, FRAG, COMP, POST_VERT, POST_SAMPLED_FRAG, SKYBOX_VERT, SHADOW_VERT] {
+            assert!(spirv_words(shader.embedded).is_ok(), "{}", shader.spirv);
+        }
+    }
+
+    #[test]
+    fn swaps_big_endian_modules() {
+        let big_endian = minimal_module().iter().flat_map(|w| w.to_be_bytes()).collect::<Vec<_>>();
+        assert_eq!(spirv_words(&big_endian).unwrap(), minimal_module());
+    }
+
+    #[test]
+    fn rejects_a_partial_word() {
+        let mut bytecode = bytes(&minimal_module());
+        bytecode.pop();
+        assert!(spirv_words(&bytecode).is_err());
+    }
+
+    #[test]
+    fn rejects_a_bare_header() {
+        assert!(spirv_words(&bytes(&minimal_module()[..SPIRV_HEADER_WORDS])).is_err());
+        assert!(spirv_words(&[]).is_err());
+    }
+
+    #[test]
+    fn rejects_a_wrong_magic_number() {
+        let mut words = minimal_module();
+        words[0] = 0xdead_beef;
+        assert!(spirv_words(&bytes(&words)).is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_versions() {
+        for version in [0x0002_0000, 0x0001_0700, 0x0001_0001, 0] {
+            let mut words = minimal_module();
+            words[1] = version;
+            assert!(spirv_words(&bytes(&words)).is_err(), "{:#x}", version);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_bounds_and_schemas() {
+        let mut words = minimal_module();
+        words[3] = 0;
+        assert!(spirv_words(&bytes(&words)).is_err());
+
+        let mut words = minimal_module();
+        words[3] = SPIRV_MAX_ID_BOUND + 1;
+        assert!(spirv_words(&bytes(&words)).is_err());
+
+        let mut words = minimal_module();
+        words[4] = 1;
+        assert!(spirv_words(&bytes(&words)).is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_instructions() {
+        let words = minimal_module();
+        assert!(spirv_words(&bytes(&words[..words.len() - 1])).is_err());
+
+        // the capability claims an operand that isn't there
+        let mut words = minimal_module();
+        words[SPIRV_HEADER_WORDS] = (3 << 16) | 17;
+        assert!(spirv_words(&bytes(&words)).is_err());
+    }
+
+    #[test]
+    fn rejects_zero_length_instructions() {
+        let mut words = minimal_module();
+        words[SPIRV_HEADER_WORDS] = 17;
+        assert!(spirv_words(&bytes(&words)).is_err());
+    }
+}
@@ -0,0 +1,17 @@
+#version 450
+
+layout(push_constant) uniform ParticleConstants {
+    mat4 viewProj;
+    float pointSize;
+} constants;
+
+layout(location = 0) in vec4 inPosition;
+layout(location = 1) in vec4 inColor;
+
+layout(location = 0) out vec3 fragColor;
+
+void main() {
+    gl_Position = constants.viewProj * inPosition;
+    gl_PointSize = constants.pointSize;
+    fragColor = inColor.rgb;
+}
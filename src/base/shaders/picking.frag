@@ -0,0 +1,12 @@
+#version 450
+
+// one above the object's index, 0 is left for the background
+layout(push_constant) uniform ObjectConstants {
+    uint id;
+} object;
+
+layout(location = 0) out uint outId;
+
+void main() {
+    outId = object.id;
+}
@@ -0,0 +1,54 @@
+#version 450
+
+// the resolved scene from the first subpass
+layout(input_attachment_index = 0, binding = 0) uniform subpassInput sceneColor;
+
+// matches PostConstants
+layout(push_constant) uniform PostConstants {
+    uint effect;
+    uint tonemap;
+    float exposure;
+    uint gamma;
+    float bloomIntensity;
+} post;
+
+layout(location = 0) out vec4 outColor;
+
+// 1 is reinhard, 2 is aces as fitted by narkowicz, both after scaling by the exposure
+vec3 tonemap(vec3 color) {
+    color *= post.exposure;
+
+    if (post.tonemap == 1) {
+        return color / (1.0 + color);
+    } else if (post.tonemap == 2) {
+        return clamp((color * (2.51 * color + 0.03)) / (color * (2.43 * color + 0.59) + 0.14), 0.0, 1.0);
+    }
+
+    return color;
+}
+
+// for unorm targets, srgb ones encode on write
+vec3 encodeSrgb(vec3 color) {
+    color = clamp(color, 0.0, 1.0);
+    return mix(color * 12.92, 1.055 * pow(color, vec3(1.0 / 2.4)) - 0.055, step(0.0031308, color));
+}
+
+void main() {
+    vec4 color = subpassLoad(sceneColor);
+
+    if (post.tonemap != 0) {
+        color.rgb = tonemap(color.rgb);
+    }
+
+    if (post.effect == 1) {
+        color.rgb = 1.0 - color.rgb;
+    } else if (post.effect == 2) {
+        color.rgb = vec3(dot(color.rgb, vec3(0.2126, 0.7152, 0.0722)));
+    }
+
+    if (post.gamma != 0) {
+        color.rgb = encodeSrgb(color.rgb);
+    }
+
+    outColor = color;
+}
@@ -0,0 +1,44 @@
+#version 450
+
+layout(binding = 0) uniform UniformBufferObject {
+    mat4 view;
+    mat4 proj;
+} ubo;
+
+layout(binding = 2) uniform ObjectUniform {
+    mat4 model;
+    // inverse transpose of the model matrix
+    mat4 normal;
+} object;
+
+// the outline pipeline draws everything a little bigger
+layout(constant_id = 0) const float OUTLINE_SCALE = 1.0;
+
+layout(location = 0) in vec3 inPosition;
+layout(location = 1) in vec3 inColor;
+layout(location = 2) in vec2 inTexCoord;
+layout(location = 3) in mat4 instanceTransform;
+layout(location = 7) in vec3 instanceColor;
+layout(location = 8) in vec3 inNormal;
+layout(location = 9) in vec4 inTangent;
+
+layout(location = 0) out vec3 fragColor;
+layout(location = 1) out vec2 fragTexCoord;
+layout(location = 2) out vec3 fragNormal;
+layout(location = 3) out vec4 fragTangent;
+layout(location = 4) out vec3 fragPosition;
+
+// the depth prepass and the equal test are separate pipelines, their depths have to match bit for bit
+invariant gl_Position;
+
+void main() {
+    vec4 position = object.model * instanceTransform * vec4(inPosition * OUTLINE_SCALE, 1.0);
+    gl_Position = ubo.proj * ubo.view * position;
+    fragColor = inColor * instanceColor;
+    fragTexCoord = inTexCoord;
+
+    // world space, instances are expected to scale evenly since only the object's transform is inverse transposed
+    fragNormal = mat3(object.normal) * mat3(instanceTransform) * inNormal;
+    fragTangent = vec4(mat3(object.model * instanceTransform) * inTangent.xyz, inTangent.w);
+    fragPosition = position.xyz;
+}
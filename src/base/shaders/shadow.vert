@@ -0,0 +1,22 @@
+#version 450
+
+layout(binding = 2) uniform ObjectUniform {
+    mat4 model;
+    mat4 normal;
+} object;
+
+// only the light's view projection is needed here
+layout(binding = 4) uniform LightUniform {
+    vec4 direction;
+    vec4 color;
+    vec4 ambient;
+    vec4 cameraPosition;
+    mat4 viewProj;
+} light;
+
+layout(location = 0) in vec3 inPosition;
+layout(location = 3) in mat4 instanceTransform;
+
+void main() {
+    gl_Position = light.viewProj * object.model * instanceTransform * vec4(inPosition, 1.0);
+}
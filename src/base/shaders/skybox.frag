@@ -0,0 +1,11 @@
+#version 450
+
+layout(binding = 0) uniform samplerCube skyboxSampler;
+
+layout(location = 0) in vec3 fragDirection;
+
+layout(location = 0) out vec4 outColor;
+
+void main() {
+    outColor = texture(skyboxSampler, fragDirection);
+}
@@ -0,0 +1,21 @@
+#version 450
+
+// projection times the view without its translation, so the cube stays around the camera
+layout(push_constant) uniform SkyboxConstants {
+    mat4 viewProj;
+} skybox;
+
+// the far plane is at depth 0 instead of 1
+layout(constant_id = 0) const bool REVERSE_Z = false;
+
+layout(location = 0) in vec3 inPosition;
+
+layout(location = 0) out vec3 fragDirection;
+
+void main() {
+    // the world is z up, cube maps are y up
+    fragDirection = vec3(inPosition.x, inPosition.z, -inPosition.y);
+    // w as depth puts the whole cube on the far plane, behind anything else, 0 does when it's reversed
+    vec4 position = skybox.viewProj * vec4(inPosition, 1.0);
+    gl_Position = REVERSE_Z ? vec4(position.xy, 0.0, position.w) : position.xyww;
+}
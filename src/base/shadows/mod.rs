@@ -0,0 +1,391 @@
+use super::allocator::{Allocation, Allocator};
+use super::app::data::{InstanceData, Vertex};
+use super::app::{create_image, create_image_view, create_shader_module, set_debug_name};
+use super::shaders;
+
+use anyhow::Result;
+
+use vulkanalia::{prelude::v1_0::*, Instance};
+
+use std::mem::size_of;
+
+// the depth of the nearest surface from the light, drawn each frame before the scene samples it
+#[derive(Debug)]
+pub struct ShadowMap {
+    size: u32,
+    // constant and slope scaled, in the units vkCmdSetDepthBias takes
+    depth_bias: (f32, f32),
+    image: vk::Image,
+    image_memory: Allocation,
+    image_view: vk::ImageView,
+    sampler: vk::Sampler,
+    render_pass: vk::RenderPass,
+    framebuffer: vk::Framebuffer,
+    pipeline_layout: vk::PipelineLayout,
+    pipeline: vk::Pipeline,
+}
+
+impl ShadowMap {
+    /// # Safety
+    ///
+    /// The handles passed in have to be valid and from one device, which has to outlive what's made here.
+    #[allow(clippy::too_many_arguments)]
+    pub unsafe fn new(
+        instance: &Instance,
+        device: &Device,
+        phys_device: vk::PhysicalDevice,
+        allocator: &mut Allocator,
+        pipeline_cache: vk::PipelineCache,
+        descriptor_set_layout: vk::DescriptorSetLayout,
+        format: vk::Format,
+        size: u32,
+        depth_bias: (f32, f32),
+    ) -> Result<Self> {
+        let (image, image_memory) = create_image(
+            instance,
+            device,
+            phys_device,
+            allocator,
+            size,
+            size,
+            1,
+            1,
+            vk::SampleCountFlags::_1,
+            format,
+            vk::ImageTiling::OPTIMAL,
+            vk::ImageCreateFlags::empty(),
+            vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT | vk::ImageUsageFlags::SAMPLED,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            "shadow-map",
+        )?;
+        let image_view = create_image_view(device, image, format, vk::ImageAspectFlags::DEPTH, 1)?;
+        let sampler = create_sampler(device)?;
+
+        let render_pass = create_render_pass(device, format)?;
+        let attachments = &[image_view];
+        let info = vk::FramebufferCreateInfo::builder()
+            .render_pass(render_pass)
+            .attachments(attachments)
+            .width(size)
+            .height(size)
+            .layers(1);
+        let framebuffer = device.create_framebuffer(&info, None)?;
+
+        let (pipeline_layout, pipeline) = create_pipeline(device, pipeline_cache, render_pass, descriptor_set_layout)?;
+
+        Ok(Self {
+            size,
+            depth_bias,
+            image,
+            image_memory,
+            image_view,
+            sampler,
+            render_pass,
+            framebuffer,
+            pipeline_layout,
+            pipeline,
+        })
+    }
+
+    pub fn image_view(&self) -> vk::ImageView {
+        self.image_view
+    }
+
+    // compares instead of returning depth, so shadow lookups give how lit a texel is
+    pub fn sampler(&self) -> vk::Sampler {
+        self.sampler
+    }
+
+    pub fn pipeline(&self) -> vk::Pipeline {
+        self.pipeline
+    }
+
+    // the main descriptor sets bind to it, the texture index push is accepted and ignored
+    pub fn pipeline_layout(&self) -> vk::PipelineLayout {
+        self.pipeline_layout
+    }
+
+    /// clears to the far plane and sets the viewport, scissor and bias for the whole map
+    ///
+    /// # Safety
+    ///
+    /// `command_buffer` has to be recording outside any rendering, cmd_end has to follow.
+    pub unsafe fn cmd_begin(&self, device: &Device, command_buffer: vk::CommandBuffer) {
+        let extent = vk::Extent2D { width: self.size, height: self.size };
+        let render_area = vk::Rect2D::builder()
+            .offset(vk::Offset2D::default())
+            .extent(extent);
+
+        let depth_clear_value = vk::ClearValue {
+            depth_stencil: vk::ClearDepthStencilValue {
+                depth: 1.0,
+                stencil: 0,
+            },
+        };
+
+        let clear_values = &[depth_clear_value];
+        let info = vk::RenderPassBeginInfo::builder()
+            .render_pass(self.render_pass)
+            .framebuffer(self.framebuffer)
+            .render_area(render_area)
+            .clear_values(clear_values);
+        device.cmd_begin_render_pass(command_buffer, &info, vk::SubpassContents::INLINE);
+
+        let viewport = vk::Viewport::builder()
+            .x(0.0)
+            .y(0.0)
+            .width(self.size as f32)
+            .height(self.size as f32)
+            .min_depth(0.0)
+            .max_depth(1.0);
+        device.cmd_set_viewport(command_buffer, 0, &[viewport]);
+        device.cmd_set_scissor(command_buffer, 0, &[render_area]);
+
+        // pushes the depth away from the light so surfaces don't shadow themselves
+        let (constant, slope) = self.depth_bias;
+        device.cmd_set_depth_bias(command_buffer, constant, 0.0, slope);
+    }
+
+    /// # Safety
+    ///
+    /// `command_buffer` has to be inside the render pass cmd_begin began.
+    pub unsafe fn cmd_end(&self, device: &Device, command_buffer: vk::CommandBuffer) {
+        device.cmd_end_render_pass(command_buffer);
+    }
+
+    /// leaves the map readable without drawing it, the scene still binds it when shadows are off
+    ///
+    /// # Safety
+    ///
+    /// `command_buffer` has to be recording outside any rendering.
+    pub unsafe fn cmd_skip(&self, device: &Device, command_buffer: vk::CommandBuffer) {
+        let subresource = vk::ImageSubresourceRange::builder()
+            .aspect_mask(vk::ImageAspectFlags::DEPTH)
+            .base_mip_level(0)
+            .level_count(1)
+            .base_array_layer(0)
+            .layer_count(1);
+
+        let barrier = vk::ImageMemoryBarrier::builder()
+            .old_layout(vk::ImageLayout::UNDEFINED)
+            .new_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .image(self.image)
+            .subresource_range(subresource)
+            .src_access_mask(vk::AccessFlags::empty())
+            .dst_access_mask(vk::AccessFlags::SHADER_READ);
+
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::TOP_OF_PIPE,
+            vk::PipelineStageFlags::FRAGMENT_SHADER,
+            vk::DependencyFlags::empty(),
+            &[] as &[vk::MemoryBarrier],
+            &[] as &[vk::BufferMemoryBarrier],
+            &[barrier],
+        );
+    }
+
+    /// for shader reloads, the old pipeline is kept if the new one fails
+    ///
+    /// # Safety
+    ///
+    /// The old pipeline can't be in use by a pending command buffer.
+    pub unsafe fn recreate_pipeline(
+        &mut self,
+        device: &Device,
+        pipeline_cache: vk::PipelineCache,
+        descriptor_set_layout: vk::DescriptorSetLayout,
+    ) -> Result<()> {
+        let (layout, pipeline) = create_pipeline(device, pipeline_cache, self.render_pass, descriptor_set_layout)?;
+        device.destroy_pipeline(self.pipeline, None);
+        device.destroy_pipeline_layout(self.pipeline_layout, None);
+        (self.pipeline_layout, self.pipeline) = (layout, pipeline);
+
+        Ok(())
+    }
+
+    /// # Safety
+    ///
+    /// Nothing made here can still be in use on the gpu, and none of it can be used after.
+    pub unsafe fn destroy(&mut self, device: &Device, allocator: &mut Allocator) {
+        device.destroy_pipeline(self.pipeline, None);
+        device.destroy_pipeline_layout(self.pipeline_layout, None);
+        device.destroy_framebuffer(self.framebuffer, None);
+        device.destroy_render_pass(self.render_pass, None);
+        device.destroy_sampler(self.sampler, None);
+        device.destroy_image_view(self.image_view, None);
+        device.destroy_image(self.image, None);
+        allocator.free(device, self.image_memory);
+    }
+
+    /// # Safety
+    ///
+    /// `device` has to be the one these were made with, created from `instance`.
+    pub unsafe fn set_debug_names(&self, instance: &Instance, device: &Device) -> Result<()> {
+        set_debug_name(instance, device, self.image, "shadow-map")?;
+        set_debug_name(instance, device, self.render_pass, "shadow-render-pass")?;
+        set_debug_name(instance, device, self.pipeline, "shadow-pipeline")
+    }
+}
+
+// outside the map is the far plane, so nothing there is shadowed
+unsafe fn create_sampler(device: &Device) -> Result<vk::Sampler> {
+    let info = vk::SamplerCreateInfo::builder()
+        .mag_filter(vk::Filter::NEAREST)
+        .min_filter(vk::Filter::NEAREST)
+        .address_mode_u(vk::SamplerAddressMode::CLAMP_TO_BORDER)
+        .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_BORDER)
+        .address_mode_w(vk::SamplerAddressMode::CLAMP_TO_BORDER)
+        .border_color(vk::BorderColor::FLOAT_OPAQUE_WHITE)
+        .compare_enable(true)
+        .compare_op(vk::CompareOp::LESS_OR_EQUAL)
+        .mipmap_mode(vk::SamplerMipmapMode::NEAREST);
+
+    Ok(device.create_sampler(&info, None)?)
+}
+
+// the map ends up readable by the scene's fragment shaders
+unsafe fn create_render_pass(device: &Device, format: vk::Format) -> Result<vk::RenderPass> {
+    let depth_attachment = vk::AttachmentDescription::builder()
+        .format(format)
+        .samples(vk::SampleCountFlags::_1)
+        .load_op(vk::AttachmentLoadOp::CLEAR)
+        .store_op(vk::AttachmentStoreOp::STORE)
+        .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+        .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+        .initial_layout(vk::ImageLayout::UNDEFINED)
+        .final_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL);
+
+    let depth_attachment_ref = vk::AttachmentReference::builder()
+        .attachment(0)
+        .layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL);
+
+    let subpass = vk::SubpassDescription::builder()
+        .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
+        .depth_stencil_attachment(&depth_attachment_ref);
+
+    // the last frame's scene has to be done reading the map before it's cleared
+    let begin_dependency = vk::SubpassDependency::builder()
+        .src_subpass(vk::SUBPASS_EXTERNAL)
+        .dst_subpass(0)
+        .src_stage_mask(vk::PipelineStageFlags::FRAGMENT_SHADER)
+        .src_access_mask(vk::AccessFlags::empty())
+        .dst_stage_mask(vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS)
+        .dst_access_mask(vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE);
+
+    // and this frame's waits for it to be written
+    let end_dependency = vk::SubpassDependency::builder()
+        .src_subpass(0)
+        .dst_subpass(vk::SUBPASS_EXTERNAL)
+        .src_stage_mask(vk::PipelineStageFlags::LATE_FRAGMENT_TESTS)
+        .src_access_mask(vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE)
+        .dst_stage_mask(vk::PipelineStageFlags::FRAGMENT_SHADER)
+        .dst_access_mask(vk::AccessFlags::SHADER_READ);
+
+    let attachments = &[depth_attachment];
+    let subpasses = &[subpass];
+    let dependencies = &[begin_dependency, end_dependency];
+    let info = vk::RenderPassCreateInfo::builder()
+        .attachments(attachments)
+        .subpasses(subpasses)
+        .dependencies(dependencies);
+
+    Ok(device.create_render_pass(&info, None)?)
+}
+
+// depth only, with no fragment shader, objects and instances placed like the scene's vertex shader does
+unsafe fn create_pipeline(
+    device: &Device,
+    pipeline_cache: vk::PipelineCache,
+    render_pass: vk::RenderPass,
+    descriptor_set_layout: vk::DescriptorSetLayout,
+) -> Result<(vk::PipelineLayout, vk::Pipeline)> {
+    let vert_shader_module = create_shader_module(device, &shaders::load(shaders::SHADOW_VERT)?)?;
+
+    let vert_stage = vk::PipelineShaderStageCreateInfo::builder()
+        .stage(vk::ShaderStageFlags::VERTEX)
+        .module(vert_shader_module)
+        .name(b"main\0");
+
+    let binding_descriptions = &[Vertex::binding_description(), InstanceData::binding_description()];
+    let attribute_descriptions = Vertex::attribute_descriptions()
+        .into_iter()
+        .chain(InstanceData::attribute_descriptions())
+        .collect::<Vec<_>>();
+    let vertex_input_state = vk::PipelineVertexInputStateCreateInfo::builder()
+        .vertex_binding_descriptions(binding_descriptions)
+        .vertex_attribute_descriptions(&attribute_descriptions);
+
+    let input_assembly_state = vk::PipelineInputAssemblyStateCreateInfo::builder()
+        .topology(vk::PrimitiveTopology::TRIANGLE_LIST)
+        .primitive_restart_enable(false);
+
+    let viewport_state = vk::PipelineViewportStateCreateInfo::builder()
+        .viewport_count(1)
+        .scissor_count(1);
+
+    let dynamic_states = &[vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR, vk::DynamicState::DEPTH_BIAS];
+    let dynamic_state = vk::PipelineDynamicStateCreateInfo::builder()
+        .dynamic_states(dynamic_states);
+
+    // both faces, so open meshes still cast shadows
+    let rasterization_state = vk::PipelineRasterizationStateCreateInfo::builder()
+        .depth_clamp_enable(false)
+        .rasterizer_discard_enable(false)
+        .polygon_mode(vk::PolygonMode::FILL)
+        .line_width(1.0)
+        .cull_mode(vk::CullModeFlags::NONE)
+        .front_face(vk::FrontFace::COUNTER_CLOCKWISE)
+        .depth_bias_enable(true);
+
+    let multisample_state = vk::PipelineMultisampleStateCreateInfo::builder()
+        .sample_shading_enable(false)
+        .rasterization_samples(vk::SampleCountFlags::_1);
+
+    let depth_stencil_state = vk::PipelineDepthStencilStateCreateInfo::builder()
+        .depth_test_enable(true)
+        .depth_write_enable(true)
+        .depth_compare_op(vk::CompareOp::LESS)
+        .depth_bounds_test_enable(false)
+        .stencil_test_enable(false);
+
+    // the scene draws push a texture index whatever they're drawn with
+    let push_constant_range = vk::PushConstantRange::builder()
+        .stage_flags(vk::ShaderStageFlags::FRAGMENT)
+        .offset(0)
+        .size(size_of::<u32>() as u32);
+
+    let set_layouts = &[descriptor_set_layout];
+    let push_constant_ranges = &[push_constant_range];
+    let layout_info = vk::PipelineLayoutCreateInfo::builder()
+        .set_layouts(set_layouts)
+        .push_constant_ranges(push_constant_ranges);
+    let pipeline_layout = device.create_pipeline_layout(&layout_info, None)?;
+
+    let stages = &[vert_stage];
+    let info = vk::GraphicsPipelineCreateInfo::builder()
+        .stages(stages)
+        .vertex_input_state(&vertex_input_state)
+        .input_assembly_state(&input_assembly_state)
+        .viewport_state(&viewport_state)
+        .rasterization_state(&rasterization_state)
+        .multisample_state(&multisample_state)
+        .depth_stencil_state(&depth_stencil_state)
+        .dynamic_state(&dynamic_state)
+        .layout(pipeline_layout)
+        .render_pass(render_pass)
+        .subpass(0);
+
+    let result = device.create_graphics_pipelines(pipeline_cache, &[info], None);
+    device.destroy_shader_module(vert_shader_module, None);
+
+    match result {
+        Ok((pipelines, _)) => Ok((pipeline_layout, pipelines[0])),
+        Err(e) => {
+            device.destroy_pipeline_layout(pipeline_layout, None);
+            Err(e.into())
+        }
+    }
+}
@@ -0,0 +1,511 @@
+use super::allocator::{Allocation, Allocator};
+use super::app::data::{Mat4, PipelineTarget, Uploader};
+use super::app::{
+    copy_buffer_to_image, create_buffer, create_image, create_shader_module, create_vertex_buffer, load_png,
+    set_debug_name, transition_image_layout,
+};
+use super::shaders;
+
+use anyhow::{anyhow, Result};
+
+use cgmath::{vec3, Vector3};
+
+use vulkanalia::{prelude::v1_0::*, Instance};
+
+use std::{mem::size_of, ptr::copy_nonoverlapping as memcpy};
+
+// cube maps are sampled in this order, +x, -x, +y, -y, +z, -z
+pub const FACES: usize = 6;
+
+const FORMAT: vk::Format = vk::Format::R8G8B8A8_SRGB;
+
+// a cube map drawn behind the scene, at the end of the opaque passes so only uncovered pixels are shaded
+#[derive(Debug)]
+pub struct Skybox {
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    descriptor_pool: vk::DescriptorPool,
+    descriptor_set: vk::DescriptorSet,
+    pipeline_layout: vk::PipelineLayout,
+    pipeline: vk::Pipeline,
+    // the far plane is at 0 instead of 1
+    reverse_z: bool,
+    vertex_buffer: vk::Buffer,
+    vertex_buffer_memory: Allocation,
+    image: vk::Image,
+    image_memory: Allocation,
+    image_view: vk::ImageView,
+    sampler: vk::Sampler,
+}
+
+impl Skybox {
+    /// # Safety
+    ///
+    /// The handles passed in have to be valid and from one device, which has to outlive what's made here.
+    #[allow(clippy::too_many_arguments)]
+    pub unsafe fn new(
+        instance: &Instance,
+        device: &Device,
+        phys_device: vk::PhysicalDevice,
+        allocator: &mut Allocator,
+        uploader: &Uploader,
+        pipeline_cache: vk::PipelineCache,
+        target: PipelineTarget,
+        samples: vk::SampleCountFlags,
+        reverse_z: bool,
+        faces: [&str; FACES],
+    ) -> Result<Self> {
+        let (image, image_memory) = create_cube_image(instance, device, phys_device, allocator, uploader, faces)?;
+        let image_view = create_cube_image_view(device, image)?;
+        let sampler = create_sampler(device)?;
+
+        let vertices = cube_vertices();
+        let (vertex_buffer, vertex_buffer_memory) = create_vertex_buffer(
+            instance,
+            device,
+            phys_device,
+            allocator,
+            uploader,
+            &vertices,
+            "skybox-vertices",
+        )?;
+
+        let descriptor_set_layout = create_descriptor_set_layout(device)?;
+        let (descriptor_pool, descriptor_set) =
+            create_descriptor_set(device, descriptor_set_layout, image_view, sampler)?;
+        let (pipeline_layout, pipeline) =
+            create_pipeline(device, pipeline_cache, target, descriptor_set_layout, samples, reverse_z)?;
+
+        Ok(Self {
+            descriptor_set_layout,
+            descriptor_pool,
+            descriptor_set,
+            pipeline_layout,
+            pipeline,
+            reverse_z,
+            vertex_buffer,
+            vertex_buffer_memory,
+            image,
+            image_memory,
+            image_view,
+            sampler,
+        })
+    }
+
+    /// records inside the scene subpass, view_proj is the camera's with the translation taken out
+    ///
+    /// # Safety
+    ///
+    /// `command_buffer` has to be recording inside the scene rendering.
+    pub unsafe fn record(&self, device: &Device, command_buffer: vk::CommandBuffer, view_proj: Mat4) {
+        device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::GRAPHICS, self.pipeline);
+        device.cmd_bind_descriptor_sets(
+            command_buffer,
+            vk::PipelineBindPoint::GRAPHICS,
+            self.pipeline_layout,
+            0,
+            &[self.descriptor_set],
+            &[],
+        );
+        device.cmd_bind_vertex_buffers(command_buffer, 0, &[self.vertex_buffer], &[0]);
+        device.cmd_push_constants(
+            command_buffer,
+            self.pipeline_layout,
+            vk::ShaderStageFlags::VERTEX,
+            0,
+            std::slice::from_raw_parts((&view_proj as *const Mat4).cast(), size_of::<Mat4>()),
+        );
+        device.cmd_draw(command_buffer, 36, 1, 0, 0);
+    }
+
+    /// built for the scene subpass, rebuilt with the render pass
+    ///
+    /// # Safety
+    ///
+    /// The old pipeline can't be in use by a pending command buffer.
+    pub unsafe fn recreate_pipeline(
+        &mut self,
+        device: &Device,
+        pipeline_cache: vk::PipelineCache,
+        target: PipelineTarget,
+        samples: vk::SampleCountFlags,
+    ) -> Result<()> {
+        self.destroy_pipeline(device);
+        (self.pipeline_layout, self.pipeline) =
+            create_pipeline(device, pipeline_cache, target, self.descriptor_set_layout, samples, self.reverse_z)?;
+
+        Ok(())
+    }
+
+    /// # Safety
+    ///
+    /// Nothing made here can still be in use on the gpu, and none of it can be used after.
+    pub unsafe fn destroy(&mut self, device: &Device, allocator: &mut Allocator) {
+        self.destroy_pipeline(device);
+        device.destroy_descriptor_pool(self.descriptor_pool, None);
+        device.destroy_descriptor_set_layout(self.descriptor_set_layout, None);
+        device.destroy_buffer(self.vertex_buffer, None);
+        allocator.free(device, self.vertex_buffer_memory);
+        device.destroy_sampler(self.sampler, None);
+        device.destroy_image_view(self.image_view, None);
+        device.destroy_image(self.image, None);
+        allocator.free(device, self.image_memory);
+    }
+
+    unsafe fn destroy_pipeline(&mut self, device: &Device) {
+        device.destroy_pipeline(self.pipeline, None);
+        device.destroy_pipeline_layout(self.pipeline_layout, None);
+    }
+
+    /// # Safety
+    ///
+    /// `device` has to be the one these were made with, created from `instance`.
+    pub unsafe fn set_debug_names(&self, instance: &Instance, device: &Device) -> Result<()> {
+        set_debug_name(instance, device, self.pipeline, "skybox-pipeline")?;
+        set_debug_name(instance, device, self.image, "skybox-image")
+    }
+}
+
+// two triangles per face, wound like any other mesh so culling front faces leaves the inside
+fn cube_vertices() -> Vec<Vector3<f32>> {
+    let axes = [vec3(1.0, 0.0, 0.0), vec3(0.0, 1.0, 0.0), vec3(0.0, 0.0, 1.0)];
+
+    (0..FACES)
+        .flat_map(|face| {
+            let sign = if face % 2 == 0 { 1.0 } else { -1.0 };
+            let normal = axes[face / 2] * sign;
+            // u cross v is the normal, so the corners go counter-clockwise seen from outside
+            let u = axes[(face / 2 + 1) % 3] * sign;
+            let v = axes[(face / 2 + 2) % 3];
+
+            [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+                .map(|(a, b)| normal + u * a + v * b)
+        })
+        .collect()
+}
+
+// every face has to be the same size and square
+unsafe fn create_cube_image(
+    instance: &Instance,
+    device: &Device,
+    phys_device: vk::PhysicalDevice,
+    allocator: &mut Allocator,
+    uploader: &Uploader,
+    faces: [&str; FACES],
+) -> Result<(vk::Image, Allocation)> {
+    let faces = faces
+        .iter()
+        .map(|path| load_png(path))
+        .collect::<Result<Vec<_>>>()?;
+
+    let (_, width, _) = faces[0];
+    if let Some((_, w, h)) = faces.iter().find(|(_, w, h)| *w != width || *h != width) {
+        return Err(anyhow!("Skybox faces must be square and the same size ({}x{} and {}x{}).", width, width, w, h));
+    }
+
+    let pixels = faces.into_iter().flat_map(|(pixels, ..)| pixels).collect::<Vec<_>>();
+    let size = pixels.len() as u64;
+
+    let (staging_buffer, staging_buffer_memory) = create_buffer(
+        instance,
+        device,
+        phys_device,
+        allocator,
+        size,
+        vk::BufferUsageFlags::TRANSFER_SRC,
+        vk::MemoryPropertyFlags::HOST_COHERENT | vk::MemoryPropertyFlags::HOST_VISIBLE,
+        "skybox-staging",
+    )?;
+
+    let memory = device.map_memory(
+        staging_buffer_memory.memory,
+        staging_buffer_memory.offset,
+        size,
+        vk::MemoryMapFlags::empty(),
+    )?;
+    memcpy(pixels.as_ptr(), memory.cast(), pixels.len());
+    device.unmap_memory(staging_buffer_memory.memory);
+
+    let (image, image_memory) = create_image(
+        instance,
+        device,
+        phys_device,
+        allocator,
+        width,
+        width,
+        1,
+        FACES as u32,
+        vk::SampleCountFlags::_1,
+        FORMAT,
+        vk::ImageTiling::OPTIMAL,
+        vk::ImageCreateFlags::CUBE_COMPATIBLE,
+        vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST,
+        vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        "skybox",
+    )?;
+
+    transition_image_layout(
+        device,
+        uploader.transfer_pool,
+        uploader.transfer_queue,
+        image,
+        FORMAT,
+        vk::ImageLayout::UNDEFINED,
+        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+        0,
+        1,
+        0,
+        FACES as u32,
+    )?;
+
+    copy_buffer_to_image(device, uploader, staging_buffer, image, width, width, 1, FACES as u32)?;
+
+    // the copy leaves the image owned by the graphics family
+    transition_image_layout(
+        device,
+        uploader.graphics_pool,
+        uploader.graphics_queue,
+        image,
+        FORMAT,
+        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+        vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        0,
+        1,
+        0,
+        FACES as u32,
+    )?;
+
+    device.destroy_buffer(staging_buffer, None);
+    allocator.free(device, staging_buffer_memory);
+
+    Ok((image, image_memory))
+}
+
+unsafe fn create_cube_image_view(device: &Device, image: vk::Image) -> Result<vk::ImageView> {
+    let subresource_range = vk::ImageSubresourceRange::builder()
+        .aspect_mask(vk::ImageAspectFlags::COLOR)
+        .base_mip_level(0)
+        .level_count(1)
+        .base_array_layer(0)
+        .layer_count(FACES as u32);
+
+    let info = vk::ImageViewCreateInfo::builder()
+        .image(image)
+        .view_type(vk::ImageViewType::CUBE)
+        .format(FORMAT)
+        .subresource_range(subresource_range);
+
+    Ok(device.create_image_view(&info, None)?)
+}
+
+// clamped so the seams between faces don't pick up the opposite edge
+unsafe fn create_sampler(device: &Device) -> Result<vk::Sampler> {
+    let info = vk::SamplerCreateInfo::builder()
+        .mag_filter(vk::Filter::LINEAR)
+        .min_filter(vk::Filter::LINEAR)
+        .address_mode_u(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+        .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+        .address_mode_w(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+        .mipmap_mode(vk::SamplerMipmapMode::LINEAR)
+        .max_lod(1.0);
+
+    Ok(device.create_sampler(&info, None)?)
+}
+
+unsafe fn create_descriptor_set_layout(device: &Device) -> Result<vk::DescriptorSetLayout> {
+    let sampler_binding = vk::DescriptorSetLayoutBinding::builder()
+        .binding(0)
+        .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+        .descriptor_count(1)
+        .stage_flags(vk::ShaderStageFlags::FRAGMENT);
+
+    let bindings = &[sampler_binding];
+    let info = vk::DescriptorSetLayoutCreateInfo::builder()
+        .bindings(bindings);
+
+    Ok(device.create_descriptor_set_layout(&info, None)?)
+}
+
+unsafe fn create_descriptor_set(
+    device: &Device,
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    image_view: vk::ImageView,
+    sampler: vk::Sampler,
+) -> Result<(vk::DescriptorPool, vk::DescriptorSet)> {
+    let sampler_size = vk::DescriptorPoolSize::builder()
+        .type_(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+        .descriptor_count(1);
+
+    let pool_sizes = &[sampler_size];
+    let info = vk::DescriptorPoolCreateInfo::builder()
+        .pool_sizes(pool_sizes)
+        .max_sets(1);
+    let descriptor_pool = device.create_descriptor_pool(&info, None)?;
+
+    let layouts = &[descriptor_set_layout];
+    let info = vk::DescriptorSetAllocateInfo::builder()
+        .descriptor_pool(descriptor_pool)
+        .set_layouts(layouts);
+    let descriptor_set = device.allocate_descriptor_sets(&info)?[0];
+
+    let info = vk::DescriptorImageInfo::builder()
+        .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+        .image_view(image_view)
+        .sampler(sampler);
+
+    let image_info = &[info];
+    let sampler_write = vk::WriteDescriptorSet::builder()
+        .dst_set(descriptor_set)
+        .dst_binding(0)
+        .dst_array_element(0)
+        .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+        .image_info(image_info);
+
+    device.update_descriptor_sets(&[sampler_write], &[] as &[vk::CopyDescriptorSet]);
+
+    Ok((descriptor_pool, descriptor_set))
+}
+
+// the inside of the cube at the far plane, tested against the scene's depth without writing it
+unsafe fn create_pipeline(
+    device: &Device,
+    pipeline_cache: vk::PipelineCache,
+    target: PipelineTarget,
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    samples: vk::SampleCountFlags,
+    reverse_z: bool,
+) -> Result<(vk::PipelineLayout, vk::Pipeline)> {
+    let vert_shader_module = create_shader_module(device, &shaders::load(shaders::SKYBOX_VERT)?)?;
+    let frag_shader_module = match shaders::load(shaders::SKYBOX_FRAG)
+        .and_then(|bytecode| create_shader_module(device, &bytecode))
+    {
+        Ok(module) => module,
+        Err(e) => {
+            device.destroy_shader_module(vert_shader_module, None);
+            return Err(e);
+        }
+    };
+
+    // which end of the depth range the cube is put at
+    let map_entries = &[vk::SpecializationMapEntry::builder()
+        .constant_id(0)
+        .offset(0)
+        .size(4)
+        .build()];
+    let data = if reverse_z { vk::TRUE } else { vk::FALSE }.to_ne_bytes();
+    let specialization_info = vk::SpecializationInfo::builder()
+        .map_entries(map_entries)
+        .data(&data);
+
+    let vert_stage = vk::PipelineShaderStageCreateInfo::builder()
+        .stage(vk::ShaderStageFlags::VERTEX)
+        .module(vert_shader_module)
+        .name(b"main\0")
+        .specialization_info(&specialization_info);
+
+    let frag_stage = vk::PipelineShaderStageCreateInfo::builder()
+        .stage(vk::ShaderStageFlags::FRAGMENT)
+        .module(frag_shader_module)
+        .name(b"main\0");
+
+    // positions only, they double as the sampling direction
+    let binding_description = vk::VertexInputBindingDescription::builder()
+        .binding(0)
+        .stride(size_of::<Vector3<f32>>() as u32)
+        .input_rate(vk::VertexInputRate::VERTEX);
+    let attribute_description = vk::VertexInputAttributeDescription::builder()
+        .binding(0)
+        .location(0)
+        .format(vk::Format::R32G32B32_SFLOAT)
+        .offset(0);
+
+    let binding_descriptions = &[binding_description];
+    let attribute_descriptions = &[attribute_description];
+    let vertex_input_state = vk::PipelineVertexInputStateCreateInfo::builder()
+        .vertex_binding_descriptions(binding_descriptions)
+        .vertex_attribute_descriptions(attribute_descriptions);
+
+    let input_assembly_state = vk::PipelineInputAssemblyStateCreateInfo::builder()
+        .topology(vk::PrimitiveTopology::TRIANGLE_LIST)
+        .primitive_restart_enable(false);
+
+    let viewport_state = vk::PipelineViewportStateCreateInfo::builder()
+        .viewport_count(1)
+        .scissor_count(1);
+
+    let dynamic_states = &[vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+    let dynamic_state = vk::PipelineDynamicStateCreateInfo::builder()
+        .dynamic_states(dynamic_states);
+
+    // the camera is always inside the cube
+    let rasterization_state = vk::PipelineRasterizationStateCreateInfo::builder()
+        .depth_clamp_enable(false)
+        .rasterizer_discard_enable(false)
+        .polygon_mode(vk::PolygonMode::FILL)
+        .line_width(1.0)
+        .cull_mode(vk::CullModeFlags::FRONT)
+        .front_face(vk::FrontFace::COUNTER_CLOCKWISE)
+        .depth_bias_enable(false);
+
+    let multisample_state = vk::PipelineMultisampleStateCreateInfo::builder()
+        .sample_shading_enable(false)
+        .rasterization_samples(samples);
+
+    // equal to the cleared depth passes, so only pixels nothing else was drawn over
+    let depth_stencil_state = vk::PipelineDepthStencilStateCreateInfo::builder()
+        .depth_test_enable(true)
+        .depth_write_enable(false)
+        .depth_compare_op(if reverse_z { vk::CompareOp::GREATER_OR_EQUAL } else { vk::CompareOp::LESS_OR_EQUAL })
+        .depth_bounds_test_enable(false)
+        .stencil_test_enable(false);
+
+    let attachment = vk::PipelineColorBlendAttachmentState::builder()
+        .color_write_mask(vk::ColorComponentFlags::all())
+        .blend_enable(false);
+
+    let attachments = &[attachment];
+    let color_blend_state = vk::PipelineColorBlendStateCreateInfo::builder()
+        .logic_op_enable(false)
+        .logic_op(vk::LogicOp::COPY)
+        .attachments(attachments)
+        .blend_constants([0.0, 0.0, 0.0, 0.0]);
+
+    let push_constant_range = vk::PushConstantRange::builder()
+        .stage_flags(vk::ShaderStageFlags::VERTEX)
+        .offset(0)
+        .size(size_of::<Mat4>() as u32);
+
+    let set_layouts = &[descriptor_set_layout];
+    let push_constant_ranges = &[push_constant_range];
+    let layout_info = vk::PipelineLayoutCreateInfo::builder()
+        .set_layouts(set_layouts)
+        .push_constant_ranges(push_constant_ranges);
+    let pipeline_layout = device.create_pipeline_layout(&layout_info, None)?;
+
+    let stages = &[vert_stage, frag_stage];
+    let mut rendering_info = target.rendering_info();
+    let mut info = vk::GraphicsPipelineCreateInfo::builder()
+        .stages(stages)
+        .vertex_input_state(&vertex_input_state)
+        .input_assembly_state(&input_assembly_state)
+        .viewport_state(&viewport_state)
+        .rasterization_state(&rasterization_state)
+        .multisample_state(&multisample_state)
+        .depth_stencil_state(&depth_stencil_state)
+        .color_blend_state(&color_blend_state)
+        .dynamic_state(&dynamic_state)
+        .layout(pipeline_layout)
+        .render_pass(target.render_pass())
+        .subpass(target.subpass());
+    if let Some(rendering_info) = &mut rendering_info {
+        info = info.push_next(rendering_info);
+    }
+
+    let pipeline = device
+        .create_graphics_pipelines(pipeline_cache, &[info], None)?
+        .0[0];
+
+    device.destroy_shader_module(vert_shader_module, None);
+    device.destroy_shader_module(frag_shader_module, None);
+
+    Ok((pipeline_layout, pipeline))
+}
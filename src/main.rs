@@ -1,31 +1,304 @@
 use anyhow::Result;
 use winit::dpi::LogicalSize;
-use winit::event::{Event, WindowEvent};
-use winit::event_loop::EventLoop;
-use winit::window::WindowBuilder;
+use winit::event::{DeviceEvent, ElementState, Event, KeyEvent, MouseButton, WindowEvent};
+use winit::event_loop::{EventLoop, EventLoopWindowTarget};
+use winit::keyboard::{KeyCode, PhysicalKey};
+use winit::window::{CursorGrabMode, Window, WindowBuilder};
 
-mod base;
-use base::app::App;
+use cgmath::{vec3, Matrix4, Rad, Vector3};
+
+use std::collections::HashSet;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use vulkanalia::vk::{self, HasBuilder};
+
+use vulkan_testing_2::base::app::{data::InstanceData, is_minimized, App, RenderError};
+use vulkan_testing_2::base::constants::SKYBOX_PATHS;
+use vulkan_testing_2::base::particles::Particle;
+
+const PARTICLE_COUNT: u32 = 8192;
 
 fn main() -> Result<()> {
     pretty_env_logger::init();
 
+    // everything drawn marks the stencil so the outline pass only draws around the edges
+    let write_stencil = vk::StencilOpState::builder()
+        .fail_op(vk::StencilOp::KEEP)
+        .pass_op(vk::StencilOp::REPLACE)
+        .depth_fail_op(vk::StencilOp::KEEP)
+        .compare_op(vk::CompareOp::ALWAYS)
+        .compare_mask(0xff)
+        .write_mask(0xff)
+        .reference(1)
+        .build();
+    let config = App::builder()
+        .stencil(true, write_stencil, write_stencil)
+        .outline(1.05, [1.0, 0.6, 0.0])
+        .enable_gui(true)
+        .recording_threads(4)
+        .build();
+
     let event_loop = EventLoop::new()?;
     let window = WindowBuilder::new()
-        .with_title("Vulkan Testing")
-        .with_inner_size(LogicalSize::new(800, 800))
+        .with_title(&config.window_title)
+        .with_inner_size(LogicalSize::new(config.window_size.0, config.window_size.1))
         .build(&event_loop)?;
 
-    let mut app = unsafe { App::create(&window)? };
+    grab_cursor(&window, true);
+
+    let mut app = unsafe { App::create(&window, config)? };
+
+    // a copy of the model either side of the spinning one, each with another texture
+    let (vertices, indices) = (app.vertices.clone(), app.indices.clone());
+    for (i, x) in [-3.0, 3.0].into_iter().enumerate() {
+        let object = unsafe { app.add_object(&vertices, &indices)? };
+        app.objects[object].transform = Matrix4::from_translation(vec3(x, 0.0, 0.0));
+        app.objects[object].texture_index = (i as u32 + 1) % app.texture_layers;
+    }
+
+    // a ring of smaller copies so there's enough to split across the recording threads
+    for i in 0..12 {
+        let angle = Rad(i as f32 / 12.0 * std::f32::consts::TAU);
+        let object = unsafe { app.add_object(&vertices, &indices)? };
+        app.objects[object].transform = Matrix4::from_angle_z(angle)
+            * Matrix4::from_translation(vec3(5.0, 0.0, 0.0))
+            * Matrix4::from_scale(0.5);
+        app.objects[object].texture_index = i % app.texture_layers;
+    }
+
+    // the model becomes a grid of small cubes below the other objects, shaded from red to blue
+    let size = 16;
+    let instances = (0..size * size)
+        .map(|i| {
+            let (x, y) = ((i % size) as f32, (i / size) as f32);
+            let offset = (size - 1) as f32 / 2.0;
+            InstanceData {
+                transform: Matrix4::from_translation(vec3((x - offset) * 0.75, (y - offset) * 0.75, -2.0))
+                    * Matrix4::from_scale(0.25),
+                color: vec3(x / size as f32, 0.5, y / size as f32),
+            }
+        })
+        .collect();
+    unsafe { app.set_instances(instances)? };
+
+    // the grid again as two indirect draws, one per half
+    let half = (size * size / 2) as u32;
+    let commands = (0..2)
+        .map(|i| vk::DrawIndexedIndirectCommand {
+            index_count: app.index_count,
+            instance_count: half,
+            first_index: 0,
+            vertex_offset: 0,
+            first_instance: 1 + i * half,
+        })
+        .collect();
+    if let Err(e) = unsafe { app.set_draw_commands(commands) } {
+        log::warn!("Failed to set draw commands, drawing the grid directly: {}", e);
+    }
+
+    // a fountain bouncing around the grid, spread with the golden angle so nothing lines up
+    let particles = (0..PARTICLE_COUNT)
+        .map(|i| {
+            let t = (i as f32 * 0.618_034).fract();
+            let angle = i as f32 * 2.399_963;
+            let speed = 1.0 + 2.0 * t;
+            Particle {
+                position: [0.0, 0.0, 3.0 * t, 1.0],
+                velocity: [angle.cos() * speed, angle.sin() * speed, 2.0 + 4.0 * t, 0.0],
+                color: [1.0, 0.4 + 0.6 * t, 0.2, 1.0],
+            }
+        })
+        .collect::<Vec<_>>();
+    unsafe { app.set_particles(&particles)? };
+
+    // the clear color shows through without one
+    if let Err(e) = unsafe { app.set_skybox(SKYBOX_PATHS) } {
+        log::warn!("Failed to load skybox: {}", e);
+    }
+
+    let mut held_keys = HashSet::new();
+    // tab frees the cursor for the debug ui and stops the camera
+    let mut ui_mode = false;
+    // in physical pixels, only tracked for picking while the cursor is free
+    let mut cursor = (0, 0);
+    // f4 opens a second window rendering the same scene, kept with its index in the app's windows
+    let mut preview: Option<(Window, usize)> = None;
+    // the app is dropped with the closure, so its surface goes before the window it was made from
+    let window = &window;
     event_loop.run(move |event, elwt| {
+        app.handle_gui_event(window, &event);
+
         match event {
-            Event::AboutToWait => window.request_redraw(),
+            // the loop waits for events while minimized rather than spinning, resizing back wakes it
+            Event::AboutToWait if !is_minimized(window) => window.request_redraw(),
+            Event::DeviceEvent { event: DeviceEvent::MouseMotion { delta: (dx, dy) }, .. } if !ui_mode => {
+                app.camera.rotate(dx as f32, dy as f32);
+            },
+            // the preview is drawn along with the main window, so it only needs to know about resizing and closing
+            Event::WindowEvent { window_id, event } if preview.as_ref().is_some_and(|(p, _)| p.id() == window_id) => {
+                let index = preview.as_ref().map_or(0, |(_, index)| *index);
+                match event {
+                    WindowEvent::Resized(size) => app.windows[index].resize(size.width, size.height),
+                    WindowEvent::CloseRequested => close_preview(&mut app, &mut preview),
+                    _ => {}
+                }
+            },
+            // its surface has to go before the window does
+            Event::LoopExiting => close_preview(&mut app, &mut preview),
             Event::WindowEvent { event, .. } => match event {
-                WindowEvent::RedrawRequested if !elwt.exiting() => unsafe { app.render(&window) }.unwrap(),
-                WindowEvent::CloseRequested => {
-                    elwt.exit();
-                    unsafe { app.destroy(); }
+                WindowEvent::RedrawRequested if !elwt.exiting() => {
+                    if !ui_mode {
+                        app.camera.translate(movement(&held_keys), app.frame_delta);
+                    }
+
+                    let mut clear_color = app.clear_color;
+                    let result = app.gui_frame(window, |ui| {
+                        ui.window("Debug").always_auto_resize(true).build(|| {
+                            ui.text(format!("{:.0} fps", ui.io().framerate));
+                            ui.color_edit4("Clear color", &mut clear_color);
+                        });
+                    });
+                    if let Err(e) = result {
+                        log::error!("Failed to build gui: {}", e);
+                    }
+                    app.set_clear_color(clear_color);
+
+                    if let Err(e) = unsafe { app.render(window) } {
+                        // a lost device can be rebuilt, anything else is fatal
+                        let recovered = matches!(e.downcast_ref(), Some(RenderError::DeviceLost))
+                            && unsafe { app.recreate_device(window) }
+                                .map_err(|e| log::error!("Failed to recreate device: {}", e))
+                                .is_ok();
+
+                        // the preview's context went with the old device
+                        if recovered {
+                            if let Some((preview_window, index)) = &mut preview {
+                                match unsafe { app.add_window(preview_window) } {
+                                    Ok(new_index) => *index = new_index,
+                                    Err(e) => {
+                                        log::error!("Failed to add the preview window again: {}", e);
+                                        preview = None;
+                                    }
+                                }
+                            }
+                        }
+
+                        if !recovered {
+                            log::error!("Failed to render: {}", e);
+                            elwt.exit();
+                        }
+                    }
+                    // a click's pick is read back a frame or two later
+                    match app.picked_object() {
+                        Some(Some(id)) => log::info!("Picked object {}.", id),
+                        Some(None) => log::info!("Picked nothing."),
+                        None => {}
+                    }
                 },
+                WindowEvent::Resized(size) => app.windows[0].resize(size.width, size.height),
+                WindowEvent::CursorMoved { position, .. } => cursor = (position.x as u32, position.y as u32),
+                // clicking an object in ui mode logs which it is
+                WindowEvent::MouseInput { state: ElementState::Pressed, button: MouseButton::Left, .. } if ui_mode => {
+                    if let Err(e) = unsafe { app.pick_object(cursor.0, cursor.1) } {
+                        log::error!("Failed to pick: {}", e);
+                    }
+                },
+                WindowEvent::KeyboardInput {
+                    event: KeyEvent {
+                        physical_key: PhysicalKey::Code(KeyCode::F1),
+                        state: ElementState::Pressed,
+                        repeat: false,
+                        ..
+                    },
+                    ..
+                } => app.toggle_wireframe(),
+                WindowEvent::KeyboardInput {
+                    event: KeyEvent {
+                        physical_key: PhysicalKey::Code(KeyCode::KeyV),
+                        state: ElementState::Pressed,
+                        repeat: false,
+                        ..
+                    },
+                    ..
+                } => {
+                    let vsync = !app.vsync();
+                    if let Err(e) = unsafe { app.set_vsync(window, vsync) } {
+                        log::error!("Failed to toggle vsync: {}", e);
+                    }
+                },
+                WindowEvent::KeyboardInput {
+                    event: KeyEvent {
+                        physical_key: PhysicalKey::Code(KeyCode::Tab),
+                        state: ElementState::Pressed,
+                        repeat: false,
+                        ..
+                    },
+                    ..
+                } => {
+                    ui_mode = !ui_mode;
+                    held_keys.clear();
+                    grab_cursor(window, !ui_mode);
+                },
+                WindowEvent::KeyboardInput {
+                    event: KeyEvent {
+                        physical_key: PhysicalKey::Code(KeyCode::F2),
+                        state: ElementState::Pressed,
+                        repeat: false,
+                        ..
+                    },
+                    ..
+                } => app.set_post_effect(app.post_effect.next()),
+                WindowEvent::KeyboardInput {
+                    event: KeyEvent {
+                        physical_key: PhysicalKey::Code(KeyCode::F3),
+                        state: ElementState::Pressed,
+                        repeat: false,
+                        ..
+                    },
+                    ..
+                } => app.toggle_depth_prepass(),
+                WindowEvent::KeyboardInput {
+                    event: KeyEvent {
+                        physical_key: PhysicalKey::Code(KeyCode::F4),
+                        state: ElementState::Pressed,
+                        repeat: false,
+                        ..
+                    },
+                    ..
+                } => {
+                    if preview.is_some() {
+                        close_preview(&mut app, &mut preview);
+                    } else {
+                        match open_preview(&mut app, elwt) {
+                            Ok(opened) => preview = Some(opened),
+                            Err(e) => log::error!("Failed to open preview window: {}", e),
+                        }
+                    }
+                },
+                WindowEvent::KeyboardInput {
+                    event: KeyEvent {
+                        physical_key: PhysicalKey::Code(KeyCode::F12),
+                        state: ElementState::Pressed,
+                        repeat: false,
+                        ..
+                    },
+                    ..
+                } => {
+                    let time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+                    let path = format!("screenshot-{}.png", time.as_secs());
+                    if let Err(e) = unsafe { app.save_screenshot(Path::new(&path)) } {
+                        log::error!("Failed to save screenshot: {}", e);
+                    }
+                },
+                WindowEvent::KeyboardInput {
+                    event: KeyEvent { physical_key: PhysicalKey::Code(code), state, .. },
+                    ..
+                } => match state {
+                    ElementState::Pressed => { held_keys.insert(code); },
+                    ElementState::Released => { held_keys.remove(&code); },
+                },
+                WindowEvent::CloseRequested => elwt.exit(),
                 _ => {}
             },
             _ => {}
@@ -34,3 +307,52 @@ fn main() -> Result<()> {
 
     Ok(())
 }
+
+fn open_preview(app: &mut App, elwt: &EventLoopWindowTarget<()>) -> Result<(Window, usize)> {
+    let window = WindowBuilder::new()
+        .with_title(format!("{} (preview)", app.config.window_title))
+        .with_inner_size(LogicalSize::new(app.config.window_size.0 / 2, app.config.window_size.1 / 2))
+        .build(elwt)?;
+    let index = unsafe { app.add_window(&window)? };
+
+    Ok((window, index))
+}
+
+// the window is only dropped once its surface is gone
+fn close_preview(app: &mut App, preview: &mut Option<(Window, usize)>) {
+    if let Some((_, index)) = preview {
+        if let Err(e) = unsafe { app.remove_window(*index) } {
+            log::error!("Failed to close preview window: {}", e);
+        }
+        *preview = None;
+    }
+}
+
+fn grab_cursor(window: &Window, grab: bool) {
+    // not every platform can lock the cursor, confining is the fallback
+    let result = if grab {
+        window
+            .set_cursor_grab(CursorGrabMode::Locked)
+            .or_else(|_| window.set_cursor_grab(CursorGrabMode::Confined))
+    } else {
+        window.set_cursor_grab(CursorGrabMode::None)
+    };
+
+    if let Err(e) = result {
+        log::warn!("Failed to {} cursor: {}", if grab { "grab" } else { "release" }, e);
+    }
+    window.set_cursor_visible(!grab);
+}
+
+// (right, forward, up) from the held movement keys
+fn movement(held_keys: &HashSet<KeyCode>) -> Vector3<f32> {
+    let axis = |positive, negative| {
+        held_keys.contains(&positive) as i32 as f32 - held_keys.contains(&negative) as i32 as f32
+    };
+
+    vec3(
+        axis(KeyCode::KeyD, KeyCode::KeyA),
+        axis(KeyCode::KeyW, KeyCode::KeyS),
+        axis(KeyCode::Space, KeyCode::ShiftLeft),
+    )
+}